@@ -0,0 +1,489 @@
+//! Score of a winning hand typed as text, e.g. `123456m234p56s99p 4s --riichi`
+//! Yaku, han and fu come from mortal's agari with washizu's rules applied on top. Mortal only reports the total fu,
+//! the breakdown is rebuilt from the interpretation of the hand that reaches it.
+#[cfg(feature = "cli")]
+use std::fmt::Write;
+
+#[cfg(feature = "cli")]
+use anyhow::{Context, Result, ensure};
+use clap::Parser;
+#[cfg(feature = "cli")]
+use riichi::algo::agari::{Agari, AgariWithYaku};
+#[cfg(feature = "cli")]
+use riichi::algo::point::Point;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+#[cfg(feature = "cli")]
+use riichi::{t, tu8};
+
+#[cfg(feature = "cli")]
+use crate::input::{HandArgs, single_tile_hand, state_from_hand_args};
+#[cfg(feature = "cli")]
+use crate::rules::RuleSet;
+#[cfg(feature = "cli")]
+use crate::scoring::{HONBA_POINTS, KYOTAKU_POINTS};
+#[cfg(feature = "cli")]
+use crate::uradora::UraDora;
+#[cfg(feature = "cli")]
+use crate::yakunames::YakuNaming;
+
+#[derive(Parser, Debug)]
+pub struct AgariArgs {
+    /// Hand without the winning tile
+    pub tehai: String,
+    /// Winning tile
+    pub pai: String,
+    /// Won by tsumo instead of ron
+    #[arg(long)]
+    pub tsumo: bool,
+    #[arg(long)]
+    pub fuuro: Option<String>,
+    #[arg(long)]
+    pub ankan: Option<String>,
+    #[arg(long)]
+    pub dora_indicators: Option<String>,
+    #[arg(long)]
+    pub ura_indicators: Option<String>,
+    #[arg(long)]
+    pub bakaze: Option<String>,
+    #[arg(long)]
+    pub jikaze: Option<String>,
+    #[arg(long)]
+    pub riichi: bool,
+    #[arg(long)]
+    pub double_riichi: bool,
+    #[arg(long, requires = "riichi")]
+    pub ippatsu: bool,
+    /// Tsumo on the replacement tile of a kan
+    #[arg(long, requires = "tsumo")]
+    pub rinshan: bool,
+    /// Win on the last tile, haitei by tsumo and houtei by ron
+    #[arg(long)]
+    pub haitei: bool,
+    #[arg(long, default_value_t = 0)]
+    pub honba: u8,
+    #[arg(long, default_value_t = 0)]
+    pub kyotaku: u8,
+}
+
+/// Set of a complete hand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Set {
+    Triplet(usize),
+    /// Sequence by its lowest tile
+    Sequence(usize),
+}
+
+/// Fu of one part of the hand
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FuPart {
+    pub name: String,
+    pub fu: u8,
+}
+
+/// Score of a winning hand
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone)]
+pub struct HandScore {
+    pub tsumo: bool,
+    pub is_oya: bool,
+    /// None for a hand without yaku
+    pub agari: Option<AgariWithYaku>,
+    pub yaku: Vec<String>,
+    /// Empty for yakuman
+    pub fu: Vec<FuPart>,
+    pub point: Point,
+    pub honba: u8,
+    pub kyotaku: u8,
+}
+
+fn is_terminal_or_honor(tile: usize) -> bool {
+    tile >= 27 || matches!(tile % 9, 0 | 8)
+}
+
+/// Every way to split closed tiles into sets
+fn split_sets(counts: &mut [u8; 34], sets: &mut Vec<Set>, found: &mut Vec<Vec<Set>>) {
+    let Some(tile) = (0..34).find(|&tile| counts[tile] > 0) else {
+        found.push(sets.clone());
+        return;
+    };
+    if counts[tile] >= 3 {
+        counts[tile] -= 3;
+        sets.push(Set::Triplet(tile));
+        split_sets(counts, sets, found);
+        sets.pop();
+        counts[tile] += 3;
+    }
+    if tile < 27 && tile % 9 <= 6 && counts[tile + 1] > 0 && counts[tile + 2] > 0 {
+        for offset in 0..3 {
+            counts[tile + offset] -= 1;
+        }
+        sets.push(Set::Sequence(tile));
+        split_sets(counts, sets, found);
+        sets.pop();
+        for offset in 0..3 {
+            counts[tile + offset] += 1;
+        }
+    }
+}
+
+/// Pair and closed sets of every interpretation of a complete hand
+fn interpretations(mut counts: [u8; 34]) -> Vec<(usize, Vec<Set>)> {
+    let mut interpretations = vec![];
+    let pairs = (0..34).filter(|&tile| counts[tile] >= 2).collect::<Vec<_>>();
+    for pair in pairs {
+        counts[pair] -= 2;
+        let mut found = vec![];
+        split_sets(&mut counts, &mut vec![], &mut found);
+        interpretations.extend(found.into_iter().map(|sets| (pair, sets)));
+        counts[pair] += 2;
+    }
+    interpretations
+}
+
+/// Fu of the pair from yakuhai
+fn pair_fu(state: &PlayerState, pair: usize) -> u8 {
+    let mut fu = 0;
+    if pair >= 31 {
+        fu += 2;
+    }
+    if pair == state.bakaze.deaka().as_usize() {
+        fu += 2;
+    }
+    if pair == state.jikaze.deaka().as_usize() {
+        fu += 2;
+    }
+    fu
+}
+
+fn rounded(parts: &[FuPart]) -> u8 {
+    parts.iter().map(|part| part.fu).sum::<u8>().div_ceil(10) * 10
+}
+
+/// Fu of every interpretation of the hand with every position of the winning tile in it
+fn fu_options(state: &PlayerState, winning: usize, tsumo: bool) -> Vec<Vec<FuPart>> {
+    let part = |name: String, fu: u8| FuPart { name, fu };
+    let name = |tile: usize| Tile::try_from(tile as u8).map_or_else(|_| "?".to_owned(), |tile| tile.to_string());
+    let mut counts = state.tehai;
+    if !tsumo {
+        counts[winning] += 1;
+    }
+    let mut melds = vec![];
+    for &tile in &state.pons {
+        let fu = if is_terminal_or_honor(tile as usize) { 4 } else { 2 };
+        melds.push(part(format!("pon {}", name(tile as usize)), fu));
+    }
+    for &tile in &state.minkans {
+        let fu = if is_terminal_or_honor(tile as usize) { 16 } else { 8 };
+        melds.push(part(format!("minkan {}", name(tile as usize)), fu));
+    }
+    for &tile in &state.ankans {
+        let fu = if is_terminal_or_honor(tile as usize) { 32 } else { 16 };
+        melds.push(part(format!("ankan {}", name(tile as usize)), fu));
+    }
+
+    let mut options = vec![];
+    for (pair, sets) in interpretations(counts) {
+        // the winning tile completes the pair or one of the sets containing it
+        let mut waits = vec![];
+        if pair == winning {
+            waits.push((None, "tanki", 2));
+        }
+        for (index, set) in sets.iter().enumerate() {
+            match *set {
+                Set::Triplet(tile) if tile == winning => waits.push((Some(index), "shanpon", 0)),
+                Set::Sequence(low) if (low..low + 3).contains(&winning) => {
+                    let (wait, fu) = match (winning - low, low % 9) {
+                        (1, _) => ("kanchan", 2),
+                        (0, 6) | (2, 0) => ("penchan", 2),
+                        _ => ("ryanmen", 0),
+                    };
+                    waits.push((Some(index), wait, fu));
+                }
+                _ => {}
+            }
+        }
+        for (completed, wait, wait_fu) in waits {
+            let mut parts = vec![part("base".to_owned(), 20)];
+            if state.is_menzen && !tsumo {
+                parts.push(part("menzen ron".to_owned(), 10));
+            }
+            let pinfu = state.is_menzen
+                && melds.is_empty()
+                && sets.iter().all(|set| matches!(set, Set::Sequence(_)))
+                && pair_fu(state, pair) == 0
+                && wait == "ryanmen";
+            if tsumo && !pinfu {
+                parts.push(part("tsumo".to_owned(), 2));
+            }
+            if pair_fu(state, pair) > 0 {
+                parts.push(part(format!("pair {}", name(pair)), pair_fu(state, pair)));
+            }
+            for (index, set) in sets.iter().enumerate() {
+                if let Set::Triplet(tile) = *set {
+                    // a triplet completed by ron counts as open
+                    let open = !tsumo && completed == Some(index);
+                    let fu = match (is_terminal_or_honor(tile), open) {
+                        (false, true) => 2,
+                        (true, true) | (false, false) => 4,
+                        (true, false) => 8,
+                    };
+                    let kind = if open { "ron triplet" } else { "closed triplet" };
+                    parts.push(part(format!("{kind} {}", name(tile)), fu));
+                }
+            }
+            parts.extend(melds.iter().cloned());
+            if wait_fu > 0 {
+                parts.push(part(wait.to_owned(), wait_fu));
+            }
+            if !state.is_menzen && parts.iter().map(|part| part.fu).sum::<u8>() == 20 {
+                parts.push(part("open pinfu".to_owned(), 10));
+            }
+            options.push(parts);
+        }
+    }
+    options
+}
+
+/// Derivation of the fu, e.g. `base 20 + menzen ron 10 + kanchan 2 = 32, rounded up to 40`
+#[cfg(feature = "cli")]
+pub fn fu_string(parts: &[FuPart]) -> String {
+    let raw = parts.iter().map(|part| part.fu).sum::<u8>();
+    let sum = parts
+        .iter()
+        .map(|part| format!("{} {}", part.name, part.fu))
+        .collect::<Vec<_>>()
+        .join(" + ");
+    match rounded(parts) {
+        // chiitoitsu is never rounded
+        _ if raw == 25 => sum,
+        fu if fu == raw => format!("{sum} = {fu}"),
+        fu => format!("{sum} = {raw}, rounded up to {fu}"),
+    }
+}
+
+/// Fu breakdown of a normal agari of `winning`, the interpretation giving mortal's fu with the most raw fu.
+/// On tsumo the winning tile is already in the hand.
+pub fn fu_breakdown(state: &PlayerState, winning: Tile, tsumo: bool, fu: u8) -> Vec<FuPart> {
+    if fu == 25 {
+        return vec![FuPart {
+            name: "chiitoitsu".to_owned(),
+            fu: 25,
+        }];
+    }
+    let raw = |parts: &Vec<FuPart>| parts.iter().map(|part| part.fu).sum::<u8>();
+    let options = fu_options(state, winning.deaka().as_usize(), tsumo);
+    let matching = options
+        .iter()
+        .filter(|parts| rounded(parts) == fu)
+        .max_by_key(|parts| raw(parts));
+    matching
+        .or_else(|| options.iter().max_by_key(|parts| raw(parts)))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// State of the winning hand with the situational flags mortal's agari reads
+#[cfg(feature = "cli")]
+fn agari_state(args: &AgariArgs) -> Result<PlayerState> {
+    let winning = single_tile_hand(&args.pai).context("incorrect winning tile")?;
+    let tehai = if args.tsumo {
+        format!("{}{}", args.tehai, args.pai)
+    } else {
+        args.tehai.clone()
+    };
+    let mut state = state_from_hand_args(HandArgs {
+        tehai,
+        fuuro: args.fuuro.clone(),
+        ankan: args.ankan.clone(),
+        dora_indicators: args.dora_indicators.clone(),
+        bakaze: args.bakaze.clone(),
+        jikaze: args.jikaze.clone(),
+        tiles_left: Some(if args.haitei { 0 } else { 70 }),
+    })?;
+    let tiles = state.tehai.iter().sum::<u8>() + if args.tsumo { 0 } else { 1 };
+    ensure!(tiles % 3 == 2, "the hand with the winning tile must have 3n+2 tiles");
+    state.oya = (4 - (state.jikaze.as_u8() - tu8!(E))) % 4;
+    state.honba = args.honba;
+    state.kyotaku = args.kyotaku;
+    state.riichi_declared[0] = args.riichi || args.double_riichi;
+    state.riichi_accepted[0] = state.riichi_declared[0];
+    state.is_w_riichi = args.double_riichi;
+    state.at_ippatsu = args.ippatsu;
+    state.at_rinshan = args.rinshan;
+    if args.tsumo {
+        state.last_self_tsumo = Some(winning);
+    }
+    Ok(state)
+}
+
+/// Score a winning hand under the rules
+#[cfg(feature = "cli")]
+pub fn score_hand(args: &AgariArgs, rules: &RuleSet, naming: YakuNaming) -> Result<HandScore> {
+    let state = agari_state(args)?;
+    let winning = single_tile_hand(&args.pai)?;
+    let mut agari = state
+        .calculate_agari(winning, !args.tsumo, &[])
+        .context("the hand is not complete with the winning tile")?;
+    // rules read a complete hand from an unknown tile
+    let tile = if args.tsumo { t!(?) } else { winning };
+    rules.apply_to_agari(&state, tile, &mut agari);
+    let mut local_yaku = rules.apply_local_yaku(&state, tile, &mut agari);
+    if let Some(indicators) = &args.ura_indicators {
+        UraDora::parse(false, Some(indicators))?.apply_to_agari(&state, tile, &mut agari);
+    }
+    let is_oya = state.is_oya();
+    let (yaku, fu, point) = match &agari {
+        None => (vec![], vec![], Point::default()),
+        Some(agari_with_yaku) => {
+            let mut yaku = naming.yaku_list(&agari_with_yaku.yaku);
+            yaku.extend(local_yaku.drain(..).map(|name| naming.washizu_yaku(name)));
+            let fu = match agari_with_yaku.agari {
+                Agari::Normal { fu, .. } => fu_breakdown(&state, winning, args.tsumo, fu),
+                Agari::Yakuman(_) => vec![],
+            };
+            (yaku, fu, agari_with_yaku.agari.point(is_oya))
+        }
+    };
+    Ok(HandScore {
+        tsumo: args.tsumo,
+        is_oya,
+        agari,
+        yaku,
+        fu,
+        point,
+        honba: args.honba,
+        kyotaku: args.kyotaku,
+    })
+}
+
+#[cfg(feature = "cli")]
+impl HandScore {
+    /// Payments with honba and riichi sticks, e.g. `ron 5800+300 from the discarder`
+    pub fn payment_string(&self) -> String {
+        let sticks = if self.kyotaku > 0 {
+            format!(", {} from riichi sticks", self.kyotaku as i32 * KYOTAKU_POINTS)
+        } else {
+            "".to_owned()
+        };
+        let honba = |points: i32| {
+            if points > 0 { format!("+{points}") } else { "".to_owned() }
+        };
+        if !self.tsumo {
+            return format!(
+                "ron {}{} from the discarder{sticks}",
+                self.point.ron,
+                honba(self.honba as i32 * HONBA_POINTS)
+            );
+        }
+        let each = honba(self.honba as i32 * HONBA_POINTS / 3);
+        if self.is_oya {
+            format!("tsumo {}{each} from each{sticks}", self.point.tsumo_ko)
+        } else {
+            format!(
+                "tsumo {}{each} from the dealer, {}{each} from each non-dealer{sticks}",
+                self.point.tsumo_oya, self.point.tsumo_ko
+            )
+        }
+    }
+
+    pub fn to_log_string(&self) -> String {
+        let Some(agari) = &self.agari else {
+            return "yakunashi = 0".to_owned();
+        };
+        let total = if self.tsumo {
+            self.point.tsumo_total(self.is_oya)
+        } else {
+            self.point.ron
+        };
+        let mut string = match agari.agari {
+            Agari::Normal { fu, han } => format!("{han}han{fu}fu = {total}\n"),
+            Agari::Yakuman(1) => format!("yakuman = {total}\n"),
+            Agari::Yakuman(count) => format!("{count}x yakuman = {total}\n"),
+        };
+        writeln!(string, "yaku: {}", self.yaku.join(", ")).unwrap();
+        if !self.fu.is_empty() {
+            writeln!(string, "fu: {}", fu_string(&self.fu)).unwrap();
+        }
+        write!(string, "payment: {}", self.payment_string()).unwrap();
+        string
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::t;
+
+    use super::*;
+
+    fn hand(tiles: &[Tile], is_menzen: bool) -> PlayerState {
+        let mut state = PlayerState {
+            bakaze: t!(E),
+            jikaze: t!(S),
+            is_menzen,
+            ..Default::default()
+        };
+        for tile in tiles {
+            state.tehai[tile.deaka().as_usize()] += 1;
+        }
+        state
+    }
+
+    fn parts(parts: &[(&str, u8)]) -> Vec<FuPart> {
+        parts
+            .iter()
+            .map(|&(name, fu)| FuPart {
+                name: name.to_owned(),
+                fu,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pinfu_tsumo_is_base_fu_only() {
+        // 234m567m345p23s99p tsumo 4s
+        let tiles = [
+            t!(2m),
+            t!(3m),
+            t!(4m),
+            t!(5m),
+            t!(6m),
+            t!(7m),
+            t!(3p),
+            t!(4p),
+            t!(5p),
+            t!(9p),
+            t!(9p),
+            t!(2s),
+            t!(3s),
+            t!(4s),
+        ];
+        let state = hand(&tiles, true);
+        assert_eq!(fu_breakdown(&state, t!(4s), true, 20), parts(&[("base", 20)]));
+        // the same hand won on the 3s closing the 2s-4s kanchan is not pinfu
+        assert_eq!(
+            fu_breakdown(&state, t!(3s), true, 30),
+            parts(&[("base", 20), ("tsumo", 2), ("kanchan", 2)])
+        );
+    }
+
+    #[test]
+    fn chiitoitsu_is_a_flat_25() {
+        let state = hand(&[t!(1m), t!(1m), t!(9p), t!(9p), t!(E), t!(E)], true);
+        assert_eq!(fu_breakdown(&state, t!(E), false, 25), parts(&[("chiitoitsu", 25)]));
+        // tsumo adds nothing to it
+        assert_eq!(fu_breakdown(&state, t!(E), true, 25), parts(&[("chiitoitsu", 25)]));
+    }
+
+    #[test]
+    fn open_hand_with_a_closed_wait_keeps_its_wait_fu() {
+        // 123m22m79p456s with a pon of 5p, ron on 8p
+        let tiles = [t!(1m), t!(2m), t!(3m), t!(2m), t!(2m), t!(7p), t!(9p), t!(4s), t!(5s), t!(6s)];
+        let mut state = hand(&tiles, false);
+        state.pons.push(t!(5p).as_u8());
+        assert_eq!(
+            fu_breakdown(&state, t!(8p), false, 30),
+            parts(&[("base", 20), ("pon 5p", 2), ("kanchan", 2)])
+        );
+    }
+}
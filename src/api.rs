@@ -0,0 +1,103 @@
+//! Entry points taking text and returning reports, so embedders never build mortal's state themselves
+use anyhow::{Context, Result, ensure};
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+
+use crate::concurrency::analyze;
+#[cfg(feature = "parallel")]
+use crate::concurrency::analyze_parallel;
+use crate::decisions::{decisions_from_events, state_from_events};
+use crate::input::{HandArgs, state_from_hand_args};
+use crate::mjaigen::{board_from_events, mjai_lines, parse_board};
+use crate::report::AnalysisReport;
+use crate::state::AnalysisOptions;
+use crate::variant::parse_event;
+
+/// Hand in the notation of the hand command, tiles are written like `123m0p`
+#[derive(Clone, Debug, Default)]
+pub struct Hand {
+    /// Closed tiles, or a forum problem such as `123m456p789s1122z dora 5m east seat 1`
+    pub tehai: String,
+    /// Open melds separated by spaces, e.g. `555z 406p`
+    pub fuuro: Option<String>,
+    /// Closed kans separated by spaces
+    pub ankan: Option<String>,
+    /// East when unset
+    pub dora_indicators: Option<String>,
+    /// East when unset
+    pub bakaze: Option<String>,
+    /// East when unset
+    pub jikaze: Option<String>,
+    /// 70 when unset
+    pub tiles_left: Option<u8>,
+}
+
+impl Hand {
+    /// Hand of closed tiles with every other field unset
+    pub fn new(tehai: &str) -> Self {
+        Self {
+            tehai: tehai.to_owned(),
+            ..Self::default()
+        }
+    }
+}
+
+impl From<&Hand> for HandArgs {
+    fn from(hand: &Hand) -> Self {
+        Self {
+            tehai: hand.tehai.clone(),
+            fuuro: hand.fuuro.clone(),
+            ankan: hand.ankan.clone(),
+            dora_indicators: hand.dora_indicators.clone(),
+            bakaze: hand.bakaze.clone(),
+            jikaze: hand.jikaze.clone(),
+            tiles_left: hand.tiles_left,
+        }
+    }
+}
+
+/// Analyze a hand, e.g. `Hand::new("123m456p789s11223z")`
+pub fn analyze_hand(hand: &Hand, options: &AnalysisOptions) -> Result<AnalysisReport> {
+    analyze(state_from_hand_args(hand.into())?, options)
+}
+
+/// Analyze the last decision of a board in the syntax of the board command
+pub fn analyze_board(board: &str, options: &AnalysisOptions) -> Result<AnalysisReport> {
+    let events = parse_board(board.split_whitespace().collect())?;
+    analyze(state_from_events(events)?, options)
+}
+
+fn parse_log(log: &str) -> Result<Vec<Event>> {
+    log.lines().filter(|line| !line.trim().is_empty()).map(parse_event).collect()
+}
+
+/// Analyze every decision of a player in a mjai log with one event per line, concurrently with the `parallel` feature
+pub fn analyze_log(log: &str, player_id: Option<u8>, options: &AnalysisOptions) -> Result<Vec<AnalysisReport>> {
+    let decisions = decisions_from_events(parse_log(log)?, player_id, None)?;
+    let states = decisions.into_iter().map(|(_, state)| state).collect();
+    analyze_states(states, options)
+}
+
+#[cfg(feature = "parallel")]
+fn analyze_states(states: Vec<PlayerState>, options: &AnalysisOptions) -> Result<Vec<AnalysisReport>> {
+    analyze_parallel(states, options)
+}
+
+#[cfg(not(feature = "parallel"))]
+fn analyze_states(states: Vec<PlayerState>, options: &AnalysisOptions) -> Result<Vec<AnalysisReport>> {
+    states.into_iter().map(|state| analyze(state, options)).collect()
+}
+
+/// Mjai log with one event per line leading to a board in the syntax of the board command
+pub fn mjai_from_board(board: &str) -> Result<String> {
+    mjai_lines(&parse_board(board.split_whitespace().collect())?)
+}
+
+/// Board in the syntax of the board command as seen by a player at the end of a mjai log
+pub fn board_from_mjai(log: &str, player_id: u8) -> Result<String> {
+    let events = parse_log(log)?;
+    ensure!(player_id < 4, "player id must be between 0 and 3");
+    Ok(board_from_events(&events, player_id)
+        .context("failed to rebuild the board")?
+        .to_board_string())
+}
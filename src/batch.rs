@@ -0,0 +1,303 @@
+//! Review of every log in a directory with aggregate statistics
+//! Games are analyzed independently, on the rayon thread pool with the `parallel` feature.
+//! A log is read as an ekyumoe review when it parses as one and as mjai events with one event per line otherwise.
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+
+use crate::checkpoint::Checkpointer;
+use crate::decisions::detect_player_id;
+use crate::ekyumoe::{Detail, EkyuMoeReview, validate_details};
+use crate::error::WashizuError;
+use crate::review::{BoardSnapshot, ReviewDecision, record_response};
+use crate::state::{AnalysisOptions, ExpandedState};
+use crate::variant::parse_event;
+
+/// Games reviewed between writes of the checkpoint
+pub const CHECKPOINT_INTERVAL: usize = 16;
+
+/// Statistics of the reviewed player in one game
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct GameStats {
+    pub log: PathBuf,
+    pub player_id: u8,
+    pub decisions: usize,
+    /// Category and EV loss of every decision that lost expected value, see `mistake_category`
+    pub mistakes: Vec<(String, f32)>,
+    pub kyokus: usize,
+    pub deal_ins: usize,
+}
+
+impl GameStats {
+    pub fn ev_loss(&self) -> f32 {
+        self.mistakes.iter().map(|(_, loss)| loss).sum()
+    }
+}
+
+/// Kind of decision a mistake was made in, from the best candidate and the action actually taken
+pub fn mistake_category(best: &Event, actual: &Event) -> &'static str {
+    match (best, actual) {
+        (Event::Reach { .. }, _) => "missed riichi",
+        (_, Event::Reach { .. }) => "riichi",
+        (Event::Dahai { .. }, Event::Dahai { .. }) => "discard",
+        (Event::Chi { .. } | Event::Pon { .. } | Event::Daiminkan { .. }, _)
+        | (_, Event::Chi { .. } | Event::Pon { .. } | Event::Daiminkan { .. }) => "call",
+        (Event::Ankan { .. } | Event::Kakan { .. }, _) | (_, Event::Ankan { .. } | Event::Kakan { .. }) => "kan",
+        _ => "other",
+    }
+}
+
+/// Events of a log with mortal's details of the decisions they lead to
+pub type EventsWithDetails = Vec<(Event, Option<Vec<Detail>>)>;
+
+/// Reviewed player and events of a log
+pub fn load_log(path: &Path, player_name: Option<&str>) -> Result<(u8, EventsWithDetails)> {
+    let text = std::fs::read_to_string(path).with_context(|| WashizuError::Log(path.display().to_string()))?;
+    if let Ok(mut log) = serde_json::from_str::<EkyuMoeReview>(&text) {
+        if let Some(player_name) = player_name {
+            let player_id = detect_player_id(log.mjai_log.first().context("empty mjai log")?, None, Some(player_name))?;
+            if player_id != log.player_id {
+                log.player_id = player_id;
+                log.review.kyokus.clear();
+            }
+        }
+        // corrupt details only lose mortal's opinion on their own decision
+        let details = log.details_by_event()?;
+        let events = log
+            .mjai_log
+            .into_iter()
+            .zip(details)
+            .map(|(event, details)| (event, details.and_then(Result::ok)))
+            .collect();
+        return Ok((log.player_id, events));
+    }
+    let events = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(parse_event)
+        .collect::<Result<Vec<_>>>()
+        .with_context(|| format!("{} is neither an ekyumoe review nor a mjai log", path.display()))?;
+    let player_id = detect_player_id(events.first().context("empty log")?, None, player_name)?;
+    Ok((player_id, events.into_iter().map(|event| (event, None)).collect()))
+}
+
+/// Analysis of every decision of the player in the events, with the action taken
+pub fn review_decisions(player_id: u8, events: EventsWithDetails, options: &AnalysisOptions) -> Result<Vec<ReviewDecision>> {
+    let mut state = PlayerState::new(player_id);
+    let mut decisions: Vec<ReviewDecision> = vec![];
+    for (event, details) in events {
+        record_response(&mut decisions, &event, player_id);
+        state.update(&event).with_context(|| WashizuError::state_update(&event))?;
+        if !state.last_cans.can_act() {
+            continue;
+        }
+        let details = details.filter(|details| validate_details(&state, details).is_ok());
+        let report = ExpandedState::from_state(state.clone(), details, options)?.to_report();
+        decisions.push(ReviewDecision {
+            trigger: event,
+            board: BoardSnapshot::from_state(&state),
+            report,
+            actual: None,
+        });
+    }
+    Ok(decisions)
+}
+
+/// Analyze every decision of the reviewed player in a log
+pub fn review_game(path: &Path, player_name: Option<&str>, options: &AnalysisOptions) -> Result<GameStats> {
+    let (player_id, events) = load_log(path, player_name)?;
+    let kyokus = events
+        .iter()
+        .filter(|(event, _)| matches!(event, Event::StartKyoku { .. }))
+        .count();
+    let deal_ins = events
+        .iter()
+        .filter(|(event, _)| matches!(event, Event::Hora { actor, target, .. } if *target == player_id && *actor != player_id))
+        .count();
+    let decisions = review_decisions(player_id, events, options)?;
+    let mistakes = decisions
+        .iter()
+        .filter_map(|decision| {
+            let ev_loss = decision.ev_loss().filter(|ev_loss| ev_loss.loss > 0.0)?;
+            let best = decision
+                .report
+                .candidates
+                .iter()
+                .find(|candidate| candidate.action == ev_loss.best)?;
+            Some((
+                mistake_category(&best.event, decision.actual.as_ref()?).to_owned(),
+                ev_loss.loss,
+            ))
+        })
+        .collect();
+    Ok(GameStats {
+        log: path.to_owned(),
+        player_id,
+        decisions: decisions.len(),
+        mistakes,
+        kyokus,
+        deal_ins,
+    })
+}
+
+/// Files under a directory, recursively and sorted
+pub fn log_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    collect_log_files(dir, &mut HashSet::new(), &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+/// Files under a directory, following symlinks but entering every directory once so a link cycle ends
+fn collect_log_files(dir: &Path, visited: &mut HashSet<PathBuf>, files: &mut Vec<PathBuf>) -> Result<()> {
+    let canonical = dir
+        .canonicalize()
+        .with_context(|| format!("failed to resolve {}", dir.display()))?;
+    if !visited.insert(canonical) {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_log_files(&path, visited, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Review every log, failures are returned next to the statistics of the other games
+pub fn review_games(
+    paths: &[PathBuf],
+    player_name: Option<&str>,
+    options: &AnalysisOptions,
+) -> Vec<(PathBuf, Result<GameStats>)> {
+    let review = |path: &PathBuf| (path.clone(), review_game(path, player_name, options));
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+
+        paths.par_iter().map(review).collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    paths.iter().map(review).collect()
+}
+
+/// Review the logs the checkpoint has not seen yet, adding every game to its aggregate and writing it every interval.
+/// Failures are returned and left unprocessed so a resumed run retries them.
+pub fn review_games_checkpointed(
+    paths: &[PathBuf],
+    player_name: Option<&str>,
+    options: &AnalysisOptions,
+    checkpointer: &mut Checkpointer<Vec<GameStats>>,
+) -> Result<Vec<(PathBuf, anyhow::Error)>> {
+    let pending = paths
+        .iter()
+        .filter(|path| !checkpointer.is_processed(&path.display().to_string()))
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut failures = vec![];
+    for chunk in pending.chunks(checkpointer.interval()) {
+        for (path, result) in review_games(chunk, player_name, options) {
+            match result {
+                Ok(game) => {
+                    checkpointer.checkpoint.aggregate.push(game);
+                    checkpointer.mark_processed(&path.display().to_string())?;
+                }
+                Err(e) => failures.push((path, e)),
+            }
+        }
+    }
+    checkpointer.save()?;
+    Ok(failures)
+}
+
+/// Totals over all games, mistake categories sorted by their total EV loss and a line per game
+pub fn batch_summary(games: &[GameStats]) -> String {
+    let decisions: usize = games.iter().map(|game| game.decisions).sum();
+    let mistakes: usize = games.iter().map(|game| game.mistakes.len()).sum();
+    let ev_loss: f32 = games.iter().map(GameStats::ev_loss).sum();
+    let kyokus: usize = games.iter().map(|game| game.kyokus).sum();
+    let deal_ins: usize = games.iter().map(|game| game.deal_ins).sum();
+    let mut out = format!(
+        "{} games, {decisions} decisions, {mistakes} lose EV, {} total, {} per game\ndeal-in rate {:.1}% ({deal_ins} of {kyokus} kyokus)\n",
+        games.len(),
+        ev_loss.round(),
+        (ev_loss / games.len().max(1) as f32).round(),
+        deal_ins as f32 / kyokus.max(1) as f32 * 100.0,
+    );
+
+    let mut categories: Vec<(&str, usize, f32)> = vec![];
+    for (category, loss) in games.iter().flat_map(|game| &game.mistakes) {
+        match categories.iter_mut().find(|(other, _, _)| other == category) {
+            Some((_, count, total)) => {
+                *count += 1;
+                *total += loss;
+            }
+            None => categories.push((category, 1, *loss)),
+        }
+    }
+    categories.sort_by(|(_, _, a), (_, _, b)| b.total_cmp(a));
+    for (category, count, total) in categories {
+        _ = writeln!(out, "{category:<13} {count:>4}x -{}", total.round());
+    }
+
+    for game in games {
+        _ = writeln!(
+            out,
+            "{} seat {}: {} decisions, {} lose EV, -{}, {} deal-ins in {} kyokus",
+            game.log.display(),
+            game.player_id,
+            game.decisions,
+            game.mistakes.len(),
+            game.ev_loss().round(),
+            game.deal_ins,
+            game.kyokus
+        );
+    }
+    out
+}
+
+/// One row per game
+pub fn batch_csv(games: &[GameStats]) -> String {
+    let mut out = "log,player_id,decisions,mistakes,ev_loss,kyokus,deal_ins\n".to_owned();
+    for game in games {
+        _ = writeln!(
+            out,
+            "\"{}\",{},{},{},{:.1},{},{}",
+            game.log.display().to_string().replace('"', "\"\""),
+            game.player_id,
+            game.decisions,
+            game.mistakes.len(),
+            game.ev_loss(),
+            game.kyokus,
+            game.deal_ins
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn log_files_survive_symlink_cycles() {
+        let dir = std::env::temp_dir().join(format!("washizu-logs-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("a")).unwrap();
+        std::fs::write(dir.join("a/1.json"), "").unwrap();
+        std::fs::write(dir.join("2.json"), "").unwrap();
+        // a/up leads back to the top, a/same to a itself
+        std::os::unix::fs::symlink(&dir, dir.join("a/up")).unwrap();
+        std::os::unix::fs::symlink(dir.join("a"), dir.join("a/same")).unwrap();
+        let files = log_files(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(files.unwrap(), [dir.join("2.json"), dir.join("a/1.json")]);
+    }
+}
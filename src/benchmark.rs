@@ -0,0 +1,69 @@
+//! Hand strength compared against typical hands at the same turn
+//! Uses bundled rough statistics of shanten by turn for hands that kept pushing,
+//! ukeire is used to place the hand within hands of the same shanten.
+use riichi::algo::sp::EventCandidate;
+
+/// Turns of the rows in `SHANTEN_BY_TURN`
+const BENCHMARK_TURNS: [u8; 7] = [0, 3, 6, 9, 12, 15, 18];
+/// Share of hands at 0..=6 shanten for each benchmark turn
+const SHANTEN_BY_TURN: [[f32; 7]; 7] = [
+    [0.001, 0.023, 0.16, 0.40, 0.33, 0.08, 0.006],
+    [0.01, 0.08, 0.29, 0.39, 0.19, 0.04, 0.0],
+    [0.07, 0.24, 0.36, 0.25, 0.07, 0.01, 0.0],
+    [0.20, 0.36, 0.29, 0.12, 0.03, 0.0, 0.0],
+    [0.35, 0.38, 0.19, 0.07, 0.01, 0.0, 0.0],
+    [0.47, 0.34, 0.14, 0.04, 0.01, 0.0, 0.0],
+    [0.56, 0.30, 0.11, 0.03, 0.0, 0.0, 0.0],
+];
+/// Typical ukeire of hands at 0..=6 shanten
+const MEDIAN_UKEIRE: [f32; 7] = [5.0, 20.0, 35.0, 50.0, 60.0, 70.0, 80.0];
+
+/// Shanten distribution at a turn, linearly interpolated between the benchmark turns
+fn shanten_distribution(turn: u8) -> [f32; 7] {
+    let turn = turn.min(*BENCHMARK_TURNS.last().unwrap());
+    let upper = BENCHMARK_TURNS.iter().position(|&t| t >= turn).unwrap();
+    if BENCHMARK_TURNS[upper] == turn {
+        return SHANTEN_BY_TURN[upper];
+    }
+    let lower = upper - 1;
+    let ratio = (turn - BENCHMARK_TURNS[lower]) as f32 / (BENCHMARK_TURNS[upper] - BENCHMARK_TURNS[lower]) as f32;
+    std::array::from_fn(|shanten| SHANTEN_BY_TURN[lower][shanten] * (1.0 - ratio) + SHANTEN_BY_TURN[upper][shanten] * ratio)
+}
+
+/// Percentile (0-100, higher is better) of a hand with the given shanten and ukeire at a turn
+pub fn hand_percentile(turn: u8, shanten: i8, ukeire: Option<u32>) -> f32 {
+    if shanten < 0 {
+        return 100.0;
+    }
+    let distribution = shanten_distribution(turn);
+    let shanten = shanten.clamp(0, 6) as usize;
+    let worse: f32 = distribution[shanten + 1..].iter().sum();
+    let within = match ukeire {
+        Some(ukeire) => (ukeire as f32 / (2.0 * MEDIAN_UKEIRE[shanten])).min(1.0),
+        None => 0.5,
+    };
+    (worse + distribution[shanten] * within) * 100.0
+}
+
+/// Best ukeire among the candidates that reach the lowest shanten
+pub fn best_ukeire(candidates: &[EventCandidate]) -> Option<u32> {
+    let min_shanten = candidates.iter().map(|candidate| candidate.shanten).min()?;
+    candidates
+        .iter()
+        .filter(|candidate| candidate.shanten == min_shanten)
+        .map(|candidate| candidate.num_required_tiles as u32)
+        .max()
+}
+
+/// English ordinal of a number, e.g. 72nd
+#[cfg(feature = "cli")]
+pub fn ordinal(n: u32) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
@@ -0,0 +1,220 @@
+//! Head-to-head comparison of every possible call against passing
+//! The call candidates' values already assume the best discard afterwards, the discard itself is found by
+//! applying the call to a copy of the state and calculating the tables of the resulting hand. The calls are
+//! independent, so with the `parallel` feature each one is calculated on its own rayon worker.
+use riichi::algo::sp::EventCandidate;
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+
+use crate::order::{SortSpec, sort_by_value};
+use crate::state::AnalysisOptions;
+use crate::tables::single_player_tables;
+
+/// A call and its best follow-up compared to passing
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct CallDecision {
+    pub call: Event,
+    pub exp_value: f32,
+    /// Best discard after the call, None for kans that draw a replacement tile first
+    pub follow_up: Option<Event>,
+    /// Expected value gained over passing
+    pub delta: f32,
+}
+
+fn is_call(event: &Event) -> bool {
+    matches!(event, Event::Chi { .. } | Event::Pon { .. } | Event::Daiminkan { .. })
+}
+
+/// Every call candidate with its follow-up, sorted by expected value. Empty when no call is possible.
+pub fn call_decisions(state: &PlayerState, candidates: &[EventCandidate], options: &AnalysisOptions) -> Vec<CallDecision> {
+    if !candidates.iter().any(|candidate| is_call(&candidate.event)) {
+        return vec![];
+    }
+    let pass_ev = candidates
+        .iter()
+        .find(|candidate| matches!(candidate.event, Event::None))
+        .and_then(|candidate| candidate.exp_values.first().copied())
+        .unwrap_or(0.0);
+    let calls = candidates
+        .iter()
+        .filter(|candidate| is_call(&candidate.event))
+        .collect::<Vec<_>>();
+    let follow_up = |call: &Event| {
+        let mut after = state.clone();
+        after
+            .update(call)
+            .ok()
+            .filter(|cans| cans.can_discard)
+            .and_then(|_| single_player_tables(&after, options).candidates.into_iter().next())
+            .map(|best| best.event)
+    };
+    let call_events = calls.iter().map(|candidate| candidate.event.clone()).collect::<Vec<_>>();
+    #[cfg(feature = "parallel")]
+    let follow_ups = {
+        use rayon::prelude::*;
+
+        call_events.par_iter().map(follow_up).collect::<Vec<_>>()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let follow_ups = call_events.iter().map(follow_up).collect::<Vec<_>>();
+    let mut decisions = calls
+        .into_iter()
+        .zip(follow_ups)
+        .map(|(candidate, follow_up)| {
+            let exp_value = candidate.exp_values.first().copied().unwrap_or(0.0);
+            CallDecision {
+                call: candidate.event.clone(),
+                exp_value,
+                follow_up,
+                delta: exp_value - pass_ev,
+            }
+        })
+        .collect::<Vec<_>>();
+    sort_by_value(&mut decisions, &SortSpec::default(), |decision| {
+        (decision.exp_value, decision.call.clone())
+    });
+    decisions
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::t;
+
+    use super::*;
+
+    fn chi() -> Event {
+        Event::Chi {
+            actor: 0,
+            target: 3,
+            pai: t!(5m),
+            consumed: [t!(3m), t!(4m)],
+        }
+    }
+
+    fn pon() -> Event {
+        Event::Pon {
+            actor: 0,
+            target: 3,
+            pai: t!(5m),
+            consumed: [t!(5m), t!(5mr)],
+        }
+    }
+
+    fn daiminkan() -> Event {
+        Event::Daiminkan {
+            actor: 0,
+            target: 3,
+            pai: t!(5m),
+            consumed: [t!(5m), t!(5m), t!(5mr)],
+        }
+    }
+
+    /// 34555m123s EESSW as the dealer to our left discards a 5m, which can be chied, poned and kanned
+    fn call_prompt() -> PlayerState {
+        let mut tehais = [[t!(?); 13]; 4];
+        tehais[0] = [
+            t!(3m),
+            t!(4m),
+            t!(5m),
+            t!(5m),
+            t!(5mr),
+            t!(1s),
+            t!(2s),
+            t!(3s),
+            t!(E),
+            t!(E),
+            t!(S),
+            t!(S),
+            t!(W),
+        ];
+        let mut state = PlayerState::new(0);
+        let events = [
+            Event::StartKyoku {
+                bakaze: t!(E),
+                dora_marker: t!(9p),
+                kyoku: 4,
+                honba: 0,
+                kyotaku: 0,
+                oya: 3,
+                scores: [25000; 4],
+                tehais,
+            },
+            Event::Tsumo { actor: 3, pai: t!(?) },
+            Event::Dahai {
+                actor: 3,
+                pai: t!(5m),
+                tsumogiri: false,
+            },
+        ];
+        for event in &events {
+            state.update(event).unwrap();
+        }
+        state
+    }
+
+    fn candidate(event: Event, exp_value: f32) -> EventCandidate {
+        EventCandidate {
+            event,
+            shanten: 1,
+            num_required_tiles: 0,
+            required_tiles: vec![],
+            exp_values: vec![exp_value],
+            win_probs: vec![0.2],
+            tenpai_probs: vec![0.5],
+            yaku: vec![],
+        }
+    }
+
+    fn decision(decisions: &[CallDecision], call: &Event) -> (f32, f32) {
+        let decision = decisions.iter().find(|decision| &decision.call == call).unwrap();
+        (decision.exp_value, decision.delta)
+    }
+
+    #[test]
+    fn every_call_is_compared_with_passing() {
+        let candidates = [
+            candidate(Event::None, 1000.0),
+            candidate(chi(), 1200.0),
+            candidate(pon(), 1500.0),
+            candidate(daiminkan(), 900.0),
+        ];
+        let decisions = call_decisions(&call_prompt(), &candidates, &AnalysisOptions::default());
+        let calls = decisions.iter().map(|decision| decision.call.clone()).collect::<Vec<_>>();
+        assert_eq!(calls, [pon(), chi(), daiminkan()]);
+        assert_eq!(decision(&decisions, &chi()), (1200.0, 200.0));
+        assert_eq!(decision(&decisions, &pon()), (1500.0, 500.0));
+        assert_eq!(decision(&decisions, &daiminkan()), (900.0, -100.0));
+        // the kan draws a replacement tile before it discards
+        assert_eq!(decisions[2].follow_up, None);
+    }
+
+    #[test]
+    fn call_without_a_pass_candidate_is_compared_with_zero() {
+        let decisions = call_decisions(&call_prompt(), &[candidate(chi(), 1200.0)], &AnalysisOptions::default());
+        assert_eq!(decision(&decisions, &chi()), (1200.0, 1200.0));
+    }
+
+    #[test]
+    fn no_decisions_without_a_call_candidate() {
+        let discard = Event::Dahai {
+            actor: 0,
+            pai: t!(W),
+            tsumogiri: false,
+        };
+        let candidates = [candidate(Event::None, 1000.0), candidate(discard, 800.0)];
+        assert!(call_decisions(&call_prompt(), &candidates, &AnalysisOptions::default()).is_empty());
+        assert!(call_decisions(&PlayerState::new(0), &[], &AnalysisOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn only_chi_pon_and_daiminkan_are_calls() {
+        assert!(is_call(&chi()) && is_call(&pon()) && is_call(&daiminkan()));
+        let kakan = Event::Kakan {
+            actor: 0,
+            pai: t!(5m),
+            consumed: [t!(5m), t!(5m), t!(5mr)],
+        };
+        assert!(!is_call(&kakan));
+        assert!(!is_call(&Event::None));
+    }
+}
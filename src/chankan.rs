@@ -0,0 +1,177 @@
+//! Robbing a kan: ron on an opponent's kakan of one of our waits
+//! Passing keeps the hand alive but the kan takes the tile for good and we become furiten on it,
+//! so only tsumo can win afterwards, which is exactly what the single player tables value.
+use riichi::algo::agari::AgariWithYaku;
+use riichi::algo::sp::EventCandidate;
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+use crate::scoring::extra_points;
+
+/// Ron on a kakan compared to passing it
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ChankanAnalysis {
+    pub tile: Tile,
+    /// Points of the ron including honba and kyotaku, None without yaku.
+    /// Mortal counts chankan itself as a yaku while the chance is open.
+    pub ron_points: Option<i32>,
+    /// Expected value of passing, tsumo-only since the hand is furiten afterwards
+    pub pass_ev: f32,
+    /// Unseen copies of our waits once the kan is completed
+    pub waits_left: u8,
+}
+
+impl ChankanAnalysis {
+    pub fn ron(&self) -> bool {
+        self.ron_points.is_some_and(|points| points as f32 > self.pass_ev)
+    }
+}
+
+/// Analysis of a chankan prompt, None for any other decision
+pub fn chankan_analysis(
+    state: &PlayerState,
+    agari: &[(Tile, Option<AgariWithYaku>)],
+    candidates: &[EventCandidate],
+) -> Option<ChankanAnalysis> {
+    state.chankan_chance?;
+    if !state.last_cans.can_agari() {
+        return None;
+    }
+    let tile = state.last_kawa_tile?;
+    let ron_points = agari
+        .iter()
+        .find(|(wait, _)| wait.deaka() == tile.deaka())
+        .and_then(|(_, agari)| agari.as_ref())
+        .map(|agari| agari.agari.point(state.is_oya()).ron + extra_points(state.honba, state.kyotaku));
+    let pass_ev = candidates
+        .iter()
+        .find(|candidate| matches!(candidate.event, Event::None))
+        .or(candidates.first())
+        .and_then(|candidate| candidate.exp_values.first().copied())
+        .unwrap_or(0.0);
+    let waits_left = state
+        .waits
+        .iter()
+        .enumerate()
+        .filter(|&(_, &wait)| wait)
+        .map(|(wait, _)| 4 - state.tiles_seen[wait].min(4))
+        .sum();
+    Some(ChankanAnalysis {
+        tile,
+        ron_points,
+        pass_ev,
+        waits_left,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::algo::agari::Agari;
+    use riichi::state::ActionCandidate;
+    use riichi::t;
+
+    use super::*;
+
+    /// Ko waiting on 3p and 6p as an opponent adds `tile` to a pon
+    fn kakan_prompt(tile: Tile) -> PlayerState {
+        let mut state = PlayerState {
+            oya: 1,
+            honba: 1,
+            kyotaku: 1,
+            chankan_chance: Some(()),
+            last_kawa_tile: Some(tile),
+            last_cans: ActionCandidate {
+                can_ron_agari: true,
+                target_actor: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        state.waits[t!(3p).as_usize()] = true;
+        state.waits[t!(6p).as_usize()] = true;
+        state.tiles_seen[t!(3p).as_usize()] = 4;
+        state.tiles_seen[t!(6p).as_usize()] = 1;
+        state
+    }
+
+    fn pass(exp_value: f32) -> EventCandidate {
+        EventCandidate {
+            event: Event::None,
+            shanten: 0,
+            num_required_tiles: 3,
+            required_tiles: vec![],
+            exp_values: vec![exp_value],
+            win_probs: vec![0.3],
+            tenpai_probs: vec![1.0],
+            yaku: vec![],
+        }
+    }
+
+    fn agari(agari: Agari) -> Option<AgariWithYaku> {
+        // chankan is the only yaku of the ron
+        Some(AgariWithYaku { agari, yaku: vec![3] })
+    }
+
+    #[test]
+    fn robbing_an_added_kan_is_compared_with_passing_it() {
+        let waits = [
+            (t!(3p), agari(Agari::Normal { fu: 40, han: 1 })),
+            (t!(6p), agari(Agari::Normal { fu: 40, han: 1 })),
+        ];
+        let analysis = chankan_analysis(&kakan_prompt(t!(3p)), &waits, &[pass(1200.0)]).unwrap();
+        assert_eq!(analysis.tile, t!(3p));
+        // 1300 with a honba and a riichi stick
+        assert_eq!(analysis.ron_points, Some(2600));
+        assert_eq!(analysis.pass_ev, 1200.0);
+        // the kan takes the last 3p, only 6p are left to tsumo
+        assert_eq!(analysis.waits_left, 3);
+        assert!(analysis.ron());
+
+        let analysis = chankan_analysis(&kakan_prompt(t!(3p)), &waits, &[pass(3000.0)]).unwrap();
+        assert!(!analysis.ron());
+    }
+
+    #[test]
+    fn ron_without_yaku_is_never_taken() {
+        let waits = [(t!(3p), None), (t!(6p), agari(Agari::Normal { fu: 40, han: 1 }))];
+        let analysis = chankan_analysis(&kakan_prompt(t!(3p)), &waits, &[pass(0.0)]).unwrap();
+        assert_eq!(analysis.ron_points, None);
+        assert!(!analysis.ron());
+    }
+
+    #[test]
+    fn closed_kan_is_only_robbed_by_kokushi() {
+        // a closed kan opens no chankan chance for a normal hand
+        let mut state = kakan_prompt(t!(3p));
+        state.chankan_chance = None;
+        state.last_cans.can_ron_agari = false;
+        let waits = [(t!(3p), agari(Agari::Normal { fu: 40, han: 1 }))];
+        assert!(chankan_analysis(&state, &waits, &[pass(1200.0)]).is_none());
+
+        // kokushi waiting on the closed kan's tile may rob it
+        let mut state = kakan_prompt(t!(N));
+        state.waits = [false; 34];
+        state.waits[t!(N).as_usize()] = true;
+        state.tiles_seen[t!(N).as_usize()] = 4;
+        let waits = [(
+            t!(N),
+            Some(AgariWithYaku {
+                agari: Agari::Yakuman(1),
+                yaku: vec![47],
+            }),
+        )];
+        let analysis = chankan_analysis(&state, &waits, &[pass(0.0)]).unwrap();
+        assert_eq!(analysis.ron_points, Some(32000 + 300 + 1000));
+        assert_eq!(analysis.waits_left, 0);
+        assert!(analysis.ron());
+    }
+
+    #[test]
+    fn hand_that_is_not_tenpai_has_nothing_to_rob() {
+        let mut state = kakan_prompt(t!(3p));
+        state.waits = [false; 34];
+        state.last_cans.can_ron_agari = false;
+        assert!(chankan_analysis(&state, &[], &[pass(800.0)]).is_none());
+    }
+}
@@ -0,0 +1,109 @@
+//! Checkpoints for long batch jobs so an interrupted run can resume instead of restarting from zero
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// Files that have been processed and the aggregate over them
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+pub struct Checkpoint<T> {
+    pub processed: BTreeSet<String>,
+    pub aggregate: T,
+}
+
+/// Periodically writes a checkpoint to disk
+pub struct Checkpointer<T> {
+    path: PathBuf,
+    /// Amount of processed files between writes
+    interval: usize,
+    since_last_save: usize,
+    pub checkpoint: Checkpoint<T>,
+}
+
+impl<T: Serialize + DeserializeOwned + Default> Checkpointer<T> {
+    /// Load the checkpoint at `path` when resuming, otherwise start from an empty aggregate
+    pub fn new(path: impl AsRef<Path>, interval: usize, resume: bool) -> Result<Self> {
+        let path = path.as_ref().to_owned();
+        let checkpoint = if resume && path.exists() {
+            let file = std::fs::File::open(&path).with_context(|| format!("failed to open checkpoint {}", path.display()))?;
+            serde_json::from_reader(std::io::BufReader::new(file)).context("malformed checkpoint")?
+        } else {
+            Checkpoint::default()
+        };
+        Ok(Self {
+            path,
+            interval: interval.max(1),
+            since_last_save: 0,
+            checkpoint,
+        })
+    }
+
+    /// Amount of processed files between writes
+    pub fn interval(&self) -> usize {
+        self.interval
+    }
+
+    pub fn is_processed(&self, file: &str) -> bool {
+        self.checkpoint.processed.contains(file)
+    }
+
+    /// Record a processed file, writing the checkpoint every `interval` files
+    pub fn mark_processed(&mut self, file: &str) -> Result<()> {
+        self.checkpoint.processed.insert(file.to_owned());
+        self.since_last_save += 1;
+        if self.since_last_save >= self.interval {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Write the checkpoint through a temporary file so a crash mid-write keeps the previous one intact
+    pub fn save(&mut self) -> Result<()> {
+        let tmp_path = self.path.with_extension("tmp");
+        let file = std::fs::File::create(&tmp_path).with_context(|| format!("failed to create {}", tmp_path.display()))?;
+        let mut writer = std::io::BufWriter::new(file);
+        serde_json::to_writer(&mut writer, &self.checkpoint)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        std::fs::rename(&tmp_path, &self.path).with_context(|| format!("failed to write {}", self.path.display()))?;
+        self.since_last_save = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkpoint_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("washizu-checkpoint-{name}-{}.json", std::process::id()))
+    }
+
+    #[test]
+    fn resume_picks_up_the_saved_files_and_aggregate() {
+        let path = checkpoint_path("resume");
+        let mut checkpointer = Checkpointer::<Vec<u32>>::new(&path, 2, false).unwrap();
+        for (file, value) in [("a", 1), ("b", 2), ("c", 3)] {
+            checkpointer.checkpoint.aggregate.push(value);
+            checkpointer.mark_processed(file).unwrap();
+        }
+        // the third file is past the last write at two files
+        let resumed = Checkpointer::<Vec<u32>>::new(&path, 2, true).unwrap();
+        assert!(resumed.is_processed("a") && resumed.is_processed("b") && !resumed.is_processed("c"));
+        assert_eq!(resumed.checkpoint.aggregate, [1, 2]);
+        let restarted = Checkpointer::<Vec<u32>>::new(&path, 2, false).unwrap();
+        assert!(restarted.checkpoint.processed.is_empty());
+        std::fs::remove_file(&path).unwrap();
+        assert!(!path.with_extension("tmp").exists());
+    }
+
+    #[test]
+    fn resume_without_a_checkpoint_starts_empty() {
+        let checkpointer = Checkpointer::<Vec<u32>>::new(checkpoint_path("missing"), 0, true).unwrap();
+        assert_eq!(checkpointer.interval(), 1);
+        assert!(checkpointer.checkpoint.aggregate.is_empty());
+    }
+}
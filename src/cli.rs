@@ -0,0 +1,1022 @@
+//! Command line interface of the washizu binary
+use std::io::{BufRead, Read, Write};
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+
+use crate::agari::{AgariArgs, score_hand};
+use crate::batch::{CHECKPOINT_INTERVAL, batch_csv, batch_summary, load_log, log_files, review_games, review_games_checkpointed};
+use crate::checkpoint::Checkpointer;
+use crate::crosscheck::{CrossCheckThresholds, cross_check_summary};
+use crate::daemon::run_daemon;
+use crate::data::verify_data;
+use crate::db::Db;
+use crate::decisions::{
+    Seats, all_seat_decisions, decisions_from_events, decisions_from_log, detect_player_id, state_from_events,
+};
+use crate::duplicate::duplicate_log;
+use crate::ekyumoe::{EkyuMoeReview, open_log, read_ekyumoe_logs, validate_details};
+use crate::error::{WashizuError, exit_code};
+use crate::glyphs::TileStyle;
+use crate::html::render_review;
+use crate::i18n::{Lang, localize_command, report_error};
+use crate::input::{HandArgs, state_from_hand_args};
+use crate::live::{LiveSession, run_websocket};
+use crate::mjaigen::{board_from_events, mjai_lines, parse_board, parse_board_json};
+use crate::oracle::{future_draws, oracle_outcomes};
+use crate::plugin::{DEFAULT_PLUGIN_TIMEOUT, Plugin};
+use crate::profile::{Profiler, profile_summary};
+use crate::render::{LiveRenderer, RenderMode};
+use crate::repl::run_repl;
+use crate::review::{BoardSnapshot, LossFilter, ReviewDecision, record_response, review_summary};
+use crate::rollout::rollout;
+use crate::rules::{OpenRiichi, RuleSet};
+use crate::schema::schema_text;
+use crate::scorer::{Objective, parse_scorer};
+use crate::scoring::RankPoints;
+use crate::seek::{Seek, SeekArgs, StateHistory};
+use crate::simulate::simulate_waits;
+use crate::spectator::Spectator;
+use crate::state::{AnalysisOptions, ExpandedState};
+use crate::stats::PlayerStats;
+use crate::timer::timing_summary;
+use crate::tradeoff::DealInPenalty;
+use crate::transparent::{VisibleTiles, relative_visible};
+use crate::tree::{TreeSpec, export_tree};
+use crate::tui::run_tui;
+use crate::uradora::UraDora;
+use crate::variant::parse_event;
+use crate::yakunames::YakuNaming;
+
+#[derive(Parser, Debug)]
+#[command(name = "washizu")]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+    /// Discount later tsumos by the chance of a four-riichi, four-kan or nine terminals abortive draw
+    #[arg(long, global = true)]
+    abortive_draws: bool,
+    /// Value a kyoku ending without a win by the tenpai and noten payments at the exhaustive draw
+    #[arg(long, global = true)]
+    draw_payments: bool,
+    /// Charge kan candidates for the dora the new indicator gives the opponents
+    #[arg(long, global = true)]
+    kan_dora_cost: bool,
+    /// Upper bound for the shanten analyzed by the single player calculator
+    #[arg(long, global = true)]
+    max_shanten: Option<i8>,
+    /// Show the least dangerous discard that keeps the current shanten
+    #[arg(long, global = true)]
+    safe_keep: bool,
+    /// Rules of the room: tenhou, majsoul or a toml file overriding tenhou rules (aka, kuitan, double_yakuman,
+    /// kazoe_yakuman, kiriage and the local yaku renhou = "mangan"/"yakuman" and daisharin)
+    #[arg(long, global = true, default_value = "tenhou")]
+    rules: String,
+    /// Allow open riichi, adding a han over riichi and revealing the hand
+    #[arg(long, global = true)]
+    open_riichi: bool,
+    /// Riichi declared open as <relative player>:<waits>, e.g. 1:36m, use 0: for our own riichi
+    #[arg(long, global = true, requires = "open_riichi")]
+    open_riichi_waits: Vec<String>,
+    /// Washizu mahjong with transparent tiles, live logs may contain transparent events with the visible tiles
+    #[arg(long, global = true)]
+    washizu: bool,
+    /// Transparent tiles in an opponent's hand as <relative player>:<tiles>, e.g. 1:123m55z
+    #[arg(long, global = true, requires = "washizu")]
+    visible: Vec<String>,
+    /// Three player rules with kita, logs may contain nukidora events
+    #[arg(long, global = true)]
+    sanma: bool,
+    /// Kita we have already set aside, tracked from nukidora events in live mode
+    #[arg(long, global = true, requires = "sanma", default_value_t = 0)]
+    nukidora: u8,
+    /// Rank candidates by a weighted sum of scorers (ev, win, placement, safety), e.g. ev+safety*100
+    #[arg(long, global = true)]
+    scorer: Option<String>,
+    /// Rank candidates by raw expected points or by expected rank points at the end of the game
+    #[arg(long, global = true, value_enum, default_value_t = Objective::Points, conflicts_with = "scorer")]
+    objective: Objective,
+    /// Rank points from first to fourth used by the placement objective
+    #[arg(long, global = true, default_value = "90,45,0,-135")]
+    pt: String,
+    /// Warn when the best candidate leaves fewer genbutsu than this against a riichi, 0 disables the warning
+    #[arg(long, global = true, default_value_t = 2)]
+    safety_floor: u8,
+    /// Estimated score paid when dealing into a non-dealer riichi, used for the defense-aware expected values
+    #[arg(long, global = true, default_value_t = DealInPenalty::default().non_dealer)]
+    deal_in_penalty: f32,
+    /// Estimated score paid when dealing into a dealer riichi
+    #[arg(long, global = true, default_value_t = DealInPenalty::default().dealer)]
+    dealer_deal_in_penalty: f32,
+    /// Command receiving every analysis as a line of json on stdin and printing a json object of extra fields for each,
+    /// kept running between analyses, can be repeated
+    #[arg(long, global = true)]
+    plugin: Vec<String>,
+    /// Milliseconds a plugin has to answer before it is killed and its fields are skipped
+    #[arg(long, global = true, default_value_t = DEFAULT_PLUGIN_TIMEOUT.as_millis() as u64)]
+    plugin_timeout: u64,
+    /// Estimate discards of 3+ shanten hands by ukeire, dora kept and safety instead of the slow single player tables
+    #[arg(long, global = true)]
+    fast: bool,
+    /// Milliseconds the single player tables may take before the fast estimates are shown instead, e.g. in live mode
+    #[arg(long, global = true)]
+    sp_budget_ms: Option<u64>,
+    /// Break down the advancing and upgrade tiles of every discard, trying every draw after every discard
+    #[arg(long, global = true)]
+    ukeire: bool,
+    /// Check every analysis against the bundled JSON schema of the report and abort on a mismatch
+    #[arg(long, global = true)]
+    validate_output: bool,
+    /// Print the top of the search tree of a hand or board instead of the analysis, e.g. depth=2,width=3,format=dot
+    #[arg(long, global = true)]
+    export_tree: Option<String>,
+    /// Also write the mjai events of the board, board-json or ekyumoe input to this file, one event per line
+    #[arg(long, global = true)]
+    emit_mjai: Option<String>,
+    /// Run the offensive analysis even for hopeless hands against a riichi instead of switching to a fold plan
+    #[arg(long, global = true)]
+    no_auto_fold: bool,
+    /// Rank the hand by safety against a riichi or three calls when the best candidate is worth less, 0 disables it
+    #[arg(long, global = true, default_value_t = 1500.0)]
+    betaori_ev: f32,
+    /// Print the time spent in the single player calculation, danger and agari of every decision when done
+    #[arg(long, global = true)]
+    profile: bool,
+    /// Leave ura dora out of the riichi wins instead of averaging them in
+    #[arg(long, global = true)]
+    no_ura: bool,
+    /// Ura indicators of a finished hand, counted in the values and the agari of the waits instead of the average, e.g. 3p
+    #[arg(long, global = true, conflicts_with = "no_ura")]
+    ura_known: Option<String>,
+    /// Red fives in the wall, overriding the rules: 0 or 3
+    #[arg(long, global = true, value_parser = PossibleValuesParser::new(["0", "3"]).try_map(|s| s.parse::<u8>()))]
+    aka_count: Option<u8>,
+    /// Naming scheme of yaku, defaults to japanese for --lang ja and romaji otherwise
+    #[arg(long, global = true, value_enum)]
+    yaku_names: Option<YakuNaming>,
+    /// Display style of the tiles in the analysis, e.g. unicode for the mahjong glyphs
+    #[arg(long, global = true, value_enum, default_value_t = TileStyle::Ascii)]
+    tiles: TileStyle,
+    /// Print the analysis without colors, also disabled by NO_COLOR or when stdout is not a terminal
+    #[arg(long, global = true)]
+    no_color: bool,
+    /// Play out the rest of the kyoku this many times after each discard candidate, 0 disables the rollouts
+    #[arg(long, global = true, default_value_t = 0)]
+    rollout: usize,
+    /// ONNX export of a mortal model whose action probabilities are shown for decisions without ekyumoe details
+    #[cfg(feature = "mortal")]
+    #[arg(long, global = true)]
+    mortal_model: Option<String>,
+    /// Observation encoding version the mortal model was trained with
+    #[cfg(feature = "mortal")]
+    #[arg(long, global = true, default_value_t = crate::model::DEFAULT_OBS_VERSION)]
+    mortal_version: u32,
+    /// Language of help and error messages, defaults to WASHIZU_LANG or LANG
+    #[arg(long, global = true, value_enum)]
+    lang: Option<Lang>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    Hand(HandArgs),
+    /// Score a winning hand: yaku, fu breakdown and payments
+    Agari(AgariArgs),
+    Board {
+        args: Vec<String>,
+        #[command(flatten)]
+        seek: SeekArgs,
+        /// Analyze the decisions of every seat, other hands are filled in from the unseen tiles
+        #[arg(long)]
+        all_seats: bool,
+    },
+    /// Analyze a board in the JSON format of `washizu schema board`, `-` for stdin
+    BoardJson {
+        path: String,
+        #[command(flatten)]
+        seek: SeekArgs,
+        /// Analyze the decisions of every seat, other hands are filled in from the unseen tiles
+        #[arg(long)]
+        all_seats: bool,
+    },
+    Parse {
+        args: Vec<String>,
+    },
+    /// Print the board of the current kyoku of an mjai log in the notation of the board command, `-` for stdin
+    Export {
+        path: String,
+        /// Seat of the player, detected from StartGame when omitted
+        #[arg(long)]
+        player_id: Option<u8>,
+        /// Detect the seat by matching a name in StartGame
+        #[arg(long)]
+        player_name: Option<String>,
+    },
+    Live {
+        /// Seat of the player, detected from StartGame when omitted
+        player_id: Option<u8>,
+        /// Detect the seat by matching a name in StartGame
+        #[arg(long)]
+        player_name: Option<String>,
+        /// Draw on the alternate screen and leave the last analysis on the normal screen on exit
+        #[arg(long)]
+        alternate_screen: bool,
+        /// Append every analysis instead of redrawing the screen
+        #[arg(long, conflicts_with = "alternate_screen")]
+        no_clear: bool,
+        /// Accept events over websockets on this address and answer with json analysis instead of reading stdin
+        #[arg(long, conflicts_with_all = ["alternate_screen", "no_clear"])]
+        listen: Option<String>,
+        /// Comment on every seat of a stream that reveals all hands instead of analyzing one player
+        #[arg(long, conflicts_with = "listen")]
+        spectator: bool,
+        /// Review database to store the think times of timestamped events in
+        #[arg(long, conflicts_with = "listen")]
+        db: Option<String>,
+    },
+    /// Enter and modify a hand at a prompt, analyzing it on demand
+    Repl,
+    /// Print the JSON schema of an input or output format: analysis-report, board, candidate or danger
+    Schema {
+        name: String,
+    },
+    /// Step through the decisions of an mjai log interactively
+    Tui {
+        /// Mjai log with one event per line, only the hand editor is available when omitted
+        path: Option<String>,
+        /// Seat of the player, detected from StartGame when omitted
+        #[arg(long)]
+        player_id: Option<u8>,
+        /// Detect the seat by matching a name in StartGame
+        #[arg(long)]
+        player_name: Option<String>,
+    },
+    Ekyumoe(EkyumoeArgs),
+    /// Review every ekyumoe or mjai log in a directory and summarize EV loss, mistake categories and deal-ins
+    Batch {
+        dir: String,
+        /// Detect the reviewed seat by matching a name in StartGame, required for mjai logs without a player id
+        #[arg(long)]
+        player_name: Option<String>,
+        /// Also write one row per game to this CSV file
+        #[arg(long)]
+        csv: Option<String>,
+        /// Record the reviewed games in this file as they finish, keep it outside of the directory
+        #[arg(long)]
+        checkpoint: Option<String>,
+        /// Skip the games already recorded in the checkpoint of an interrupted run
+        #[arg(long, requires = "checkpoint")]
+        resume: bool,
+    },
+    /// Compare the actions of a log against the engine's at every decision and sum the EV difference
+    Duplicate {
+        /// Ekyumoe review or mjai log
+        log: String,
+        /// Detect the reviewed seat by matching a name in StartGame, required for mjai logs without a player id
+        #[arg(long)]
+        player_name: Option<String>,
+    },
+    /// Play out the rest of the kyoku from a board with a heuristic agent in every seat
+    Rollout {
+        /// Board in the same format as the board command
+        #[arg(long)]
+        board: String,
+        /// Number of playouts
+        #[arg(long, default_value_t = 1000)]
+        n: usize,
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Enumerate the tenpai hands an opponent could hold on a board and show the distribution of their waits and values
+    SimulateWaits {
+        /// Board in the same format as the board command
+        #[arg(long)]
+        board: String,
+        /// Opponent relative to the board's player, 1 is shimocha
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=3))]
+        opponent: u8,
+    },
+    Daemon {
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: String,
+        /// Number of requests handled concurrently, defaults to the number of cpus
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+    /// Riichi, call, win and deal-in rates, average hand values and placements over logs or directories of logs
+    Stats {
+        paths: Vec<String>,
+        /// Detect the seat by matching a name in StartGame, required for mjai logs without a player id
+        #[arg(long)]
+        player_name: Option<String>,
+    },
+    /// Check the table files of a data directory against the checksums in its checksums.txt
+    VerifyData {
+        dir: String,
+    },
+    /// Review database of analyzed logs
+    Db {
+        /// Database file, created when missing
+        #[arg(long, default_value = "washizu-db.json")]
+        db: String,
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DbCommand {
+    /// Analyze mjai logs and store them
+    Add {
+        paths: Vec<String>,
+        #[arg(long)]
+        player_id: Option<u8>,
+        #[arg(long)]
+        player_name: Option<String>,
+    },
+    /// Recompute stored analyses with the current configuration
+    Reanalyze {
+        /// Only entries produced under a different model, ruleset or report version
+        #[arg(long)]
+        stale: bool,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+pub struct EkyumoeArgs {
+    /// Review exported by ekyumoe, `-` for stdin, optionally gzip-compressed and with several reviews back to back
+    path: String,
+    /// Detect the seat by matching a name in StartGame instead of trusting the log's player id
+    #[arg(long)]
+    player_name: Option<String>,
+    /// Review this seat instead of the log's player, without mortal's details unless it is the reviewed one
+    #[arg(long, conflicts_with = "player_name")]
+    player: Option<u8>,
+    /// Only print the decisions where the action taken loses EV against the best candidate
+    #[arg(long, conflicts_with = "step")]
+    only_mistakes: bool,
+    /// Only print the decisions where the action taken loses at least this much EV
+    #[arg(long, conflicts_with = "step")]
+    min_ev_loss: Option<f32>,
+    /// Write a self-contained HTML review of the game
+    #[arg(long)]
+    report: Option<String>,
+    /// Print the decisions that lost expected value, sorted by severity
+    #[arg(long)]
+    review: bool,
+    /// List the decisions where mortal's choice loses at least this EV in washizu's analysis or differs in danger
+    #[arg(long)]
+    cross_check: Option<f32>,
+    /// Also list the decisions where mortal's discard and washizu's differ by at least this deal-in probability in percent
+    #[arg(long, requires = "cross_check", default_value_t = 10.0)]
+    cross_check_danger: f32,
+    /// Exit when a decision's details are corrupt instead of warning and analyzing it without mortal's details
+    #[arg(long)]
+    strict_details: bool,
+    #[command(flatten)]
+    seek: SeekArgs,
+    /// Also analyze the decisions of the other seats, without mortal's details
+    #[arg(long)]
+    all_seats: bool,
+    /// Replay every discard against the tiles actually drawn afterwards
+    #[arg(long)]
+    oracle: bool,
+    /// Comment on every seat with the drawn tiles of the log instead of reviewing one player
+    #[arg(long)]
+    spectator: bool,
+}
+
+pub fn single_hand_analysis(args: HandArgs, options: &AnalysisOptions) -> Result<()> {
+    let state = state_from_hand_args(args)?;
+    println!("{}", ExpandedState::from_state(state.clone(), None, options)?.to_log_string());
+    Ok(())
+}
+
+/// Text of a JSON board file, `-` for stdin
+fn read_board_json(path: &str) -> Result<String> {
+    let mut text = String::new();
+    open_log(path)?
+        .read_to_string(&mut text)
+        .with_context(|| format!("failed to read {path}"))?;
+    Ok(text)
+}
+
+pub fn board_analysis(events: Vec<Event>, seek_args: &SeekArgs, all_seats: bool, options: &AnalysisOptions) -> Result<()> {
+    let seek = Seek::from_args(seek_args)?;
+    if !seek.is_set() && !seek_args.step && !all_seats {
+        let state = state_from_events(events.clone())?;
+        println!("board: {}", board_from_events(&events, state.player_id)?.to_board_string());
+        println!("{}", ExpandedState::from_state(state.clone(), None, options)?.to_log_string());
+        return Ok(());
+    }
+
+    let show = |(event, state): &(Event, PlayerState)| -> Result<()> {
+        if all_seats {
+            println!("\nseat {} after {event:?}", state.player_id);
+        } else {
+            println!("\n{event:?}");
+        }
+        println!("{}", ExpandedState::from_state(state.clone(), None, options)?.to_log_string());
+        Ok(())
+    };
+    let decisions = if all_seats {
+        all_seat_decisions(&events)?
+    } else {
+        decisions_from_events(events, None, None)?
+    };
+    let mut history = StateHistory::default();
+    for decision in decisions {
+        if !seek.matches(&decision.1) {
+            continue;
+        }
+        show(&decision)?;
+        history.push(decision);
+        if seek_args.step && !history.step(show)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+pub fn main_live_analysis(
+    player_id: Option<u8>,
+    player_name: Option<&str>,
+    render_mode: RenderMode,
+    db: Option<&str>,
+    options: &AnalysisOptions,
+) -> Result<()> {
+    let mut session = LiveSession::new(player_id, player_name, options);
+    let mut renderer = LiveRenderer::new(render_mode);
+    let mut games_shown = 0;
+    let stdin = std::io::stdin();
+    for line in stdin.lock().lines() {
+        let Ok(l) = line else {
+            eprintln!("failed to read line");
+            continue;
+        };
+        // a bad event is reported and skipped, the stream goes on with the next one
+        let handled = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| session.handle_line(&l)))
+            .unwrap_or_else(|_| Err(anyhow!("analysis panicked at {l}")));
+        if session.games().len() > games_shown {
+            games_shown = session.games().len();
+            let summary = &session.games()[games_shown - 1];
+            if let Err(e) = renderer.render(&format!("game {games_shown}: {}", summary.to_log_string())) {
+                eprintln!("failed to render: {e}");
+            }
+        }
+        let expanded = match handled {
+            Ok(Some(expanded)) => expanded,
+            Ok(None) => continue,
+            Err(e) => {
+                eprintln!("skipping event: {e:#}");
+                continue;
+            }
+        };
+        let board = session
+            .board_string()
+            .map(|board| format!("board: {board}\n"))
+            .unwrap_or_default();
+        if let Err(e) = renderer.render(&format!("{board}{}", expanded.to_log_string())) {
+            eprintln!("failed to render: {e}");
+        }
+    }
+    if let Some(summary) = timing_summary(session.timings()) {
+        println!("{summary}");
+    }
+    if let Some(db) = db
+        && !session.timings().is_empty()
+    {
+        let path = std::path::Path::new(db);
+        let mut database = Db::load(path)?;
+        database.timings.extend_from_slice(session.timings());
+        database.save(path)?;
+    }
+    Ok(())
+}
+
+pub fn main_spectator_analysis(render_mode: RenderMode, options: &AnalysisOptions) {
+    let mut spectator = Spectator::new(options);
+    let mut renderer = LiveRenderer::new(render_mode);
+    for line in std::io::stdin().lock().lines() {
+        let Ok(l) = line else {
+            eprintln!("failed to read line");
+            continue;
+        };
+        let event = match parse_event(&l) {
+            Ok(event) => event,
+            Err(e) => {
+                eprintln!("skipping event: {e:#}");
+                continue;
+            }
+        };
+        match spectator.update(&event, None) {
+            Ok(comments) if comments.is_empty() => {}
+            Ok(comments) => {
+                if let Err(e) = renderer.render(&format!("{event:?}\n{}", comments.join("\n"))) {
+                    eprintln!("failed to render: {e}");
+                }
+            }
+            Err(e) => eprintln!("{e:#}"),
+        }
+    }
+}
+
+/// Write the events of an imported game as a mjai log when requested
+fn emit_mjai(path: Option<&str>, events: &[Event]) -> Result<()> {
+    if let Some(path) = path {
+        std::fs::write(path, mjai_lines(events)?).with_context(|| format!("failed to write {path}"))?;
+    }
+    Ok(())
+}
+
+pub fn main_ekyumoe_analysis(args: &EkyumoeArgs, emit_mjai: Option<&str>, options: &AnalysisOptions) -> Result<()> {
+    let mut decisions: Vec<ReviewDecision> = vec![];
+    // the games of a session are written back to back
+    let mut emitted = emit_mjai
+        .map(|path| std::fs::File::create(path).with_context(|| format!("failed to write {path}")))
+        .transpose()?;
+    for (game, log) in read_ekyumoe_logs(&args.path)?.enumerate() {
+        if game > 0 {
+            println!("\ngame {}", game + 1);
+        }
+        let log = log?;
+        if let Some(file) = &mut emitted {
+            file.write_all(mjai_lines(&log.mjai_log)?.as_bytes())?;
+        }
+        main_ekyumoe_game(args, log, options, &mut decisions)?;
+    }
+    if args.review {
+        println!("\n{}", review_summary(&decisions));
+    }
+    if let Some(ev) = args.cross_check {
+        let thresholds = CrossCheckThresholds {
+            ev,
+            danger: args.cross_check_danger,
+        };
+        println!("\n{}", cross_check_summary(&decisions, thresholds));
+    }
+    if let Some(report) = &args.report {
+        std::fs::write(report, render_review(&args.path, &decisions)).with_context(|| format!("failed to write {report}"))?;
+    }
+    Ok(())
+}
+
+/// Analyze one review of a log, the decisions of the report are collected across the reviews of a session
+fn main_ekyumoe_game(
+    args: &EkyumoeArgs,
+    mut log: EkyuMoeReview,
+    options: &AnalysisOptions,
+    decisions: &mut Vec<ReviewDecision>,
+) -> Result<()> {
+    let seek = Seek::from_args(&args.seek)?;
+    if args.spectator {
+        let mut spectator = Spectator::new(options);
+        for (index, event) in log.mjai_log.iter().enumerate() {
+            let comments = spectator.update(event, Some(&log.mjai_log[index + 1..]))?;
+            if !comments.is_empty() {
+                println!("\n{event:?}\n{}", comments.join("\n"));
+            }
+        }
+        return Ok(());
+    }
+    if args.player.is_some() || args.player_name.is_some() {
+        let start_game = log.mjai_log.first().context("empty mjai log")?;
+        let player_id = detect_player_id(start_game, args.player, args.player_name.as_deref())?;
+        if player_id != log.player_id {
+            eprintln!(
+                "reviewing player {player_id} but the review is for player {}, ignoring review",
+                log.player_id
+            );
+            log.player_id = player_id;
+            log.review.kyokus.clear();
+        }
+    }
+    let mut state = PlayerState::new(log.player_id);
+    let mut seats = args.all_seats.then(Seats::default);
+    let details_by_event = log.details_by_event()?;
+
+    let pb = if !console::user_attended() {
+        Some(indicatif::ProgressBar::new(log.mjai_log.len() as u64))
+    } else {
+        None
+    };
+    let mut history = StateHistory::default();
+    let filter = LossFilter {
+        only_mistakes: args.only_mistakes,
+        min_ev_loss: args.min_ev_loss,
+    };
+    // with a loss filter a decision is printed once the action taken is known
+    let mut pending = None;
+    for (index, (event, details)) in log.mjai_log.iter().zip(details_by_event).enumerate() {
+        if let Some(ref pb) = pb {
+            pb.inc(1);
+        }
+        record_response(decisions, event, state.player_id);
+        if let Some(decision) = decisions.last()
+            && decision.actual.is_some()
+            && let Some(output) = pending.take()
+            && filter.matches(decision)
+        {
+            println!("{output}");
+        }
+        state.update(event).with_context(|| WashizuError::state_update(event))?;
+        if !seek.is_set() && !filter.is_set() {
+            println!("\n{event:?}");
+        }
+        if let Some(seats) = &mut seats {
+            seats.update(event)?;
+            for other in seats
+                .deciding()
+                .filter(|other| other.player_id != state.player_id && seek.matches(other))
+            {
+                println!("\nseat {} after {event:?}", other.player_id);
+                println!("{}", ExpandedState::from_state(other.clone(), None, options)?.to_log_string());
+            }
+        }
+        if !state.last_cans.can_act() || !seek.matches(&state) {
+            continue;
+        }
+        let mut output = vec![];
+        if seek.is_set() || filter.is_set() {
+            output.push(format!("\n{event:?}"));
+        }
+        let details = match details.map(|details| details.and_then(|details| validate_details(&state, &details).map(|_| details)))
+        {
+            Some(Err(e)) if !args.strict_details => {
+                eprintln!(
+                    "warning: skipping corrupt ekyumoe details kyoku={} turn={} event={event:?} error={e:#}",
+                    state.kyoku, state.at_turn
+                );
+                None
+            }
+            details => details.transpose()?,
+        };
+        let expanded = ExpandedState::from_state(state.clone(), details.clone(), options)?;
+        output.push(expanded.to_log_string());
+        if args.oracle {
+            let draws = future_draws(&log.mjai_log[index + 1..], state.player_id);
+            let outcomes = oracle_outcomes(&state, &draws);
+            if !outcomes.is_empty() {
+                output.push(format!(
+                    "oracle: {}",
+                    outcomes
+                        .iter()
+                        .map(|outcome| outcome.to_log_string())
+                        .collect::<Vec<_>>()
+                        .join(" | ")
+                ));
+            }
+        }
+        if filter.is_set() {
+            pending = Some(output.join("\n"));
+        } else {
+            println!("{}", output.join("\n"));
+        }
+        if args.seek.step {
+            history.push((event.clone(), state.clone(), details));
+        }
+        if args.report.is_some() || args.review || args.cross_check.is_some() || filter.is_set() {
+            decisions.push(ReviewDecision {
+                trigger: event.clone(),
+                board: BoardSnapshot::from_state(&state),
+                report: expanded.to_report(),
+                actual: None,
+            });
+        }
+        if args.seek.step
+            && !history.step(|(event, state, details)| {
+                println!("\n{event:?}");
+                println!(
+                    "{}",
+                    ExpandedState::from_state(state.clone(), details.clone(), options)?.to_log_string()
+                );
+                Ok(())
+            })?
+        {
+            break;
+        }
+    }
+    // the log ended before the response, nothing rules the decision out
+    if let Some(output) = pending {
+        println!("{output}");
+    }
+    if let Some(ref pb) = pb {
+        pb.finish();
+    }
+    Ok(())
+}
+
+pub fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let lang = Lang::detect(&args);
+    let matches = localize_command(Cli::command(), lang).get_matches_from(args);
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Err(e) = run(cli, lang) {
+        report_error(&e, lang);
+        std::process::exit(exit_code(&e));
+    }
+}
+
+/// Analysis options of the global flags
+fn analysis_options(cli: &Cli, lang: Lang) -> Result<AnalysisOptions> {
+    let deal_in_penalty = DealInPenalty {
+        non_dealer: cli.deal_in_penalty,
+        dealer: cli.dealer_deal_in_penalty,
+    };
+    Ok(AnalysisOptions {
+        abortive_draws: cli.abortive_draws,
+        draw_payments: cli.draw_payments,
+        kan_dora_cost: cli.kan_dora_cost,
+        max_shanten: cli.max_shanten,
+        safe_keep: cli.safe_keep,
+        rules: {
+            let rules = RuleSet::load(&cli.rules)?;
+            RuleSet {
+                open_riichi: rules.open_riichi || cli.open_riichi,
+                sanma: rules.sanma || cli.sanma,
+                washizu: rules.washizu || cli.washizu,
+                aka: cli.aka_count.unwrap_or(rules.aka),
+                ..rules
+            }
+        },
+        open_riichi: cli
+            .open_riichi_waits
+            .iter()
+            .map(|s| OpenRiichi::parse(s))
+            .collect::<Result<_>>()?,
+        scorer: match cli.objective {
+            Objective::Points => cli.scorer.as_deref().map(parse_scorer).transpose()?,
+            objective => objective.scorer(RankPoints::parse(&cli.pt)?, deal_in_penalty.typical_hand()),
+        },
+        safety_floor: cli.safety_floor,
+        nukidora: cli.nukidora,
+        deal_in_penalty,
+        passed_tiles: Default::default(),
+        visible_tiles: relative_visible(
+            &cli.visible
+                .iter()
+                .map(|s| VisibleTiles::parse(s))
+                .collect::<Result<Vec<_>>>()?,
+        ),
+        fast: cli.fast,
+        sp_budget: cli.sp_budget_ms.map(Duration::from_millis),
+        ukeire: cli.ukeire,
+        plugins: cli
+            .plugin
+            .iter()
+            .map(|s| Plugin::parse(s, Duration::from_millis(cli.plugin_timeout)))
+            .collect::<Result<_>>()?,
+        validate_output: cli.validate_output,
+        no_auto_fold: cli.no_auto_fold,
+        betaori_ev: cli.betaori_ev,
+        profiler: cli.profile.then(Profiler::default),
+        ura_dora: UraDora::parse(cli.no_ura, cli.ura_known.as_deref())?,
+        yaku_naming: cli.yaku_names.unwrap_or(match lang {
+            Lang::Ja => YakuNaming::Japanese,
+            Lang::En => YakuNaming::Romaji,
+        }),
+        tile_style: cli.tiles,
+        color: !cli.no_color && console::colors_enabled(),
+        rollout: cli.rollout,
+        #[cfg(feature = "mortal")]
+        mortal_model: match &cli.mortal_model {
+            Some(path) => Some(std::sync::Arc::new(crate::model::MortalModel::load(
+                std::path::Path::new(path),
+                cli.mortal_version,
+            )?)),
+            None => None,
+        },
+    })
+}
+
+fn run(cli: Cli, lang: Lang) -> Result<()> {
+    let options = analysis_options(&cli, lang).context(WashizuError::Options)?;
+    let profiler = options.profiler.clone();
+    let result = run_command(cli, options);
+    if let Some(profiler) = profiler {
+        eprintln!("{}", profile_summary(&profiler.profiles()));
+    }
+    result
+}
+
+/// Output flags are global for the help but only the commands reading a single board produce trees or mjai events
+fn check_output_flags(cli: &Cli) -> Result<()> {
+    let board = matches!(cli.command, Commands::Board { .. } | Commands::BoardJson { .. });
+    if cli.export_tree.is_some() && !board && !matches!(cli.command, Commands::Hand(_)) {
+        bail!("--export-tree only applies to the hand, board and board-json commands");
+    }
+    if cli.emit_mjai.is_some() && !board && !matches!(cli.command, Commands::Ekyumoe(_)) {
+        bail!("--emit-mjai only applies to the board, board-json and ekyumoe commands");
+    }
+    Ok(())
+}
+
+fn run_command(cli: Cli, options: AnalysisOptions) -> Result<()> {
+    check_output_flags(&cli).context(WashizuError::Options)?;
+    let tree_spec = cli
+        .export_tree
+        .as_deref()
+        .map(TreeSpec::parse)
+        .transpose()
+        .context(WashizuError::Options)?;
+    match cli.command {
+        Commands::Hand(args) if let Some(spec) = tree_spec => {
+            let state = state_from_hand_args(args)?;
+            println!("{}", export_tree(&state, &spec, &options)?);
+        }
+        Commands::Board { args, .. } if let Some(spec) = tree_spec => {
+            let events = parse_board(args.iter().map(|s| s.as_str()).collect())?;
+            emit_mjai(cli.emit_mjai.as_deref(), &events)?;
+            println!("{}", export_tree(&state_from_events(events)?, &spec, &options)?);
+        }
+        Commands::BoardJson { path, .. } if let Some(spec) = tree_spec => {
+            let events = parse_board_json(&read_board_json(&path)?)?;
+            emit_mjai(cli.emit_mjai.as_deref(), &events)?;
+            println!("{}", export_tree(&state_from_events(events)?, &spec, &options)?);
+        }
+        Commands::Live {
+            player_id,
+            player_name,
+            alternate_screen,
+            no_clear,
+            listen,
+            spectator,
+            db,
+        } => {
+            if let Some(listen) = listen {
+                run_websocket(&listen, player_id, player_name.as_deref(), &options)?;
+                return Ok(());
+            }
+            let render_mode = if no_clear {
+                RenderMode::Append
+            } else if alternate_screen {
+                RenderMode::Alternate
+            } else {
+                RenderMode::Diff
+            };
+            if spectator {
+                main_spectator_analysis(render_mode, &options);
+                return Ok(());
+            }
+            main_live_analysis(player_id, player_name.as_deref(), render_mode, db.as_deref(), &options)?;
+        }
+        Commands::Tui {
+            path,
+            player_id,
+            player_name,
+        } => {
+            let decisions = match path {
+                Some(path) => decisions_from_log(&path, player_id, player_name.as_deref())?,
+                None => vec![],
+            };
+            run_tui(decisions, options)?;
+        }
+        Commands::Ekyumoe(args) => {
+            main_ekyumoe_analysis(&args, cli.emit_mjai.as_deref(), &options)?;
+        }
+        Commands::Batch {
+            dir,
+            player_name,
+            csv,
+            checkpoint,
+            resume,
+        } => {
+            let paths = log_files(std::path::Path::new(&dir))?;
+            let (mut games, failures) = match checkpoint {
+                Some(checkpoint) => {
+                    let mut checkpointer = Checkpointer::new(checkpoint, CHECKPOINT_INTERVAL, resume)?;
+                    let failures = review_games_checkpointed(&paths, player_name.as_deref(), &options, &mut checkpointer)?;
+                    (checkpointer.checkpoint.aggregate, failures)
+                }
+                None => {
+                    let (mut games, mut failures) = (vec![], vec![]);
+                    for (path, result) in review_games(&paths, player_name.as_deref(), &options) {
+                        match result {
+                            Ok(game) => games.push(game),
+                            Err(e) => failures.push((path, e)),
+                        }
+                    }
+                    (games, failures)
+                }
+            };
+            for (path, e) in failures {
+                eprintln!("skipping {}: {e:#}", path.display());
+            }
+            // resumed games come first in the checkpoint
+            games.sort_by(|a, b| a.log.cmp(&b.log));
+            print!("{}", batch_summary(&games));
+            if let Some(csv) = csv {
+                std::fs::write(&csv, batch_csv(&games))?;
+            }
+        }
+        Commands::Duplicate { log, player_name } => {
+            let comparison = duplicate_log(std::path::Path::new(&log), player_name.as_deref(), &options)?;
+            println!("{}", comparison.to_log_string());
+        }
+        Commands::Repl => run_repl(&options),
+        Commands::Schema { name } => print!("{}", schema_text(&name)?),
+        Commands::Hand(args) => {
+            single_hand_analysis(args, &options)?;
+        }
+        Commands::Agari(args) => println!("{}", score_hand(&args, &options.rules, options.yaku_naming)?.to_log_string()),
+        Commands::Board { args, seek, all_seats } => {
+            let events = parse_board(args.iter().map(|s| s.as_str()).collect())?;
+            emit_mjai(cli.emit_mjai.as_deref(), &events)?;
+            board_analysis(events, &seek, all_seats, &options)?;
+        }
+        Commands::BoardJson { path, seek, all_seats } => {
+            let events = parse_board_json(&read_board_json(&path)?)?;
+            emit_mjai(cli.emit_mjai.as_deref(), &events)?;
+            board_analysis(events, &seek, all_seats, &options)?;
+        }
+        Commands::Rollout { board, n, seed } => {
+            let state = state_from_events(parse_board(board.split_whitespace().collect())?)?;
+            println!("{}", rollout(&state, n, seed).to_log_string());
+        }
+        Commands::SimulateWaits { board, opponent } => {
+            let state = state_from_events(parse_board(board.split_whitespace().collect())?)?;
+            println!("{}", simulate_waits(&state, opponent as usize).to_log_string());
+        }
+        Commands::Daemon { listen, threads } => {
+            let threads = threads.unwrap_or_else(|| std::thread::available_parallelism().map_or(4, |n| n.get()));
+            run_daemon(&listen, threads, &options)?;
+        }
+        Commands::Db { db, command } => {
+            let path = std::path::Path::new(&db);
+            let mut database = Db::load(path)?;
+            match command {
+                DbCommand::Add {
+                    paths,
+                    player_id,
+                    player_name,
+                } => {
+                    for log in &paths {
+                        database.add(log, player_id, player_name.as_deref(), &options)?;
+                    }
+                    eprintln!("added {} logs", paths.len());
+                }
+                DbCommand::Reanalyze { stale } => {
+                    let count = database.reanalyze(stale, &options)?;
+                    eprintln!("reanalyzed {count} of {} logs", database.entries.len());
+                }
+            }
+            database.save(path)?;
+        }
+        Commands::Stats { paths, player_name } => {
+            let mut stats = PlayerStats::default();
+            for path in &paths {
+                let path = std::path::Path::new(path);
+                let files = if path.is_dir() {
+                    log_files(path)?
+                } else {
+                    vec![path.to_owned()]
+                };
+                for file in files {
+                    match load_log(&file, player_name.as_deref()) {
+                        Ok((player_id, events)) => {
+                            let events = events.into_iter().map(|(event, _)| event).collect::<Vec<_>>();
+                            stats.add_game(&events, player_id);
+                        }
+                        Err(e) => eprintln!("skipping {}: {e:#}", file.display()),
+                    }
+                }
+            }
+            print!("{}", stats.to_log_string());
+        }
+        Commands::VerifyData { dir } => {
+            let checks = verify_data(std::path::Path::new(&dir))?;
+            for check in &checks {
+                match &check.error {
+                    None => println!("{}: ok", check.file),
+                    Some(error) => println!("{}: FAILED {error}", check.file),
+                }
+            }
+            if checks.iter().any(|check| !check.ok) {
+                std::process::exit(1);
+            }
+        }
+        Commands::Export {
+            path,
+            player_id,
+            player_name,
+        } => {
+            let events = open_log(&path)?
+                .lines()
+                .map(|line| line.with_context(|| format!("failed to read {path}")))
+                .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+                .map(|line| parse_event(&line?))
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| WashizuError::Log(path.clone()))?;
+            let player_id = detect_player_id(events.first().context("no events")?, player_id, player_name.as_deref())?;
+            println!("{}", board_from_events(&events, player_id)?.to_board_string());
+        }
+        Commands::Parse { args } => {
+            let args = args.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+            print!("{}", mjai_lines(&parse_board(args)?)?);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,30 @@
+//! Thread safety of the analysis API
+//! The single player calculator keeps its caches in `Rc`, so a calculation must start and finish on one thread.
+//! `ExpandedState::from_state` creates the calculator per call, which makes it safe to run analyses on many threads
+//! as long as only `PlayerState` goes in and `AnalysisReport` comes out, like the daemon's workers and `analyze_log` do.
+use anyhow::Result;
+use riichi::state::PlayerState;
+
+use crate::report::AnalysisReport;
+use crate::state::{AnalysisOptions, ExpandedState};
+
+/// Types that cross threads in the daemon and `analyze_parallel`
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<PlayerState>();
+    assert_send_sync::<AnalysisOptions>();
+    assert_send_sync::<AnalysisReport>();
+};
+
+/// Analyze a single state on the current thread, returning only thread safe data
+pub fn analyze(state: PlayerState, options: &AnalysisOptions) -> Result<AnalysisReport> {
+    Ok(ExpandedState::from_state(state, None, options)?.to_report())
+}
+
+/// Analyze states concurrently on the rayon thread pool, each calculation stays on the worker that started it
+#[cfg(feature = "parallel")]
+pub fn analyze_parallel(states: Vec<PlayerState>, options: &AnalysisOptions) -> Result<Vec<AnalysisReport>> {
+    use rayon::prelude::*;
+
+    states.into_par_iter().map(|state| analyze(state, options)).collect()
+}
@@ -0,0 +1,232 @@
+//! Cross-check of mortal's details against washizu's own analysis of the same decision
+//! Mortal's choice is the detail with the highest probability and washizu's the choice of the engine policy. A decision
+//! is flagged when the two differ and mortal's choice loses at least the EV threshold in washizu's expected values, or
+//! when one of the two discards is more likely to deal in than the other by at least the danger threshold.
+use riichi::mjai::Event;
+use riichi::tile::Tile;
+
+use crate::duplicate::{action_value, engine_choice};
+use crate::report::AnalysisReport;
+use crate::review::ReviewDecision;
+
+/// Gaps from which a disagreement is flagged
+#[derive(Debug, Clone, Copy)]
+pub struct CrossCheckThresholds {
+    /// Expected value mortal's choice loses
+    pub ev: f32,
+    /// Difference in deal-in probability in percent between the two discards
+    pub danger: f32,
+}
+
+/// Decision where mortal and washizu disagree beyond the thresholds
+#[derive(Debug, Clone)]
+pub struct Disagreement {
+    pub mortal: String,
+    pub mortal_prob: f32,
+    pub washizu: String,
+    /// Expected value washizu gives up by following mortal, None when mortal's choice is not valued
+    pub ev_gap: Option<f32>,
+    /// Deal-in probability in percent of mortal's discard minus washizu's, None unless both discard
+    pub danger_gap: Option<f32>,
+}
+
+/// Highest deal-in probability of a discard against any opponent, 0 for tiles that are not listed
+fn discard_danger(report: &AnalysisReport, tile: Tile) -> f32 {
+    report
+        .danger
+        .iter()
+        .flat_map(|weights| weights.iter())
+        .filter(|(danger_tile, _)| danger_tile.deaka() == tile.deaka())
+        .map(|(_, weight)| *weight)
+        .fold(0.0, f32::max)
+}
+
+/// Discard of an action of the analysis given by its decision string
+fn action_discard(report: &AnalysisReport, action: &str) -> Option<Tile> {
+    let events = report
+        .candidates
+        .iter()
+        .map(|candidate| &candidate.event)
+        .chain(report.defense_values.iter().map(|(event, _)| event));
+    for event in events {
+        if event.to_decision_string() == action {
+            return match event {
+                Event::Dahai { pai, .. } => Some(*pai),
+                _ => None,
+            };
+        }
+    }
+    report
+        .fold
+        .as_ref()?
+        .safe_order
+        .iter()
+        .map(|(tile, _, _)| *tile)
+        .find(|tile| tile.to_string() == action)
+}
+
+/// Disagreement between mortal's details and washizu's analysis, None when they agree or stay within the thresholds
+pub fn cross_check(report: &AnalysisReport, thresholds: CrossCheckThresholds) -> Option<Disagreement> {
+    let mortal = report.details.iter().max_by(|a, b| a.prob.total_cmp(&b.prob))?;
+    let mortal_action = mortal.action.to_decision_string();
+    let washizu = engine_choice(report)?;
+    if mortal_action == washizu {
+        return None;
+    }
+    let ev_gap = action_value(report, &washizu)
+        .zip(action_value(report, &mortal_action))
+        .map(|(washizu_value, mortal_value)| washizu_value - mortal_value);
+    let danger_gap = match (&mortal.action, action_discard(report, &washizu)) {
+        (Event::Dahai { pai: mortal_tile, .. }, Some(washizu_tile)) => {
+            Some(discard_danger(report, *mortal_tile) - discard_danger(report, washizu_tile))
+        }
+        _ => None,
+    };
+    let flagged = ev_gap.is_some_and(|gap| gap >= thresholds.ev) || danger_gap.is_some_and(|gap| gap.abs() >= thresholds.danger);
+    flagged.then_some(Disagreement {
+        mortal: mortal_action,
+        mortal_prob: mortal.prob,
+        washizu,
+        ev_gap,
+        danger_gap,
+    })
+}
+
+/// Summary of the decisions where mortal and washizu disagree, from the largest EV gap
+pub fn cross_check_summary(decisions: &[ReviewDecision], thresholds: CrossCheckThresholds) -> String {
+    let mut flagged = decisions
+        .iter()
+        .filter_map(|decision| Some((decision, cross_check(&decision.report, thresholds)?)))
+        .collect::<Vec<_>>();
+    flagged.sort_by(|(_, a), (_, b)| b.ev_gap.unwrap_or(0.0).total_cmp(&a.ev_gap.unwrap_or(0.0)));
+    let checked = decisions
+        .iter()
+        .filter(|decision| !decision.report.details.is_empty())
+        .count();
+    let mut lines = vec![format!(
+        "cross-check: mortal and washizu disagree at {} of {} decisions with details",
+        flagged.len(),
+        checked
+    )];
+    lines.extend(flagged.iter().map(|(decision, disagreement)| {
+        let ev = disagreement
+            .ev_gap
+            .map_or_else(|| "?".to_owned(), |gap| format!("-{}", gap.round()));
+        let danger = disagreement
+            .danger_gap
+            .filter(|gap| gap.abs() >= thresholds.danger)
+            .map(|gap| format!(" danger {gap:+.1}%"))
+            .unwrap_or_default();
+        format!(
+            "{} turn {:<2} mortal {:<3} ({:.1}%) washizu {:<3} EV {ev}{danger}",
+            decision.board.round,
+            decision.board.turn,
+            disagreement.mortal,
+            disagreement.mortal_prob * 100.0,
+            disagreement.washizu,
+        )
+    }));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::t;
+
+    use super::*;
+    use crate::ekyumoe::Detail;
+    use crate::report::CandidateReport;
+    use crate::review::BoardSnapshot;
+
+    fn discard(tile: Tile) -> Event {
+        Event::Dahai {
+            actor: 0,
+            pai: tile,
+            tsumogiri: false,
+        }
+    }
+
+    fn candidate(tile: Tile, exp_value: f32) -> CandidateReport {
+        CandidateReport {
+            action: tile.to_string(),
+            event: discard(tile),
+            exp_value,
+            draw_value: 0.0,
+            delta: exp_value - 1000.0,
+            win_prob: 0.0,
+            expected_draws: None,
+            tenpai_prob: 0.0,
+            shanten: 1,
+            num_required_tiles: 0,
+            required_tiles: vec![],
+            yaku: vec![],
+        }
+    }
+
+    /// Washizu prefers 9p by 300 EV over mortal's 1m, which is 25% more likely to deal into the next player
+    fn report(mortal: Tile) -> AnalysisReport {
+        AnalysisReport {
+            details: vec![
+                Detail {
+                    action: discard(mortal),
+                    q_value: 1.0,
+                    prob: 0.8,
+                },
+                Detail {
+                    action: discard(t!(9p)),
+                    q_value: 0.5,
+                    prob: 0.2,
+                },
+            ],
+            candidates: vec![candidate(t!(9p), 1000.0), candidate(t!(1m), 700.0)],
+            danger: vec![vec![], vec![(t!(1m), 25.0)], vec![], vec![]],
+            ..Default::default()
+        }
+    }
+
+    fn thresholds(ev: f32, danger: f32) -> CrossCheckThresholds {
+        CrossCheckThresholds { ev, danger }
+    }
+
+    #[test]
+    fn agreement_is_not_flagged() {
+        assert!(cross_check(&report(t!(9p)), thresholds(0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn disagreement_is_flagged_by_either_threshold() {
+        let flagged = cross_check(&report(t!(1m)), thresholds(200.0, 30.0)).unwrap();
+        assert_eq!((flagged.mortal.as_str(), flagged.washizu.as_str()), ("1m", "9p"));
+        assert_eq!(flagged.ev_gap, Some(300.0));
+        assert_eq!(flagged.danger_gap, Some(25.0));
+        assert!(cross_check(&report(t!(1m)), thresholds(500.0, 20.0)).is_some());
+        assert!(cross_check(&report(t!(1m)), thresholds(500.0, 30.0)).is_none());
+    }
+
+    #[test]
+    fn summary_shows_the_danger_gap_beyond_its_threshold() {
+        let decision = ReviewDecision {
+            trigger: Event::None,
+            board: BoardSnapshot {
+                round: "E1-0".to_owned(),
+                turn: 7,
+                scores: [25000; 4],
+                dora_indicators: vec![t!(1m)],
+                tehai: String::new(),
+                fuuro: vec![],
+                kawa: Default::default(),
+                riichi_declared: [false, true, false, false],
+            },
+            report: report(t!(1m)),
+            actual: Some(discard(t!(1m))),
+        };
+        let summary = cross_check_summary(std::slice::from_ref(&decision), thresholds(200.0, 20.0));
+        assert!(
+            summary.starts_with("cross-check: mortal and washizu disagree at 1 of 1 decisions"),
+            "{summary}"
+        );
+        assert!(summary.ends_with("EV -300 danger +25.0%"), "{summary}");
+        let summary = cross_check_summary(&[decision], thresholds(200.0, 30.0));
+        assert!(summary.ends_with("EV -300"), "{summary}");
+    }
+}
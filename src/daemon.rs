@@ -0,0 +1,253 @@
+//! Long-running analysis server
+//! Keeps a warmed-up process (lazily loaded tables and caches) that multiple tools on one machine can share.
+//!
+//! `POST /analyze` accepts either a board string or a list of mjai events and responds with an `AnalysisReport`.
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{RecvTimeoutError, TrySendError};
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use riichi::mjai::Event;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::concurrency::analyze;
+use crate::decisions::state_from_events;
+use crate::error::WashizuError;
+use crate::mjaigen::parse_board;
+use crate::report::AnalysisReport;
+use crate::state::AnalysisOptions;
+
+#[derive(serde::Deserialize, Debug)]
+pub struct AnalyzeRequest {
+    /// Board string as accepted by the `board` subcommand
+    pub board: Option<String>,
+    /// Mjai events of the current kyoku starting with StartGame
+    pub events: Option<Vec<Event>>,
+    /// Upper bound for the shanten analyzed by the single player calculator
+    pub max_shanten: Option<i8>,
+    /// Time after which the request is answered with a timeout. A calculation that has not started by then is
+    /// dropped, one that has keeps its worker until it finishes.
+    pub budget_ms: Option<u64>,
+}
+
+/// Position of a request, parsed by the worker so a board that panics the parser only loses its own request
+enum Position {
+    Board(String),
+    Events(Vec<Event>),
+}
+
+impl Position {
+    fn events(self) -> Result<Vec<Event>> {
+        match self {
+            Self::Board(board) => parse_board(board.split_whitespace().collect()),
+            Self::Events(events) => Ok(events),
+        }
+    }
+}
+
+/// Analysis waiting for a worker
+struct Job {
+    position: Position,
+    options: AnalysisOptions,
+    /// Set when the request stopped waiting
+    cancelled: Arc<AtomicBool>,
+    reply: mpsc::Sender<Result<AnalysisReport>>,
+}
+
+/// Fixed set of analysis workers fed by a queue of at most one job per worker
+struct WorkerPool {
+    jobs: mpsc::SyncSender<Job>,
+}
+
+impl WorkerPool {
+    fn new(workers: usize) -> Self {
+        let (jobs, receiver) = mpsc::sync_channel::<Job>(workers);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..workers {
+            let receiver = receiver.clone();
+            std::thread::spawn(move || {
+                loop {
+                    let next = receiver.lock().ok().and_then(|jobs| jobs.recv().ok());
+                    let Some(job) = next else {
+                        return;
+                    };
+                    if job.cancelled.load(Ordering::Relaxed) {
+                        continue;
+                    }
+                    let Job {
+                        position,
+                        options,
+                        reply,
+                        ..
+                    } = job;
+                    // a panic drops the reply, which the request answers as an internal error
+                    let report = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                        position
+                            .events()
+                            .and_then(state_from_events)
+                            .and_then(|state| analyze(state, &options))
+                    }));
+                    if let Ok(report) = report {
+                        _ = reply.send(report);
+                    }
+                }
+            });
+        }
+        Self { jobs }
+    }
+
+    /// Queue an analysis and wait for its report up to the budget
+    fn analyze(&self, position: Position, options: AnalysisOptions, budget: Option<Duration>) -> (u16, String) {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (reply, receiver) = mpsc::channel();
+        let job = Job {
+            position,
+            options,
+            cancelled: cancelled.clone(),
+            reply,
+        };
+        match self.jobs.try_send(job) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => return (503, error_json(&anyhow!("every worker is busy"))),
+            Err(TrySendError::Disconnected(_)) => return (500, error_json(&anyhow!("no analysis workers left"))),
+        }
+        let report = match budget {
+            Some(budget) => receiver.recv_timeout(budget),
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        if report.is_err() {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+        report_response(report, budget)
+    }
+}
+
+fn report_response(report: Result<Result<AnalysisReport>, RecvTimeoutError>, budget: Option<Duration>) -> (u16, String) {
+    match report {
+        Ok(Ok(report)) => match serde_json::to_string(&report) {
+            Ok(json) => (200, json),
+            Err(e) => (500, error_json(&e.into())),
+        },
+        Ok(Err(e)) if matches!(e.downcast_ref(), Some(WashizuError::Report(_))) => (500, error_json(&e)),
+        Ok(Err(e)) => (400, error_json(&e)),
+        Err(RecvTimeoutError::Timeout) => (
+            504,
+            error_json(&anyhow!(
+                "analysis exceeded budget of {}ms",
+                budget.unwrap_or_default().as_millis()
+            )),
+        ),
+        Err(RecvTimeoutError::Disconnected) => (500, error_json(&anyhow!("analysis panicked"))),
+    }
+}
+
+pub fn run_daemon(listen: &str, threads: usize, options: &AnalysisOptions) -> Result<()> {
+    let server = Arc::new(Server::http(listen).map_err(|e| anyhow!("failed to listen on {listen}: {e}"))?);
+    eprintln!("listening on {listen} with {threads} threads");
+    let pool = Arc::new(WorkerPool::new(threads));
+
+    let handles = (0..threads)
+        .map(|_| {
+            let server = server.clone();
+            let pool = pool.clone();
+            let options = options.clone();
+            std::thread::spawn(move || {
+                while let Ok(request) = server.recv() {
+                    handle_request(request, &pool, &options);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.join().map_err(|_| anyhow!("daemon thread panicked"))?;
+    }
+    Ok(())
+}
+
+fn handle_request(mut request: Request, pool: &WorkerPool, options: &AnalysisOptions) {
+    let (status, body) = match (request.method(), request.url()) {
+        (Method::Post, "/analyze") => {
+            let mut body = String::new();
+            match request.as_reader().read_to_string(&mut body) {
+                Ok(_) => match parse_request(&body, options) {
+                    Ok((position, options, budget)) => pool.analyze(position, options, budget),
+                    Err(e) => (400, error_json(&e)),
+                },
+                Err(e) => (400, error_json(&anyhow!(e).context("failed to read body"))),
+            }
+        }
+        _ => (404, error_json(&anyhow!("not found"))),
+    };
+    let response = Response::from_string(body)
+        .with_status_code(status)
+        .with_header(Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap());
+    if let Err(e) = request.respond(response) {
+        eprintln!("failed to respond: {e}");
+    }
+}
+
+fn parse_request(body: &str, options: &AnalysisOptions) -> Result<(Position, AnalysisOptions, Option<Duration>)> {
+    let request: AnalyzeRequest = serde_json::from_str(body).context("malformed request")?;
+    let position = match (request.board, request.events) {
+        (Some(board), None) => Position::Board(board),
+        (None, Some(events)) => Position::Events(events),
+        _ => bail!("request must contain exactly one of board or events"),
+    };
+    let options = AnalysisOptions {
+        max_shanten: request.max_shanten.or(options.max_shanten),
+        ..options.clone()
+    };
+    Ok((position, options, request.budget_ms.map(Duration::from_millis)))
+}
+
+fn error_json(e: &anyhow::Error) -> String {
+    serde_json::json!({ "error": format!("{e:#}") }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropped_reply_is_an_internal_error_and_timeout_a_gateway_timeout() {
+        let budget = Some(Duration::from_millis(10));
+        assert_eq!(report_response(Err(RecvTimeoutError::Disconnected), budget).0, 500);
+        let (status, body) = report_response(Err(RecvTimeoutError::Timeout), budget);
+        assert_eq!(status, 504);
+        assert!(body.contains("budget of 10ms"), "{body}");
+        assert_eq!(report_response(Ok(Err(anyhow!("no events"))), budget).0, 400);
+        let invalid = WashizuError::Report("missing tehai".to_owned());
+        assert_eq!(report_response(Ok(Err(invalid.into())), budget).0, 500);
+    }
+
+    #[test]
+    fn cancelled_jobs_are_dropped_before_they_start() {
+        let pool = WorkerPool::new(1);
+        let (reply, receiver) = mpsc::channel();
+        let job = Job {
+            position: Position::Events(vec![]),
+            options: AnalysisOptions::default(),
+            cancelled: Arc::new(AtomicBool::new(true)),
+            reply,
+        };
+        pool.jobs.send(job).unwrap();
+        assert!(receiver.recv().is_err());
+
+        // the same job without cancellation is analyzed and fails for its missing events
+        assert_eq!(
+            pool.analyze(Position::Events(vec![]), AnalysisOptions::default(), None).0,
+            400
+        );
+    }
+
+    #[test]
+    fn boards_are_parsed_by_the_workers() {
+        let pool = WorkerPool::new(1);
+        let board = "E5 E 0 0 1m 25000 25000 25000 25000 123m456p789s11222z / / / / / / / /";
+        let (status, body) = pool.analyze(Position::Board(board.to_owned()), AnalysisOptions::default(), None);
+        assert_eq!(status, 400);
+        assert!(body.contains("kyoku must be between 1 and 4"), "{body}");
+    }
+}
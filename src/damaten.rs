@@ -0,0 +1,312 @@
+//! Riichi or damaten for a tenpai discard
+//! Both lines are calculated by the single player tables: riichi with ura dora, ippatsu and no changes to the hand
+//! afterwards, damaten keeping tegawari. Riichi additionally puts a 1000 point stick at risk that only comes back
+//! with a win. Values are tsumo-only so the ron chances riichi scares away are not modeled.
+//! Where the stick ends up additionally races our tsumo-only win curve against a rough chance per go-around that an
+//! opponent wins first.
+use riichi::algo::sp::{EventCandidate, SPOptions};
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+
+use crate::scoring::KYOTAKU_POINTS;
+use crate::state::AnalysisOptions;
+use crate::tables::{single_player_tables_with, sp_options};
+
+/// Chance per go-around that an opponent wins, by whether they are in riichi
+const OPPONENT_WIN_HAZARD: f32 = 0.03;
+const RIICHI_OPPONENT_WIN_HAZARD: f32 = 0.1;
+
+/// Where the riichi stick of a discard ends up, the chances sum to 1
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct StickOutcome {
+    pub discard: Event,
+    /// We win and take the stick back
+    pub recovered: f32,
+    /// An opponent wins first and takes it
+    pub lost: f32,
+    /// The kyoku ends in a draw and the stick stays on the table
+    pub carried_over: f32,
+}
+
+/// Best tenpai discard with and without riichi
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct RiichiDecision {
+    pub riichi_discard: Event,
+    /// Expected value of riichi after the risk to the stick
+    pub riichi_ev: f32,
+    pub damaten_discard: Event,
+    pub damaten_ev: f32,
+    /// Where the stick ends up for every tenpai discard with riichi
+    pub stick_outcomes: Vec<StickOutcome>,
+}
+
+impl RiichiDecision {
+    pub fn declare(&self) -> bool {
+        self.riichi_ev > self.damaten_ev
+    }
+}
+
+fn best_tenpai(candidates: &[EventCandidate]) -> Option<(&EventCandidate, f32, f32)> {
+    candidates
+        .iter()
+        .filter(|candidate| candidate.shanten == 0)
+        .map(|candidate| {
+            (
+                candidate,
+                candidate.exp_values.first().copied().unwrap_or(0.0),
+                candidate.win_probs.first().copied().unwrap_or(0.0),
+            )
+        })
+        .max_by(|(_, a, _), (_, b, _)| a.total_cmp(b))
+}
+
+/// Rough chance per go-around that an opponent relative to us wins
+pub fn opponent_win_hazard(state: &PlayerState, player: usize) -> f32 {
+    if state.riichi_declared[player] {
+        RIICHI_OPPONENT_WIN_HAZARD
+    } else {
+        OPPONENT_WIN_HAZARD
+    }
+}
+
+/// Race our win curve against the opponents, who get a go-around before each of our tsumos
+fn stick_outcome(state: &PlayerState, candidate: &EventCandidate) -> StickOutcome {
+    let survive = (1..4).map(|player| 1.0 - opponent_win_hazard(state, player)).product::<f32>();
+    // win_probs[n] is the chance to win within `len - n` tsumos
+    let len = candidate.win_probs.len();
+    let (mut recovered, mut lost) = (0.0, 0.0);
+    let mut won_before = 0.0;
+    let mut alive = 1.0;
+    for draws in 1..=len {
+        lost += (1.0 - won_before) * alive * (1.0 - survive);
+        alive *= survive;
+        let won = candidate.win_probs[len - draws];
+        recovered += (won - won_before) * alive;
+        won_before = won;
+    }
+    StickOutcome {
+        discard: candidate.event.clone(),
+        recovered,
+        lost,
+        carried_over: (1.0 - won_before) * alive,
+    }
+}
+
+/// Compare riichi with damaten, None when riichi cannot be declared
+pub fn riichi_decision(state: &PlayerState, analysis_options: &AnalysisOptions) -> Option<RiichiDecision> {
+    if !state.last_cans.can_riichi {
+        return None;
+    }
+    let base = sp_options(state.real_time_shanten(), analysis_options);
+    let riichi = || {
+        single_player_tables_with(
+            state,
+            SPOptions {
+                prefer_riichi: true,
+                calc_tegawari: None,
+                ..base.clone()
+            },
+            analysis_options,
+        )
+    };
+    let damaten = || {
+        single_player_tables_with(
+            state,
+            SPOptions {
+                prefer_riichi: false,
+                ..base.clone()
+            },
+            analysis_options,
+        )
+    };
+    // both calculations are independent and the slowest part of a riichi decision
+    #[cfg(feature = "parallel")]
+    let (riichi_tables, damaten_tables) = rayon::join(riichi, damaten);
+    #[cfg(not(feature = "parallel"))]
+    let (riichi_tables, damaten_tables) = (riichi(), damaten());
+    let (riichi, riichi_ev, riichi_win_prob) = best_tenpai(&riichi_tables.candidates)?;
+    let (damaten, damaten_ev, _) = best_tenpai(&damaten_tables.candidates)?;
+    Some(RiichiDecision {
+        riichi_discard: riichi.event.clone(),
+        riichi_ev: riichi_ev - KYOTAKU_POINTS as f32 * (1.0 - riichi_win_prob),
+        damaten_discard: damaten.event.clone(),
+        damaten_ev,
+        stick_outcomes: riichi_tables
+            .candidates
+            .iter()
+            .filter(|candidate| candidate.shanten == 0)
+            .map(|candidate| stick_outcome(state, candidate))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::t;
+    use riichi::tile::Tile;
+
+    use super::*;
+
+    fn discard(pai: Tile, shanten: i8, exp_value: f32, win_probs: Vec<f32>) -> EventCandidate {
+        EventCandidate {
+            event: Event::Dahai {
+                actor: 0,
+                pai,
+                tsumogiri: false,
+            },
+            shanten,
+            num_required_tiles: 0,
+            required_tiles: vec![],
+            exp_values: vec![exp_value],
+            win_probs,
+            tenpai_probs: vec![],
+            yaku: vec![],
+        }
+    }
+
+    /// Dealer tenpai on 36s with 123m456m789p11s45s after drawing a north
+    fn riichi_prompt() -> PlayerState {
+        let mut tehais = [[t!(?); 13]; 4];
+        tehais[0] = [
+            t!(1m),
+            t!(2m),
+            t!(3m),
+            t!(4m),
+            t!(5m),
+            t!(6m),
+            t!(7p),
+            t!(8p),
+            t!(9p),
+            t!(1s),
+            t!(1s),
+            t!(4s),
+            t!(5s),
+        ];
+        let mut state = PlayerState::new(0);
+        let events = [
+            Event::StartKyoku {
+                bakaze: t!(E),
+                dora_marker: t!(9m),
+                kyoku: 1,
+                honba: 0,
+                kyotaku: 0,
+                oya: 0,
+                scores: [25000; 4],
+                tehais,
+            },
+            Event::Tsumo { actor: 0, pai: t!(N) },
+        ];
+        for event in &events {
+            state.update(event).unwrap();
+        }
+        state
+    }
+
+    fn total(outcome: &StickOutcome) -> f32 {
+        outcome.recovered + outcome.lost + outcome.carried_over
+    }
+
+    #[test]
+    fn stick_is_recovered_lost_or_carried_over() {
+        let state = PlayerState::default();
+        let outcome = stick_outcome(&state, &discard(t!(N), 0, 0.0, vec![0.5, 0.3]));
+        let survive = (1.0 - OPPONENT_WIN_HAZARD).powi(3);
+        // the opponents get a go-around before each of our two tsumos
+        assert!((outcome.recovered - (0.3 * survive + 0.2 * survive * survive)).abs() < 1e-6);
+        assert!((outcome.lost - ((1.0 - survive) + 0.7 * survive * (1.0 - survive))).abs() < 1e-6);
+        assert!((outcome.carried_over - 0.5 * survive * survive).abs() < 1e-6);
+        assert!((total(&outcome) - 1.0).abs() < 1e-6);
+
+        // a hand that cannot win only loses the stick or leaves it on the table
+        let outcome = stick_outcome(&state, &discard(t!(N), 0, 0.0, vec![0.0, 0.0]));
+        assert_eq!(outcome.recovered, 0.0);
+        assert!((total(&outcome) - 1.0).abs() < 1e-6);
+        // without a tsumo left the stick stays on the table
+        let outcome = stick_outcome(&state, &discard(t!(N), 0, 0.0, vec![]));
+        assert_eq!((outcome.recovered, outcome.lost, outcome.carried_over), (0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn honba_and_sticks_on_the_table_leave_the_race_alone() {
+        let candidate = discard(t!(N), 0, 0.0, vec![0.5, 0.3]);
+        let empty = stick_outcome(&PlayerState::default(), &candidate);
+        let state = PlayerState {
+            honba: 3,
+            kyotaku: 2,
+            ..Default::default()
+        };
+        let outcome = stick_outcome(&state, &candidate);
+        assert_eq!(
+            (outcome.recovered, outcome.lost, outcome.carried_over),
+            (empty.recovered, empty.lost, empty.carried_over)
+        );
+    }
+
+    #[test]
+    fn best_tenpai_discard_is_compared() {
+        let candidates = [
+            discard(t!(1m), 1, 5000.0, vec![0.4]),
+            discard(t!(N), 0, 2000.0, vec![0.3]),
+            discard(t!(4s), 0, 1500.0, vec![0.2]),
+        ];
+        let (best, exp_value, win_prob) = best_tenpai(&candidates).unwrap();
+        assert_eq!(best.event, candidates[1].event);
+        assert_eq!((exp_value, win_prob), (2000.0, 0.3));
+        assert!(best_tenpai(&candidates[..1]).is_none());
+
+        let decision = RiichiDecision {
+            riichi_discard: candidates[1].event.clone(),
+            riichi_ev: 2000.0 - KYOTAKU_POINTS as f32 * 0.7,
+            damaten_discard: candidates[1].event.clone(),
+            damaten_ev: 1200.0,
+            stick_outcomes: vec![],
+        };
+        assert!(decision.declare());
+        assert!(
+            !RiichiDecision {
+                damaten_ev: 1400.0,
+                ..decision
+            }
+            .declare()
+        );
+    }
+
+    #[test]
+    fn riichi_and_damaten_of_a_fixed_hand() {
+        let decision = riichi_decision(&riichi_prompt(), &AnalysisOptions::default()).unwrap();
+        // the north is the only discard keeping tenpai
+        assert!(matches!(decision.riichi_discard, Event::Dahai { pai, .. } if pai == t!(N)));
+        assert!(matches!(decision.damaten_discard, Event::Dahai { pai, .. } if pai == t!(N)));
+        assert_eq!(decision.declare(), decision.riichi_ev > decision.damaten_ev);
+
+        let mut state = riichi_prompt();
+        state.last_cans.can_riichi = false;
+        assert!(riichi_decision(&state, &AnalysisOptions::default()).is_none());
+    }
+    #[test]
+    fn opponent_riichi_takes_the_stick_more_often() {
+        let candidate = discard(t!(N), 0, 0.0, vec![0.5, 0.3]);
+        let quiet = stick_outcome(&PlayerState::default(), &candidate);
+        let mut state = PlayerState::default();
+        state.riichi_declared[2] = true;
+        let riichi = stick_outcome(&state, &candidate);
+        assert!(riichi.lost > quiet.lost);
+        assert!(riichi.recovered < quiet.recovered && riichi.carried_over < quiet.carried_over);
+        assert!((total(&riichi) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn riichi_decision_breaks_down_the_stick_of_every_tenpai_discard() {
+        let decision = riichi_decision(&riichi_prompt(), &AnalysisOptions::default()).unwrap();
+        assert_eq!(decision.stick_outcomes.len(), 1);
+        let outcome = &decision.stick_outcomes[0];
+        assert_eq!(outcome.discard, decision.riichi_discard);
+        assert!(outcome.recovered > 0.0);
+        assert!((total(outcome) - 1.0).abs() < 1e-4);
+
+        let mut state = riichi_prompt();
+        state.riichi_declared[2] = true;
+        let against_riichi = riichi_decision(&state, &AnalysisOptions::default()).unwrap();
+        assert!(against_riichi.stick_outcomes[0].lost > outcome.lost);
+    }
+}
@@ -0,0 +1,67 @@
+//! Integrity checks of external table data
+//! A data directory holds table files next to a `checksums.txt` in the format written by `sha256sum`,
+//! so alternate tables can be checked before they are swapped in.
+use std::fmt::Write;
+use std::path::{Component, Path};
+
+use anyhow::{Context, Result, bail};
+use sha2::{Digest, Sha256};
+
+/// Manifest listing the files of a data directory with their checksums
+pub const MANIFEST: &str = "checksums.txt";
+
+/// Outcome of checking one file of the manifest
+#[derive(Debug)]
+pub struct DataCheck {
+    pub file: String,
+    pub ok: bool,
+    /// Reason of a failed check
+    pub error: Option<String>,
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().fold(String::new(), |mut out, byte| {
+        _ = write!(out, "{byte:02x}");
+        out
+    })
+}
+
+/// Contents of a file listed in the manifest, which must stay inside the data directory even through symlinks
+fn read_listed(dir: &Path, root: &Path, file: &str) -> Result<Vec<u8>> {
+    let inside = Path::new(file)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    let path = dir.join(file);
+    if !inside || !path.canonicalize()?.starts_with(root) {
+        bail!("outside the data directory");
+    }
+    Ok(std::fs::read(path)?)
+}
+
+/// Check every file listed in the manifest of a data directory
+pub fn verify_data(dir: &Path) -> Result<Vec<DataCheck>> {
+    let manifest_path = dir.join(MANIFEST);
+    let manifest =
+        std::fs::read_to_string(&manifest_path).with_context(|| format!("failed to read {}", manifest_path.display()))?;
+    let root = dir
+        .canonicalize()
+        .with_context(|| format!("failed to resolve {}", dir.display()))?;
+    let mut checks = vec![];
+    for line in manifest.lines().filter(|line| !line.trim().is_empty()) {
+        let Some((expected, file)) = line.split_once(char::is_whitespace) else {
+            bail!("malformed manifest line {line:?}, expected <sha256> <file>");
+        };
+        // sha256sum marks binary mode with a leading '*'
+        let file = file.trim_start().trim_start_matches('*').to_owned();
+        let (ok, error) = match read_listed(dir, &root, &file) {
+            Ok(bytes) => {
+                let actual = sha256_hex(&bytes);
+                let ok = actual.eq_ignore_ascii_case(expected);
+                (ok, (!ok).then(|| format!("checksum {actual} does not match {expected}")))
+            }
+            Err(e) => (false, Some(e.to_string())),
+        };
+        checks.push(DataCheck { file, ok, error });
+    }
+    Ok(checks)
+}
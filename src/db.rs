@@ -0,0 +1,232 @@
+//! Review database of analyzed logs
+//! Every stored analysis records the fingerprint of the configuration that produced it, so changes to the
+//! danger model, the ruleset or the report layout can be caught up with by reanalyzing only the stale entries.
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::concurrency::analyze;
+use crate::decisions::decisions_from_log;
+use crate::report::AnalysisReport;
+use crate::state::AnalysisOptions;
+use crate::timer::DecisionTiming;
+use crate::version::{CORE_VERSION, REPORT_SCHEMA_VERSION};
+
+/// Analyses of one log
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct DbEntry {
+    pub log: String,
+    pub player_id: u8,
+    /// Configuration the reports were produced with, see `fingerprint`
+    pub fingerprint: String,
+    pub reports: Vec<AnalysisReport>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+pub struct Db {
+    pub entries: Vec<DbEntry>,
+    /// Think times of live sessions for long-term tracking
+    #[serde(default)]
+    pub timings: Vec<DecisionTiming>,
+}
+
+/// Everything that changes the numbers of a stored analysis: the core version (which pins the danger model and the
+/// single player calculator), the report layout and the options that alter the values.
+/// Every option is destructured, so a new one does not compile until it is either added or ignored here.
+pub fn fingerprint(options: &AnalysisOptions) -> String {
+    let AnalysisOptions {
+        abortive_draws,
+        draw_payments,
+        kan_dora_cost,
+        max_shanten,
+        safe_keep,
+        rules,
+        open_riichi,
+        scorer,
+        nukidora,
+        safety_floor,
+        deal_in_penalty,
+        passed_tiles,
+        visible_tiles,
+        fast,
+        sp_budget,
+        ukeire,
+        plugins,
+        no_auto_fold,
+        betaori_ev,
+        ura_dora,
+        yaku_naming,
+        rollout,
+        #[cfg(feature = "mortal")]
+        mortal_model,
+        // only change how the text output looks or what is checked and timed
+        validate_output: _,
+        profiler: _,
+        tile_style: _,
+        color: _,
+    } = options;
+    #[cfg(feature = "mortal")]
+    let model = format!(" model={mortal_model:?}");
+    #[cfg(not(feature = "mortal"))]
+    let model = "";
+    format!(
+        "washizu {CORE_VERSION} report {REPORT_SCHEMA_VERSION} rules={rules:?} abortive_draws={abortive_draws} draw_payments={draw_payments} kan_dora_cost={kan_dora_cost} max_shanten={max_shanten:?} safe_keep={safe_keep} open_riichi={open_riichi:?} scorer={scorer:?} nukidora={nukidora} safety_floor={safety_floor} deal_in={deal_in_penalty:?} passed={passed_tiles:?} visible={visible_tiles:?} fast={fast} sp_budget={sp_budget:?} ukeire={ukeire} plugins={plugins:?} no_auto_fold={no_auto_fold} betaori_ev={betaori_ev} ura_dora={ura_dora:?} yaku_naming={yaku_naming:?} rollout={rollout}{model}"
+    )
+}
+
+impl Db {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = std::fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        serde_json::from_reader(std::io::BufReader::new(file)).context("malformed database")
+    }
+
+    /// Write through a temporary file so a crash mid-write keeps the previous database intact
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp_path = path.with_extension("tmp");
+        let file = std::fs::File::create(&tmp_path).with_context(|| format!("failed to create {}", tmp_path.display()))?;
+        let mut writer = std::io::BufWriter::new(file);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        std::fs::rename(&tmp_path, path).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Analyze a mjai log and store it, replacing an earlier analysis of the same log and player
+    pub fn add(&mut self, log: &str, player_id: Option<u8>, player_name: Option<&str>, options: &AnalysisOptions) -> Result<()> {
+        let decisions = decisions_from_log(log, player_id, player_name)?;
+        let player_id = decisions.first().map_or(player_id.unwrap_or(0), |(_, state)| state.player_id);
+        let entry = DbEntry {
+            log: log.to_owned(),
+            player_id,
+            fingerprint: fingerprint(options),
+            reports: decisions
+                .into_iter()
+                .map(|(_, state)| analyze(state, options))
+                .collect::<Result<_>>()?,
+        };
+        self.entries
+            .retain(|other| other.log != entry.log || other.player_id != entry.player_id);
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Reanalyze the entries whose fingerprint differs from the current one, or all of them.
+    /// Returns the number of reanalyzed entries.
+    pub fn reanalyze(&mut self, stale_only: bool, options: &AnalysisOptions) -> Result<usize> {
+        let current = fingerprint(options);
+        let mut count = 0;
+        for entry in self
+            .entries
+            .iter_mut()
+            .filter(|entry| !stale_only || entry.fingerprint != current)
+        {
+            let decisions = decisions_from_log(&entry.log, Some(entry.player_id), None)?;
+            entry.reports = decisions
+                .into_iter()
+                .map(|(_, state)| analyze(state, options))
+                .collect::<Result<_>>()?;
+            entry.fingerprint = current.clone();
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::RuleSet;
+    use crate::scorer::parse_scorer;
+    use crate::tradeoff::DealInPenalty;
+    use crate::uradora::UraDora;
+
+    #[test]
+    fn every_value_option_changes_the_fingerprint() {
+        let base = AnalysisOptions::default();
+        let changed = [
+            AnalysisOptions {
+                kan_dora_cost: true,
+                ..Default::default()
+            },
+            AnalysisOptions {
+                scorer: Some(parse_scorer("ev+safety*100").unwrap()),
+                ..Default::default()
+            },
+            AnalysisOptions {
+                safety_floor: 2,
+                ..Default::default()
+            },
+            AnalysisOptions {
+                rules: RuleSet {
+                    aka: 0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            AnalysisOptions {
+                no_auto_fold: true,
+                ..Default::default()
+            },
+            AnalysisOptions {
+                betaori_ev: 1000.0,
+                ..Default::default()
+            },
+            AnalysisOptions {
+                ura_dora: UraDora::Disabled,
+                ..Default::default()
+            },
+            AnalysisOptions {
+                deal_in_penalty: DealInPenalty {
+                    non_dealer: 8000.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            AnalysisOptions {
+                rollout: 100,
+                ..Default::default()
+            },
+            AnalysisOptions {
+                sp_budget: Some(std::time::Duration::from_millis(200)),
+                ..Default::default()
+            },
+        ];
+        for options in changed {
+            assert_ne!(fingerprint(&options), fingerprint(&base), "{options:?}");
+        }
+    }
+
+    #[test]
+    fn display_options_keep_the_fingerprint() {
+        let colored = AnalysisOptions {
+            color: true,
+            validate_output: true,
+            ..Default::default()
+        };
+        assert_eq!(fingerprint(&colored), fingerprint(&AnalysisOptions::default()));
+    }
+
+    #[test]
+    fn saved_database_loads_back() {
+        let path = std::env::temp_dir().join(format!("washizu-db-{}.json", std::process::id()));
+        let db = Db {
+            entries: vec![DbEntry {
+                log: "log".to_owned(),
+                player_id: 2,
+                fingerprint: fingerprint(&AnalysisOptions::default()),
+                reports: vec![],
+            }],
+            timings: vec![],
+        };
+        db.save(&path).unwrap();
+        let loaded = Db::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.entries.len(), 1);
+        assert_eq!(loaded.entries[0].player_id, 2);
+        assert!(!path.with_extension("tmp").exists());
+    }
+}
@@ -0,0 +1,141 @@
+//! Decision points of mjai event sequences and logs
+#[cfg(feature = "cli")]
+use std::io::BufRead;
+
+#[cfg(feature = "cli")]
+use anyhow::anyhow;
+use anyhow::{Context, Result, bail, ensure};
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+
+use crate::error::WashizuError;
+#[cfg(feature = "cli")]
+use crate::variant::parse_event;
+
+/// State after all events, the first one being StartGame
+pub fn state_from_events(events: impl IntoIterator<Item = Event>) -> Result<PlayerState> {
+    let mut events = events.into_iter();
+    let start_game = events.next().context("no events")?;
+    let mut state = PlayerState::new(detect_player_id(&start_game, None, None)?);
+    for event in events {
+        state.update(&event).with_context(|| WashizuError::state_update(&event))?;
+    }
+    Ok(state)
+}
+
+/// Seat of the player: an explicit id, the seat matching `player_name` in StartGame, or the id in StartGame
+pub fn detect_player_id(start_game: &Event, player_id: Option<u8>, player_name: Option<&str>) -> Result<u8> {
+    if let Some(player_id) = player_id {
+        ensure!(player_id < 4, "player id {player_id} out of range");
+        return Ok(player_id);
+    }
+    let Event::StartGame { names, id, .. } = start_game else {
+        bail!("first event must be StartGame")
+    };
+    if let Some(player_name) = player_name {
+        let position = names.iter().position(|name| name == player_name);
+        return position
+            .map(|seat| seat as u8)
+            .with_context(|| format!("no player named {player_name} in {names:?}"));
+    }
+    id.context("StartGame has no player id, specify the player id or name")
+}
+
+/// Decision points of a mjai log as the event leading to them and the state after it
+#[cfg(feature = "cli")]
+pub fn decisions_from_log(path: &str, player_id: Option<u8>, player_name: Option<&str>) -> Result<Vec<(Event, PlayerState)>> {
+    let file = std::fs::File::open(path).with_context(|| WashizuError::Log(path.to_owned()))?;
+    let events = std::io::BufReader::new(file)
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| parse_event(&line.with_context(|| format!("failed to read {path}"))?))
+        .collect::<Result<Vec<_>>>()
+        .with_context(|| WashizuError::Log(path.to_owned()))?;
+    decisions_from_events(events, player_id, player_name)
+}
+
+/// Decision points of an event sequence starting with StartGame
+pub fn decisions_from_events(
+    events: Vec<Event>,
+    player_id: Option<u8>,
+    player_name: Option<&str>,
+) -> Result<Vec<(Event, PlayerState)>> {
+    let start_game = events.first().context("no events")?;
+    let mut state = PlayerState::new(detect_player_id(start_game, player_id, player_name)?);
+    let mut decisions = vec![];
+    for event in events {
+        state.update(&event).with_context(|| WashizuError::state_update(&event))?;
+        if state.last_cans.can_act() {
+            decisions.push((event, state.clone()));
+        }
+    }
+    Ok(decisions)
+}
+
+/// The four seats of a table kept in sync, for logs and streams that reveal every hand
+#[cfg(feature = "cli")]
+pub struct Seats {
+    states: [PlayerState; 4],
+    /// Seats whose update failed, they sit out until the next kyoku deals them a new hand
+    broken: [bool; 4],
+}
+
+#[cfg(feature = "cli")]
+impl Default for Seats {
+    fn default() -> Self {
+        Self {
+            states: std::array::from_fn(|player_id| PlayerState::new(player_id as u8)),
+            broken: [false; 4],
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+impl Seats {
+    /// Apply an event to every seat, a seat failing to take it does not keep the others from seeing it
+    pub fn update(&mut self, event: &Event) -> Result<()> {
+        if matches!(event, Event::StartKyoku { .. }) {
+            self.broken = [false; 4];
+        }
+        let mut errors = vec![];
+        for (state, broken) in self.states.iter_mut().zip(&mut self.broken) {
+            if *broken {
+                continue;
+            }
+            if let Err(e) = state.update(event) {
+                *broken = true;
+                errors.push(format!("seat {}: {e:#}", state.player_id));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(errors.join(", "))).with_context(|| WashizuError::state_update(event))
+        }
+    }
+
+    pub fn states(&self) -> &[PlayerState; 4] {
+        &self.states
+    }
+
+    /// Seats with a decision after the last event, in seat order
+    pub fn deciding(&self) -> impl Iterator<Item = &PlayerState> {
+        self.states
+            .iter()
+            .zip(self.broken)
+            .filter(|(state, broken)| !broken && state.last_cans.can_act())
+            .map(|(state, _)| state)
+    }
+}
+
+/// Decision points of every seat in log order, each with the state of the seat that decides
+#[cfg(feature = "cli")]
+pub fn all_seat_decisions(events: &[Event]) -> Result<Vec<(Event, PlayerState)>> {
+    let mut seats = Seats::default();
+    let mut decisions = vec![];
+    for event in events {
+        seats.update(event)?;
+        decisions.extend(seats.deciding().map(|state| (event.clone(), state.clone())));
+    }
+    Ok(decisions)
+}
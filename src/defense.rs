@@ -0,0 +1,148 @@
+//! Defensive queries combining the single player tables with tile danger
+use riichi::algo::sp::EventCandidate;
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+use crate::order::{SortKey, SortSpec, sort_candidates};
+
+/// Danger weights are deal-in probabilities in percent, a wait revealed by an opponent in open riichi is certain
+pub const CERTAIN_DANGER: f32 = 100.0;
+
+/// Danger of discarding each tile summed over all opponents.
+/// Opponents with exposed waits are only dangerous on those waits.
+pub fn combined_danger(danger_weights: &[Vec<(Tile, f32)>; 4], exposed_waits: &[Option<Vec<Tile>>; 4]) -> [f32; 34] {
+    let mut combined = [0.0; 34];
+    for (weights, exposed) in danger_weights.iter().zip(exposed_waits).skip(1) {
+        match exposed {
+            Some(waits) => {
+                for tile in waits {
+                    combined[tile.deaka().as_usize()] += CERTAIN_DANGER;
+                }
+            }
+            None => {
+                for (tile, weight) in weights {
+                    combined[tile.deaka().as_usize()] += weight;
+                }
+            }
+        }
+    }
+    combined
+}
+
+/// Discard of a candidate, None for other actions
+pub fn candidate_discard(candidate: &EventCandidate) -> Option<Tile> {
+    match candidate.event {
+        Event::Dahai { pai, .. } => Some(pai),
+        _ => None,
+    }
+}
+
+/// Least dangerous discard that keeps the current shanten, agari hands are required to stay tenpai.
+/// Returns the candidate and its combined danger.
+pub fn safe_keep_discard<'a>(
+    candidates: &'a [EventCandidate],
+    danger_weights: &[Vec<(Tile, f32)>; 4],
+    exposed_waits: &[Option<Vec<Tile>>; 4],
+    shanten: i8,
+) -> Option<(&'a EventCandidate, f32)> {
+    let combined = combined_danger(danger_weights, exposed_waits);
+    let mut keeping = candidates
+        .iter()
+        .filter(|candidate| candidate.shanten <= shanten.max(0) && candidate_discard(candidate).is_some())
+        .collect::<Vec<_>>();
+    let spec = SortSpec {
+        key: SortKey::Safety,
+        danger: Some(combined),
+        ..Default::default()
+    };
+    sort_candidates(&mut keeping, &spec);
+    let safest = keeping.first()?;
+    Some((safest, combined[candidate_discard(safest)?.deaka().as_usize()]))
+}
+
+/// Tiles that are genbutsu against each opponent: their own discards and the tiles they passed on, see
+/// `PassedTiles`, or everything but the waits of an open riichi
+pub fn genbutsu(state: &PlayerState, exposed_waits: &[Option<Vec<Tile>>; 4], passed: &[Vec<Tile>; 4]) -> [[bool; 34]; 4] {
+    std::array::from_fn(|player| {
+        let mut safe = [false; 34];
+        if player == 0 {
+            return safe;
+        }
+        match &exposed_waits[player] {
+            Some(waits) => {
+                safe = [true; 34];
+                for tile in waits {
+                    safe[tile.deaka().as_usize()] = false;
+                }
+            }
+            None => {
+                for tile in state.kawa_overview[player].iter().chain(&passed[player]) {
+                    safe[tile.deaka().as_usize()] = true;
+                }
+            }
+        }
+        safe
+    })
+}
+
+/// Number of tiles in our hand that are genbutsu against each opponent, always 0 for ourselves
+pub fn safety_reserve(state: &PlayerState, genbutsu: &[[bool; 34]; 4]) -> [u8; 4] {
+    std::array::from_fn(|player| {
+        state
+            .tehai
+            .iter()
+            .zip(genbutsu[player])
+            .filter(|(_, safe)| *safe)
+            .map(|(count, _)| count)
+            .sum()
+    })
+}
+
+/// Opponents in riichi whose safety reserve would drop below `floor` after the candidate, with the reserve left
+pub fn reserve_below_floor(
+    state: &PlayerState,
+    candidate: &EventCandidate,
+    genbutsu: &[[bool; 34]; 4],
+    reserve: &[u8; 4],
+    floor: u8,
+) -> Vec<(usize, u8)> {
+    let Some(discard) = candidate_discard(candidate) else {
+        return vec![];
+    };
+    (1..4)
+        .filter(|&player| state.riichi_declared[player])
+        .map(|player| {
+            let spent = genbutsu[player][discard.deaka().as_usize()] as u8;
+            (player, reserve[player].saturating_sub(spent))
+        })
+        .filter(|&(_, left)| left < floor)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::t;
+
+    use super::*;
+
+    #[test]
+    fn tiles_passed_after_a_riichi_are_genbutsu() {
+        let mut state = PlayerState {
+            riichi_declared: [false, true, false, false],
+            ..Default::default()
+        };
+        state.kawa_overview[1].push(t!(E));
+        let mut passed: [Vec<Tile>; 4] = Default::default();
+        passed[1] = vec![t!(5m), t!(9p)];
+        let genbutsu = genbutsu(&state, &Default::default(), &passed);
+        for tile in [t!(E), t!(5m), t!(9p)] {
+            assert!(genbutsu[1][tile.as_usize()], "{tile} is genbutsu");
+        }
+        assert!(!genbutsu[1][t!(1s).as_usize()]);
+        assert!(
+            !genbutsu[2][t!(5m).as_usize()],
+            "only the seat that passed a tile is furiten on it"
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! Dora counting from indicators, independent of mortal's incremental `doras_owned` bookkeeping
+use riichi::tile::Tile;
+
+/// Tile indicated as dora by an indicator: the next number, winds and dragons cycle within their group
+pub fn indicated_dora(indicator: Tile) -> Tile {
+    let index = indicator.deaka().as_u8();
+    let next = match index {
+        0..27 => index / 9 * 9 + (index % 9 + 1) % 9,
+        27..31 => 27 + (index - 27 + 1) % 4,
+        _ => 31 + (index - 31 + 1) % 3,
+    };
+    Tile::try_from(next).expect("dora of a valid tile")
+}
+
+/// Indicator that makes a tile dora, the inverse of `indicated_dora`
+pub fn dora_indicator(dora: Tile) -> Tile {
+    let index = dora.deaka().as_u8();
+    let previous = match index {
+        0..27 => index / 9 * 9 + (index % 9 + 8) % 9,
+        27..31 => 27 + (index - 27 + 3) % 4,
+        _ => 31 + (index - 31 + 2) % 3,
+    };
+    Tile::try_from(previous).expect("indicator of a valid tile")
+}
+
+/// How many times each tile counts as dora given the indicators
+pub fn dora_factor(indicators: &[Tile]) -> [u8; 34] {
+    let mut factor = [0; 34];
+    for indicator in indicators {
+        factor[indicated_dora(*indicator).as_usize()] += 1;
+    }
+    factor
+}
+
+/// Dora in a set of tiles including aka dora
+pub fn doras_in_tiles<'a>(tiles: impl IntoIterator<Item = &'a Tile>, indicators: &[Tile]) -> u8 {
+    let factor = dora_factor(indicators);
+    tiles
+        .into_iter()
+        .map(|tile| factor[tile.deaka().as_usize()] + tile.is_aka() as u8)
+        .sum()
+}
+
+/// Dora in called melds and ankan, counted tile by tile so kan dora pointing into an ankan count all four tiles
+pub fn doras_in_fuuro(fuuro: &[Vec<Tile>], ankan: &[Vec<Tile>], indicators: &[Tile]) -> u8 {
+    doras_in_tiles(fuuro.iter().chain(ankan).flatten(), indicators)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::t;
+
+    #[test]
+    fn indicators_wrap_within_their_group() {
+        assert_eq!(indicated_dora(t!(4m)), t!(5m));
+        assert_eq!(indicated_dora(t!(9m)), t!(1m));
+        assert_eq!(indicated_dora(t!(9s)), t!(1s));
+        assert_eq!(indicated_dora(t!(N)), t!(E));
+        assert_eq!(indicated_dora(t!(C)), t!(P));
+        assert_eq!(indicated_dora(t!(5pr)), t!(6p));
+    }
+
+    #[test]
+    fn kan_dora_pointing_into_ankan_counts_every_tile() {
+        let ankan = vec![vec![t!(5m), t!(5m), t!(5mr), t!(5m)]];
+        // the first indicator misses, the kan dora revealed after the ankan points into it
+        assert_eq!(doras_in_fuuro(&[], &ankan, &[t!(E)]), 1);
+        assert_eq!(doras_in_fuuro(&[], &ankan, &[t!(E), t!(4m)]), 5);
+    }
+
+    #[test]
+    fn aka_in_melds() {
+        let fuuro = vec![vec![t!(5pr), t!(5p), t!(5p)], vec![t!(3s), t!(4s), t!(5sr)]];
+        assert_eq!(doras_in_fuuro(&fuuro, &[], &[t!(E)]), 2);
+        assert_eq!(doras_in_fuuro(&fuuro, &[], &[t!(4p)]), 5);
+    }
+
+    #[test]
+    fn multiple_indicators() {
+        let factor = dora_factor(&[t!(4m), t!(4m), t!(W)]);
+        assert_eq!(factor[t!(5m).as_usize()], 2);
+        assert_eq!(factor[t!(N).as_usize()], 1);
+        assert_eq!(factor.iter().map(|&f| f as u32).sum::<u32>(), 3);
+
+        let tehai = [t!(5m), t!(5mr), t!(N), t!(1p)];
+        assert_eq!(doras_in_tiles(&tehai, &[t!(4m), t!(4m), t!(W)]), 6);
+    }
+
+    #[test]
+    fn indicator_is_derived_back_from_dora() {
+        for tile in [t!(1m), t!(5p), t!(9s), t!(E), t!(N), t!(P), t!(C)] {
+            assert_eq!(indicated_dora(dora_indicator(tile)), tile);
+        }
+        assert_eq!(dora_indicator(t!(1m)), t!(9m));
+        assert_eq!(dora_indicator(t!(E)), t!(N));
+        assert_eq!(dora_indicator(t!(5pr)), t!(4p));
+    }
+}
@@ -0,0 +1,273 @@
+//! Duplicate comparison of two policies over the decisions of a log
+//! Both policies face the same decisions, the ones the logged player reached, and each choice is valued in the analysis
+//! of that decision: by the deal-in payment of a fold plan for a hopeless hand, by the defense-aware values when an
+//! opponent is in riichi and by the expected values of the single player tables otherwise. The engine chooses the
+//! best action by the same values. The game is not replayed after a divergence, so the comparison is per decision like a review.
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::batch::{load_log, review_decisions};
+use crate::report::AnalysisReport;
+use crate::review::ReviewDecision;
+use crate::state::AnalysisOptions;
+
+/// Chooses an action at a decision point from its analysis
+pub trait Policy {
+    fn name(&self) -> &str;
+    /// Decision string of the action taken, None when the policy cannot choose
+    fn choose(&self, decision: &ReviewDecision) -> Option<String>;
+}
+
+/// Actions actually taken in the log
+pub struct LoggedPolicy;
+
+impl Policy for LoggedPolicy {
+    fn name(&self) -> &str {
+        "logged"
+    }
+
+    fn choose(&self, decision: &ReviewDecision) -> Option<String> {
+        decision.actual.as_ref().map(|actual| actual.to_decision_string())
+    }
+}
+
+/// Cheapest tile of a fold plan, the best defense-aware discard against a riichi and the best candidate otherwise
+pub struct EnginePolicy;
+
+impl Policy for EnginePolicy {
+    fn name(&self) -> &str {
+        "engine"
+    }
+
+    fn choose(&self, decision: &ReviewDecision) -> Option<String> {
+        engine_choice(&decision.report)
+    }
+}
+
+/// Decision string of the engine's action in an analysis
+pub fn engine_choice(report: &AnalysisReport) -> Option<String> {
+    if let Some(fold) = &report.fold {
+        return fold.cheapest().map(|(tile, _)| tile.to_string());
+    }
+    if let Some((event, _)) = report.defense_values.first() {
+        return Some(event.to_decision_string());
+    }
+    report
+        .candidates
+        .iter()
+        .max_by(|a, b| a.exp_value.total_cmp(&b.exp_value))
+        .map(|candidate| candidate.action.clone())
+}
+
+/// Value of an action in the analysis of its decision, None when the analysis does not cover it.
+/// With a fold plan it is the deal-in payment given up against the cheapest tile, negated.
+pub fn action_value(report: &AnalysisReport, action: &str) -> Option<f32> {
+    if let Some(fold) = &report.fold {
+        return fold.deal_in_loss(action).map(|loss| -loss);
+    }
+    if !report.defense_values.is_empty() {
+        return report
+            .defense_values
+            .iter()
+            .find(|(event, _)| event.to_decision_string() == action)
+            .map(|(_, value)| *value);
+    }
+    report
+        .candidates
+        .iter()
+        .find(|candidate| candidate.action == action)
+        .map(|candidate| candidate.exp_value)
+}
+
+/// Decision where the two policies chose differently
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    /// Round wind, kyoku and honba, e.g. E1-0
+    pub round: String,
+    pub turn: u8,
+    pub first: String,
+    pub second: String,
+    /// Value of the second choice minus the first, None when either choice is not valued
+    pub difference: Option<f32>,
+}
+
+/// Comparison of two policies over the same decisions
+#[derive(Debug, Clone)]
+pub struct DuplicateComparison {
+    pub first: String,
+    pub second: String,
+    /// Decisions both policies chose at
+    pub decisions: usize,
+    pub divergences: Vec<Divergence>,
+}
+
+impl DuplicateComparison {
+    /// Sum of the valued differences, positive when the second policy did better
+    pub fn total_difference(&self) -> f32 {
+        self.divergences.iter().filter_map(|divergence| divergence.difference).sum()
+    }
+
+    pub fn to_log_string(&self) -> String {
+        let valued = self
+            .divergences
+            .iter()
+            .filter(|divergence| divergence.difference.is_some())
+            .count();
+        let mut lines = vec![format!(
+            "duplicate: {} vs {} diverge at {} of {} decisions, {} valued, {} {:+} EV",
+            self.first,
+            self.second,
+            self.divergences.len(),
+            self.decisions,
+            valued,
+            self.second,
+            self.total_difference().round()
+        )];
+        let mut cumulative = 0.0;
+        for divergence in &self.divergences {
+            cumulative += divergence.difference.unwrap_or(0.0);
+            let difference = divergence
+                .difference
+                .map_or_else(|| "?".to_owned(), |difference| format!("{:+}", difference.round()));
+            lines.push(format!(
+                "{} turn {:<2} {:<3} vs {:<3} {difference} (total {:+})",
+                divergence.round,
+                divergence.turn,
+                divergence.first,
+                divergence.second,
+                cumulative.round()
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Compare the choices of two policies at every decision
+pub fn compare_policies(decisions: &[ReviewDecision], first: &dyn Policy, second: &dyn Policy) -> DuplicateComparison {
+    let mut compared = 0;
+    let mut divergences = vec![];
+    for decision in decisions {
+        let (Some(first_action), Some(second_action)) = (first.choose(decision), second.choose(decision)) else {
+            continue;
+        };
+        compared += 1;
+        if first_action == second_action {
+            continue;
+        }
+        let difference = action_value(&decision.report, &second_action)
+            .zip(action_value(&decision.report, &first_action))
+            .map(|(second_value, first_value)| second_value - first_value);
+        divergences.push(Divergence {
+            round: decision.board.round.clone(),
+            turn: decision.board.turn,
+            first: first_action,
+            second: second_action,
+            difference,
+        });
+    }
+    DuplicateComparison {
+        first: first.name().to_owned(),
+        second: second.name().to_owned(),
+        decisions: compared,
+        divergences,
+    }
+}
+
+/// Compare the logged actions of a log against the engine's
+pub fn duplicate_log(path: &Path, player_name: Option<&str>, options: &AnalysisOptions) -> Result<DuplicateComparison> {
+    let (player_id, events) = load_log(path, player_name)?;
+    let decisions = review_decisions(player_id, events, options)?;
+    Ok(compare_policies(&decisions, &LoggedPolicy, &EnginePolicy))
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::mjai::Event;
+    use riichi::t;
+    use riichi::tile::Tile;
+
+    use super::*;
+    use crate::fold::FoldPlan;
+    use crate::report::CandidateReport;
+    use crate::review::BoardSnapshot;
+
+    fn discard(tile: Tile) -> Event {
+        Event::Dahai {
+            actor: 0,
+            pai: tile,
+            tsumogiri: false,
+        }
+    }
+
+    fn candidate(tile: Tile, exp_value: f32, delta: f32) -> CandidateReport {
+        CandidateReport {
+            action: tile.to_string(),
+            event: discard(tile),
+            exp_value,
+            draw_value: 0.0,
+            delta,
+            win_prob: 0.0,
+            expected_draws: None,
+            tenpai_prob: 0.0,
+            shanten: 1,
+            num_required_tiles: 0,
+            required_tiles: vec![],
+            yaku: vec![],
+        }
+    }
+
+    fn decision(report: AnalysisReport, actual: Tile) -> ReviewDecision {
+        ReviewDecision {
+            trigger: Event::None,
+            board: BoardSnapshot {
+                round: "E1-0".to_owned(),
+                turn: 5,
+                scores: [25000; 4],
+                dora_indicators: vec![t!(1m)],
+                tehai: String::new(),
+                fuuro: vec![],
+                kawa: Default::default(),
+                riichi_declared: [false, true, false, false],
+            },
+            report,
+            actual: Some(discard(actual)),
+        }
+    }
+
+    #[test]
+    fn engine_chooses_by_the_values_it_is_compared_with() {
+        // a scorer ranks the cheaper hand first, the comparison is in expected value
+        let report = AnalysisReport {
+            candidates: vec![candidate(t!(1m), 900.0, 0.0), candidate(t!(9p), 1000.0, -0.1)],
+            ..Default::default()
+        };
+        assert_eq!(engine_choice(&report).as_deref(), Some("9p"));
+        let comparison = compare_policies(&[decision(report, t!(1m))], &LoggedPolicy, &EnginePolicy);
+        assert_eq!(comparison.decisions, 1);
+        assert_eq!(comparison.divergences[0].difference, Some(100.0));
+    }
+
+    #[test]
+    fn fold_divergences_are_valued_by_their_deal_in_payment() {
+        let fold = FoldPlan {
+            against: vec![1],
+            safe_order: vec![(t!(1m), 0.0, true), (t!(5p), 20.0, false)],
+            deal_in_costs: vec![0.0, 1500.0],
+            genbutsu_left: 1,
+            keiten_prob: 0.0,
+            keiten_value: 0.0,
+        };
+        let report = AnalysisReport {
+            fold: Some(fold),
+            defense_values: vec![(discard(t!(5p)), 800.0), (discard(t!(1m)), 500.0)],
+            ..Default::default()
+        };
+        assert_eq!(engine_choice(&report).as_deref(), Some("1m"));
+        assert_eq!(action_value(&report, "5p"), Some(-1500.0));
+        assert_eq!(action_value(&report, "7z"), None);
+        let comparison = compare_policies(&[decision(report, t!(5p))], &LoggedPolicy, &EnginePolicy);
+        assert_eq!(comparison.divergences[0].difference, Some(1500.0));
+        assert_eq!(comparison.total_difference(), 1500.0);
+    }
+}
@@ -1,5 +1,26 @@
-use riichi::{mjai::Event, tile::Tile};
+//! Reviews exported by ekyumoe, mortal's details of every decision attached to the mjai log they were made in
+//! Logs are read from a path or stdin (`-`), gzip-compressed or not, and a session file may hold several reviews
+//! back to back which are parsed one at a time. The details of a decision stay raw json until they are paired with
+//! their event, and the events are never copied, so a session costs about the size of its largest game.
+#[cfg(feature = "cli")]
+use std::io::{BufRead, BufReader, Read};
 
+#[cfg(feature = "cli")]
+use anyhow::{Context, Result, bail, ensure};
+#[cfg(feature = "cli")]
+use flate2::read::MultiGzDecoder;
+use riichi::mjai::Event;
+#[cfg(feature = "cli")]
+use riichi::state::PlayerState;
+#[cfg(feature = "cli")]
+use riichi::tile::Tile;
+#[cfg(feature = "cli")]
+use serde_json::value::RawValue;
+
+#[cfg(feature = "cli")]
+use crate::error::WashizuError;
+
+#[cfg(feature = "cli")]
 #[derive(serde::Deserialize, Debug)]
 pub struct EkyuMoeReview {
     pub player_id: u8,
@@ -7,22 +28,26 @@ pub struct EkyuMoeReview {
     pub mjai_log: Vec<Event>,
 }
 
+#[cfg(feature = "cli")]
 #[derive(serde::Deserialize, Debug)]
 pub struct Review {
     pub kyokus: Vec<KyokuReview>,
 }
 
+#[cfg(feature = "cli")]
 #[derive(serde::Deserialize, Debug)]
 pub struct KyokuReview {
     pub entries: Vec<Entry>,
 }
 
+#[cfg(feature = "cli")]
 #[derive(serde::Deserialize, Debug)]
 pub struct Entry {
     pub junme: u8,
     pub last_actor: u8,
     pub tile: Tile,
-    pub details: Vec<Detail>,
+    /// Kept unparsed so a corrupt detail only affects its own decision
+    pub details: Vec<Box<RawValue>>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
@@ -32,46 +57,136 @@ pub struct Detail {
     pub prob: f32,
 }
 
-pub fn read_ekyumoe_log(path: &str) -> EkyuMoeReview {
-    serde_json::from_reader(std::io::BufReader::new(std::fs::File::open(path).unwrap())).unwrap()
+#[cfg(feature = "cli")]
+impl Entry {
+    pub fn parse_details(&self) -> Result<Vec<Detail>> {
+        self.details
+            .iter()
+            .map(|detail| serde_json::from_str(detail.get()).with_context(|| format!("malformed detail {detail}")))
+            .collect()
+    }
+}
+
+/// Check that the details describe actions that are possible in the state they are attached to
+#[cfg(feature = "cli")]
+pub fn validate_details(state: &PlayerState, details: &[Detail]) -> Result<()> {
+    for detail in details {
+        ensure!(
+            detail.prob.is_finite() && (0.0..=1.0).contains(&detail.prob) && detail.q_value.is_finite(),
+            "detail for {:?} has impossible values q={} p={}",
+            detail.action,
+            detail.q_value,
+            detail.prob
+        );
+        if let Some(actor) = detail.action.actor()
+            && actor != state.player_id
+        {
+            bail!("detail for {:?} is not for player {}", detail.action, state.player_id);
+        }
+        let cans = state.last_cans;
+        let possible = match &detail.action {
+            Event::Dahai { pai, .. } => cans.can_discard && state.tehai[pai.deaka().as_usize()] > 0,
+            Event::Reach { .. } => cans.can_riichi,
+            Event::Chi { .. } => cans.can_chi(),
+            Event::Pon { .. } => cans.can_pon,
+            Event::Daiminkan { .. } => cans.can_daiminkan,
+            Event::Kakan { .. } | Event::Ankan { .. } => cans.can_kakan || cans.can_ankan,
+            Event::Hora { .. } => cans.can_agari(),
+            Event::Ryukyoku { .. } => cans.can_ryukyoku,
+            _ => true,
+        };
+        ensure!(possible, "detail for {:?} is not a possible action", detail.action);
+    }
+    Ok(())
 }
 
+/// Reader of a log file or stdin for `-`, decompressed when it starts with the gzip magic
+#[cfg(feature = "cli")]
+pub fn open_log(path: &str) -> Result<Box<dyn BufRead>> {
+    let reader: Box<dyn Read> = if path == "-" {
+        Box::new(std::io::stdin().lock())
+    } else {
+        Box::new(std::fs::File::open(path).with_context(|| format!("failed to open {path}"))?)
+    };
+    let mut reader = BufReader::new(reader);
+    let gzip = reader
+        .fill_buf()
+        .with_context(|| format!("failed to read {path}"))?
+        .starts_with(&[0x1f, 0x8b]);
+    Ok(if gzip {
+        Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+    } else {
+        Box::new(reader)
+    })
+}
+
+/// Reviews of a log in order, only the review being analyzed is held in memory
+#[cfg(feature = "cli")]
+pub fn read_ekyumoe_logs(path: &str) -> Result<impl Iterator<Item = Result<EkyuMoeReview>>> {
+    let reader = open_log(path).with_context(|| WashizuError::Log(path.to_owned()))?;
+    let path = path.to_owned();
+    Ok(serde_json::Deserializer::from_reader(reader)
+        .into_iter::<EkyuMoeReview>()
+        .enumerate()
+        .map(move |(index, review)| {
+            review
+                .with_context(|| format!("malformed review {index}"))
+                .with_context(|| WashizuError::Log(path.clone()))
+        }))
+}
+
+/// Details of the decision each event of a review leads to, or why they could not be parsed
+#[cfg(feature = "cli")]
+pub type ReviewDetails = Vec<Option<Result<Vec<Detail>>>>;
+
+#[cfg(feature = "cli")]
 impl EkyuMoeReview {
-    pub fn events_with_detail(&self) -> Vec<(Event, Option<Vec<Detail>>)> {
+    /// Details of the decision each event of `mjai_log` leads to, parse errors are returned per decision.
+    /// Fails when the review's kyokus do not line up with the log.
+    pub fn details_by_event(&self) -> Result<ReviewDetails> {
         if self.review.kyokus.is_empty() {
-            return self.mjai_log.iter().map(|e| (e.clone(), None)).collect::<Vec<_>>();
+            return Ok(self.mjai_log.iter().map(|_| None).collect());
         }
 
         let mut kyoku = 0;
         let mut index = 0;
-        let mut events_with_details = vec![];
+        let mut details_by_event = Vec::with_capacity(self.mjai_log.len());
         for event in self.mjai_log.iter() {
             if matches!(event, Event::EndGame) {
-                events_with_details.push((event.clone(), None));
+                details_by_event.push(None);
                 continue;
             }
+            let Some(review) = self.review.kyokus.get(kyoku) else {
+                bail!("log has more kyokus than the review's {}", self.review.kyokus.len());
+            };
             if matches!(event, Event::EndKyoku) {
-                if index < self.review.kyokus[kyoku].entries.len() {
-                    panic!("Didn't merge all events in kyoku {kyoku}");
-                }
+                ensure!(
+                    index >= review.entries.len(),
+                    "only {index} of {} entries in kyoku {kyoku} match the log",
+                    review.entries.len()
+                );
 
                 index = 0;
                 kyoku += 1;
-                events_with_details.push((event.clone(), None));
+                details_by_event.push(None);
                 continue;
             }
-            let Some(entry) = self.review.kyokus[kyoku].entries.get(index) else {
-                events_with_details.push((event.clone(), None));
+            let Some(entry) = review.entries.get(index) else {
+                details_by_event.push(None);
                 continue;
             };
             if matches!(event, Event::Tsumo { actor, .. } if *actor != self.player_id)
                 || matches!(event, Event::Dahai { actor, .. } if *actor == self.player_id)
             {
-                events_with_details.push((event.clone(), None));
+                details_by_event.push(None);
                 continue;
             }
             let self_riichi_discard = matches!(event, Event::Reach { actor } if *actor == self.player_id)
-                && self.review.kyokus[kyoku].entries.get(index - 1).map(|x| x.junme) == Some(entry.junme);
+                && index
+                    .checked_sub(1)
+                    .and_then(|previous| review.entries.get(previous))
+                    .map(|x| x.junme)
+                    == Some(entry.junme);
             let last_tsumo_or_discard = match event {
                 Event::Tsumo { pai, .. }
                 | Event::Dahai { pai, .. }
@@ -82,13 +197,16 @@ impl EkyuMoeReview {
                 _ => None,
             };
             if event.actor() == Some(entry.last_actor) && (last_tsumo_or_discard == Some(entry.tile) || self_riichi_discard) {
-                events_with_details.push((event.clone(), Some(entry.details.clone())));
+                let details = entry
+                    .parse_details()
+                    .with_context(|| format!("corrupt details in kyoku {kyoku} entry {index}"));
+                details_by_event.push(Some(details));
                 index += 1;
                 continue;
             }
-            events_with_details.push((event.clone(), None));
+            details_by_event.push(None);
         }
 
-        events_with_details
+        Ok(details_by_event)
     }
 }
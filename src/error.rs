@@ -0,0 +1,62 @@
+//! Kinds of input the analysis pipeline rejects
+//! Errors stay anyhow chains, a `WashizuError` is attached as context where the input enters the pipeline so callers
+//! can tell bad input apart from other failures with `anyhow::Error::downcast_ref`.
+use std::fmt;
+
+use riichi::mjai::Event;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WashizuError {
+    /// Options that cannot be used, e.g. unknown rules or a malformed scorer
+    Options,
+    /// Hand in the syntax of the hand command
+    Hand(String),
+    /// Board in the syntax of the board command
+    Board,
+    /// Line of a mjai stream or log that is not an event
+    Event(String),
+    /// Event rejected by mortal's state, usually out of order or from another game
+    StateUpdate(String),
+    /// Log file that cannot be read
+    Log(String),
+    /// Analysis whose report does not match the bundled JSON schema, only checked with `--validate-output`
+    Report(String),
+}
+
+impl fmt::Display for WashizuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Options => write!(f, "invalid options"),
+            Self::Hand(hand) => write!(f, "invalid hand {hand}"),
+            Self::Board => write!(f, "invalid board"),
+            Self::Event(line) => write!(f, "malformed event {line}"),
+            Self::StateUpdate(event) => write!(f, "state update failed at {event}"),
+            Self::Log(path) => write!(f, "failed to load log {path}"),
+            Self::Report(reason) => write!(f, "report does not match its schema: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for WashizuError {}
+
+impl WashizuError {
+    /// Rejected state update of an event, shown as its mjai json
+    pub fn state_update(event: &Event) -> Self {
+        Self::StateUpdate(serde_json::to_string(event).unwrap_or_else(|_| format!("{event:?}")))
+    }
+
+    /// Exit code of the command line: 2 for input given on the command line, 3 for logs and event streams and 1 for
+    /// reports that are washizu's own fault
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Options | Self::Hand(_) | Self::Board => 2,
+            Self::Event(_) | Self::StateUpdate(_) | Self::Log(_) => 3,
+            Self::Report(_) => 1,
+        }
+    }
+}
+
+/// Exit code of any error, 1 unless it is caused by rejected input
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    error.downcast_ref::<WashizuError>().map_or(1, WashizuError::exit_code)
+}
@@ -0,0 +1,216 @@
+//! Cheap approximation of the single player tables for hands they cannot afford
+//! Every discard is valued by a one-step lookahead: the chance per tsumo of advancing shanten is its ukeire over the
+//! unseen tiles, kept constant until the hand wins, and a win is worth a static 1 han 30 fu hand grown by the dora kept.
+//! Compared to the exact tables it ignores tegawari, shape changes after the next tsumo and yaku, so it overrates wide
+//! 3+ shanten hands whose ukeire shrinks as they progress and underrates hands with valuable yaku.
+//! The ranking of discards mostly follows ukeire and is meant as a principled fallback, not as exact values: on the
+//! 3-shanten hands of `tests/api.rs` the discard it ranks first is always in the better half of the exact tables.
+//! Fast mode uses it for hands of `FAST_MIN_SHANTEN` or worse, where the exact tables blow up, and every analysis falls
+//! back to it when the tables exceed `AnalysisOptions::sp_budget`.
+use riichi::mjai::Event;
+use riichi::must_tile;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+use crate::order::{SortSpec, sort_by_value};
+use crate::rules::han_gain_ratio;
+use crate::tradeoff::DealInRisk;
+use crate::ukeire::{advancing_tiles, unseen_tiles};
+
+/// Non-dealer points of the static hand value before dora
+const BASE_POINTS: f32 = 1000.0;
+/// Best shanten that fast mode approximates, better hands are still calculated exactly
+pub const FAST_MIN_SHANTEN: i8 = 3;
+
+/// Approximate values of a discard
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct FastEstimate {
+    pub discard: Tile,
+    /// Shanten after the discard
+    pub shanten: i8,
+    /// Unseen tiles that advance shanten
+    pub ukeire: u32,
+    pub win_prob: f32,
+    pub exp_value: f32,
+    /// Combined danger of the discard, filled in by `rank_estimates`
+    #[serde(default)]
+    pub danger: f32,
+}
+
+impl FastEstimate {
+    /// Discard the estimate is about, for the order
+    fn event(&self) -> Event {
+        Event::Dahai {
+            actor: 0,
+            pai: self.discard,
+            tsumogiri: false,
+        }
+    }
+}
+
+/// Whether the single player tables of a hand are replaced by the estimates
+pub fn approximated(shanten: i8, fast: bool) -> bool {
+    fast && shanten >= FAST_MIN_SHANTEN
+}
+
+/// Chance of at least `needed` successes in `trials` independent tries
+pub fn binomial_tail(trials: u32, needed: u32, p: f32) -> f32 {
+    if needed == 0 {
+        return 1.0;
+    }
+    if needed > trials {
+        return 0.0;
+    }
+    // 1 - P(fewer than `needed` successes)
+    let mut below = 0.0;
+    let mut term = (1.0 - p).powi(trials as i32);
+    for k in 0..needed {
+        below += term;
+        if p >= 1.0 {
+            break;
+        }
+        term *= (trials - k) as f32 / (k + 1) as f32 * p / (1.0 - p);
+    }
+    (1.0 - below).clamp(0.0, 1.0)
+}
+
+/// Estimates for every discard in hand sorted by expected value, empty when not at a discard
+pub fn fast_estimates(state: &PlayerState) -> Vec<FastEstimate> {
+    if !state.last_cans.can_discard {
+        return vec![];
+    }
+    let unseen = unseen_tiles(state);
+    let unseen_total = unseen.iter().map(|&left| left as u32).sum::<u32>().max(1);
+    let draws = (state.tiles_left as u32).div_ceil(4);
+    let mut hand = state.tehai;
+    let mut estimates = (0..34)
+        .filter(|&tile| state.tehai[tile] > 0)
+        .map(|discard| {
+            hand[discard] -= 1;
+            let (shanten, advance) = advancing_tiles(&mut hand, state.tehai_len_div3, &unseen);
+            let ukeire = advance.iter().map(|&(_, left)| left as u32).sum::<u32>();
+            hand[discard] += 1;
+
+            let win_prob = binomial_tail(draws, (shanten + 1).max(0) as u32, ukeire as f32 / unseen_total as f32);
+            let doras = state.doras_owned[0].saturating_sub(state.dora_factor[discard]);
+            let points = BASE_POINTS * han_gain_ratio(BASE_POINTS, doras) * if state.is_oya() { 1.5 } else { 1.0 };
+            FastEstimate {
+                discard: must_tile!(discard),
+                shanten,
+                ukeire,
+                win_prob,
+                exp_value: win_prob * points,
+                danger: 0.0,
+            }
+        })
+        .collect::<Vec<_>>();
+    sort_by_value(&mut estimates, &SortSpec::default(), |estimate| {
+        (estimate.exp_value, estimate.event())
+    });
+    estimates
+}
+
+/// Rank estimates by their expected value, which grows with ukeire and the dora kept, ties broken by safety.
+/// Against a riichi the expected value of a discard first loses its chance of dealing into each opponent in riichi at
+/// that opponent's penalty.
+pub fn rank_estimates(estimates: &mut [FastEstimate], combined_danger: &[f32; 34], risk: &DealInRisk) {
+    for estimate in estimates.iter_mut() {
+        estimate.danger = combined_danger[estimate.discard.deaka().as_usize()];
+    }
+    let value = |estimate: &FastEstimate| {
+        if risk.threats.is_empty() {
+            return estimate.exp_value;
+        }
+        let (deal_in, loss) = risk.of(estimate.discard);
+        (1.0 - deal_in) * estimate.exp_value - loss
+    };
+    let spec = SortSpec {
+        danger: Some(*combined_danger),
+        ..Default::default()
+    };
+    sort_by_value(estimates, &spec, |estimate| (value(estimate), estimate.event()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::defense::combined_danger;
+    use crate::tiles::t;
+    use crate::tradeoff::DealInPenalty;
+
+    fn estimate(discard: riichi::tile::Tile, ukeire: u32, exp_value: f32) -> FastEstimate {
+        FastEstimate {
+            discard,
+            shanten: 3,
+            ukeire,
+            win_prob: 0.0,
+            exp_value,
+            danger: 0.0,
+        }
+    }
+
+    /// Risk against the given players in riichi with their danger weights, the first one being the dealer
+    fn risk(riichi: &[usize], weights: &[(Tile, f32)]) -> (DealInRisk, [f32; 34]) {
+        let mut state = PlayerState {
+            oya: 1,
+            ..Default::default()
+        };
+        let danger_weights = std::array::from_fn(|player| {
+            state.riichi_declared[player] = riichi.contains(&player);
+            if riichi.contains(&player) { weights.to_vec() } else { vec![] }
+        });
+        let exposed_waits = Default::default();
+        let combined = combined_danger(&danger_weights, &exposed_waits);
+        let risk = DealInRisk::new(&state, &danger_weights, &exposed_waits, DealInPenalty::default());
+        (risk, combined)
+    }
+
+    fn order(estimates: &[FastEstimate]) -> Vec<Tile> {
+        estimates.iter().map(|estimate| estimate.discard).collect()
+    }
+
+    #[test]
+    fn binomial_tail_counts_at_least_the_needed_successes() {
+        assert_eq!(binomial_tail(3, 0, 0.1), 1.0);
+        assert_eq!(binomial_tail(2, 3, 0.9), 0.0);
+        assert!((binomial_tail(1, 1, 0.25) - 0.25).abs() < 1e-6);
+        assert!((binomial_tail(2, 1, 0.5) - 0.75).abs() < 1e-6);
+        assert!((binomial_tail(2, 2, 0.5) - 0.25).abs() < 1e-6);
+        assert_eq!(binomial_tail(4, 2, 1.0), 1.0);
+    }
+
+    #[test]
+    fn estimates_trade_value_for_safety_only_against_riichi() {
+        let weights = [(t!(5m), 20.0), (t!(9p), 5.0)];
+        let mut estimates = vec![
+            estimate(t!(5m), 40, 500.0),
+            estimate(t!(E), 30, 300.0),
+            estimate(t!(9p), 30, 300.0),
+        ];
+        let (nobody, combined) = risk(&[], &weights);
+        rank_estimates(&mut estimates, &combined, &nobody);
+        assert_eq!(order(&estimates), [t!(5m), t!(9p), t!(E)]);
+        let (dealer, combined) = risk(&[1], &weights);
+        rank_estimates(&mut estimates, &combined, &dealer);
+        assert_eq!(order(&estimates), [t!(E), t!(9p), t!(5m)]);
+        assert_eq!(estimates[2].danger, 20.0);
+    }
+
+    #[test]
+    fn each_riichi_charges_its_own_penalty() {
+        let mut estimates = vec![estimate(t!(5m), 40, 1000.0)];
+        let weights = [(t!(5m), 20.0)];
+        let (dealer, _) = risk(&[1], &weights);
+        let (deal_in, loss) = dealer.of(t!(5m));
+        assert!((deal_in - 0.2).abs() < 1e-6);
+        assert_eq!(loss, 0.2 * DealInPenalty::default().dealer);
+        // two riichi at 20% each deal in 36% of the time, not 40% of the summed danger
+        let (both, combined) = risk(&[1, 2], &weights);
+        rank_estimates(&mut estimates, &combined, &both);
+        assert_eq!(estimates[0].danger, 40.0);
+        let (deal_in, loss) = both.of(t!(5m));
+        assert!((deal_in - 0.36).abs() < 1e-6);
+        let penalty = DealInPenalty::default();
+        assert!((loss - 0.2 * (penalty.dealer + penalty.non_dealer)).abs() < 1e-3);
+    }
+}
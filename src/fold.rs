@@ -0,0 +1,368 @@
+//! Safety-first mode for hands with nothing to gain against a riichi
+//! A hand that is far from tenpai and has little value has no realistic push against a live riichi, so the offensive
+//! single player tables are skipped and the analysis only orders the discards by safety, the way such turns are
+//! triaged at the table. Keiten is estimated with the one-step lookahead of the fast estimates.
+//! Hands that are not hopeless but worth little against a threat get a safety ranking next to the offensive analysis,
+//! reading genbutsu, suji and kabe against every threatening opponent at once.
+use riichi::algo::sp::EventCandidate;
+use riichi::must_tile;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+use crate::defense::combined_danger;
+use crate::fast::{binomial_tail, fast_estimates};
+use crate::ryukyoku::draw_payments;
+use crate::suji::{SujiKind, WallKind, suji_kinds, suspected_tenpai, wall_kinds};
+use crate::tradeoff::DealInRisk;
+use crate::yakuhai::yakuhai_han;
+
+/// Best shanten that is still considered hopeless
+const FOLD_MIN_SHANTEN: i8 = 2;
+/// Most dora a hopeless hand can hold
+const FOLD_MAX_DORA: u8 = 1;
+/// Most tiles outside one suit and the honors of a hand still heading for honitsu, melds counting three tiles
+const HONITSU_MAX_OFF_SUIT: u8 = 3;
+
+/// Defensive plan replacing the offensive analysis
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct FoldPlan {
+    /// Opponents in riichi relative to us
+    pub against: Vec<usize>,
+    /// Tiles in hand from the safest with their combined danger and whether they are genbutsu against every riichi
+    pub safe_order: Vec<(Tile, f32, bool)>,
+    /// Expected score paid when dealing in with each tile of `safe_order`, in the same order
+    #[serde(default)]
+    pub deal_in_costs: Vec<f32>,
+    /// Tiles in hand that are genbutsu against every riichi, the turns we can fold without reading suji
+    pub genbutsu_left: u8,
+    /// Chance to be tenpai at the draw when every draw that advances the hand is kept, an upper bound while folding
+    pub keiten_prob: f32,
+    /// Expected gain of the keiten chance in draw payments
+    pub keiten_value: f32,
+}
+
+impl FoldPlan {
+    /// Tile in hand with the lowest expected deal-in payment and that payment
+    pub fn cheapest(&self) -> Option<(Tile, f32)> {
+        self.safe_order
+            .iter()
+            .zip(&self.deal_in_costs)
+            .map(|(&(tile, _, _), &cost)| (tile, cost))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Expected score given up by discarding a tile instead of the cheapest one, None for actions other than a
+    /// discard of a tile in hand
+    pub fn deal_in_loss(&self, action: &str) -> Option<f32> {
+        let tile = action.parse::<Tile>().ok()?.deaka();
+        let index = self.safe_order.iter().position(|(safe, _, _)| *safe == tile)?;
+        let (_, cheapest) = self.cheapest()?;
+        Some(self.deal_in_costs.get(index)? - cheapest)
+    }
+}
+
+/// Melds of a player as their first tile with the number of tiles they count for in a hand
+fn meld_tiles(state: &PlayerState) -> impl Iterator<Item = usize> + '_ {
+    let kans = state.minkans.iter().chain(&state.ankans);
+    state.pons.iter().chain(kans).map(|&tile| tile as usize)
+}
+
+/// Whether the hand holds value the dora count misses: a yakuhai pair or triplet, or a shape heading for honitsu
+fn has_hidden_value(state: &PlayerState) -> bool {
+    let yakuhai = (27..34).any(|tile| {
+        let triplet = meld_tiles(state).any(|meld| meld == tile);
+        (triplet || state.tehai[tile] >= 2) && yakuhai_han(state, must_tile!(tile)) > 0
+    });
+    let chis = state.chis.iter().map(|&tile| tile as usize);
+    let suit_tiles = |suit: usize| {
+        let in_hand = (suit * 9..suit * 9 + 9).map(|tile| state.tehai[tile]).sum::<u8>();
+        let melded = chis.clone().chain(meld_tiles(state)).filter(|tile| tile / 9 == suit).count() as u8;
+        in_hand + 3 * melded
+    };
+    let honitsu = (0..3).any(|suit| {
+        let off_suit = (0..3).filter(|&other| other != suit).map(suit_tiles).sum::<u8>();
+        off_suit <= HONITSU_MAX_OFF_SUIT
+    });
+    yakuhai || honitsu
+}
+
+/// Opponents in riichi to fold against, empty when the hand is still worth pushing or there is nothing to discard
+pub fn hopeless_against(state: &PlayerState, shanten: i8) -> Vec<usize> {
+    if !state.last_cans.can_discard
+        || state.riichi_declared[0]
+        || shanten < FOLD_MIN_SHANTEN
+        || state.doras_owned[0] > FOLD_MAX_DORA
+        || has_hidden_value(state)
+    {
+        return vec![];
+    }
+    (1..4).filter(|&player| state.riichi_declared[player]).collect()
+}
+
+/// Discards ordered by safety and the keiten outlook against the opponents of `hopeless_against`
+pub fn fold_plan(
+    state: &PlayerState,
+    against: Vec<usize>,
+    danger_weights: &[Vec<(Tile, f32)>; 4],
+    exposed_waits: &[Option<Vec<Tile>>; 4],
+    genbutsu: &[[bool; 34]; 4],
+    risk: &DealInRisk,
+) -> FoldPlan {
+    let combined = combined_danger(danger_weights, exposed_waits);
+    let safe_against_all = |tile: usize| against.iter().all(|&player| genbutsu[player][tile]);
+    let mut safe_order = (0..34)
+        .filter(|&tile| state.tehai[tile] > 0)
+        .map(|tile| (must_tile!(tile), combined[tile], safe_against_all(tile)))
+        .collect::<Vec<_>>();
+    safe_order.sort_by(|a, b| b.2.cmp(&a.2).then(a.1.total_cmp(&b.1)));
+    let deal_in_costs = safe_order.iter().map(|&(tile, _, _)| risk.of(tile).1).collect();
+    let genbutsu_left = (0..34)
+        .filter(|&tile| safe_against_all(tile))
+        .map(|tile| state.tehai[tile])
+        .sum();
+
+    let unseen_total = (0..34)
+        .map(|tile| 4 - state.tiles_seen[tile].min(4) as u32)
+        .sum::<u32>()
+        .max(1);
+    let draws = (state.tiles_left as u32).div_ceil(4);
+    let keiten_prob = safe_order
+        .first()
+        .and_then(|(safest, _, _)| fast_estimates(state).into_iter().find(|estimate| estimate.discard == *safest))
+        .map_or(0.0, |estimate| {
+            binomial_tail(
+                draws,
+                estimate.shanten.max(0) as u32,
+                estimate.ukeire as f32 / unseen_total as f32,
+            )
+        });
+    let payments = draw_payments(state);
+    FoldPlan {
+        against,
+        safe_order,
+        deal_in_costs,
+        genbutsu_left,
+        keiten_prob,
+        keiten_value: keiten_prob * (payments.tenpai - payments.noten),
+    }
+}
+
+/// How a tile in hand is read against every threatening opponent, from the safest
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum SafetyTier {
+    Genbutsu,
+    /// Suji or no-chance against every threat, only a kanchan, penchan, shanpon or tanki can hit it
+    Suji,
+    Other,
+}
+
+impl SafetyTier {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Genbutsu => "genbutsu",
+            Self::Suji => "suji",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Tile in hand in the safety ranking
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct SafeTile {
+    pub tile: Tile,
+    pub tier: SafetyTier,
+    /// Danger summed over the threatening opponents
+    pub danger: f32,
+    /// Weakest suji against the threats, None for honors
+    pub suji: Option<SujiKind>,
+    pub wall: Option<WallKind>,
+}
+
+/// Tiles in hand ranked by safety alone against the opponents we would fold against
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct SafetyRanking {
+    /// Opponents in riichi or with three calls relative to us
+    pub against: Vec<usize>,
+    pub tiles: Vec<SafeTile>,
+    /// Copies in hand that are genbutsu or suji against every threat, the turns we can fold on reads we trust
+    pub safe_turns: u8,
+}
+
+/// Opponents to show betaori against: they threaten us and our best candidate is worth less than `max_ev`
+pub fn betaori_plausible(state: &PlayerState, candidates: &[EventCandidate], max_ev: f32) -> Vec<usize> {
+    let best_ev = candidates
+        .first()
+        .and_then(|candidate| candidate.exp_values.first().copied())
+        .unwrap_or(0.0);
+    if max_ev <= 0.0 || !state.last_cans.can_discard || state.riichi_declared[0] || best_ev >= max_ev {
+        return vec![];
+    }
+    (1..4).filter(|&player| suspected_tenpai(state, player)).collect()
+}
+
+/// Tiles in hand from the safest against the opponents of `betaori_plausible`
+pub fn safety_ranking(
+    state: &PlayerState,
+    against: Vec<usize>,
+    danger_weights: &[Vec<(Tile, f32)>; 4],
+    exposed_waits: &[Option<Vec<Tile>>; 4],
+    genbutsu: &[[bool; 34]; 4],
+) -> SafetyRanking {
+    let threat_weights: [_; 4] = std::array::from_fn(|player| {
+        if against.contains(&player) {
+            danger_weights[player].clone()
+        } else {
+            vec![]
+        }
+    });
+    let threat_waits: [_; 4] = std::array::from_fn(|player| exposed_waits[player].clone().filter(|_| against.contains(&player)));
+    let danger = combined_danger(&threat_weights, &threat_waits);
+    let sujis = against.iter().map(|&player| suji_kinds(state, player)).collect::<Vec<_>>();
+    let walls = wall_kinds(state);
+
+    let mut tiles = (0..34)
+        .filter(|&tile| state.tehai[tile] > 0)
+        .map(|tile| {
+            // the weakest read decides, a tile that is suji against one threat only is not safe to fold on
+            let suji = sujis.iter().filter_map(|kinds| kinds[tile]).max_by_key(|kind| *kind as u8);
+            let tier = if against.iter().all(|&player| genbutsu[player][tile]) {
+                SafetyTier::Genbutsu
+            } else if suji == Some(SujiKind::Suji) || walls[tile] == Some(WallKind::NoChance) {
+                SafetyTier::Suji
+            } else {
+                SafetyTier::Other
+            };
+            SafeTile {
+                tile: must_tile!(tile),
+                tier,
+                danger: danger[tile],
+                suji,
+                wall: walls[tile],
+            }
+        })
+        .collect::<Vec<_>>();
+    tiles.sort_by(|a, b| a.tier.cmp(&b.tier).then(a.danger.total_cmp(&b.danger)));
+    let safe_turns = tiles
+        .iter()
+        .filter(|safe| safe.tier != SafetyTier::Other)
+        .map(|safe| state.tehai[safe.tile.as_usize()])
+        .sum();
+    SafetyRanking {
+        against,
+        tiles,
+        safe_turns,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::state::ActionCandidate;
+    use riichi::{t, tuz};
+
+    use super::*;
+
+    /// Non-dealer facing a riichi from the next player with a closed hand of the given tiles
+    fn facing_riichi(tiles: &[Tile]) -> PlayerState {
+        let mut state = PlayerState {
+            bakaze: t!(E),
+            jikaze: t!(S),
+            last_cans: ActionCandidate {
+                can_discard: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        state.riichi_declared[1] = true;
+        for tile in tiles {
+            state.tehai[tile.as_usize()] += 1;
+        }
+        state
+    }
+
+    #[test]
+    fn scattered_hand_folds_but_yakuhai_and_honitsu_keep_their_value() {
+        let scattered = [
+            t!(1m),
+            t!(4m),
+            t!(8m),
+            t!(2p),
+            t!(6p),
+            t!(9p),
+            t!(3s),
+            t!(7s),
+            t!(9s),
+            t!(E),
+            t!(W),
+            t!(N),
+            t!(P),
+            t!(F),
+        ];
+        assert_eq!(hopeless_against(&facing_riichi(&scattered), 4), [1]);
+
+        let mut dragon_pair = scattered;
+        dragon_pair[13] = t!(P);
+        assert!(hopeless_against(&facing_riichi(&dragon_pair), 4).is_empty());
+        // a pair of a guest wind is worth nothing
+        let mut guest_pair = scattered;
+        guest_pair[13] = t!(W);
+        assert_eq!(hopeless_against(&facing_riichi(&guest_pair), 4), [1]);
+
+        let honitsu = [
+            t!(1m),
+            t!(3m),
+            t!(5m),
+            t!(7m),
+            t!(9m),
+            t!(2m),
+            t!(8m),
+            t!(E),
+            t!(W),
+            t!(N),
+            t!(P),
+            t!(F),
+            t!(2p),
+            t!(9s),
+        ];
+        assert!(hopeless_against(&facing_riichi(&honitsu), 3).is_empty());
+    }
+
+    #[test]
+    fn yakuhai_pon_keeps_the_hand_worth_pushing() {
+        let tiles = [
+            t!(1m),
+            t!(4m),
+            t!(8m),
+            t!(2p),
+            t!(6p),
+            t!(9p),
+            t!(3s),
+            t!(7s),
+            t!(9s),
+            t!(E),
+            t!(W),
+        ];
+        let mut state = facing_riichi(&tiles);
+        assert_eq!(hopeless_against(&state, 4), [1]);
+        state.pons.push(tuz!(C) as u8);
+        assert!(hopeless_against(&state, 4).is_empty());
+    }
+
+    #[test]
+    fn folding_discards_lose_their_deal_in_payment_over_the_cheapest() {
+        let plan = FoldPlan {
+            against: vec![1],
+            safe_order: vec![(t!(E), 0.0, true), (t!(9p), 4.0, false), (t!(5m), 12.0, false)],
+            deal_in_costs: vec![0.0, 200.0, 700.0],
+            genbutsu_left: 1,
+            keiten_prob: 0.0,
+            keiten_value: 0.0,
+        };
+        assert_eq!(plan.cheapest(), Some((t!(E), 0.0)));
+        assert_eq!(plan.deal_in_loss("E"), Some(0.0));
+        assert_eq!(plan.deal_in_loss("5mr"), Some(700.0));
+        // tiles not in hand and actions other than discards are not valued
+        assert_eq!(plan.deal_in_loss("1s"), None);
+        assert_eq!(plan.deal_in_loss("none"), None);
+    }
+}
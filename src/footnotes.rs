@@ -0,0 +1,51 @@
+//! Modeling assumptions behind the candidate table, generated from the options the tables were calculated with
+//! so a copy of the output explains its own numbers.
+use riichi::algo::sp::SPOptions;
+use riichi::state::PlayerState;
+
+use crate::ryukyoku::DrawPayments;
+
+/// Short notes for the candidate table of a decision
+pub fn footnotes(
+    state: &PlayerState,
+    options: &SPOptions,
+    abortive_draw_hazard: Option<f32>,
+    kyuushu_hazard: Option<f32>,
+    draw_payments: Option<DrawPayments>,
+) -> Vec<String> {
+    let mut notes = vec![format!(
+        "tsumo-only over {} tsumos, no ron or calls",
+        (state.tiles_left as u32).div_ceil(4)
+    )];
+    notes.push(if options.prefer_riichi {
+        "tenpai assumed with riichi, ura dora averaged in".to_owned()
+    } else {
+        "tenpai assumed damaten".to_owned()
+    });
+    notes.push(match options.calc_tegawari {
+        Some(shanten) => format!("tegawari up to {shanten} shanten"),
+        None => "no tegawari".to_owned(),
+    });
+    if let Some(shanten) = options.calc_shanten_down {
+        notes.push(format!("shanten down up to {shanten} shanten"));
+    }
+    notes.push(format!("haitei {}", if options.calc_haitei { "on" } else { "off" }));
+    if options.calc_double_riichi {
+        notes.push("double riichi on".to_owned());
+    }
+    notes.push(format!("hands over {} shanten not analyzed", options.max_shanten));
+    if let Some(hazard) = abortive_draw_hazard {
+        notes.push(format!("abortive draw {:.0}% per go-around", hazard * 100.0));
+    }
+    if let Some(hazard) = kyuushu_hazard.filter(|&hazard| hazard > 0.0) {
+        notes.push(format!("kyuushu kyuuhai {:.1}% before our draw", hazard * 100.0));
+    }
+    match draw_payments {
+        Some(payments) => notes.push(format!(
+            "draw pays {:+.0} tenpai / {:+.0} noten",
+            payments.tenpai, payments.noten
+        )),
+        None => notes.push("no draw payments".to_owned()),
+    }
+    notes
+}
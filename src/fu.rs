@@ -0,0 +1,133 @@
+//! Scoring boundaries that depend on fu
+//! The expected values average over every win, so whether a tenpai discard wins for 30 or 40 fu is invisible in them.
+//! Every tenpai discard's waits are scored as ron and the candidate is annotated when the wait or the shape decides
+//! the fu, or when the hand sits right below mangan in a room with kiriage, which rounds it up.
+use riichi::algo::agari::Agari;
+use riichi::algo::point::Point;
+use riichi::algo::sp::EventCandidate;
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+use crate::defense::candidate_discard;
+use crate::rules::RuleSet;
+
+/// Fu and han of a ron on one wait
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy)]
+pub struct FuWin {
+    pub tile: Tile,
+    pub fu: u8,
+    pub han: u8,
+    pub ron: i32,
+}
+
+/// Tenpai discard whose value depends on fu
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct FuBoundary {
+    pub discard: Event,
+    /// Wins on each wait, yakuman and yakunashi waits are left out
+    pub wins: Vec<FuWin>,
+    pub notes: Vec<String>,
+}
+
+/// Whether kiriage rounds a hand up to mangan
+fn kiriage(fu: u8, han: u8) -> bool {
+    matches!((fu, han), (30, 4) | (60, 3))
+}
+
+/// Ron wins on every wait of a tenpai discard
+fn tenpai_wins(state: &PlayerState, rules: &RuleSet, discard: Tile, waits: impl Iterator<Item = Tile>) -> Vec<FuWin> {
+    let mut after = state.clone();
+    after.tehai[discard.deaka().as_usize()] -= 1;
+    if discard.is_aka() {
+        after.akas_in_hand[discard.deaka().as_usize() / 9] = false;
+    }
+    waits
+        .filter_map(|tile| match after.calculate_agari(tile, true, &[]).ok()??.agari {
+            Agari::Normal { fu, han } => Some(FuWin {
+                tile,
+                fu,
+                han,
+                // any 5 han hand is a mangan
+                ron: Point::calc(state.is_oya(), fu, if rules.kiriage && kiriage(fu, han) { 5 } else { han }).ron,
+            }),
+            Agari::Yakuman(_) => None,
+        })
+        .collect()
+}
+
+/// Tenpai discards whose value sits on a fu boundary, only at a discard
+pub fn fu_boundaries(state: &PlayerState, rules: &RuleSet, candidates: &[EventCandidate]) -> Vec<FuBoundary> {
+    if !state.last_cans.can_discard {
+        return vec![];
+    }
+    let tenpai = candidates
+        .iter()
+        .filter(|candidate| candidate.shanten == 0)
+        .filter_map(|candidate| {
+            let discard = candidate_discard(candidate)?;
+            let wins = tenpai_wins(
+                state,
+                rules,
+                discard,
+                candidate.required_tiles.iter().map(|required| required.tile),
+            );
+            Some((candidate, wins))
+        })
+        .filter(|(_, wins)| !wins.is_empty())
+        .collect::<Vec<_>>();
+
+    tenpai
+        .iter()
+        .filter_map(|(candidate, wins)| {
+            let mut notes = vec![];
+            let same_han_fu = |win: &FuWin| wins.iter().any(|other| other.han == win.han && other.fu != win.fu);
+            if wins.iter().any(same_han_fu) {
+                notes.push("the wait decides the fu".to_owned());
+            }
+            let best = wins.iter().max_by_key(|win| win.ron)?;
+            let better_shape = tenpai
+                .iter()
+                .filter(|(other, _)| other.event != candidate.event)
+                .filter_map(|(other, other_wins)| {
+                    let win = other_wins
+                        .iter()
+                        .filter(|win| win.han == best.han && win.fu > best.fu)
+                        .max_by_key(|win| win.fu)?;
+                    Some((other, win))
+                })
+                .max_by_key(|(_, win)| win.fu);
+            if let Some((other, win)) = better_shape {
+                notes.push(format!(
+                    "{} reaches {}fu for {}",
+                    other.event.to_decision_string(),
+                    win.fu,
+                    win.ron
+                ));
+            }
+            if rules.kiriage && wins.iter().any(|win| kiriage(win.fu, win.han)) {
+                notes.push("one fu step below mangan, kiriage rounds it up".to_owned());
+            }
+            (!notes.is_empty()).then(|| FuBoundary {
+                discard: candidate.event.clone(),
+                wins: wins.clone(),
+                notes,
+            })
+        })
+        .collect()
+}
+
+impl FuBoundary {
+    pub fn to_log_string(&self) -> String {
+        format!(
+            "{} {} - {}",
+            self.discard.to_decision_string(),
+            self.wins
+                .iter()
+                .map(|win| format!("{} {}fu{}han {}", win.tile, win.fu, win.han, win.ron))
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.notes.join(", ")
+        )
+    }
+}
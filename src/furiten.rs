@@ -0,0 +1,63 @@
+//! Furiten of opponents from their own discards and the tiles they passed on
+//! A wait containing a furiten tile cannot ron anything, so every tile it covers is safe from it.
+//! Mortal's danger only knows the kawa, tiles passed since an opponent's last discard or after their riichi
+//! have to be tracked from the events next to it.
+use riichi::algo::danger::Wait;
+#[cfg(feature = "cli")]
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+/// Tiles each seat has passed on and cannot ron until its next discard, or for the rest of the kyoku in riichi
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Default)]
+pub struct PassedTiles {
+    /// Absolute seats
+    passed: [Vec<Tile>; 4],
+    riichi: [bool; 4],
+}
+
+#[cfg(feature = "cli")]
+impl PassedTiles {
+    pub fn update(&mut self, event: &Event) {
+        match *event {
+            Event::StartKyoku { .. } => *self = Self::default(),
+            Event::ReachAccepted { actor } => self.riichi[actor as usize] = true,
+            Event::Dahai { actor, pai, .. } | Event::Kakan { actor, pai, .. } => {
+                if matches!(event, Event::Dahai { .. }) && !self.riichi[actor as usize] {
+                    self.passed[actor as usize].clear();
+                }
+                for (seat, passed) in self.passed.iter_mut().enumerate() {
+                    if seat != actor as usize {
+                        passed.push(pai.deaka());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Passed tiles of each player relative to us
+    pub fn relative(&self, player_id: u8) -> [Vec<Tile>; 4] {
+        std::array::from_fn(|player| self.passed[(player + player_id as usize) % 4].clone())
+    }
+}
+
+/// Tiles each opponent cannot ron on: their kawa and the tiles they passed on, always empty for ourselves
+pub fn opponent_furiten(state: &PlayerState, passed: &[Vec<Tile>; 4]) -> [[bool; 34]; 4] {
+    std::array::from_fn(|player| {
+        let mut furiten = [false; 34];
+        if player == 0 {
+            return furiten;
+        }
+        for tile in state.kawa_overview[player].iter().chain(&passed[player]) {
+            furiten[tile.deaka().as_usize()] = true;
+        }
+        furiten
+    })
+}
+
+/// Whether a wait contains a tile the opponent cannot ron on
+pub fn furiten_wait(wait: &Wait, furiten: &[bool; 34]) -> bool {
+    wait.kind.waits.iter().any(|&w| furiten[w as usize])
+}
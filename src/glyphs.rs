@@ -0,0 +1,123 @@
+//! Display style of the tiles in the text output
+//! The output is written in board notation and restyled as a whole, so candidate tables, rivers and danger lists
+//! change together. A tile is a run of digits followed by its suit, e.g. `123m` or `5mr`, or a single honor letter,
+//! standing alone between non-alphanumeric characters. Columns padded for ascii tiles may shift with other styles.
+
+/// Unicode mahjong tiles of the manzu, pinzu and souzu ones and of east
+#[cfg(feature = "cli")]
+const MAN: u32 = 0x1f007;
+#[cfg(feature = "cli")]
+const PIN: u32 = 0x1f019;
+#[cfg(feature = "cli")]
+const SOU: u32 = 0x1f010;
+/// Honors in tile order from east to chun
+#[cfg(feature = "cli")]
+const HONORS: [u32; 7] = [0x1f000, 0x1f001, 0x1f002, 0x1f003, 0x1f006, 0x1f005, 0x1f004];
+/// Mortal's letters of the honors in tile order
+#[cfg(feature = "cli")]
+const HONOR_LETTERS: [char; 7] = ['E', 'S', 'W', 'N', 'P', 'F', 'C'];
+
+#[cfg(feature = "cli")]
+const RESET: &str = "\x1b[0m";
+#[cfg(feature = "cli")]
+const AKA_COLOR: &str = "\x1b[1;35m";
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TileStyle {
+    /// Board notation, e.g. 123m5mr
+    #[default]
+    Ascii,
+    /// Glyphs of the unicode mahjong block
+    Unicode,
+    /// Board notation colored by suit with ansi escapes, red fives in magenta
+    Color,
+}
+
+#[cfg(feature = "cli")]
+fn suit_color(suit: char) -> &'static str {
+    match suit {
+        'm' => "\x1b[31m",
+        'p' => "\x1b[34m",
+        's' => "\x1b[32m",
+        _ => "\x1b[33m",
+    }
+}
+
+#[cfg(feature = "cli")]
+fn glyph(digit: u32, suit: char) -> char {
+    // the block has no red fives, 0 is the red five of board notation
+    let number = if digit == 0 { 5 } else { digit };
+    let code = match suit {
+        'm' => MAN + number - 1,
+        'p' => PIN + number - 1,
+        's' => SOU + number - 1,
+        _ => HONORS[(number as usize - 1).min(6)],
+    };
+    char::from_u32(code).unwrap_or('?')
+}
+
+/// Length of the tile token at the start of `chars` and its digits, suit and whether it is a red five
+#[cfg(feature = "cli")]
+fn tile_token(chars: &[char]) -> Option<(usize, Vec<u32>, char, bool)> {
+    let boundary = |index: usize| chars.get(index).is_none_or(|c| !c.is_ascii_alphanumeric());
+    if let Some(honor) = HONOR_LETTERS.iter().position(|&letter| chars.first() == Some(&letter))
+        && boundary(1)
+    {
+        return Some((1, vec![honor as u32 + 1], 'z', false));
+    }
+    let digits = chars.iter().take_while(|c| c.is_ascii_digit()).count();
+    let suit = *chars.get(digits)?;
+    if digits == 0 || !matches!(suit, 'm' | 'p' | 's' | 'z') {
+        return None;
+    }
+    let numbers = chars[..digits].iter().filter_map(|c| c.to_digit(10)).collect::<Vec<_>>();
+    if suit == 'z' && numbers.iter().any(|&number| !(1..=7).contains(&number)) {
+        return None;
+    }
+    let aka = chars.get(digits + 1) == Some(&'r') && suit != 'z';
+    let len = digits + 1 + aka as usize;
+    boundary(len).then_some((len, numbers, suit, aka))
+}
+
+/// Text with every tile token restyled
+#[cfg(feature = "cli")]
+pub fn render_tiles(text: &str, style: TileStyle) -> String {
+    if style == TileStyle::Ascii {
+        return text.to_owned();
+    }
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut rendered = String::with_capacity(text.len());
+    let mut index = 0;
+    while index < chars.len() {
+        // escapes of the severity colors end in a letter that is not part of a tile
+        if chars[index] == '\x1b' {
+            let end = chars[index..]
+                .iter()
+                .position(|c| c.is_ascii_alphabetic())
+                .map_or(chars.len(), |end| index + end + 1);
+            rendered.extend(&chars[index..end]);
+            index = end;
+            continue;
+        }
+        let after_boundary = index == 0 || !chars[index - 1].is_ascii_alphanumeric();
+        let Some((len, numbers, suit, aka)) = after_boundary.then(|| tile_token(&chars[index..])).flatten() else {
+            rendered.push(chars[index]);
+            index += 1;
+            continue;
+        };
+        match style {
+            TileStyle::Unicode => rendered.extend(numbers.iter().map(|&number| glyph(number, suit))),
+            _ => {
+                rendered.push_str(if aka || numbers.contains(&0) {
+                    AKA_COLOR
+                } else {
+                    suit_color(suit)
+                });
+                rendered.extend(&chars[index..index + len]);
+                rendered.push_str(RESET);
+            }
+        }
+        index += len;
+    }
+    rendered
+}
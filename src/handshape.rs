@@ -0,0 +1,267 @@
+//! Class of the current hand shape and the block decomposition behind its shanten
+//! The closed tiles are split into sets, pairs, taatsu and floating tiles, keeping the split that reaches the hand's
+//! standard shanten with the most sets and ryanmen. Iishanten hands are named by their classic types, chiitoitsu and
+//! kokushi are named when they are as close as the standard shape.
+use riichi::algo::shanten::calc_normal;
+use riichi::must_tile;
+use riichi::state::PlayerState;
+
+use crate::mjaigen::tiles_string;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockKind {
+    Set,
+    /// Called or kan meld
+    Called,
+    Pair,
+    Ryanmen,
+    Kanchan,
+    Penchan,
+    Floating,
+}
+
+/// Block of the decomposition
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub kind: BlockKind,
+    /// Tiles in board notation, aka fives are not told apart
+    pub tiles: String,
+}
+
+/// Class and decomposition of a hand
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct HandShape {
+    /// Standard shanten, chiitoitsu and kokushi are in the class
+    pub shanten: i8,
+    pub class: String,
+    pub blocks: Vec<Block>,
+}
+
+impl HandShape {
+    pub fn to_log_string(&self) -> String {
+        let blocks = self.blocks.iter().map(|block| block.tiles.as_str()).collect::<Vec<_>>();
+        format!("{} | blocks: {}", self.class, blocks.join(" "))
+    }
+}
+
+/// Blocks of one decomposition as tile indices
+#[derive(Debug, Clone, Default)]
+struct Split {
+    blocks: Vec<(BlockKind, Vec<usize>)>,
+}
+
+impl Split {
+    fn count(&self, kind: BlockKind) -> usize {
+        self.blocks.iter().filter(|(block, _)| *block == kind).count()
+    }
+
+    fn taatsu(&self) -> usize {
+        self.count(BlockKind::Ryanmen) + self.count(BlockKind::Kanchan) + self.count(BlockKind::Penchan)
+    }
+
+    /// Standard shanten of the split with `called` melds
+    fn shanten(&self, called: usize) -> i8 {
+        let sets = self.count(BlockKind::Set) + called;
+        let pairs = self.count(BlockKind::Pair);
+        let head = (pairs > 0) as usize;
+        let partial = (self.taatsu() + pairs - head).min(4 - sets.min(4));
+        8 - 2 * sets as i8 - partial as i8 - head as i8
+    }
+}
+
+fn split_hand(counts: &mut [u8; 34], split: &mut Split, best: &mut Option<(Split, (i8, usize, usize))>, called: usize) {
+    let Some(tile) = (0..34).find(|&tile| counts[tile] > 0) else {
+        let key = (
+            split.shanten(called),
+            usize::MAX - split.count(BlockKind::Set),
+            usize::MAX - split.count(BlockKind::Ryanmen),
+        );
+        if best.as_ref().is_none_or(|(_, best_key)| key < *best_key) {
+            *best = Some((split.clone(), key));
+        }
+        return;
+    };
+    let suited = tile < 27;
+    let mut shapes = vec![];
+    if counts[tile] >= 3 {
+        shapes.push((BlockKind::Set, vec![tile; 3]));
+    }
+    if suited && tile % 9 <= 6 && counts[tile + 1] > 0 && counts[tile + 2] > 0 {
+        shapes.push((BlockKind::Set, vec![tile, tile + 1, tile + 2]));
+    }
+    if counts[tile] >= 2 {
+        shapes.push((BlockKind::Pair, vec![tile; 2]));
+    }
+    if suited && tile % 9 <= 7 && counts[tile + 1] > 0 {
+        let kind = if matches!(tile % 9, 0 | 7) {
+            BlockKind::Penchan
+        } else {
+            BlockKind::Ryanmen
+        };
+        shapes.push((kind, vec![tile, tile + 1]));
+    }
+    if suited && tile % 9 <= 6 && counts[tile + 2] > 0 {
+        shapes.push((BlockKind::Kanchan, vec![tile, tile + 2]));
+    }
+    shapes.push((BlockKind::Floating, vec![tile]));
+    for (kind, tiles) in shapes {
+        for &tile in &tiles {
+            counts[tile] -= 1;
+        }
+        split.blocks.push((kind, tiles));
+        split_hand(counts, split, best, called);
+        let (_, tiles) = split.blocks.pop().unwrap();
+        for tile in tiles {
+            counts[tile] += 1;
+        }
+    }
+}
+
+fn chiitoitsu_shanten(hand: &[u8; 34]) -> i8 {
+    let pairs = hand.iter().filter(|&&count| count >= 2).count() as i8;
+    let kinds = hand.iter().filter(|&&count| count > 0).count() as i8;
+    6 - pairs + (7 - kinds).max(0)
+}
+
+fn kokushi_shanten(hand: &[u8; 34]) -> i8 {
+    let yaochuu = (0..34)
+        .filter(|&tile| tile >= 27 || matches!(tile % 9, 0 | 8))
+        .collect::<Vec<_>>();
+    let kinds = yaochuu.iter().filter(|&&tile| hand[tile] > 0).count() as i8;
+    let pair = yaochuu.iter().any(|&tile| hand[tile] >= 2) as i8;
+    13 - kinds - pair
+}
+
+/// Name of a standard hand by shanten and blocks
+fn standard_class(shanten: i8, split: &Split, called: usize) -> String {
+    let sets = split.count(BlockKind::Set) + called;
+    let pairs = split.count(BlockKind::Pair);
+    let ryanmen = split.count(BlockKind::Ryanmen);
+    let taatsu = split.taatsu();
+    match shanten {
+        -1 => "complete hand".to_owned(),
+        0 => "tenpai".to_owned(),
+        1 if sets >= 3 && pairs >= 1 && taatsu == 0 => "1-shanten, kutsuki type".to_owned(),
+        1 if sets >= 3 && pairs == 0 => "1-shanten, headless type".to_owned(),
+        1 if ryanmen >= 2 && pairs >= 2 => "perfect iishanten".to_owned(),
+        1 if ryanmen >= 2 => "1-shanten, ryanmen-ryanmen".to_owned(),
+        1 if pairs >= 3 => "1-shanten, pair-heavy".to_owned(),
+        1 => "1-shanten, weak shapes".to_owned(),
+        shanten => format!("{shanten}-shanten, {sets} sets {taatsu} taatsu {pairs} pairs"),
+    }
+}
+
+/// Class and decomposition of the hand in a state
+pub fn hand_shape(state: &PlayerState) -> HandShape {
+    let mut counts = state.tehai;
+    let called = 4 - (state.tehai_len_div3 as usize).min(4);
+    let mut best = None;
+    split_hand(&mut counts, &mut Split::default(), &mut best, called);
+    let split = best.map(|(split, _)| split).unwrap_or_default();
+    let shanten = calc_normal(&state.tehai, state.tehai_len_div3);
+    let mut class = standard_class(shanten, &split, called);
+    if called == 0 {
+        let chiitoitsu = chiitoitsu_shanten(&state.tehai);
+        let kokushi = kokushi_shanten(&state.tehai);
+        if kokushi < shanten.min(chiitoitsu) {
+            class = format!("{kokushi}-shanten kokushi");
+        } else if chiitoitsu < shanten {
+            class = format!("{chiitoitsu}-shanten chiitoi, standard {class}");
+        } else if chiitoitsu == shanten && shanten >= 0 {
+            class = format!("{class}, chiitoi hybrid");
+        }
+    }
+
+    let mut blocks = split
+        .blocks
+        .iter()
+        .map(|(kind, tiles)| Block {
+            kind: *kind,
+            tiles: tiles_string(&tiles.iter().map(|&tile| must_tile!(tile)).collect::<Vec<_>>()),
+        })
+        .collect::<Vec<_>>();
+    let melds = state.chis.iter().map(|&low| vec![low, low + 1, low + 2]);
+    let melds = melds.chain(state.pons.iter().map(|&tile| vec![tile; 3]));
+    let melds = melds.chain(state.minkans.iter().chain(&state.ankans).map(|&tile| vec![tile; 4]));
+    for tiles in melds {
+        blocks.push(Block {
+            kind: BlockKind::Called,
+            tiles: format!(
+                "[{}]",
+                tiles_string(&tiles.into_iter().map(|tile| must_tile!(tile)).collect::<Vec<_>>())
+            ),
+        });
+    }
+    HandShape { shanten, class, blocks }
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::t;
+
+    use super::*;
+    use crate::mjaigen::parse_tiles;
+
+    fn shape_with(tiles: &str, pons: &[u8]) -> HandShape {
+        let mut state = PlayerState {
+            tehai_len_div3: 4 - pons.len() as u8,
+            ..Default::default()
+        };
+        for tile in parse_tiles(tiles).unwrap() {
+            state.tehai[tile.deaka().as_usize()] += 1;
+        }
+        state.pons.extend(pons.iter().copied());
+        hand_shape(&state)
+    }
+
+    fn class(tiles: &str) -> String {
+        shape_with(tiles, &[]).class
+    }
+
+    #[test]
+    fn complete_and_tenpai_hands() {
+        let shape = shape_with("123m456m789p123s55s", &[]);
+        assert_eq!((shape.shanten, shape.class.as_str()), (-1, "complete hand"));
+        assert_eq!(shape.to_log_string(), "complete hand | blocks: 123m 456m 789p 123s 55s");
+        assert_eq!(class("123m456m789p123s5s"), "tenpai");
+    }
+
+    #[test]
+    fn iishanten_types() {
+        assert_eq!(class("123m456m789p55s19s"), "1-shanten, kutsuki type");
+        assert_eq!(class("123m456m789p24s68s"), "1-shanten, headless type");
+        assert_eq!(class("123m456m34p99p67s11s"), "perfect iishanten");
+        assert_eq!(class("123m456m34p67s99s1z"), "1-shanten, ryanmen-ryanmen");
+        assert_eq!(class("123m456m11p55s99s1z"), "1-shanten, pair-heavy");
+        assert_eq!(class("123m456m13p99p79s1z"), "1-shanten, weak shapes");
+    }
+
+    #[test]
+    fn farther_hands_count_their_blocks() {
+        let shape = shape_with("123m456m159p159s1z", &[]);
+        assert_eq!(shape.shanten, 4);
+        assert_eq!(shape.class, "4-shanten, 2 sets 0 taatsu 0 pairs");
+    }
+
+    #[test]
+    fn chiitoitsu_and_kokushi_are_named_when_they_are_as_close() {
+        assert_eq!(
+            class("11m44m77p99p22s55s1z"),
+            "0-shanten chiitoi, standard 3-shanten, 0 sets 0 taatsu 6 pairs"
+        );
+        assert_eq!(class("556677m22344p88s"), "tenpai, chiitoi hybrid");
+        let shape = shape_with("19m159p19s123456z", &[]);
+        assert_eq!(shape.class, "1-shanten kokushi");
+        // the shanten stays the standard one
+        assert_eq!(shape.shanten, 8);
+    }
+
+    #[test]
+    fn called_melds_are_sets_of_their_own() {
+        let shape = shape_with("123m456m789p5s", &[t!(C).as_u8()]);
+        assert_eq!(shape.class, "tenpai");
+        assert_eq!(shape.to_log_string(), "tenpai | blocks: 123m 456m 789p 5s [777z]");
+        assert_eq!(shape.blocks.last().unwrap().kind, BlockKind::Called);
+    }
+}
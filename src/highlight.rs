@@ -0,0 +1,34 @@
+//! Severity colors of the text output
+//! The recommended candidate is green, dangerous tiles red and furiten, yakuless and other warnings yellow. Styling
+//! goes through console and is left out without the cli feature, so the library output stays plain.
+
+/// Deal-in probability in percent from which a tile is shown as dangerous
+pub const DANGER_HIGHLIGHT: f32 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Recommended,
+    Danger,
+    Warning,
+}
+
+/// Text in the color of a severity, unchanged when `enabled` is false
+pub fn paint(text: &str, severity: Severity, enabled: bool) -> String {
+    if !enabled {
+        return text.to_owned();
+    }
+    #[cfg(feature = "cli")]
+    {
+        let style = match severity {
+            Severity::Recommended => console::Style::new().green().bold(),
+            Severity::Danger => console::Style::new().red(),
+            Severity::Warning => console::Style::new().yellow(),
+        };
+        style.force_styling(true).apply_to(text).to_string()
+    }
+    #[cfg(not(feature = "cli"))]
+    {
+        let _ = severity;
+        text.to_owned()
+    }
+}
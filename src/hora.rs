@@ -0,0 +1,194 @@
+//! Winning as a candidate action next to declining it
+//! When the last event lets us tsumo or ron, the value of the win is compared to declining it, which keeps the hand
+//! the way the single player tables value it. Riichi wins add the ura dora they are expected to reveal while ura dora
+//! are averaged. The win is also listed among the candidates at its value. Ron on a kakan is left to the chankan
+//! analysis.
+use riichi::algo::agari::{Agari, AgariWithYaku};
+use riichi::algo::sp::EventCandidate;
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+use riichi::t;
+use riichi::tile::Tile;
+
+use crate::rules::han_gain_ratio;
+use crate::scoring::extra_points;
+use crate::uradora::{UraDora, expected_ura};
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HoraKind {
+    Tsumo,
+    Ron,
+}
+
+impl HoraKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Tsumo => "tsumo",
+            Self::Ron => "ron",
+        }
+    }
+}
+
+/// Win on the last tile compared to declining it
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct HoraDecision {
+    pub kind: HoraKind,
+    pub tile: Tile,
+    /// Points of the win including honba and kyotaku
+    pub points: i32,
+    /// Ura dora expected on top of the win, 0 unless in riichi with averaged ura dora
+    pub expected_ura: f32,
+    /// Points with the expected ura dora
+    pub points_with_ura: f32,
+    /// Expected value of declining, passing a ron or the best discard after a tsumo
+    pub decline_ev: f32,
+}
+
+impl HoraDecision {
+    pub fn take(&self) -> bool {
+        self.points_with_ura >= self.decline_ev
+    }
+
+    /// Key of the win in the agari of the analysis, unknown for a complete hand
+    pub fn agari_tile(&self) -> Tile {
+        match self.kind {
+            HoraKind::Tsumo => t!(?),
+            HoraKind::Ron => self.tile.deaka(),
+        }
+    }
+
+    /// The win as the event declaring it
+    pub fn event(&self, state: &PlayerState) -> Event {
+        Event::Hora {
+            actor: state.player_id,
+            target: match self.kind {
+                HoraKind::Tsumo => state.player_id,
+                HoraKind::Ron => state.last_cans.target_actor,
+            },
+            deltas: None,
+            ura_markers: None,
+        }
+    }
+}
+
+/// Points of a win with `expected_ura` ura dora on top, a fraction of a han is interpolated from the gain of a whole one
+fn points_with_ura(base: i32, is_oya: bool, expected_ura: f32) -> f32 {
+    let non_dealer = if is_oya { base as f32 / 1.5 } else { base as f32 };
+    base as f32 * (1.0 + expected_ura * (han_gain_ratio(non_dealer, 1) - 1.0))
+}
+
+/// Win offered by the last event, None when neither tsumo nor ron is possible or the win has no yaku
+pub fn hora_decision(
+    state: &PlayerState,
+    agari: &[(Tile, Option<AgariWithYaku>)],
+    candidates: &[EventCandidate],
+    ura_dora: &UraDora,
+) -> Option<HoraDecision> {
+    let (kind, tile) = if state.last_cans.can_tsumo_agari {
+        (HoraKind::Tsumo, state.last_self_tsumo?)
+    } else if state.last_cans.can_ron_agari && state.chankan_chance.is_none() {
+        (HoraKind::Ron, state.last_kawa_tile?)
+    } else {
+        return None;
+    };
+    let key = match kind {
+        HoraKind::Tsumo => t!(?),
+        HoraKind::Ron => tile.deaka(),
+    };
+    let agari_with_yaku = agari
+        .iter()
+        .find(|(wait, _)| wait.deaka() == key)
+        .and_then(|(_, agari)| agari.as_ref())?;
+    let is_oya = state.is_oya();
+    let point = agari_with_yaku.agari.point(is_oya);
+    let base = match kind {
+        HoraKind::Tsumo => point.tsumo_total(is_oya),
+        HoraKind::Ron => point.ron,
+    };
+    let expected_ura = match agari_with_yaku.agari {
+        Agari::Normal { .. } if state.riichi_declared[0] && *ura_dora == UraDora::Averaged => expected_ura(state, tile),
+        _ => 0.0,
+    };
+    let with_ura = points_with_ura(base, is_oya, expected_ura);
+    let extra = extra_points(state.honba, state.kyotaku);
+
+    let decline = match kind {
+        HoraKind::Ron => candidates.iter().find(|candidate| matches!(candidate.event, Event::None)),
+        HoraKind::Tsumo => candidates
+            .iter()
+            .find(|candidate| matches!(candidate.event, Event::Dahai { .. })),
+    };
+    let decline_ev = decline
+        .or(candidates.first())
+        .and_then(|candidate| candidate.exp_values.first().copied())
+        .unwrap_or(0.0);
+    Some(HoraDecision {
+        kind,
+        tile,
+        points: base + extra,
+        expected_ura,
+        points_with_ura: with_ura + extra as f32,
+        decline_ev,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ron(points_with_ura: f32, decline_ev: f32) -> HoraDecision {
+        HoraDecision {
+            kind: HoraKind::Ron,
+            tile: t!(5mr),
+            points: points_with_ura as i32,
+            expected_ura: 0.0,
+            points_with_ura,
+            decline_ev,
+        }
+    }
+
+    #[test]
+    fn win_is_taken_unless_declining_is_worth_more() {
+        assert!(ron(2000.0, 1500.0).take());
+        assert!(ron(2000.0, 2000.0).take());
+        assert!(!ron(2000.0, 2500.0).take());
+        assert_eq!(ron(2000.0, 0.0).agari_tile(), t!(5m));
+        let tsumo = HoraDecision {
+            kind: HoraKind::Tsumo,
+            ..ron(2000.0, 0.0)
+        };
+        assert_eq!(tsumo.agari_tile(), t!(?));
+    }
+
+    #[test]
+    fn expected_ura_counts_the_unseen_indicators_of_our_tiles() {
+        let mut tehai = [0; 34];
+        tehai[t!(2m).as_usize()] = 2;
+        let mut state = PlayerState {
+            tehai,
+            tiles_seen: [4; 34],
+            ..Default::default()
+        };
+        state.dora_indicators.push(t!(E));
+        // every indicator is seen, no ura can show up
+        assert_eq!(expected_ura(&state, t!(9p)), 0.0);
+        // the only unseen tile is a 1m, which makes our 2m ura dora
+        state.tiles_seen[t!(1m).as_usize()] = 3;
+        assert_eq!(expected_ura(&state, t!(9p)), 2.0);
+        // winning on a third 2m adds one more
+        assert_eq!(expected_ura(&state, t!(2m)), 3.0);
+        state.dora_indicators.push(t!(S));
+        assert_eq!(expected_ura(&state, t!(9p)), 4.0);
+    }
+
+    #[test]
+    fn expected_ura_raises_the_points_by_a_share_of_a_han() {
+        assert_eq!(points_with_ura(2000, false, 0.0), 2000.0);
+        let whole = points_with_ura(2000, false, 1.0);
+        assert_eq!(whole, 2000.0 * han_gain_ratio(2000.0, 1));
+        assert!((points_with_ura(2000, false, 0.5) - (2000.0 + whole) / 2.0).abs() < 1e-3);
+        // a dealer hand gains like the non-dealer hand it is worth 1.5 times of
+        assert_eq!(points_with_ura(3000, true, 1.0), 3000.0 * han_gain_ratio(2000.0, 1));
+    }
+}
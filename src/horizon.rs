@@ -0,0 +1,132 @@
+//! Abortive draw hazard for the single player horizon
+//! The single player tables assume the kyoku lasts until the wall runs out,
+//! in volatile boards a four-riichi or four-kan abort may end it before that.
+//! In an uninterrupted first go-around an opponent may also abort with nine terminals and honors before our next draw.
+use riichi::algo::sp::EventCandidate;
+use riichi::state::PlayerState;
+
+/// Chance per go-around that the last player without riichi declares it
+const FOUR_RIICHI_HAZARD: f32 = 0.04;
+/// Chance per go-around that a fourth kan is called
+const FOUR_KAN_HAZARD: f32 = 0.02;
+/// Chance that 14 tiles of a shuffled wall hold nine kinds of terminals and honors, every such hand is taken to abort
+const KYUUSHU_KYUUHAI_CHANCE: f32 = 0.0032;
+
+/// Kans of each player, called or closed
+fn kan_counts(state: &PlayerState) -> [usize; 4] {
+    std::array::from_fn(|player| {
+        state.fuuro_overview[player].iter().filter(|fuuro| fuuro.len() == 4).count() + state.ankan_overview[player].len()
+    })
+}
+
+/// Chance per go-around that the kyoku ends in an abortive draw given the observed riichi and kan counts
+pub fn abortive_draw_hazard(state: &PlayerState) -> f32 {
+    let riichi_count = state.riichi_declared.iter().filter(|&&declared| declared).count();
+    let kans = kan_counts(state);
+
+    let mut hazard = 0.0;
+    if riichi_count == 3 {
+        hazard += FOUR_RIICHI_HAZARD;
+    }
+    if kans.iter().sum::<usize>() == 3 {
+        // a fourth kan by the player holding the other three goes for suukantsu instead of aborting
+        let aborting_players = if kans.contains(&3) { 3.0 } else { 4.0 };
+        hazard += FOUR_KAN_HAZARD * aborting_players / 4.0;
+    }
+    hazard
+}
+
+/// Chance that an opponent aborts with nine terminals and honors at their first draw, before our next draw
+pub fn kyuushu_hazard(state: &PlayerState) -> f32 {
+    // any call or kan ends the first go-around for everyone
+    let interrupted = (0..4).any(|player| !state.fuuro_overview[player].is_empty() || !state.ankan_overview[player].is_empty());
+    if interrupted {
+        return 0.0;
+    }
+    let first_draws_left = (1..4).filter(|&player| state.kawa[player].is_empty()).count();
+    1.0 - (1.0 - KYUUSHU_KYUUHAI_CHANCE).powi(first_draws_left as i32)
+}
+
+/// Weight each tsumo's contribution to the candidate's values by the chance the kyoku survives until it, given the
+/// chance per go-around and the chance of an abort before our next draw
+pub fn apply_hazard(candidate: &mut EventCandidate, hazard: f32, before_next_draw: f32) {
+    if hazard <= 0.0 && before_next_draw <= 0.0 {
+        return;
+    }
+    for values in [
+        &mut candidate.exp_values,
+        &mut candidate.win_probs,
+        &mut candidate.tenpai_probs,
+    ] {
+        // values[n] is accumulated over `len - n` tsumos, so the increment over values[n + 1] belongs to the last tsumo
+        let len = values.len();
+        let mut adjusted = vec![0.0; len];
+        let mut accumulated = 0.0;
+        for n in (0..len).rev() {
+            let increment = values[n] - values.get(n + 1).copied().unwrap_or(0.0);
+            accumulated += increment * (1.0 - before_next_draw) * (1.0 - hazard).powi((len - n - 1) as i32);
+            adjusted[n] = accumulated;
+        }
+        *values = adjusted;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::mjai::Event;
+    use riichi::t;
+    use tinyvec::ArrayVec;
+
+    use super::*;
+
+    fn with_kans(owners: &[usize]) -> PlayerState {
+        let mut state = PlayerState::default();
+        for &player in owners {
+            state.ankan_overview[player].push(t!(E));
+        }
+        state
+    }
+
+    #[test]
+    fn fourth_kan_aborts_unless_it_makes_suukantsu() {
+        assert_eq!(abortive_draw_hazard(&with_kans(&[0, 1])), 0.0);
+        assert_eq!(abortive_draw_hazard(&with_kans(&[0, 1, 1])), FOUR_KAN_HAZARD);
+        // only the three other players abort with the fourth kan
+        assert_eq!(abortive_draw_hazard(&with_kans(&[2, 2, 2])), FOUR_KAN_HAZARD * 0.75);
+        // four kans of one player leave nothing to abort
+        assert_eq!(abortive_draw_hazard(&with_kans(&[2, 2, 2, 2])), 0.0);
+    }
+
+    #[test]
+    fn kyuushu_is_only_possible_before_the_first_go_around_ends() {
+        let mut state = PlayerState::default();
+        let all_three = kyuushu_hazard(&state);
+        assert!((all_three - (1.0 - (1.0 - KYUUSHU_KYUUHAI_CHANCE).powi(3))).abs() < 1e-6);
+        state.kawa[1].push(None);
+        state.kawa[2].push(None);
+        assert!((kyuushu_hazard(&state) - KYUUSHU_KYUUHAI_CHANCE).abs() < 1e-6);
+        state.fuuro_overview[3].push(ArrayVec::from_array_len([t!(P); 4], 3));
+        assert_eq!(kyuushu_hazard(&state), 0.0);
+        assert_eq!(kyuushu_hazard(&with_kans(&[0])), 0.0);
+    }
+
+    #[test]
+    fn abort_before_the_next_draw_discounts_every_tsumo() {
+        let mut candidate = EventCandidate {
+            event: Event::None,
+            shanten: 0,
+            num_required_tiles: 0,
+            required_tiles: vec![],
+            exp_values: vec![3000.0, 2000.0, 1000.0],
+            win_probs: vec![0.3, 0.2, 0.1],
+            tenpai_probs: vec![1.0, 1.0, 1.0],
+            yaku: vec![],
+        };
+        apply_hazard(&mut candidate, 0.0, 0.5);
+        assert_eq!(candidate.exp_values, [1500.0, 1000.0, 500.0]);
+        assert_eq!(candidate.tenpai_probs, [0.5, 0.5, 0.5]);
+        // the go-around hazard leaves the first tsumo alone and compounds after it
+        apply_hazard(&mut candidate, 0.5, 0.0);
+        assert_eq!(candidate.exp_values, [875.0, 750.0, 500.0]);
+    }
+}
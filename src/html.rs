@@ -0,0 +1,204 @@
+//! Self-contained HTML review of a full game
+use std::fmt::Write;
+
+use riichi::mjai::Event;
+use riichi::must_tile;
+use riichi::tile::Tile;
+
+use crate::benchmark::ordinal;
+use crate::report::AnalysisReport;
+use crate::review::{BoardSnapshot, ReviewDecision};
+
+const SEATS: [&str; 4] = ["self", "shimocha", "toimen", "kamicha"];
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin: 0.5em 0; }
+td, th { border: 1px solid #ccc; padding: 2px 6px; text-align: right; }
+.decision { border-top: 2px solid #444; padding: 0.5em 0; }
+.mistake { background: #fff0f0; }
+.actual { font-weight: bold; }
+.heat td { width: 1.6em; text-align: center; font-size: 0.8em; }
+";
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn tiles(tiles: &[Tile]) -> String {
+    tiles.iter().map(|tile| tile.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+fn decision_string(event: Option<&Event>) -> String {
+    event.map_or_else(|| "?".to_owned(), |event| escape(&event.to_decision_string()))
+}
+
+fn render_summary(out: &mut String, decisions: &[ReviewDecision]) {
+    let mistakes = decisions
+        .iter()
+        .enumerate()
+        .filter(|(_, decision)| decision.is_mistake())
+        .collect::<Vec<_>>();
+    let reviewed = decisions
+        .iter()
+        .filter(|decision| !decision.report.details.is_empty())
+        .count();
+    _ = writeln!(
+        out,
+        "<h2>Summary</h2><p>{} decisions, {} reviewed by mortal, {} differ from mortal's choice</p>",
+        decisions.len(),
+        reviewed,
+        mistakes.len()
+    );
+    if mistakes.is_empty() {
+        return;
+    }
+    out.push_str(
+        "<table><tr><th>round</th><th>turn</th><th>actual</th><th>mortal</th><th>actual prob</th><th>EV loss</th></tr>\n",
+    );
+    for (index, decision) in mistakes {
+        _ = writeln!(
+            out,
+            "<tr><td><a href=\"#d{index}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&decision.board.round),
+            decision.board.turn,
+            decision_string(decision.actual.as_ref()),
+            decision_string(decision.mortal_choice().map(|detail| &detail.action)),
+            decision
+                .actual_prob()
+                .map_or_else(|| "-".to_owned(), |prob| format!("{:.1}%", prob * 100.0)),
+            decision
+                .ev_loss()
+                .map_or_else(|| "-".to_owned(), |ev_loss| format!("{:.0}", ev_loss.loss)),
+        );
+    }
+    out.push_str("</table>\n");
+}
+
+fn render_board(out: &mut String, board: &BoardSnapshot) {
+    _ = writeln!(
+        out,
+        "<p>{} dora {} | hand <b>{}</b>{}</p>",
+        escape(&board.round),
+        tiles(&board.dora_indicators),
+        escape(&board.tehai),
+        board
+            .fuuro
+            .iter()
+            .map(|fuuro| format!(" [{}]", tiles(fuuro)))
+            .collect::<String>()
+    );
+    out.push_str("<table><tr><th>seat</th><th>score</th><th>discards</th></tr>\n");
+    for (player, seat) in SEATS.iter().enumerate() {
+        _ = writeln!(
+            out,
+            "<tr><td>{seat}{}</td><td>{}</td><td style=\"text-align: left\">{}</td></tr>",
+            if board.riichi_declared[player] { " (riichi)" } else { "" },
+            board.scores[player],
+            tiles(&board.kawa[player])
+        );
+    }
+    out.push_str("</table>\n");
+}
+
+fn render_candidates(out: &mut String, decision: &ReviewDecision) {
+    let report = &decision.report;
+    if report.candidates.is_empty() {
+        return;
+    }
+    let actual = decision.actual.as_ref().map(|event| event.to_decision_string());
+    out.push_str(
+        "<table><tr><th>act</th><th>EV</th><th>&Delta;</th><th>win%</th><th>draws</th><th>tenpai%</th><th>shanten</th><th>ukeire</th><th>mortal</th></tr>\n",
+    );
+    for candidate in &report.candidates {
+        let prob = report
+            .details
+            .iter()
+            .find(|detail| detail.action.to_decision_string() == candidate.action)
+            .map_or_else(String::new, |detail| format!("{:.1}%", detail.prob * 100.0));
+        _ = writeln!(
+            out,
+            "<tr{}><td>{}</td><td>{:.0}</td><td>{:.0}</td><td>{:.2}</td><td>{}</td><td>{:.2}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            if actual.as_ref() == Some(&candidate.action) {
+                " class=\"actual\""
+            } else {
+                ""
+            },
+            escape(&candidate.action),
+            candidate.exp_value,
+            candidate.delta,
+            candidate.win_prob * 100.0,
+            candidate
+                .expected_draws
+                .map_or_else(|| "-".to_owned(), |draws| format!("{draws:.1}")),
+            candidate.tenpai_prob * 100.0,
+            candidate.shanten,
+            candidate.num_required_tiles,
+            prob
+        );
+    }
+    out.push_str("</table>\n");
+}
+
+fn render_heatmap(out: &mut String, report: &AnalysisReport) {
+    let max = report.danger.iter().flatten().map(|(_, weight)| *weight).fold(0.0, f32::max);
+    if max <= 0.0 {
+        return;
+    }
+    out.push_str("<table class=\"heat\">\n");
+    for (player, danger) in report.danger.iter().enumerate().skip(1) {
+        _ = write!(out, "<tr><th>{}</th>", SEATS[player]);
+        for tile in 0..34 {
+            let weight = danger
+                .iter()
+                .find(|(t, _)| t.deaka().as_usize() == tile)
+                .map_or(0.0, |(_, weight)| *weight);
+            let alpha = weight / max;
+            _ = write!(
+                out,
+                "<td style=\"background: rgba(220, 30, 30, {alpha:.2})\" title=\"{weight:.1}\">{}</td>",
+                must_tile!(tile)
+            );
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>\n");
+}
+
+/// Render a full game review as a single HTML page without external resources
+pub fn render_review(title: &str, decisions: &[ReviewDecision]) -> String {
+    let mut out = String::new();
+    _ = writeln!(
+        out,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title><style>{STYLE}</style></head><body>\n<h1>{0}</h1>",
+        escape(title)
+    );
+    render_summary(&mut out, decisions);
+    for (index, decision) in decisions.iter().enumerate() {
+        _ = writeln!(
+            out,
+            "<div class=\"decision{}\" id=\"d{index}\"><h3>{} after {}</h3>",
+            if decision.is_mistake() { " mistake" } else { "" },
+            escape(&decision.board.round),
+            escape(&format!("{:?}", decision.trigger))
+        );
+        render_board(&mut out, &decision.board);
+        _ = writeln!(
+            out,
+            "<p>actual {} | mortal {} | shanten {}{} | {} percentile</p>",
+            decision_string(decision.actual.as_ref()),
+            decision_string(decision.mortal_choice().map(|detail| &detail.action)),
+            decision.report.shanten,
+            if decision.report.furiten { " furiten" } else { "" },
+            ordinal(decision.report.percentile.round() as u32)
+        );
+        render_candidates(&mut out, decision);
+        render_heatmap(&mut out, &decision.report);
+        out.push_str("</div>\n");
+    }
+    out.push_str("</body></html>\n");
+    out
+}
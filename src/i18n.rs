@@ -0,0 +1,448 @@
+//! Japanese help text and error messages of the command line
+//! The language is taken from --lang, then WASHIZU_LANG, then LANG. Text without a translation stays in English.
+use clap::Command;
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    #[default]
+    En,
+    Ja,
+}
+
+impl Lang {
+    /// Language of a locale or language code, e.g. ja, ja_JP.UTF-8 or en_US
+    pub fn from_code(code: &str) -> Option<Self> {
+        let code = code.to_lowercase();
+        if code.starts_with("ja") {
+            Some(Self::Ja)
+        } else if code.starts_with("en") || code == "c" || code == "posix" {
+            Some(Self::En)
+        } else {
+            None
+        }
+    }
+
+    /// Language of the process, read before clap parses the arguments since help is printed while parsing
+    pub fn detect(args: &[String]) -> Self {
+        let flag = args
+            .iter()
+            .enumerate()
+            .find_map(|(index, arg)| match arg.strip_prefix("--lang") {
+                Some("") => args.get(index + 1).cloned(),
+                Some(value) => value.strip_prefix('=').map(str::to_owned),
+                None => None,
+            });
+        flag.into_iter()
+            .chain(std::env::var("WASHIZU_LANG"))
+            .chain(std::env::var("LANG"))
+            .find_map(|code| Self::from_code(&code))
+            .unwrap_or_default()
+    }
+}
+
+const COMMANDS_JA: &[(&str, &str)] = &[
+    ("hand", "手牌を解析する"),
+    ("board", "盤面全体を解析する"),
+    ("agari", "和了形の役・符の内訳・支払いを計算する"),
+    ("board-json", "JSONファイルの盤面を解析する"),
+    ("parse", "盤面をmjaiイベントに変換する"),
+    ("export", "mjaiログの現在の局を盤面の表記で表示する"),
+    ("live", "標準入力のmjaiイベントをリアルタイムで解析する"),
+    ("repl", "プロンプトで手牌を組み立てながら解析する"),
+    (
+        "schema",
+        "入出力形式のJSONスキーマを表示する: analysis-report, board, candidate, danger",
+    ),
+    ("tui", "mjaiログの判断を対話的に確認する"),
+    ("ekyumoe", "ekyumoeの検討結果を解析する"),
+    ("batch", "ディレクトリ内の全ログを検討し、EV損失・ミスの種類・放銃をまとめる"),
+    (
+        "duplicate",
+        "ログの行動と本ツールの選択を全判断で比較し、期待値の差を合計する",
+    ),
+    ("rollout", "全席ヒューリスティックで局の残りをシミュレーションする"),
+    (
+        "simulate-waits",
+        "他家の手牌としてありうる聴牌形を列挙し、待ちと打点の分布を表示する",
+    ),
+    ("daemon", "HTTPで解析リクエストを受け付ける"),
+    ("stats", "立直率・副露率・和了率・放銃率・平均打点・平均順位を集計する"),
+    (
+        "verify-data",
+        "データディレクトリのテーブルをchecksums.txtのチェックサムで検証する",
+    ),
+    ("db", "解析済みログの検討データベース"),
+    ("add", "mjaiログを解析して保存する"),
+    ("reanalyze", "保存された解析を現在の設定で再計算する"),
+];
+
+const ARGS_JA: &[(&str, &str)] = &[
+    (
+        "abortive_draws",
+        "四家立直・四開槓・九種九牌による途中流局の確率で後のツモを割り引く",
+    ),
+    ("draw_payments", "和了なしで終わる局を荒牌流局のテンパイ料で評価する"),
+    ("kan_dora_cost", "槓で他家に乗る新ドラの損失を槓の候補から差し引く"),
+    ("max_shanten", "一人麻雀計算で解析する向聴数の上限"),
+    ("safe_keep", "向聴数を保つ中で最も安全な打牌を表示する"),
+    (
+        "open_riichi_waits",
+        "オープン立直の待ちを <相対席>:<待ち> で指定する、例: 1:36m、自分の立直は 0:",
+    ),
+    ("visible", "相手の手牌の透明牌を <相対席>:<牌> で指定する、例: 1:123m55z"),
+    ("nukidora", "抜いた北の枚数、liveではnukidoraイベントから数える"),
+    ("rules", "ルール: tenhou, majsoul またはtenhouルールを上書きするtomlファイル"),
+    ("open_riichi", "オープン立直を許可する"),
+    ("sanma", "北抜きありの三人麻雀ルール"),
+    ("washizu", "透明牌ありの鷲巣麻雀"),
+    (
+        "scorer",
+        "評価関数の重み付き和で候補を並べる (ev, win, placement, safety)、例: ev+safety*100",
+    ),
+    ("objective", "候補を期待点数か終局時の期待順位点で並べる"),
+    ("pt", "placementで使う1位から4位の順位点"),
+    (
+        "safety_floor",
+        "立直に対して最善候補の残す現物がこれより少なければ警告する、0で無効",
+    ),
+    (
+        "deal_in_penalty",
+        "子の立直に放銃したときの推定失点、守備を考慮した期待値に使う",
+    ),
+    ("dealer_deal_in_penalty", "親の立直に放銃したときの推定失点"),
+    (
+        "plugin",
+        "全ての解析を一行のJSONで標準入力に受け取り、それぞれに追加項目のJSONオブジェクトを出力するコマンド、解析の間も起動したまま、複数指定可",
+    ),
+    (
+        "plugin_timeout",
+        "プラグインの応答を待つミリ秒、過ぎると停止して追加項目を省く",
+    ),
+    (
+        "fast",
+        "三向聴以上の手は一人麻雀テーブルを省略し、受け入れ・ドラ・安全度で打牌を見積もる",
+    ),
+    (
+        "sp_budget_ms",
+        "一人麻雀テーブルの計算に許すミリ秒、超えると代わりに簡易見積もりを表示する",
+    ),
+    ("ukeire", "全ての打牌について有効牌と改良牌を内訳表示する"),
+    ("validate_output", "全ての解析結果をJSONスキーマで検証し、不一致なら中断する"),
+    (
+        "export_tree",
+        "解析の代わりに探索木の上部を出力する、例: depth=2,width=3,format=dot",
+    ),
+    (
+        "emit_mjai",
+        "board・board-json・ekyumoe の入力をmjaiイベントとしてこのファイルにも書き出す",
+    ),
+    (
+        "no_auto_fold",
+        "望みのない手でも立直に対して攻撃の解析を行い、ベタオリに切り替えない",
+    ),
+    (
+        "betaori_ev",
+        "立直や三副露に対して最善候補の期待値がこれ未満なら手牌を安全度順に並べる、0で無効",
+    ),
+    ("tiles", "解析中の牌の表示形式、例: unicode で麻雀牌の文字を使う"),
+    (
+        "no_color",
+        "解析結果を色なしで表示する、NO_COLOR や端末以外への出力でも無効になる",
+    ),
+    ("rollout", "各打牌候補の後に局の残りをこの回数シミュレーションする、0で無効"),
+    (
+        "mortal_model",
+        "ekyumoeの詳細がない判断にMortalモデル(ONNX)の行動確率を表示する",
+    ),
+    ("mortal_version", "Mortalモデルの学習時の観測エンコードのバージョン"),
+    (
+        "profile",
+        "終了時に各判断の一人麻雀計算・危険度・和了判定の所要時間を表示する",
+    ),
+    ("no_ura", "立直の和了に裏ドラの平均を含めない"),
+    (
+        "ura_known",
+        "終局した手の裏ドラ表示牌、平均の代わりに待ちの和了に数える、例: 3p",
+    ),
+    ("aka_count", "ルールを上書きする赤五の枚数: 0 または 3"),
+    ("yaku_names", "役名の表記、--lang jaでは日本語、それ以外ではローマ字が既定"),
+    ("lang", "ヘルプとエラーメッセージの言語"),
+];
+
+/// Arguments of the subcommands meaning the same in every subcommand defining them
+const SHARED_ARGS_JA: &[(&str, &str)] = &[
+    ("player_id", "プレイヤーの席、省略時はStartGameから検出する"),
+    ("player_name", "StartGameの名前と照合して席を検出する"),
+    ("kyoku", "この局の判断のみ解析する、例: E2"),
+    ("turn", "この巡目の判断のみ解析する"),
+    ("step", "解析ごとに入力を待ち、前後の判断に移動する"),
+    ("fuuro", "副露をスペース区切りで指定する、例: 555z 406p"),
+    ("ankan", "暗槓をスペース区切りで指定する"),
+    ("dora_indicators", "ドラ表示牌"),
+    ("bakaze", "場風"),
+    ("jikaze", "自風"),
+];
+
+const BOARD_JA: &str = "boardコマンドと同じ表記の盤面";
+const REQUIRED_PLAYER_NAME_JA: &str = "StartGameの名前と照合して検討する席を検出する、プレイヤーIDのないmjaiログでは必須";
+
+/// Arguments of one subcommand by the subcommand's name, taking precedence over the shared ones
+const SUBCOMMAND_ARGS_JA: &[(&str, &str, &str)] = &[
+    (
+        "hand",
+        "tehai",
+        "手牌、または 123m456p789s1122z dora 5m east seat 1 のような何切る問題",
+    ),
+    ("hand", "tiles_left", "残り枚数、省略時は70"),
+    ("agari", "tehai", "和了牌を除いた手牌"),
+    ("agari", "pai", "和了牌"),
+    ("agari", "tsumo", "ロンではなくツモで和了する"),
+    ("agari", "ura_indicators", "裏ドラ表示牌"),
+    ("agari", "riichi", "立直"),
+    ("agari", "double_riichi", "ダブル立直"),
+    ("agari", "ippatsu", "一発"),
+    ("agari", "rinshan", "槓の嶺上牌でツモ和了する"),
+    ("agari", "haitei", "最後の牌で和了する、ツモは海底、ロンは河底"),
+    ("agari", "honba", "本場"),
+    ("agari", "kyotaku", "供託"),
+    (
+        "board",
+        "args",
+        "盤面、例: E1 E 0 0 1m 25000 25000 25000 25000 123m456p789s11223z / / / / / / / /",
+    ),
+    ("board", "all_seats", "全席の判断を解析する、他家の手牌は見えていない牌で補う"),
+    ("board-json", "path", "`washizu schema board` のJSON形式の盤面、標準入力は -"),
+    (
+        "board-json",
+        "all_seats",
+        "全席の判断を解析する、他家の手牌は見えていない牌で補う",
+    ),
+    ("parse", "args", BOARD_JA),
+    ("export", "path", "mjaiログ、標準入力は -"),
+    (
+        "live",
+        "alternate_screen",
+        "代替画面に描画し、終了時に最後の解析を通常の画面に残す",
+    ),
+    ("live", "no_clear", "画面を再描画せずに解析を追記する"),
+    (
+        "live",
+        "listen",
+        "標準入力の代わりにこのアドレスでWebSocketのイベントを受け付け、JSONの解析で応答する",
+    ),
+    (
+        "live",
+        "spectator",
+        "一人のプレイヤーを解析する代わりに、全員の手牌が見える配信の全席を解説する",
+    ),
+    ("live", "db", "タイムスタンプ付きイベントの考慮時間を保存する検討データベース"),
+    ("schema", "name", "スキーマ名: analysis-report, board, candidate, danger"),
+    ("tui", "path", "一行一イベントのmjaiログ、省略時は手牌エディタのみ"),
+    (
+        "ekyumoe",
+        "path",
+        "ekyumoeの検討結果、標準入力は -、gzip圧縮や複数の検討の連結も読める",
+    ),
+    (
+        "ekyumoe",
+        "player_name",
+        "ログのプレイヤーIDの代わりにStartGameの名前と照合して席を検出する",
+    ),
+    (
+        "ekyumoe",
+        "player",
+        "ログのプレイヤーの代わりにこの席を検討する、検討対象の席以外はMortalの詳細なし",
+    ),
+    ("ekyumoe", "only_mistakes", "選んだ行動が最善候補よりEVを失う判断のみ表示する"),
+    ("ekyumoe", "min_ev_loss", "選んだ行動がこれ以上のEVを失う判断のみ表示する"),
+    ("ekyumoe", "report", "対局の単体のHTML検討レポートを書き出す"),
+    ("ekyumoe", "review", "期待値を失った判断を重大な順に表示する"),
+    (
+        "ekyumoe",
+        "cross_check",
+        "Mortalの選択が本ツールの解析でこれ以上のEVを失うか危険度が異なる判断を列挙する",
+    ),
+    (
+        "ekyumoe",
+        "cross_check_danger",
+        "Mortalと本ツールの打牌の放銃率がこのパーセント以上異なる判断も列挙する",
+    ),
+    (
+        "ekyumoe",
+        "strict_details",
+        "判断の詳細が壊れているとき、警告してMortalの詳細なしで解析する代わりに終了する",
+    ),
+    ("ekyumoe", "all_seats", "他の席の判断もMortalの詳細なしで解析する"),
+    ("ekyumoe", "oracle", "各打牌をその後に実際に引いた牌で再現する"),
+    (
+        "ekyumoe",
+        "spectator",
+        "一人のプレイヤーを検討する代わりに、ログのツモで全席を解説する",
+    ),
+    ("batch", "dir", "検討するログのディレクトリ"),
+    ("batch", "player_name", REQUIRED_PLAYER_NAME_JA),
+    ("batch", "csv", "対局ごとに一行をこのCSVファイルにも書き出す"),
+    (
+        "batch",
+        "checkpoint",
+        "検討した対局を終わり次第このファイルに記録する、ディレクトリの外に置くこと",
+    ),
+    ("batch", "resume", "中断した実行のチェックポイントに記録済みの対局を飛ばす"),
+    ("duplicate", "log", "ekyumoeの検討結果またはmjaiログ"),
+    ("duplicate", "player_name", REQUIRED_PLAYER_NAME_JA),
+    ("rollout", "board", BOARD_JA),
+    ("rollout", "n", "プレイアウトの回数"),
+    ("rollout", "seed", "乱数のシード"),
+    ("simulate-waits", "board", BOARD_JA),
+    ("simulate-waits", "opponent", "盤面のプレイヤーから見た相手、1は下家"),
+    ("daemon", "listen", "待ち受けるアドレス"),
+    ("daemon", "threads", "同時に処理するリクエスト数、省略時はCPU数"),
+    ("stats", "paths", "ログまたはログのディレクトリ"),
+    ("stats", "player_name", REQUIRED_PLAYER_NAME_JA),
+    ("verify-data", "dir", "データディレクトリ"),
+    ("db", "db", "データベースファイル、なければ作成する"),
+    ("add", "paths", "mjaiログ"),
+    (
+        "reanalyze",
+        "stale",
+        "別のモデル・ルール・レポートのバージョンで作られた項目のみ",
+    ),
+];
+
+/// Error messages by pattern, `{}` stands for the rest of the message
+const ERRORS_JA: &[(&str, &str)] = &[
+    ("failed to open {}", "{}を開けませんでした"),
+    ("failed to read {}", "{}を読み込めませんでした"),
+    ("failed to write {}", "{}を書き込めませんでした"),
+    ("failed to create {}", "{}を作成できませんでした"),
+    ("malformed {}", "{}の形式が正しくありません"),
+    ("incorrect {}", "{}が正しくありません"),
+    ("missing {}", "{}がありません"),
+    ("empty {}", "{}が空です"),
+    ("unknown {}", "不明な{}"),
+    ("no events", "イベントがありません"),
+    (
+        "first event must be StartGame",
+        "最初のイベントはStartGameでなければなりません",
+    ),
+    (
+        "StartGame has no player id, specify the player id or name",
+        "StartGameにプレイヤーIDがありません。IDか名前を指定してください",
+    ),
+    ("unsupported variant: {}", "対応していないルール: {}"),
+    ("invalid options", "オプションが正しくありません"),
+    ("invalid board", "盤面が正しくありません"),
+    ("invalid hand {}", "手牌{}が正しくありません"),
+    ("state update failed at {}", "{}で状態を更新できませんでした"),
+    ("failed to load log {}", "ログ{}を読み込めませんでした"),
+    ("report does not match its schema: {}", "解析結果がスキーマと一致しません: {}"),
+];
+
+const TERMS_JA: &[(&str, &str)] = &[
+    ("tiles left", "残り枚数"),
+    ("bakaze", "場風"),
+    ("jikaze", "自風"),
+    ("kyoku", "局"),
+    ("honba", "本場"),
+    ("kyotaku", "供託"),
+    ("tehai", "手牌"),
+    ("fuuro", "副露"),
+    ("kawa", "河"),
+    ("dora", "ドラ"),
+    ("score", "点数"),
+    ("turn", "巡目"),
+    ("json", "JSON"),
+];
+
+/// Help of the arguments a command defines itself, arguments compiled out by a feature are skipped
+fn localize_args<'a>(mut command: Command, helps: impl IntoIterator<Item = &'a (&'static str, &'static str)>) -> Command {
+    for &(id, help) in helps {
+        if command.get_arguments().any(|arg| arg.get_id() == id) {
+            command = command.mut_arg(id, |arg| arg.help(help));
+        }
+    }
+    command
+}
+
+/// Japanese about and argument help of a subcommand and of its own subcommands
+fn localize_subcommand(mut subcommand: Command) -> Command {
+    let name = subcommand.get_name().to_owned();
+    if let Some((_, about)) = COMMANDS_JA.iter().find(|(command, _)| *command == name) {
+        subcommand = subcommand.about(*about);
+    }
+    let specific = SUBCOMMAND_ARGS_JA
+        .iter()
+        .filter(|(command, _, _)| *command == name)
+        .map(|(_, id, help)| (*id, *help))
+        .collect::<Vec<_>>();
+    subcommand = localize_args(subcommand, SHARED_ARGS_JA.iter().chain(&specific));
+    let names = subcommand
+        .get_subcommands()
+        .map(|nested| nested.get_name().to_owned())
+        .collect::<Vec<_>>();
+    for name in names {
+        subcommand = subcommand.mut_subcommand(name, localize_subcommand);
+    }
+    subcommand
+}
+
+/// Help of the subcommands, their arguments and the global options in the language
+pub fn localize_command(command: Command, lang: Lang) -> Command {
+    if lang != Lang::Ja {
+        return command;
+    }
+    // the top level has no about of its own to translate
+    localize_subcommand(localize_args(command, ARGS_JA))
+}
+
+fn translate_line(line: &str) -> String {
+    for (pattern, translation) in ERRORS_JA {
+        let (prefix, suffix) = pattern.split_once("{}").unwrap_or((pattern, ""));
+        let Some(rest) = line.strip_prefix(prefix).and_then(|rest| rest.strip_suffix(suffix)) else {
+            continue;
+        };
+        if !pattern.contains("{}") && !rest.is_empty() {
+            continue;
+        }
+        let term = TERMS_JA
+            .iter()
+            .find(|(english, _)| *english == rest)
+            .map_or(rest, |(_, japanese)| *japanese);
+        return translation.replace("{}", term);
+    }
+    line.to_owned()
+}
+
+/// Error message with its cause chain in the language, lines without a translation stay in English
+pub fn localize_error(message: &str, lang: Lang) -> String {
+    if lang != Lang::Ja {
+        return message.to_owned();
+    }
+    message
+        .lines()
+        .map(|line| {
+            let indent = &line[..line.len() - line.trim_start().len()];
+            let trimmed = line.trim_start();
+            // anyhow's numbered causes, e.g. "    0: missing kawa"
+            let (number, text) = match trimmed.split_once(": ") {
+                Some((number, text)) if number.parse::<u32>().is_ok() => (format!("{number}: "), text),
+                _ => (String::new(), trimmed),
+            };
+            match text {
+                "Caused by:" => format!("{indent}原因:"),
+                _ => format!("{indent}{number}{}", translate_line(text)),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn print_error(message: &str, lang: Lang) {
+    let label = if lang == Lang::Ja { "エラー" } else { "error" };
+    eprintln!("{label}: {}", localize_error(message, lang));
+}
+
+/// Print an error of a command with its cause chain in the language
+pub fn report_error(error: &anyhow::Error, lang: Lang) {
+    print_error(&format!("{error:?}"), lang);
+}
@@ -0,0 +1,221 @@
+//! Hands typed as text, e.g. `123m456p789s11223z --fuuro 555z`
+use anyhow::{Context, Result, ensure};
+use clap::Parser;
+use riichi::algo::shanten::calc_all;
+use riichi::hand::{hand_with_aka, tile37_to_vec, tiles_to_string};
+use riichi::state::{ActionCandidate, PlayerState};
+use riichi::tile::Tile;
+use riichi::{must_tile, t};
+use tinyvec::array_vec;
+
+use crate::dora::{dora_factor, dora_indicator, doras_in_fuuro, doras_in_tiles};
+use crate::error::WashizuError;
+
+/// First tile of a hand string
+pub fn single_tile_hand(s: &str) -> Result<Tile> {
+    Ok(*hand_with_aka_vec(s)?.first().context("Hand must contain one tile")?)
+}
+
+/// Tiles of a hand string, e.g. 123m0p
+pub fn hand_with_aka_vec(s: &str) -> Result<Vec<Tile>> {
+    Ok(tile37_to_vec(&hand_with_aka(s)?))
+}
+
+/// Whitespace separated groups of tiles, e.g. melds
+pub fn nested_hand_with_aka_vec(s: &str) -> Result<Vec<Vec<Tile>>> {
+    s.split_whitespace().map(hand_with_aka_vec).collect()
+}
+
+// clap is insanely annoying with builtin custom parsers, so we parse later
+#[derive(Parser, Debug)]
+pub struct HandArgs {
+    pub tehai: String,
+    #[arg(long)]
+    pub fuuro: Option<String>,
+    #[arg(long)]
+    pub ankan: Option<String>,
+    #[arg(long)]
+    pub dora_indicators: Option<String>,
+    #[arg(long)]
+    pub bakaze: Option<String>,
+    #[arg(long)]
+    pub jikaze: Option<String>,
+    #[arg(long)]
+    pub tiles_left: Option<u8>,
+}
+
+/// Wind tile in hand notation from its name
+fn wind(word: &str) -> Option<&'static str> {
+    match word {
+        "east" | "e" => Some("1z"),
+        "south" | "s" => Some("2z"),
+        "west" | "w" => Some("3z"),
+        "north" | "n" => Some("4z"),
+        _ => None,
+    }
+}
+
+/// Seat as a 1-based number from the dealer or a wind name
+fn seat(word: &str) -> Option<&'static str> {
+    match word {
+        "1" => Some("1z"),
+        "2" => Some("2z"),
+        "3" => Some("3z"),
+        "4" => Some("4z"),
+        _ => wind(word),
+    }
+}
+
+/// Fill in the options from a hand written as a forum "what would you discard" problem,
+/// e.g. `123m456p789s1122z dora 5m east seat 1, turn 6`. Dora are turned back into their indicators and the turn into
+/// the tiles left in the wall. Options given as flags take precedence over the text.
+pub fn apply_wwyd(mut args: HandArgs) -> Result<HandArgs> {
+    let text = args.tehai.to_lowercase().replace([',', ':'], " ");
+    let mut words = text.split_whitespace().peekable();
+    args.tehai = words.next().context("empty hand")?.to_owned();
+    let (mut bakaze, mut jikaze, mut indicators, mut tiles_left) = (None, None, None, None);
+    while let Some(word) = words.next() {
+        match word {
+            "dora" => {
+                let doras = hand_with_aka_vec(words.next().context("dora must be followed by tiles")?)?;
+                let mut counts = [0; 34];
+                for dora in doras {
+                    counts[dora_indicator(dora).as_usize()] += 1;
+                }
+                indicators = Some(tiles_to_string(&counts, [false; 3]));
+            }
+            "indicator" | "indicators" => {
+                indicators = Some(words.next().context("indicator must be followed by tiles")?.to_owned());
+            }
+            "seat" => jikaze = Some(words.next().and_then(seat).context("seat must be a number or a wind")?),
+            "turn" => {
+                let turn: u8 = words
+                    .next()
+                    .context("turn must be followed by a number")?
+                    .parse()
+                    .context("incorrect turn")?;
+                ensure!((1..=18).contains(&turn), "turn must be between 1 and 18");
+                tiles_left = Some(70 - 4 * (turn - 1));
+            }
+            "round" => {}
+            _ => {
+                let wind = wind(word).with_context(|| format!("unknown word {word:?} in hand"))?;
+                let mut after = words.clone();
+                if after.next() == Some("seat") && after.next().and_then(seat).is_none() {
+                    jikaze = Some(wind);
+                    words.next();
+                } else {
+                    bakaze = Some(wind);
+                    // kyoku number, e.g. east 1
+                    words.next_if(|next| next.parse::<u8>().is_ok());
+                }
+            }
+        }
+    }
+    args.bakaze = args.bakaze.or(bakaze.map(str::to_owned));
+    args.jikaze = args.jikaze.or(jikaze.map(str::to_owned));
+    args.dora_indicators = args.dora_indicators.or(indicators);
+    args.tiles_left = args.tiles_left.or(tiles_left);
+    Ok(args)
+}
+
+/// State of a hand at our own discard, or waiting for a discard when the hand has 3n+1 tiles.
+/// A hand followed by words is read as a forum problem, see `apply_wwyd`.
+pub fn state_from_hand_args(args: HandArgs) -> Result<PlayerState> {
+    let tehai = args.tehai.clone();
+    hand_state(args).with_context(|| WashizuError::Hand(tehai))
+}
+
+fn hand_state(args: HandArgs) -> Result<PlayerState> {
+    let args = if args.tehai.contains(char::is_whitespace) {
+        apply_wwyd(args)?
+    } else {
+        args
+    };
+    let parsed_tehai = hand_with_aka(&args.tehai)?;
+    let mut tehai = [0; 34];
+    tehai.copy_from_slice(&parsed_tehai[..34]);
+
+    let mut tiles_seen = tehai;
+
+    let mut akas_in_hand = [false; 3];
+    for (i, count) in parsed_tehai[34..].iter().enumerate() {
+        if *count >= 1 {
+            akas_in_hand[i] = true;
+            tehai[must_tile!(34 + i).deaka().as_usize()] += 1;
+        }
+    }
+
+    let mut chis = array_vec![];
+    let mut pons = array_vec![];
+    let mut minkans = array_vec![];
+    let mut ankans = array_vec![];
+    let fuuros = nested_hand_with_aka_vec(&args.fuuro.unwrap_or_default())?;
+    let ankan_tiles = nested_hand_with_aka_vec(&args.ankan.unwrap_or_default())?;
+    for fuuro in fuuros.iter() {
+        let first = fuuro[0].deaka();
+        if fuuro.len() == 3 {
+            if first != fuuro[1].deaka() {
+                chis.push(first.as_u8());
+            } else {
+                pons.push(first.as_u8());
+            }
+        } else {
+            minkans.push(first.as_u8());
+        }
+        for tile in fuuro {
+            tiles_seen[tile.deaka().as_usize()] += 1;
+        }
+    }
+    for ankan in ankan_tiles.iter() {
+        tiles_seen[ankan[0].deaka().as_usize()] += 4;
+        ankans.push(ankan[0].deaka().as_u8());
+    }
+
+    let dora_indicators = if let Some(dora_indicators_string) = &args.dora_indicators {
+        hand_with_aka_vec(dora_indicators_string)?
+    } else {
+        vec![t!(E)]
+    };
+    for tile in &dora_indicators {
+        tiles_seen[tile.deaka().as_usize()] += 1;
+    }
+    let doras_owned =
+        doras_in_tiles(&tile37_to_vec(&parsed_tehai), &dora_indicators) + doras_in_fuuro(&fuuros, &ankan_tiles, &dora_indicators);
+
+    let tehai_len: u8 = parsed_tehai.iter().sum();
+    let tehai_len_div3 = tehai_len / 3;
+    let is_menzen = chis.is_empty() && pons.is_empty() && minkans.is_empty();
+    let shanten = calc_all(&tehai, tehai_len_div3);
+    let can_discard = tehai_len % 3 == 2;
+    let can_riichi = can_discard && is_menzen && shanten == 0;
+    let target_actor = if can_discard { 0 } else { 3 };
+
+    Ok(PlayerState {
+        player_id: 0,
+        tehai,
+        tiles_left: args.tiles_left.unwrap_or(70),
+        tehai_len_div3,
+        akas_in_hand,
+        akas_seen: akas_in_hand,
+        tiles_seen,
+        chis,
+        pons,
+        minkans,
+        ankans,
+        doras_owned: [doras_owned, 0, 0, 0],
+        dora_factor: dora_factor(&dora_indicators),
+        is_menzen,
+        shanten,
+        bakaze: single_tile_hand(&args.bakaze.unwrap_or_default()).unwrap_or(t!(E)),
+        jikaze: single_tile_hand(&args.jikaze.unwrap_or_default()).unwrap_or(t!(E)),
+        dora_indicators: dora_indicators.into_iter().collect(),
+        last_cans: ActionCandidate {
+            can_discard,
+            can_riichi,
+            target_actor,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
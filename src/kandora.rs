@@ -0,0 +1,148 @@
+//! Kan dora gained by the opponents when we declare a kan
+//! Mortal's tables value our own chance at the new dora, but the indicator is flipped for everyone. Each opponent's
+//! expected new dora comes from their visible melds and a hidden hand of random unseen tiles, and costs us when they
+//! win, which is estimated with the same rough chance per go-around as the riichi stick race.
+//! An opponent's hand is valued at the deal-in penalty, and their win is taken to be a tsumo or a ron off any of the
+//! other three players with equal chance, so we pay all of it when we deal in and our part of it on a tsumo.
+//! The cost is kept apart from the candidates and only subtracted from the total expected value, see `ExpandedState`.
+use crate::dora::indicated_dora;
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+
+use crate::damaten::opponent_win_hazard;
+use crate::rules::han_gain_ratio;
+use crate::tradeoff::DealInPenalty;
+
+/// New dora of every opponent after a kan and the expected value it costs us
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct KanDoraCost {
+    pub kan: Event,
+    /// Expected new dora of each player relative to us, ours is counted by the tables and left at 0
+    pub opponent_dora: [f32; 4],
+    pub cost: f32,
+}
+
+/// Expected number of dora an opponent gains from one new indicator drawn from the unseen tiles
+pub fn expected_new_dora(state: &PlayerState, player: usize) -> f32 {
+    let unseen: [f32; 34] = std::array::from_fn(|tile| 4u8.saturating_sub(state.tiles_seen[tile]) as f32);
+    let total: f32 = unseen.iter().sum();
+    if total <= 0.0 {
+        return 0.0;
+    }
+    // chance that each tile becomes dora
+    let mut dora_chance = [0.0; 34];
+    for (indicator, count) in unseen.iter().enumerate() {
+        let dora = indicated_dora(riichi::must_tile!(indicator));
+        dora_chance[dora.as_usize()] += count / total;
+    }
+    let meld_tiles = state.fuuro_overview[player]
+        .iter()
+        .flatten()
+        .copied()
+        .chain(state.ankan_overview[player].iter().flat_map(|&tile| [tile; 4]));
+    let in_melds: f32 = meld_tiles.map(|tile| dora_chance[tile.deaka().as_usize()]).sum();
+    let melds = state.fuuro_overview[player].len() + state.ankan_overview[player].len();
+    let hidden = (13 - 3 * melds) as f32;
+    let per_hidden_tile: f32 = (0..34).map(|tile| unseen[tile] / total * dora_chance[tile]).sum();
+    in_melds + hidden * per_hidden_tile
+}
+
+/// Share of an opponent's win we pay: a ron off us out of four equally likely ways to win, or a tsumo we pay our part of
+fn our_payment_share(state: &PlayerState, player: usize) -> f32 {
+    let tsumo_part = if state.oya == 0 {
+        0.5
+    } else if player == state.oya as usize {
+        1.0 / 3.0
+    } else {
+        0.25
+    };
+    (1.0 + tsumo_part) / 4.0
+}
+
+/// Cost of the opponents' new dora for a kan, None for any other action
+pub fn kan_dora_cost(state: &PlayerState, kan: &Event, penalty: DealInPenalty) -> Option<KanDoraCost> {
+    if !matches!(kan, Event::Ankan { .. } | Event::Kakan { .. } | Event::Daiminkan { .. }) {
+        return None;
+    }
+    let go_arounds = (state.tiles_left / 4) as i32;
+    let opponent_dora: [f32; 4] = std::array::from_fn(|player| if player == 0 { 0.0 } else { expected_new_dora(state, player) });
+    let cost = (1..4)
+        .map(|player| {
+            let points = if player == state.oya as usize {
+                penalty.dealer
+            } else {
+                penalty.non_dealer
+            };
+            let gain = points * (han_gain_ratio(points, 1) - 1.0);
+            let win_chance = 1.0 - (1.0 - opponent_win_hazard(state, player)).powi(go_arounds);
+            win_chance * our_payment_share(state, player) * gain * opponent_dora[player]
+        })
+        .sum();
+    Some(KanDoraCost {
+        kan: kan.clone(),
+        opponent_dora,
+        cost,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::t;
+
+    use super::*;
+
+    fn ankan() -> Event {
+        Event::Ankan {
+            actor: 0,
+            consumed: [t!(1m); 4],
+        }
+    }
+
+    fn non_dealer(tiles_left: u8) -> PlayerState {
+        PlayerState {
+            oya: 1,
+            tiles_left,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn only_kans_are_charged() {
+        let discard = Event::Dahai {
+            actor: 0,
+            pai: t!(1m),
+            tsumogiri: false,
+        };
+        assert!(kan_dora_cost(&non_dealer(60), &discard, DealInPenalty::default()).is_none());
+        assert!(kan_dora_cost(&non_dealer(60), &ankan(), DealInPenalty::default()).is_some());
+    }
+
+    #[test]
+    fn closed_opponent_expects_a_dora_per_34_tiles() {
+        let dora = expected_new_dora(&non_dealer(60), 2);
+        assert!((dora - 13.0 / 34.0).abs() < 1e-4, "{dora}");
+    }
+
+    #[test]
+    fn cost_grows_with_the_wall_and_the_riichi() {
+        let cost = |state: &PlayerState| kan_dora_cost(state, &ankan(), DealInPenalty::default()).unwrap().cost;
+        let early = cost(&non_dealer(60));
+        assert_eq!(cost(&non_dealer(0)), 0.0);
+        assert!(cost(&non_dealer(20)) < early);
+        let riichi = PlayerState {
+            riichi_declared: [false, false, true, false],
+            ..non_dealer(60)
+        };
+        assert!(cost(&riichi) > early);
+    }
+
+    #[test]
+    fn dealer_pays_more_of_a_tsumo() {
+        let dealer = PlayerState {
+            oya: 0,
+            ..non_dealer(60)
+        };
+        assert!(our_payment_share(&dealer, 2) > our_payment_share(&non_dealer(60), 2));
+        assert!(our_payment_share(&non_dealer(60), 1) > our_payment_share(&non_dealer(60), 2));
+    }
+}
@@ -0,0 +1,45 @@
+//! Discard rivers of every player as text, in the kawa syntax of the board command
+//! Tedashi are marked with `.`, the riichi tile with `-` and tiles called away are kept in parentheses where they were
+//! discarded, so a river reads the same as the board that produced it. A call of the player is shown in brackets in
+//! front of the discard that followed it.
+use riichi::state::PlayerState;
+
+/// River of a player relative to us, oldest discard first
+pub fn kawa_string(state: &PlayerState, player: usize) -> String {
+    state.kawa[player]
+        .iter()
+        .zip(&state.kawa_overview[player])
+        .map(|(item, &tile)| match item {
+            Some(item) => {
+                let mark = match (item.sutehai.is_riichi, item.sutehai.is_tedashi) {
+                    (true, _) => "-",
+                    (false, true) => ".",
+                    (false, false) => "",
+                };
+                let mut called = item.kan.iter().map(|tile| tile.to_string()).collect::<Vec<_>>();
+                if let Some(chi_pon) = &item.chi_pon {
+                    called.extend(chi_pon.consumed.iter().map(|tile| tile.to_string()));
+                    called.push(chi_pon.target_tile.to_string());
+                }
+                let call = if called.is_empty() {
+                    String::new()
+                } else {
+                    format!("[{}] ", called.concat())
+                };
+                format!("{call}{}{mark}", item.sutehai.tile)
+            }
+            None => format!("({tile})"),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Rivers of all four players relative to us, one line each
+pub fn kawa_lines(state: &PlayerState) -> Vec<String> {
+    (0..4)
+        .map(|player| {
+            let riichi = if state.riichi_declared[player] { "*" } else { "" };
+            format!("kawa {player}{riichi}: {}", kawa_string(state, player))
+        })
+        .collect()
+}
@@ -0,0 +1,115 @@
+//! Library surface of washizu for crates embedding the analysis
+//! The analysis is reached through `api`, which takes text and returns reports, everything else is internal.
+mod agari;
+pub mod api;
+#[cfg(feature = "cli")]
+mod batch;
+mod benchmark;
+mod calls;
+mod chankan;
+#[cfg(feature = "cli")]
+mod checkpoint;
+#[cfg(feature = "cli")]
+pub mod cli;
+mod concurrency;
+#[cfg(feature = "cli")]
+mod crosscheck;
+#[cfg(feature = "cli")]
+mod daemon;
+mod damaten;
+#[cfg(feature = "cli")]
+mod data;
+#[cfg(feature = "cli")]
+mod db;
+mod decisions;
+mod defense;
+mod dora;
+#[cfg(feature = "cli")]
+mod duplicate;
+mod ekyumoe;
+pub mod error;
+mod fast;
+mod fold;
+#[cfg(feature = "cli")]
+mod footnotes;
+mod fu;
+mod furiten;
+mod glyphs;
+mod handshape;
+#[cfg(feature = "cli")]
+mod highlight;
+mod hora;
+mod horizon;
+#[cfg(feature = "cli")]
+mod html;
+#[cfg(feature = "cli")]
+mod i18n;
+mod input;
+mod kandora;
+#[cfg(feature = "cli")]
+mod kawa;
+#[cfg(feature = "cli")]
+mod live;
+mod mjaigen;
+#[cfg(feature = "mortal")]
+mod model;
+#[cfg(feature = "cli")]
+mod oracle;
+mod order;
+mod plugin;
+mod profile;
+mod pushfold;
+mod readability;
+#[cfg(feature = "cli")]
+mod render;
+#[cfg(feature = "cli")]
+mod repl;
+mod report;
+#[cfg(feature = "cli")]
+mod review;
+mod rollout;
+mod rules;
+mod ryukyoku;
+mod sanma;
+mod schema;
+mod scorer;
+mod scoring;
+#[cfg(feature = "cli")]
+mod seek;
+#[cfg(feature = "cli")]
+mod shape;
+#[cfg(feature = "cli")]
+mod simulate;
+#[cfg(feature = "cli")]
+mod spectator;
+mod state;
+#[cfg(feature = "cli")]
+mod stats;
+mod suji;
+mod tables;
+mod tenpai;
+pub mod tiles;
+#[cfg(feature = "cli")]
+mod timer;
+mod tradeoff;
+mod transparent;
+#[cfg(feature = "cli")]
+mod tree;
+#[cfg(feature = "cli")]
+mod tui;
+mod ukeire;
+mod uradora;
+mod variant;
+pub mod version;
+mod waitmodel;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod yakuhai;
+mod yakunames;
+
+pub use api::{Hand, analyze_board, analyze_hand, analyze_log, board_from_mjai, mjai_from_board};
+pub use order::{SortKey, SortSpec, SortableCandidate, sort_candidates};
+pub use report::{AgariReport, AnalysisReport, CandidateReport, TablesReport};
+pub use rules::RuleSet;
+pub use state::AnalysisOptions;
+pub use uradora::UraDora;
@@ -0,0 +1,225 @@
+//! Live analysis of an incoming event stream, from stdin or over a websocket
+use std::net::{TcpListener, TcpStream};
+
+use anyhow::{Context, Result, anyhow};
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+use tungstenite::Message;
+
+use crate::decisions::detect_player_id;
+use crate::error::WashizuError;
+use crate::furiten::PassedTiles;
+use crate::mjaigen::board_from_events;
+use crate::sanma::{LogEvent, Nukidora, parse_log_event};
+use crate::state::{AnalysisOptions, ExpandedState};
+use crate::stats::PlayerStats;
+use crate::timer::{DecisionTimer, DecisionTiming, event_timestamp};
+
+/// Finished game of a live stream
+pub struct GameSummary {
+    pub player_id: u8,
+    pub stats: PlayerStats,
+    /// Decision points analyzed during the game
+    pub decisions: usize,
+}
+
+impl GameSummary {
+    pub fn to_log_string(&self) -> String {
+        format!(
+            "game over as player {}, {} decisions analyzed\n{}",
+            self.player_id,
+            self.decisions,
+            self.stats.to_log_string()
+        )
+    }
+}
+
+/// Live stream of one or more games, the game state is recreated on every StartGame and dropped on EndGame
+pub struct LiveSession {
+    player_id: Option<u8>,
+    player_name: Option<String>,
+    state: Option<PlayerState>,
+    /// Events of the current game for its summary
+    game_events: Vec<Event>,
+    /// Decision points analyzed in the current game
+    game_decisions: usize,
+    games: Vec<GameSummary>,
+    nukidora: Nukidora,
+    passed_tiles: PassedTiles,
+    /// Transparent tiles of absolute seats, reset every kyoku
+    visible_tiles: [Vec<Tile>; 4],
+    timer: DecisionTimer,
+    options: AnalysisOptions,
+}
+
+impl LiveSession {
+    pub fn new(player_id: Option<u8>, player_name: Option<&str>, options: &AnalysisOptions) -> Self {
+        Self {
+            player_id,
+            player_name: player_name.map(str::to_owned),
+            state: player_id.map(PlayerState::new),
+            game_events: vec![],
+            game_decisions: 0,
+            games: vec![],
+            nukidora: Nukidora::default(),
+            passed_tiles: PassedTiles::default(),
+            visible_tiles: Default::default(),
+            timer: DecisionTimer::default(),
+            options: options.clone(),
+        }
+    }
+
+    /// Feed a line of the log, returns the analysis when the event is worth showing
+    pub fn handle_line(&mut self, line: &str) -> Result<Option<ExpandedState>> {
+        let event = match parse_log_event(line).with_context(|| WashizuError::Event(line.to_owned()))? {
+            LogEvent::Mjai(event) => event,
+            LogEvent::Nukidora { actor } => {
+                if let Some(state) = self.state.as_mut() {
+                    self.nukidora
+                        .apply(state, actor)
+                        .with_context(|| WashizuError::Event(line.to_owned()))?;
+                    self.options.nukidora = self.nukidora.counts[0];
+                }
+                return Ok(None);
+            }
+            LogEvent::Transparent { actor, tiles } => {
+                *self
+                    .visible_tiles
+                    .get_mut(actor as usize)
+                    .context("transparent actor out of range")? = tiles;
+                return Ok(None);
+            }
+        };
+        if let Event::StartGame { id, .. } = &event {
+            // the seat of each game's StartGame wins over the command line, which only covers streams without it
+            let id = match (id, &self.player_name) {
+                (Some(id), None) => *id,
+                _ => detect_player_id(&event, self.player_id, self.player_name.as_deref())?,
+            };
+            if !self.game_events.is_empty() {
+                eprintln!("warning: game ended without EndGame, starting a new game");
+            }
+            self.state = Some(PlayerState::new(id));
+            self.game_events.clear();
+            self.game_decisions = 0;
+            self.nukidora = Nukidora::default();
+            self.passed_tiles = PassedTiles::default();
+            self.visible_tiles = Default::default();
+        }
+        self.nukidora.update(&event);
+        self.options.nukidora = self.nukidora.counts[0];
+        let state = self
+            .state
+            .as_mut()
+            .context("player id is unknown until StartGame, pass it for streams starting mid-game")?;
+        let timestamp = event_timestamp(line);
+        self.timer.responded(timestamp, &event, state.player_id);
+        state.update(&event).with_context(|| WashizuError::state_update(&event))?;
+        self.passed_tiles.update(&event);
+        self.options.passed_tiles = self.passed_tiles.relative(state.player_id);
+        if matches!(event, Event::StartKyoku { .. }) {
+            self.visible_tiles = Default::default();
+        }
+        self.options.visible_tiles =
+            std::array::from_fn(|player| self.visible_tiles[(player + state.player_id as usize) % 4].clone());
+        self.game_events.push(event.clone());
+        if matches!(event, Event::EndGame) {
+            self.finish_game();
+            return Ok(None);
+        }
+        let Some(state) = self.state.as_ref() else {
+            return Ok(None);
+        };
+        match event {
+            Event::Tsumo { actor, .. } if actor != state.player_id => return Ok(None),
+            Event::Hora { actor, .. } if actor == state.player_id => return Ok(None),
+            Event::EndKyoku => return Ok(None),
+            _ => {}
+        }
+        let expanded = ExpandedState::from_state(state.clone(), None, &self.options)?;
+        if state.last_cans.can_act() {
+            self.timer.decided(timestamp, &expanded);
+            self.game_decisions += 1;
+        }
+        Ok(Some(expanded))
+    }
+
+    /// Summarize the current game and drop its state, events before the next StartGame are rejected
+    fn finish_game(&mut self) {
+        let Some(state) = self.state.take() else {
+            return;
+        };
+        let mut stats = PlayerStats::default();
+        stats.add_game(&self.game_events, state.player_id);
+        self.games.push(GameSummary {
+            player_id: state.player_id,
+            stats,
+            decisions: self.game_decisions,
+        });
+        self.game_events.clear();
+        self.game_decisions = 0;
+    }
+
+    /// Board of the current kyoku in the notation of the board command
+    pub fn board_string(&self) -> Option<String> {
+        let state = self.state.as_ref()?;
+        board_from_events(&self.game_events, state.player_id)
+            .ok()
+            .map(|board| board.to_board_string())
+    }
+
+    /// Games finished so far
+    pub fn games(&self) -> &[GameSummary] {
+        &self.games
+    }
+
+    /// Think time and EV loss of the decisions so far, empty for streams without timestamps
+    pub fn timings(&self) -> &[DecisionTiming] {
+        &self.timer.timings
+    }
+}
+
+/// Accept mjai events over websockets, one session per connection.
+/// Every text message may contain one or more events separated by newlines,
+/// each analyzed event is answered with an `AnalysisReport` or `{"error": ...}`.
+pub fn run_websocket(listen: &str, player_id: Option<u8>, player_name: Option<&str>, options: &AnalysisOptions) -> Result<()> {
+    let listener = TcpListener::bind(listen).with_context(|| format!("failed to listen on {listen}"))?;
+    eprintln!("listening on ws://{listen}");
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("failed to accept connection: {e}");
+                continue;
+            }
+        };
+        let session = LiveSession::new(player_id, player_name, options);
+        std::thread::spawn(move || {
+            if let Err(e) = serve_connection(stream, session) {
+                eprintln!("{e:#}");
+            }
+        });
+    }
+    Ok(())
+}
+
+fn serve_connection(stream: TcpStream, mut session: LiveSession) -> Result<()> {
+    let mut socket = tungstenite::accept(stream).map_err(|e| anyhow!("websocket handshake failed: {e}"))?;
+    loop {
+        let text = match socket.read() {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) | Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Ok(_) => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let reply = match session.handle_line(line) {
+                Ok(Some(expanded)) => serde_json::to_string(&expanded.to_report())?,
+                Ok(None) => continue,
+                Err(e) => serde_json::json!({ "error": format!("{e:#}") }).to_string(),
+            };
+            socket.send(Message::Text(reply))?;
+        }
+    }
+}
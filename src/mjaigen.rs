@@ -1,11 +1,16 @@
 #![allow(dead_code)]
 //! Generate mjai logs from the current gamestate
 //! Information is lost by not knowing when a tile was called.
+//! Tiles of the kawa and fuuro may be written as `?` when they cannot be read. Unknown tiles of a meld are inferred from
+//! its known tiles, the remaining ones become the unseen tile with the most copies left so the tile counts stay
+//! consistent. The analysis then treats them as seen, which barely matters for tiles with many copies left.
 use anyhow::{Context, Result, bail, ensure};
-use riichi::{mjai::Event, must_tile, t, tile::Tile, tu8, tuz};
-use std::{array::from_fn, iter::once, str::FromStr};
+use riichi::{mjai::Event, must_tile, state::PlayerState, t, tile::Tile, tu8, tuz};
+use std::{array::from_fn, collections::HashSet, iter::once, str::FromStr};
 use tinyvec::ArrayVec;
 
+use crate::error::WashizuError;
+
 /// read an ordered tile string
 pub fn parse_tiles(s: &str) -> Result<Vec<Tile>> {
     ensure!(s.is_ascii(), "hand {s} contains non-ascii content");
@@ -46,6 +51,18 @@ pub fn parse_tiles(s: &str) -> Result<Vec<Tile>> {
     Ok(tiles)
 }
 
+/// Tile of a kawa or fuuro, two characters or `?` for an unknown tile
+fn next_tile(chars: &mut std::iter::Peekable<impl Iterator<Item = char>>, what: &str) -> Result<Tile> {
+    let first = chars.next().with_context(|| format!("incorrect {what}"))?;
+    if first == '?' {
+        return Ok(t!(?));
+    }
+    parse_tile(&format!(
+        "{first}{}",
+        chars.next().with_context(|| format!("incorrect {what}"))?
+    ))
+}
+
 fn parse_tile(s: &str) -> Result<Tile> {
     let tiles = parse_tiles(s)?;
     ensure!(tiles.len() == 1, "too many tiles");
@@ -98,157 +115,320 @@ pub struct Board {
     pub tehai: Vec<Tile>,
 }
 
-pub fn naki_to_event(naki: Naki, actor: u8, player_abs: impl Fn(usize) -> u8) -> Event {
-    if naki.consumed.len() == 2 && naki.consumed[0].deaka() != naki.consumed[1].deaka() {
-        Event::Chi {
+/// Event of a meld, `fuuro` are the earlier melds of the player which give the pon a kakan adds to
+pub fn naki_to_event(naki: Naki, fuuro: &[Naki], actor: u8, player_abs: impl Fn(usize) -> u8) -> Result<Event> {
+    Ok(match *naki.consumed.as_slice() {
+        [a, b] if a.deaka() != b.deaka() => Event::Chi {
             actor,
             target: player_abs(naki.target as usize),
             pai: naki.pai,
-            consumed: naki.consumed.as_slice().try_into().unwrap(),
-        }
-    } else if naki.consumed.len() == 2 {
-        Event::Pon {
+            consumed: [a, b],
+        },
+        [a, b] => Event::Pon {
             actor,
             target: player_abs(naki.target as usize),
             pai: naki.pai,
-            consumed: naki.consumed.as_slice().try_into().unwrap(),
-        }
-    } else if naki.consumed.len() == 3 && actor != naki.target {
-        Event::Daiminkan {
+            consumed: [a, b],
+        },
+        [a, b, c] => Event::Daiminkan {
             actor,
             target: player_abs(naki.target as usize),
             pai: naki.pai,
-            consumed: naki.consumed.as_slice().try_into().unwrap(),
-        }
-    } else if naki.consumed.len() == 4 {
-        Event::Ankan {
+            consumed: [a, b, c],
+        },
+        [a, b, c, d] => Event::Ankan {
             actor,
-            consumed: naki.consumed.as_slice().try_into().unwrap(),
+            consumed: [a, b, c, d],
+        },
+        [] => {
+            // the kakan shows the tiles of its pon, which may hold a red five the added tile does not
+            let pon = fuuro
+                .iter()
+                .find(|pon| pon.consumed.len() == 2 && pon.pai.deaka() == naki.pai.deaka())
+                .map(|pon| [pon.pai, pon.consumed[0], pon.consumed[1]]);
+            Event::Kakan {
+                actor,
+                pai: naki.pai,
+                consumed: pon.unwrap_or([naki.pai; 3]),
+            }
         }
-    } else if naki.consumed.is_empty() {
-        Event::Kakan {
-            actor,
-            pai: naki.pai,
-            consumed: [naki.pai; 3],
+        _ => bail!("unknown meld combination {naki:?}"),
+    })
+}
+
+/// Tiles that may not be discarded right after a chi or pon
+fn kuikae(call: &Event) -> Vec<usize> {
+    match *call {
+        Event::Pon { pai, .. } => vec![pai.deaka().as_usize()],
+        Event::Chi { pai, consumed, .. } => {
+            let called = pai.deaka().as_usize();
+            let low = consumed.iter().map(|tile| tile.deaka().as_usize()).min().unwrap_or(called);
+            let high = consumed.iter().map(|tile| tile.deaka().as_usize()).max().unwrap_or(called);
+            let mut forbidden = vec![called];
+            if called < low && high % 9 < 8 {
+                forbidden.push(high + 1);
+            } else if called > high && low % 9 > 0 {
+                forbidden.push(low - 1);
+            }
+            forbidden
         }
-    } else {
-        panic!("Unknown meld combination {naki:?}");
+        _ => vec![],
     }
 }
 
-pub fn generate_mjai_logs(board: Board) -> Result<Vec<Event>> {
-    let oya = board.kyoku - 1;
-    let player_id = (4 + oya + board.jikaze.as_u8() - tu8!(E)) % 4;
+/// Kawa and meld positions of every player with the actor of a turn, whether they drew and whether a kan dora is pending
+type Position = ([usize; 4], [usize; 4], u8, bool, bool);
 
-    let player_abs = |player| (player as u8 + player_id) % 4;
-    let player_rel = |player| (4 + player - player_id) % 4;
-    let mut scores = board.scores;
-    scores.rotate_right(player_id as usize);
+/// Order of the turns of a board, found by a search since a board does not tell when its tiles were called
+/// Each discard is either the next tile of the player's kawa, which lets the next player draw, or the tile another
+/// player calls next from them. Draws per player are fixed by the discards and calls, the kan dora revealed at the
+/// end by the dora indicators, and positions that failed are remembered so the search visits each at most once. Tsumo
+/// tiles and dora indicators are left unknown.
+struct Schedule<'a> {
+    player_id: u8,
+    at_discard: bool,
+    kan_doras: usize,
+    /// Kawa and melds of each absolute seat, the melds as events
+    kawa: [&'a [Sutehai]; 4],
+    fuuro: [Vec<Event>; 4],
+    kawa_pos: [usize; 4],
+    fuuro_pos: [usize; 4],
+    draws: [usize; 4],
+    max_draws: [usize; 4],
+    events: Vec<Event>,
+    failed: HashSet<Position>,
+}
 
-    // information about some discards is lost, we'll estimate them to be tedashi non-riichi for simplicity
-    let mut called_sutehais: [Vec<(Sutehai, u8)>; 4] = from_fn(|_| vec![]); // rel
-    for (rel_player, fuuro) in board.fuuro.iter().enumerate() {
-        for naki in fuuro.iter() {
-            if naki.target != rel_player as u8 {
-                let sutehai = Sutehai {
-                    pai: naki.pai,
-                    tedashi: true,
-                    riichi: false,
-                };
-                called_sutehais[naki.target as usize].push((sutehai, player_abs(rel_player)));
-            }
+impl Schedule<'_> {
+    fn done(&self) -> bool {
+        (0..4)
+            .all(|player| self.kawa_pos[player] == self.kawa[player].len() && self.fuuro_pos[player] == self.fuuro[player].len())
+    }
+
+    fn finished(&self) -> bool {
+        self.done() && self.events.iter().filter(|event| matches!(event, Event::Dora { .. })).count() == self.kan_doras
+    }
+
+    fn restore(&mut self, (len, kawa_pos, fuuro_pos, draws): (usize, [usize; 4], [usize; 4], [usize; 4])) {
+        self.events.truncate(len);
+        self.kawa_pos = kawa_pos;
+        self.fuuro_pos = fuuro_pos;
+        self.draws = draws;
+    }
+
+    fn snapshot(&self) -> (usize, [usize; 4], [usize; 4], [usize; 4]) {
+        (self.events.len(), self.kawa_pos, self.fuuro_pos, self.draws)
+    }
+
+    fn draw(&mut self, actor: u8) -> bool {
+        if self.draws[actor as usize] >= self.max_draws[actor as usize] {
+            return false;
         }
+        let snapshot = self.snapshot();
+        self.draws[actor as usize] += 1;
+        self.events.push(Event::Tsumo { actor, pai: t!(?) });
+        if self.turn(actor, true, false) {
+            return true;
+        }
+        self.restore(snapshot);
+        false
     }
 
-    let at_discard = board.tehai.len() % 3 == 2;
+    /// Rest of a turn after its draw or call, `pending` when a kan dora is revealed at the discard
+    fn turn(&mut self, actor: u8, drew: bool, pending: bool) -> bool {
+        let key = (self.kawa_pos, self.fuuro_pos, actor, drew, pending);
+        if self.failed.contains(&key) {
+            return false;
+        }
+        let snapshot = self.snapshot();
+        let player = actor as usize;
+        // ankan and kakan come right after a draw, the pending kan dora is revealed by the next kan
+        if drew
+            && let Some(kan @ (Event::Ankan { .. } | Event::Kakan { .. })) =
+                self.fuuro[player].get(self.fuuro_pos[player]).cloned()
+        {
+            self.fuuro_pos[player] += 1;
+            let is_ankan = matches!(kan, Event::Ankan { .. });
+            self.events.push(kan);
+            if pending {
+                self.events.push(Event::Dora { dora_marker: t!(?) });
+            }
+            if is_ankan {
+                self.events.push(Event::Dora { dora_marker: t!(?) });
+            }
+            self.events.push(Event::Tsumo { actor, pai: t!(?) });
+            if self.turn(actor, true, !is_ankan) {
+                return true;
+            }
+            self.restore(snapshot);
+        }
+        if self.discard(actor, drew, pending) {
+            return true;
+        }
+        self.restore(snapshot);
+        self.failed.insert(key);
+        false
+    }
 
-    // construct player turns by attaching calls to the first viable dahai
-    // contains: tsumo or call replacing tsumo, ankan/kakan calls, riichi declaration, dahai, dora reveal
-    // included is the player that will want to call after the last event
-    // the last turn may not have a dahai action if the provided Board is right after a call
-    // unknown tsumo tiles and dora indicators will be filled in later based on what tiles could possibly be in there
-    let mut turns: [Vec<(Vec<Event>, Option<u8>)>; 4] = from_fn(|_| vec![]);
-    for (rel_player, kawa) in board.kawa.iter().enumerate() {
-        let actor = player_abs(rel_player);
-        let mut fuuro_iter = board.fuuro[rel_player]
-            .iter()
-            .map(|naki| naki_to_event(*naki, actor, player_abs))
-            .peekable();
-        let mut kakan_candidates = vec![];
-        for (sutehai, next_player) in called_sutehais[rel_player]
-            .iter()
-            .map(|(called_sutehai, next_player)| (Some(called_sutehai), Some(next_player)))
-            .chain(kawa.iter().map(|sutehai| (Some(sutehai), None)))
-            .chain(once((None, None)))
+    fn discard(&mut self, actor: u8, drew: bool, pending: bool) -> bool {
+        let player = actor as usize;
+        if actor == self.player_id && self.at_discard && self.finished() {
+            return true;
+        }
+        let forbidden = match drew {
+            true => vec![],
+            false => kuikae(&self.fuuro[player][self.fuuro_pos[player] - 1]),
+        };
+        let snapshot = self.snapshot();
+        if let Some(&sutehai) = self.kawa[player].get(self.kawa_pos[player])
+            && (drew || sutehai.tedashi)
+            && !forbidden.contains(&sutehai.pai.deaka().as_usize())
         {
-            let mut reveal_dora_at_discard = false;
-            let mut events = vec![];
-
-            let is_first_oya_act = actor == oya && turns[actor as usize].is_empty();
-            let draw_event = if !is_first_oya_act
-                && let Some(next_event) = fuuro_iter.peek()
-                && (matches!(next_event, Event::Chi { .. } | Event::Pon { .. } if sutehai.is_none_or(|sutehai| sutehai.tedashi))
-                    || matches!(next_event, Event::Daiminkan { .. }))
-            {
-                match next_event {
-                    Event::Pon { pai, .. } => kakan_candidates.push(*pai),
-                    Event::Daiminkan { .. } => reveal_dora_at_discard = true,
-                    _ => {}
-                }
-                fuuro_iter.next().unwrap()
-            } else {
-                Event::Tsumo { actor, pai: t!(?) }
+            self.kawa_pos[player] += 1;
+            if sutehai.riichi {
+                self.events.push(Event::Reach { actor });
+            }
+            self.events.push(Event::Dahai {
+                actor,
+                pai: sutehai.pai,
+                tsumogiri: !sutehai.tedashi,
+            });
+            if sutehai.riichi {
+                self.events.push(Event::ReachAccepted { actor });
+            }
+            if pending {
+                self.events.push(Event::Dora { dora_marker: t!(?) });
+            }
+            if self.finished() && !self.at_discard || self.draw((actor + 1) % 4) {
+                return true;
+            }
+            self.restore(snapshot);
+        }
+        for caller in (1..4).map(|offset| (actor + offset) % 4) {
+            let call = match self.fuuro[caller as usize].get(self.fuuro_pos[caller as usize]) {
+                Some(
+                    call @ (Event::Chi { target, pai, .. }
+                    | Event::Pon { target, pai, .. }
+                    | Event::Daiminkan { target, pai, .. }),
+                ) if *target == actor && !forbidden.contains(&pai.deaka().as_usize()) => call.clone(),
+                _ => continue,
             };
-            events.push(draw_event.clone());
-
-            if !matches!(draw_event, Event::Chi { .. } | Event::Pon { .. }) {
-                while let Some(next_naki) = fuuro_iter.peek() {
-                    match next_naki {
-                        Event::Ankan { .. } => {
-                            if reveal_dora_at_discard {
-                                events.push(Event::Dora { dora_marker: t!(?) });
-                                reveal_dora_at_discard = false;
-                            }
-                            events.push(fuuro_iter.next().unwrap());
-                            events.push(Event::Dora { dora_marker: t!(?) });
-                            events.push(Event::Tsumo { actor, pai: t!(?) });
-                        }
-                        Event::Kakan { pai, .. } if kakan_candidates.contains(pai) => {
-                            events.push(fuuro_iter.next().unwrap());
-                            if reveal_dora_at_discard {
-                                events.push(Event::Dora { dora_marker: t!(?) });
-                            }
-                            events.push(Event::Tsumo { actor, pai: t!(?) });
-                            reveal_dora_at_discard = true;
-                        }
-                        _ => break,
-                    }
-                }
+            let (Event::Chi { pai, .. } | Event::Pon { pai, .. } | Event::Daiminkan { pai, .. }) = call else {
+                unreachable!()
+            };
+            self.fuuro_pos[caller as usize] += 1;
+            // information about called discards is lost, they are estimated to be tedashi non-riichi
+            self.events.push(Event::Dahai {
+                actor,
+                pai,
+                tsumogiri: false,
+            });
+            if pending {
+                self.events.push(Event::Dora { dora_marker: t!(?) });
             }
-
-            if let Some(sutehai) = sutehai {
-                if sutehai.riichi {
-                    events.push(Event::Reach { actor });
-                    scores[actor as usize] += 1000;
-                }
-                events.push(Event::Dahai {
-                    actor,
-                    pai: sutehai.pai,
-                    tsumogiri: !sutehai.tedashi,
+            let is_daiminkan = matches!(call, Event::Daiminkan { .. });
+            self.events.push(call);
+            if is_daiminkan {
+                self.events.push(Event::Tsumo {
+                    actor: caller,
+                    pai: t!(?),
                 });
-                if reveal_dora_at_discard {
-                    events.push(Event::Dora { dora_marker: t!(?) });
-                }
             }
-
-            if !(events.len() == 1 && matches!(events[0], Event::Tsumo { .. }) && !(rel_player == 0 && at_discard)) {
-                turns[actor as usize].push((events, next_player.copied()));
+            if self.turn(caller, is_daiminkan, is_daiminkan) {
+                return true;
             }
+            self.restore(snapshot);
         }
-        if fuuro_iter.next().is_some() {
-            bail!("more calls than tsumos");
+        false
+    }
+}
+
+pub fn generate_mjai_logs(mut board: Board) -> Result<Vec<Event>> {
+    fill_unknown_tiles(&mut board)?;
+    let oya = board.kyoku - 1;
+    let player_id = (4 + oya + board.jikaze.as_u8() - tu8!(E)) % 4;
+
+    let player_abs = |player| (player as u8 + player_id) % 4;
+    let player_rel = |player| (4 + player - player_id) % 4;
+    let mut scores = board.scores;
+    scores.rotate_right(player_id as usize);
+    let mut kyotaku = board.kyotaku;
+
+    ensure!(!board.dora_indicators.is_empty(), "missing dora indicator");
+    ensure!(
+        board
+            .fuuro
+            .iter()
+            .all(|fuuro| fuuro.iter().filter(|naki| !naki.consumed.is_empty()).count() <= 4),
+        "more than 4 melds"
+    );
+    let at_discard = board.tehai.len() % 3 == 2;
+    let mut fuuro: [Vec<Event>; 4] = Default::default();
+    for (player, events) in fuuro.iter_mut().enumerate() {
+        let melds = &board.fuuro[player_rel(player as u8) as usize];
+        *events = melds
+            .iter()
+            .enumerate()
+            .map(|(index, naki)| naki_to_event(*naki, &melds[..index], player as u8, player_abs))
+            .collect::<Result<_>>()?;
+    }
+    let kawa = from_fn(|player| board.kawa[player_rel(player as u8) as usize].as_slice());
+    // every turn but the last of the viewer at discard ends with a discard, the calls replace draws
+    let max_draws = from_fn(|player| {
+        let called = fuuro
+            .iter()
+            .flatten()
+            .filter(|event| {
+                matches!(event, Event::Chi { target, .. } | Event::Pon { target, .. } | Event::Daiminkan { target, .. } if *target as usize == player)
+            })
+            .count();
+        let calls = fuuro[player]
+            .iter()
+            .filter(|event| matches!(event, Event::Chi { .. } | Event::Pon { .. } | Event::Daiminkan { .. }))
+            .count();
+        (kawa[player].len() + called + (at_discard && player as u8 == player_id) as usize).saturating_sub(calls)
+    });
+    let mut schedule = Schedule {
+        player_id,
+        at_discard,
+        kan_doras: board.dora_indicators.len().saturating_sub(1),
+        kawa,
+        fuuro,
+        kawa_pos: [0; 4],
+        fuuro_pos: [0; 4],
+        draws: [0; 4],
+        max_draws,
+        events: vec![],
+        failed: HashSet::new(),
+    };
+    ensure!(
+        schedule.finished() && !at_discard || schedule.draw(oya),
+        "no order of the turns fits the kawa, calls and dora indicators"
+    );
+    let mut turns = schedule.events;
+
+    // hand sizes at the end of the events
+    let mut sizes = [13usize; 4];
+    for event in &turns {
+        match *event {
+            Event::Tsumo { actor, .. } => sizes[actor as usize] += 1,
+            Event::Dahai { actor, .. } | Event::Kakan { actor, .. } => sizes[actor as usize] -= 1,
+            Event::Chi { actor, .. } | Event::Pon { actor, .. } => sizes[actor as usize] -= 2,
+            Event::Daiminkan { actor, .. } => sizes[actor as usize] -= 3,
+            Event::Ankan { actor, .. } => sizes[actor as usize] -= 4,
+            Event::Reach { actor } => {
+                scores[actor as usize] += 1000;
+                // boards written by hand often leave the riichi sticks out of the kyotaku
+                kyotaku = kyotaku.saturating_sub(1);
+            }
+            _ => {}
         }
     }
+    ensure!(
+        sizes[player_id as usize] == board.tehai.len(),
+        "tehai of {} tiles does not fit the calls",
+        board.tehai.len()
+    );
 
     // remaining tiles which have not been witnessed and could therefore be in someone's tehai
     let mut remaining_tiles = [4i8; 37]; // may go negative in case it's not 3aka
@@ -264,20 +444,18 @@ pub fn generate_mjai_logs(board: Board) -> Result<Vec<Event>> {
     for &Sutehai { pai, .. } in board.kawa.iter().flatten() {
         remaining_tiles[pai.as_usize()] -= 1;
     }
-    for &Naki { pai, .. } in board.fuuro.iter().flatten() {
-        remaining_tiles[pai.as_usize()] -= 1;
+    for naki in board.fuuro.iter().flatten() {
+        // the called tile of an ankan is unknown, its tiles are all consumed
+        for tile in once(naki.pai).filter(|pai| !pai.is_unknown()).chain(naki.consumed) {
+            remaining_tiles[tile.as_usize()] -= 1;
+        }
     }
 
     // reverse pass to fill in tehai and tsumo tiles
-    let mut tehais: [Vec<Tile>; 4] = from_fn(|_| vec![t!(?); 13]);
+    let mut tehais: [Vec<Tile>; 4] = from_fn(|_| vec![]);
     tehais[player_id as usize] = board.tehai;
     for player in (0..=3).filter(|player| *player != player_id) {
-        // TODO: merge shouminkan and pon
-        let fuuro_size = board.fuuro[player_rel(player) as usize]
-            .iter()
-            .filter(|naki| !naki.consumed.is_empty())
-            .count();
-        let expected_tehai_size = 13 - 3 * fuuro_size;
+        let expected_tehai_size = sizes[player as usize];
         let mut tehai = vec![];
         'outer: for (tile, count) in remaining_tiles.iter_mut().enumerate() {
             for _ in 0..*count {
@@ -291,95 +469,390 @@ pub fn generate_mjai_logs(board: Board) -> Result<Vec<Event>> {
         tehais[player as usize] = tehai;
     }
 
-    for (player, turns) in turns.iter_mut().enumerate() {
-        for (turn, _) in turns.iter_mut().rev() {
-            for event in turn.iter_mut().rev() {
-                match event {
-                    Event::Tsumo { .. } => {
-                        *event = Event::Tsumo {
-                            actor: player as u8,
-                            pai: tehais[player].pop().unwrap(),
-                        }
-                    }
-                    Event::Dahai { tsumogiri, pai, .. } => {
-                        if *tsumogiri {
-                            tehais[player].push(*pai);
-                        } else {
-                            tehais[player].insert(tehais[player].len() - 2, *pai);
-                        }
-                    }
-                    Event::Chi { consumed, .. } | Event::Pon { consumed, .. } => {
-                        tehais[player].extend(consumed.iter());
-                    }
-                    Event::Daiminkan { consumed, .. } => {
-                        tehais[player].extend(consumed.iter());
-                    }
-                    Event::Kakan { pai, .. } => {
-                        tehais[player].push(*pai);
-                    }
-                    Event::Ankan { consumed, .. } => {
-                        tehais[player].extend(consumed.iter());
-                    }
-                    _ => {}
+    for event in turns.iter_mut().rev() {
+        match event {
+            Event::Tsumo { actor, .. } => {
+                let actor = *actor;
+                *event = Event::Tsumo {
+                    actor,
+                    pai: tehais[actor as usize].pop().context("not enough tiles left for the draws")?,
                 }
             }
+            Event::Dahai {
+                actor, tsumogiri, pai, ..
+            } => {
+                let tehai = &mut tehais[*actor as usize];
+                if *tsumogiri {
+                    tehai.push(*pai);
+                } else {
+                    tehai.insert(tehai.len().saturating_sub(2), *pai);
+                }
+            }
+            Event::Chi { actor, consumed, .. } | Event::Pon { actor, consumed, .. } => {
+                tehais[*actor as usize].extend(consumed.iter());
+            }
+            Event::Daiminkan { actor, consumed, .. } => {
+                tehais[*actor as usize].extend(consumed.iter());
+            }
+            Event::Kakan { actor, pai, .. } => {
+                tehais[*actor as usize].push(*pai);
+            }
+            Event::Ankan { actor, consumed } => {
+                tehais[*actor as usize].extend(consumed.iter());
+            }
+            _ => {}
         }
     }
 
     let seen_aka = remaining_tiles[34..37].iter().any(|count| *count < 1);
 
+    let tehais: [[Tile; 13]; 4] = tehais
+        .into_iter()
+        .map(|tehai| tehai.try_into().ok())
+        .collect::<Option<Vec<_>>>()
+        .and_then(|tehais| tehais.try_into().ok())
+        .context("incorrect tehai size")?;
     let mut events = vec![];
-    events.push(Event::StartGame { id: Some(player_id), aka_flag: seen_aka, names: from_fn(|_| "".to_owned()), kyoku_first: 1 });
+    events.push(Event::StartGame {
+        id: Some(player_id),
+        aka_flag: seen_aka,
+        names: from_fn(|_| "".to_owned()),
+        kyoku_first: 1,
+    });
     events.push(Event::StartKyoku {
         bakaze: board.bakaze,
         dora_marker: board.dora_indicators[0],
         kyoku: board.kyoku,
         honba: board.honba,
-        kyotaku: board.kyotaku,
+        kyotaku,
         oya,
         scores,
-        tehais: tehais.map(|tehai| tehai.try_into().expect("Incorrect tehai size")),
+        tehais,
     });
 
-    let mut current_player = oya;
-    let mut revealed_doras = 1;
-    let mut turns_iter = turns.map(|turns| turns.into_iter());
-    loop {
-        let Some((mut turn_events, next_player)) = turns_iter[current_player as usize].next() else {
-            break;
-        };
-        for event in turn_events.iter_mut() {
-            if matches!(event, Event::Dora { .. }) {
-                // this is the only place we can be sure the events are well ordered
-                *event = Event::Dora {
-                    dora_marker: board.dora_indicators[revealed_doras],
-                };
-                revealed_doras += 1;
+    // kan dora are revealed in the order of the board's indicators
+    let mut dora_indicators = board.dora_indicators[1..].iter();
+    for event in turns.iter_mut() {
+        if matches!(event, Event::Dora { .. }) {
+            *event = Event::Dora {
+                dora_marker: *dora_indicators.next().unwrap(),
+            };
+        }
+    }
+    events.append(&mut turns);
+
+    validate_mjai_logs(&events)?;
+    Ok(events)
+}
+
+/// Hands, melds and witnessed tiles while replaying a generated log
+struct Replay {
+    hands: [Vec<Tile>; 4],
+    pons: [Vec<Tile>; 4],
+    /// Copies of each tile in hands, kawa, melds and dora indicators
+    seen: [u8; 34],
+    last_tsumo: [Option<Tile>; 4],
+    last_dahai: Option<(u8, Tile)>,
+}
+
+impl Replay {
+    fn witness(&mut self, tile: Tile) -> Result<()> {
+        ensure!(!tile.is_unknown(), "unknown tile {tile}");
+        let seen = &mut self.seen[tile.deaka().as_usize()];
+        ensure!(*seen < 4, "witnessing a 5th {}", tile.deaka());
+        *seen += 1;
+        Ok(())
+    }
+
+    fn take(&mut self, actor: u8, tile: Tile) -> Result<()> {
+        let hand = &mut self.hands[actor as usize];
+        let index = hand
+            .iter()
+            .position(|held| *held == tile)
+            .with_context(|| format!("{tile} is not in the hand of player {actor}"))?;
+        hand.remove(index);
+        Ok(())
+    }
+
+    fn call(&mut self, actor: u8, target: u8, pai: Tile, consumed: &[Tile]) -> Result<()> {
+        ensure!(actor != target, "player {actor} calls their own discard");
+        ensure!(
+            self.last_dahai.take() == Some((target, pai)),
+            "{pai} is not the last discard of player {target}"
+        );
+        for &tile in consumed {
+            self.take(actor, tile)?;
+        }
+        self.last_tsumo[actor as usize] = None;
+        Ok(())
+    }
+
+    fn update(&mut self, event: &Event) -> Result<()> {
+        // a kan dora or the acceptance of a riichi comes between a discard and its call
+        if !matches!(
+            event,
+            Event::Chi { .. } | Event::Pon { .. } | Event::Daiminkan { .. } | Event::Dora { .. } | Event::ReachAccepted { .. }
+        ) {
+            self.last_dahai = None;
+        }
+        match *event {
+            Event::StartKyoku {
+                dora_marker, ref tehais, ..
+            } => {
+                self.witness(dora_marker)?;
+                for (hand, tehai) in self.hands.iter_mut().zip(tehais) {
+                    *hand = tehai.to_vec();
+                }
+                for &tile in tehais.iter().flatten() {
+                    self.witness(tile)?;
+                }
+            }
+            Event::Tsumo { actor, pai } => {
+                ensure!(
+                    self.hands[actor as usize].len() % 3 == 1,
+                    "player {actor} draws with a full hand"
+                );
+                self.witness(pai)?;
+                self.hands[actor as usize].push(pai);
+                self.last_tsumo[actor as usize] = Some(pai);
+            }
+            Event::Dahai { actor, pai, tsumogiri } => {
+                ensure!(
+                    self.hands[actor as usize].len() % 3 == 2,
+                    "player {actor} discards without a draw"
+                );
+                if tsumogiri {
+                    ensure!(
+                        self.last_tsumo[actor as usize] == Some(pai),
+                        "tsumogiri of {pai} which player {actor} did not draw"
+                    );
+                }
+                self.take(actor, pai)?;
+                self.last_dahai = Some((actor, pai));
+            }
+            Event::Chi {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                ensure!(target == (actor + 3) % 4, "player {actor} calls chi from player {target}");
+                let mut tiles = [pai, consumed[0], consumed[1]].map(|tile| tile.deaka().as_usize());
+                tiles.sort_unstable();
+                ensure!(
+                    tiles[2] < 27 && tiles[0] / 9 == tiles[2] / 9 && tiles[1] == tiles[0] + 1 && tiles[2] == tiles[1] + 1,
+                    "chi of {pai} with {}{} is not a sequence",
+                    consumed[0],
+                    consumed[1]
+                );
+                self.call(actor, target, pai, &consumed)?;
+            }
+            Event::Pon {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                self.call(actor, target, pai, &consumed)?;
+                self.pons[actor as usize].push(pai.deaka());
             }
+            Event::Daiminkan {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => self.call(actor, target, pai, &consumed)?,
+            Event::Kakan { actor, pai, .. } => {
+                let pons = &mut self.pons[actor as usize];
+                let index = pons
+                    .iter()
+                    .position(|pon| *pon == pai.deaka())
+                    .with_context(|| format!("kakan of {pai} without a pon"))?;
+                pons.remove(index);
+                self.take(actor, pai)?;
+            }
+            Event::Ankan { actor, consumed } => {
+                for tile in consumed {
+                    self.take(actor, tile)?;
+                }
+            }
+            Event::Dora { dora_marker } => self.witness(dora_marker)?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Replay a generated log checking the tiles of every event and the state of every seat, the error names the
+/// offending event by its index
+pub fn validate_mjai_logs(events: &[Event]) -> Result<()> {
+    let mut replay = Replay {
+        hands: Default::default(),
+        pons: Default::default(),
+        seen: [0; 34],
+        last_tsumo: [None; 4],
+        last_dahai: None,
+    };
+    let mut states = from_fn::<_, 4, _>(|player| PlayerState::new(player as u8));
+    for (index, event) in events.iter().enumerate().skip(1) {
+        let describe = || format!("invalid event {index} {}", serde_json::to_string(event).unwrap_or_default());
+        replay.update(event).with_context(describe)?;
+        for state in states.iter_mut() {
+            state
+                .update(event)
+                .with_context(|| format!("state of player {} rejects it", state.player_id))
+                .with_context(describe)?;
+        }
+    }
+    Ok(())
+}
+
+/// Unseen tile with the most copies left, taking one copy
+fn take_most_left(left: &mut [i8; 34], copies: i8) -> Result<Tile> {
+    let (tile, count) = left
+        .iter_mut()
+        .enumerate()
+        .max_by_key(|(tile, count)| (**count, std::cmp::Reverse(*tile)))
+        .context("no tiles left")?;
+    ensure!(*count >= copies, "not enough tiles left for the unknown tiles");
+    *count -= copies;
+    Ok(must_tile!(tile))
+}
+
+/// Fill the unknown tiles of a meld from its known ones: copies for a pon or kan, the missing tile of a chi
+fn fill_meld(tiles: &mut [Tile], left: &mut [i8; 34]) -> Result<()> {
+    let unknown = tiles.iter().filter(|tile| tile.is_unknown()).count();
+    if unknown == 0 {
+        return Ok(());
+    }
+    let mut known = tiles
+        .iter()
+        .filter(|tile| !tile.is_unknown())
+        .map(|tile| tile.deaka().as_usize())
+        .collect::<Vec<_>>();
+    known.sort_unstable();
+    known.dedup();
+    let fill = match known[..] {
+        [] => take_most_left(left, unknown as i8)?,
+        [tile] => must_tile!(tile),
+        [low, high] if tiles.len() == 3 && low / 9 == high / 9 && low < 27 && high - low <= 2 => {
+            let below = (low % 9 > 0 && high - low == 1).then(|| low - 1);
+            let above = (high % 9 < 8 && high - low == 1).then(|| high + 1);
+            let middle = (high - low == 2).then(|| low + 1);
+            let tile = middle
+                .or(below.filter(|&tile| left[tile] > 0))
+                .or(above)
+                .or(below)
+                .context("cannot infer the unknown tile of a chi")?;
+            must_tile!(tile)
         }
-        events.append(&mut turn_events);
-        current_player = next_player.unwrap_or((current_player + 1) % 4);
+        _ => bail!("cannot infer the unknown tiles of a meld"),
+    };
+    if !known.is_empty() {
+        left[fill.as_usize()] -= unknown as i8;
     }
-    if turns_iter.iter_mut().any(|turns| turns.len() != 0) {
-        bail!(
-            "Incorrect number of turns, remaining {:?}",
-            turns_iter.map(|turns| turns.len())
+    for tile in tiles.iter_mut().filter(|tile| tile.is_unknown()) {
+        *tile = fill;
+    }
+    Ok(())
+}
+
+/// Replace the unknown tiles of the kawa and fuuro by tiles consistent with the rest of the board, unknown discards by
+/// tiles that do not make our hand look safer
+fn fill_unknown_tiles(board: &mut Board) -> Result<()> {
+    let mut left = [4i8; 34];
+    let known = board
+        .dora_indicators
+        .iter()
+        .chain(board.tehai.iter())
+        .chain(board.kawa.iter().flatten().map(|sutehai| &sutehai.pai))
+        .chain(
+            board
+                .fuuro
+                .iter()
+                .flatten()
+                .flat_map(|naki| once(&naki.pai).chain(naki.consumed.iter())),
         );
+    for tile in known.filter(|tile| !tile.is_unknown()) {
+        left[tile.deaka().as_usize()] -= 1;
+    }
+    for fuuro in board.fuuro.iter_mut() {
+        let mut pons = vec![];
+        for naki in fuuro.iter_mut() {
+            if naki.consumed.is_empty() {
+                // kakan of an unknown tile adds to the earliest pon
+                if naki.pai.is_unknown() {
+                    naki.pai = *pons.first().context("kakan of an unknown tile without a pon")?;
+                    left[naki.pai.deaka().as_usize()] -= 1;
+                }
+                continue;
+            }
+            let is_ankan = naki.consumed.len() == 4;
+            let mut tiles = naki.consumed.to_vec();
+            if !is_ankan {
+                tiles.push(naki.pai);
+            }
+            fill_meld(&mut tiles, &mut left)?;
+            if !is_ankan {
+                naki.pai = tiles.pop().unwrap();
+            }
+            naki.consumed = tiles.iter().copied().collect();
+            if naki.consumed.len() == 2 && naki.consumed[0].deaka() == naki.consumed[1].deaka() {
+                pons.push(naki.pai.deaka());
+            }
+        }
     }
+    let mut in_hand = [false; 34];
+    for tile in &board.tehai {
+        in_hand[tile.deaka().as_usize()] = true;
+    }
+    for kawa in board.kawa.iter_mut() {
+        let discarded = kawa
+            .iter()
+            .filter(|sutehai| !sutehai.pai.is_unknown())
+            .map(|sutehai| sutehai.pai.deaka().as_usize())
+            .collect::<Vec<_>>();
+        for sutehai in kawa.iter_mut().filter(|sutehai| sutehai.pai.is_unknown()) {
+            sutehai.pai = take_unknown_discard(&mut left, &in_hand, &discarded)?;
+        }
+    }
+    Ok(())
+}
 
-    Ok(events)
+/// Tile standing in for an unknown discard that tells as little as possible about the safety of our hand: an honor we
+/// do not hold is genbutsu only for itself and has no suji, a tile the player discarded before adds nothing new
+fn take_unknown_discard(left: &mut [i8; 34], in_hand: &[bool; 34], discarded: &[usize]) -> Result<Tile> {
+    let honor = (27..34)
+        .filter(|&tile| !in_hand[tile] && left[tile] > 0)
+        .max_by_key(|&tile| (left[tile], std::cmp::Reverse(tile)));
+    let repeated = || discarded.iter().copied().find(|&tile| left[tile] > 0);
+    match honor.or_else(repeated) {
+        Some(tile) => {
+            left[tile] -= 1;
+            Ok(must_tile!(tile))
+        }
+        None => take_most_left(left, 1),
+    }
 }
 
 /// Parse a string representation of a board
 pub fn parse_board(args: Vec<&str>) -> Result<Vec<Event>> {
+    board_events(args).context(WashizuError::Board)
+}
+
+fn board_events(args: Vec<&str>) -> Result<Vec<Event>> {
     let mut parts = args.into_iter();
 
     let mut board = Board::default();
 
     let kyoku = parts.next().context("missing kyoku")?;
-    ensure!(kyoku.len() == 2, "kyoku must be <bakaze><honba> (e.g. S3)");
-    board.bakaze = Tile::from_str(&kyoku[0..1]).context("incorrect bakaze")?;
-    board.kyoku = kyoku[1..2].parse().context("incorrect kyoku")?;
+    let mut chars = kyoku.chars();
+    let (Some(bakaze), Some(number), None) = (chars.next(), chars.next(), chars.next()) else {
+        bail!("kyoku must be <bakaze><kyoku> (e.g. S3)");
+    };
+    board.bakaze = Tile::from_str(&bakaze.to_string()).context("incorrect bakaze")?;
+    board.kyoku = number.to_digit(10).context("incorrect kyoku")? as u8;
+    ensure!((1..=4).contains(&board.kyoku), "kyoku must be between 1 and 4");
 
     board.jikaze = parts.next().context("missing jikaze")?.parse().context("incorrect jikaze")?;
     board.kyotaku = parts.next().context("missing kyotaku")?.parse().context("incorrectkyotaku")?;
@@ -396,7 +869,7 @@ pub fn parse_board(args: Vec<&str>) -> Result<Vec<Event>> {
             continue;
         }
         while chars.peek().is_some() {
-            let tile_string = format!("{}{}", chars.next().unwrap(), chars.next().context("incorrect kawa")?);
+            let pai = next_tile(&mut chars, "kawa")?;
             let (tedashi, riichi) = match chars.peek() {
                 Some('.') => {
                     chars.next();
@@ -408,11 +881,7 @@ pub fn parse_board(args: Vec<&str>) -> Result<Vec<Event>> {
                 }
                 _ => (false, false),
             };
-            kawa.push(Sutehai {
-                pai: parse_tile(&tile_string)?,
-                tedashi,
-                riichi,
-            });
+            kawa.push(Sutehai { pai, tedashi, riichi });
         }
     }
     for (player, fuuro) in board.fuuro.iter_mut().enumerate() {
@@ -422,32 +891,29 @@ pub fn parse_board(args: Vec<&str>) -> Result<Vec<Event>> {
         }
         // chi (1p)2p3p, pon (1p)1p1p, daiminkan (1p)1p1p1p, ankan 1p1p1p1p, kakan (1p)
         for naki_chars in fuuro_iter.collect::<Vec<_>>().split(|char| *char == ',') {
-            let mut naki_iter = naki_chars.iter().peekable();
+            let mut naki_iter = naki_chars.iter().copied().peekable();
             let mut pai: Option<(Tile, u8)> = None;
             let mut consumed: Vec<Tile> = vec![];
             loop {
                 match naki_iter.peek() {
                     Some('(') => {
                         _ = naki_iter.next();
-                        let tile_string = format!(
-                            "{}{}",
-                            naki_iter.next().unwrap(),
-                            naki_iter.next().context("incorrect fuuro")?
-                        );
-                        ensure!(naki_iter.next() == Some(&')'), "missing closing parenthesis");
-                        pai = Some((parse_tile(&tile_string)?, consumed.len() as u8))
-                    }
-                    Some(_) => {
-                        let tile_string = format!(
-                            "{}{}",
-                            naki_iter.next().unwrap(),
-                            naki_iter.next().context("incorrect fuuro")?
-                        );
-                        consumed.push(parse_tile(&tile_string)?);
+                        let tile = next_tile(&mut naki_iter, "fuuro")?;
+                        ensure!(naki_iter.next() == Some(')'), "missing closing parenthesis");
+                        pai = Some((tile, consumed.len() as u8))
                     }
+                    Some(_) => consumed.push(next_tile(&mut naki_iter, "fuuro")?),
                     None => break,
                 }
             }
+            ensure!(
+                matches!(
+                    (consumed.len(), pai.is_some()),
+                    (0, true) | (2, true) | (3, true) | (4, false)
+                ),
+                "incorrect fuuro {}",
+                naki_chars.iter().collect::<String>()
+            );
             // TODO: Make Naki more accurate to what is inputted
             if let Some((pai, pai_pos)) = pai {
                 let rel_target = match pai_pos {
@@ -472,3 +938,343 @@ pub fn parse_board(args: Vec<&str>) -> Result<Vec<Event>> {
 
     generate_mjai_logs(board)
 }
+
+/// Discarded tile of a JSON board
+#[derive(serde::Deserialize, Debug, Clone)]
+struct SutehaiJson {
+    pai: String,
+    #[serde(default)]
+    tedashi: bool,
+    #[serde(default)]
+    riichi: bool,
+}
+
+/// Meld of a JSON board
+#[derive(serde::Deserialize, Debug, Clone)]
+struct NakiJson {
+    /// Called tile, omitted for ankan
+    #[serde(default)]
+    pai: Option<String>,
+    /// Tiles from the caller's hand, empty for kakan
+    #[serde(default)]
+    consumed: Vec<String>,
+    /// Discarder relative to the caller, 3 (kamicha) when omitted
+    #[serde(default)]
+    target: Option<u8>,
+}
+
+/// Board in the JSON format of `schemas/board.schema.json`, for producers like screenshot readers
+#[derive(serde::Deserialize, Debug, Clone)]
+struct BoardJson {
+    bakaze: String,
+    kyoku: u8,
+    jikaze: String,
+    #[serde(default)]
+    honba: u8,
+    #[serde(default)]
+    kyotaku: u8,
+    dora_indicators: Vec<String>,
+    scores: [i32; 4],
+    tehai: Vec<String>,
+    kawa: [Vec<SutehaiJson>; 4],
+    fuuro: [Vec<NakiJson>; 4],
+}
+
+/// Tile of a JSON board, `?` for an unknown tile
+fn json_tile(s: &str) -> Result<Tile> {
+    if s == "?" {
+        return Ok(t!(?));
+    }
+    parse_tile(s).with_context(|| format!("incorrect tile {s}"))
+}
+
+fn json_tiles(tiles: &[String]) -> Result<Vec<Tile>> {
+    tiles.iter().map(|tile| json_tile(tile)).collect()
+}
+
+impl BoardJson {
+    fn to_board(&self) -> Result<Board> {
+        ensure!((1..=4).contains(&self.kyoku), "incorrect kyoku");
+        let mut board = Board {
+            bakaze: Tile::from_str(&self.bakaze).context("incorrect bakaze")?,
+            jikaze: Tile::from_str(&self.jikaze).context("incorrect jikaze")?,
+            kyoku: self.kyoku,
+            honba: self.honba,
+            kyotaku: self.kyotaku,
+            scores: self.scores,
+            dora_indicators: json_tiles(&self.dora_indicators).context("incorrect dora")?,
+            tehai: json_tiles(&self.tehai).context("incorrect tehai")?,
+            ..Default::default()
+        };
+        ensure!(board.tehai.iter().all(|tile| !tile.is_unknown()), "incorrect tehai");
+        for (kawa, kawa_json) in board.kawa.iter_mut().zip(&self.kawa) {
+            for sutehai in kawa_json {
+                kawa.push(Sutehai {
+                    pai: json_tile(&sutehai.pai).context("incorrect kawa")?,
+                    tedashi: sutehai.tedashi || sutehai.riichi,
+                    riichi: sutehai.riichi,
+                });
+            }
+        }
+        for (player, (fuuro, fuuro_json)) in board.fuuro.iter_mut().zip(&self.fuuro).enumerate() {
+            for naki in fuuro_json {
+                let consumed = json_tiles(&naki.consumed).context("incorrect fuuro")?;
+                ensure!(
+                    matches!(
+                        (consumed.len(), naki.pai.is_some()),
+                        (0, true) | (2, true) | (3, true) | (4, false)
+                    ),
+                    "incorrect fuuro"
+                );
+                let target = naki.target.unwrap_or(3);
+                ensure!((1..=3).contains(&target), "incorrect fuuro target");
+                fuuro.push(match &naki.pai {
+                    Some(pai) => Naki {
+                        pai: json_tile(pai).context("incorrect fuuro")?,
+                        consumed: consumed.into_iter().collect(),
+                        target: (target + player as u8) % 4,
+                    },
+                    None => Naki {
+                        pai: t!(?),
+                        consumed: consumed.into_iter().collect(),
+                        target: 0,
+                    },
+                });
+            }
+        }
+        Ok(board)
+    }
+}
+
+/// Parse a board in the JSON format of `schemas/board.schema.json`
+pub fn parse_board_json(text: &str) -> Result<Vec<Event>> {
+    let board = serde_json::from_str::<BoardJson>(text)
+        .context("malformed board json")
+        .and_then(|board| board.to_board())
+        .context(WashizuError::Board)?;
+    generate_mjai_logs(board).context(WashizuError::Board)
+}
+
+/// Events as a mjai log, one JSON event per line
+pub fn mjai_lines(events: &[Event]) -> Result<String> {
+    let mut lines = String::new();
+    for event in events {
+        lines.push_str(&serde_json::to_string(event)?);
+        lines.push('\n');
+    }
+    Ok(lines)
+}
+
+/// Tile in the two character notation of boards, `0` for an aka five
+fn tile_code(tile: Tile) -> (char, char) {
+    let index = tile.deaka().as_usize();
+    let digit = if tile.is_aka() { 0 } else { index % 9 + 1 };
+    (char::from(b'0' + digit as u8), char::from(b"mpsz"[index / 9]))
+}
+
+/// Tiles in board notation keeping their order, runs of one suit share the suit letter, e.g. 123m45p
+pub fn tiles_string(tiles: &[Tile]) -> String {
+    let mut string = String::new();
+    for (index, &tile) in tiles.iter().enumerate() {
+        let (digit, suit) = tile_code(tile);
+        string.push(digit);
+        if tiles.get(index + 1).is_none_or(|&next| tile_code(next).1 != suit) {
+            string.push(suit);
+        }
+    }
+    string
+}
+
+/// Wind letter of a board, E S W N
+fn wind_letter(wind: usize) -> char {
+    ['E', 'S', 'W', 'N'][wind % 4]
+}
+
+impl Board {
+    /// Board in the notation of `parse_board`, which generates the same events back up to the information boards lose
+    pub fn to_board_string(&self) -> String {
+        let bakaze = wind_letter(self.bakaze.deaka().as_usize().saturating_sub(27));
+        let jikaze = wind_letter(self.jikaze.deaka().as_usize().saturating_sub(27));
+        let mut parts = vec![
+            format!("{bakaze}{}", self.kyoku),
+            jikaze.to_string(),
+            self.kyotaku.to_string(),
+            self.honba.to_string(),
+            tiles_string(&self.dora_indicators),
+        ];
+        parts.extend(self.scores.iter().map(i32::to_string));
+        parts.push(tiles_string(&self.tehai));
+        for kawa in &self.kawa {
+            let kawa = kawa
+                .iter()
+                .map(|sutehai| {
+                    let (digit, suit) = tile_code(sutehai.pai);
+                    let mark = match (sutehai.riichi, sutehai.tedashi) {
+                        (true, _) => "-",
+                        (false, true) => ".",
+                        (false, false) => "",
+                    };
+                    format!("{digit}{suit}{mark}")
+                })
+                .collect::<String>();
+            parts.push(if kawa.is_empty() { "/".to_owned() } else { kawa });
+        }
+        for (player, fuuro) in self.fuuro.iter().enumerate() {
+            let melds = fuuro
+                .iter()
+                .map(|naki| {
+                    let code = |tile: Tile| {
+                        let (digit, suit) = tile_code(tile);
+                        format!("{digit}{suit}")
+                    };
+                    let mut tiles = naki.consumed.iter().map(|&tile| code(tile)).collect::<Vec<_>>();
+                    if naki.consumed.len() == 4 {
+                        return tiles.concat();
+                    }
+                    // the called tile's position tells who discarded it: first kamicha, last shimocha, else toimen
+                    let position = match (4 + naki.target as usize - player) % 4 {
+                        3 => 0,
+                        1 => tiles.len(),
+                        _ => 1.min(tiles.len()),
+                    };
+                    tiles.insert(position, format!("({})", code(naki.pai)));
+                    tiles.concat()
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            parts.push(if melds.is_empty() { "/".to_owned() } else { melds });
+        }
+        parts.join(" ")
+    }
+}
+
+/// Board of the current kyoku seen by a player from the events so far
+pub fn board_from_events(events: &[Event], player_id: u8) -> Result<Board> {
+    let rel = |player: u8| ((4 + player - player_id) % 4) as usize;
+    let mut board = None;
+    let mut riichi = [false; 4];
+    for event in events {
+        if let Event::StartKyoku {
+            bakaze,
+            dora_marker,
+            kyoku,
+            honba,
+            kyotaku,
+            oya,
+            scores,
+            tehais,
+        } = event
+        {
+            riichi = [false; 4];
+            board = Some(Board {
+                bakaze: *bakaze,
+                jikaze: must_tile!(tuz!(E) + (4 - rel(*oya)) % 4),
+                kyoku: *kyoku,
+                honba: *honba,
+                kyotaku: *kyotaku,
+                scores: from_fn(|player| scores[(player + player_id as usize) % 4]),
+                dora_indicators: vec![*dora_marker],
+                kawa: Default::default(),
+                fuuro: Default::default(),
+                tehai: tehais[player_id as usize].to_vec(),
+            });
+            continue;
+        }
+        let Some(board) = board.as_mut() else {
+            continue;
+        };
+        let take = |board: &mut Board, tiles: &[Tile]| -> Result<()> {
+            for tile in tiles {
+                let position = board
+                    .tehai
+                    .iter()
+                    .position(|held| held == tile)
+                    .with_context(|| format!("{tile} is not in tehai"))?;
+                board.tehai.remove(position);
+            }
+            Ok(())
+        };
+        match *event {
+            Event::Tsumo { actor, pai } if actor == player_id => board.tehai.push(pai),
+            Event::Reach { actor } => riichi[actor as usize] = true,
+            Event::ReachAccepted { actor } => {
+                board.scores[rel(actor)] -= 1000;
+                board.kyotaku += 1;
+            }
+            Event::Dahai { actor, pai, tsumogiri } => {
+                if actor == player_id {
+                    take(board, &[pai])?;
+                }
+                board.kawa[rel(actor)].push(Sutehai {
+                    pai,
+                    tedashi: !tsumogiri || riichi[actor as usize],
+                    riichi: std::mem::take(&mut riichi[actor as usize]),
+                });
+            }
+            Event::Chi {
+                actor,
+                target,
+                pai,
+                consumed: [a, b],
+            }
+            | Event::Pon {
+                actor,
+                target,
+                pai,
+                consumed: [a, b],
+            } => {
+                board.kawa[rel(target)].pop();
+                if actor == player_id {
+                    take(board, &[a, b])?;
+                }
+                board.fuuro[rel(actor)].push(Naki {
+                    pai,
+                    consumed: [a, b].into_iter().collect(),
+                    target: rel(target) as u8,
+                });
+            }
+            Event::Daiminkan {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                board.kawa[rel(target)].pop();
+                if actor == player_id {
+                    take(board, &consumed)?;
+                }
+                board.fuuro[rel(actor)].push(Naki {
+                    pai,
+                    consumed: consumed.into_iter().collect(),
+                    target: rel(target) as u8,
+                });
+            }
+            Event::Ankan { actor, consumed } => {
+                if actor == player_id {
+                    take(board, &consumed)?;
+                }
+                board.fuuro[rel(actor)].push(Naki {
+                    pai: t!(?),
+                    consumed: consumed.into_iter().collect(),
+                    target: rel(actor) as u8,
+                });
+            }
+            Event::Kakan { actor, pai, .. } => {
+                if actor == player_id {
+                    take(board, &[pai])?;
+                }
+                board.fuuro[rel(actor)].push(Naki {
+                    pai,
+                    consumed: ArrayVec::new(),
+                    target: rel(actor) as u8,
+                });
+            }
+            Event::Dora { dora_marker } => board.dora_indicators.push(dora_marker),
+            _ => {}
+        }
+    }
+    board.context("no StartKyoku")
+}
+
+#[cfg(test)]
+mod tests;
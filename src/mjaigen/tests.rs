@@ -0,0 +1,376 @@
+use proptest::prelude::*;
+use proptest::sample::Index;
+
+use super::{Board, Naki, Sutehai, board_from_events, generate_mjai_logs, parse_board};
+use crate::tiles::{Tile, must_tile, t, tuz};
+
+/// How a seat enters its turn
+enum Entry {
+    Draw,
+    /// Chi or pon with the tiles it forbids to discard
+    Call(Vec<usize>),
+    Daiminkan,
+}
+
+/// Seat of a simulated game, melds keep the absolute discarder
+#[derive(Default)]
+struct Seat {
+    hand: Vec<Tile>,
+    kawa: Vec<Sutehai>,
+    fuuro: Vec<Naki>,
+    pons: Vec<usize>,
+    riichi: bool,
+}
+
+impl Seat {
+    /// Closed apart from ankan, whose called tile is unknown
+    fn is_closed(&self) -> bool {
+        self.fuuro.iter().all(|naki| naki.pai.is_unknown())
+    }
+}
+
+/// Decisions of a simulated game drawn by proptest, so that failing games shrink to fewer and earlier choices
+struct Choices(std::vec::IntoIter<Index>);
+
+impl Choices {
+    /// One of `len` options, the first once the decisions run out
+    fn index(&mut self, len: usize) -> usize {
+        self.0.next().map_or(0, |index| index.index(len))
+    }
+
+    fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        (!items.is_empty()).then(|| &items[self.index(items.len())])
+    }
+
+    /// Whether something of this chance in percent happens, never once the decisions run out
+    fn chance(&mut self, percent: usize) -> bool {
+        self.0.next().is_some_and(|index| index.index(100) < percent)
+    }
+}
+
+fn kind(tile: Tile) -> usize {
+    tile.deaka().as_usize()
+}
+
+fn count(hand: &[Tile], tile_kind: usize) -> usize {
+    hand.iter().filter(|&&tile| kind(tile) == tile_kind).count()
+}
+
+/// Take one tile of each kind out of a hand, any of the copies
+fn take(hand: &mut Vec<Tile>, kinds: &[usize], choices: &mut Choices) -> Vec<Tile> {
+    kinds
+        .iter()
+        .map(|&tile_kind| {
+            let copies = (0..hand.len())
+                .filter(|&index| kind(hand[index]) == tile_kind)
+                .collect::<Vec<_>>();
+            hand.remove(*choices.choose(&copies).unwrap())
+        })
+        .collect()
+}
+
+/// Sequences a discard completes with the tiles of a chi and the tiles it forbids to discard
+fn chi_options(tile: Tile) -> Vec<([usize; 2], Vec<usize>)> {
+    let called = kind(tile);
+    if called >= 27 {
+        return vec![];
+    }
+    let number = called % 9;
+    let mut options = vec![];
+    if number <= 6 {
+        let forbidden = if number <= 5 { vec![called, called + 3] } else { vec![called] };
+        options.push(([called + 1, called + 2], forbidden));
+    }
+    if (1..=7).contains(&number) {
+        options.push(([called - 1, called + 1], vec![called]));
+    }
+    if number >= 2 {
+        let forbidden = if number >= 3 { vec![called, called - 3] } else { vec![called] };
+        options.push(([called - 2, called - 1], forbidden));
+    }
+    options
+}
+
+/// The 136 tiles with one red five of each suit
+fn all_tiles() -> Vec<Tile> {
+    (0..136)
+        .map(|index| {
+            let tile_kind = index / 4;
+            match tile_kind {
+                4 | 13 | 22 if index % 4 == 0 => must_tile!(34 + tile_kind / 9),
+                _ => must_tile!(tile_kind),
+            }
+        })
+        .collect()
+}
+
+/// Board seen by `viewer` after a random legal game on a shuffled wall, stopped after about `steps` discards
+///
+/// The viewer never declares riichi, its hand is known to the log and rarely tenpai.
+fn random_board(mut wall: Vec<Tile>, choices: Vec<Index>, steps: usize, viewer: usize, oya: usize) -> Board {
+    let mut choices = Choices(choices.into_iter());
+    let dead_wall = wall.split_off(wall.len() - 14);
+    let mut rinshan = dead_wall[5..9].to_vec();
+    let mut dora_indicators = vec![dead_wall[0]];
+    let mut seats = (0..4).map(|_| Seat::default()).collect::<Vec<_>>();
+    for seat in &mut seats {
+        seat.hand = wall.split_off(wall.len() - 13);
+    }
+
+    let mut actor = oya;
+    let mut entry = Entry::Draw;
+    let mut steps_left = steps;
+    let mut kans = 0;
+    let mut scores = [25000; 4];
+    let mut kyotaku = 0;
+    loop {
+        let seat = &mut seats[actor];
+        let mut pending = false;
+        let mut drew = true;
+        let forbidden = match entry {
+            Entry::Draw => {
+                let Some(tile) = wall.pop() else { break };
+                seat.hand.push(tile);
+                vec![]
+            }
+            Entry::Daiminkan => {
+                seat.hand.push(rinshan.pop().unwrap());
+                wall.remove(0);
+                pending = true;
+                vec![]
+            }
+            Entry::Call(forbidden) => {
+                drew = false;
+                forbidden
+            }
+        };
+        while drew && !seat.riichi && kans < 4 && wall.len() > 1 && choices.chance(30) {
+            let ankan = (0..34).find(|&tile_kind| count(&seat.hand, tile_kind) == 4);
+            let kakan = seat.pons.iter().copied().find(|&pon| count(&seat.hand, pon) > 0);
+            if let Some(tile_kind) = ankan {
+                let consumed = take(&mut seat.hand, &[tile_kind; 4], &mut choices);
+                seat.fuuro.push(Naki {
+                    pai: t!(?),
+                    consumed: consumed.into_iter().collect(),
+                    target: actor as u8,
+                });
+                if pending {
+                    dora_indicators.push(dead_wall[dora_indicators.len()]);
+                }
+                dora_indicators.push(dead_wall[dora_indicators.len()]);
+                pending = false;
+            } else if let Some(pon) = kakan {
+                let pai = take(&mut seat.hand, &[pon], &mut choices)[0];
+                seat.pons.retain(|&other| other != pon);
+                seat.fuuro.push(Naki {
+                    pai,
+                    consumed: Default::default(),
+                    target: actor as u8,
+                });
+                if pending {
+                    dora_indicators.push(dead_wall[dora_indicators.len()]);
+                }
+                pending = true;
+            } else {
+                break;
+            }
+            kans += 1;
+            seat.hand.push(rinshan.pop().unwrap());
+            wall.remove(0);
+        }
+        // boards end after a discard or with the viewer to discard
+        if steps_left == 0 && actor == viewer {
+            break;
+        }
+
+        // a riichi needs a closed hand and a draw left for everyone after it
+        let declares = drew && !seat.riichi && actor != viewer && seat.is_closed() && wall.len() >= 4 && choices.chance(10);
+        let index = if seat.riichi {
+            seat.hand.len() - 1
+        } else {
+            let discards = (0..seat.hand.len())
+                .filter(|&index| !forbidden.contains(&kind(seat.hand[index])))
+                .collect::<Vec<_>>();
+            *choices.choose(&discards).unwrap()
+        };
+        let pai = seat.hand.remove(index);
+        // the drawn tile is always the last one of the hand
+        let tsumogiri = drew && index == seat.hand.len();
+        // boards always show the riichi tile as tedashi
+        seat.kawa.push(Sutehai {
+            pai,
+            tedashi: !tsumogiri || declares,
+            riichi: declares,
+        });
+        if declares {
+            seat.riichi = true;
+            scores[actor] -= 1000;
+            kyotaku += 1;
+        }
+        if pending {
+            dora_indicators.push(dead_wall[dora_indicators.len()]);
+        }
+        steps_left = steps_left.saturating_sub(1);
+
+        let discarder = actor;
+        let mut callers = (1..4).map(|offset| (discarder + offset) % 4).collect::<Vec<_>>();
+        callers.rotate_left(choices.index(3));
+        let call = callers.into_iter().find_map(|caller| {
+            let hand = &seats[caller].hand;
+            if seats[caller].riichi || seats[caller].fuuro.len() >= 4 || wall.is_empty() || !choices.chance(25) {
+                return None;
+            }
+            let copies = count(hand, kind(pai));
+            let mut options = vec![];
+            if copies >= 3 && kans < 4 {
+                options.push((vec![kind(pai); 3], None));
+            }
+            if copies >= 2 {
+                options.push((vec![kind(pai); 2], Some(vec![kind(pai)])));
+            }
+            if caller == (discarder + 1) % 4 {
+                for (consumed, forbidden) in chi_options(pai) {
+                    if consumed.iter().all(|&tile_kind| count(hand, tile_kind) > 0) {
+                        options.push((consumed.to_vec(), Some(forbidden)));
+                    }
+                }
+            }
+            // a chi or pon needs a tile left that it does not forbid
+            options.retain(|(consumed, forbidden)| {
+                let mut rest = hand.clone();
+                for &tile_kind in consumed {
+                    let index = rest.iter().position(|&tile| kind(tile) == tile_kind).unwrap();
+                    rest.remove(index);
+                }
+                forbidden
+                    .as_ref()
+                    .is_none_or(|forbidden| rest.iter().any(|&tile| !forbidden.contains(&kind(tile))))
+            });
+            choices.choose(&options).cloned().map(|option| (caller, option))
+        });
+        let Some((caller, (consumed, forbidden))) = call else {
+            if steps_left == 0 && choices.chance(50) {
+                break;
+            }
+            actor = (discarder + 1) % 4;
+            entry = Entry::Draw;
+            continue;
+        };
+        seats[discarder].kawa.pop();
+        let seat = &mut seats[caller];
+        let consumed = take(&mut seat.hand, &consumed, &mut choices);
+        let is_pon = consumed.len() == 2 && kind(consumed[0]) == kind(consumed[1]);
+        if is_pon {
+            seat.pons.push(kind(pai));
+        }
+        seat.fuuro.push(Naki {
+            pai,
+            consumed: consumed.into_iter().collect(),
+            target: discarder as u8,
+        });
+        entry = match forbidden {
+            Some(forbidden) => Entry::Call(forbidden),
+            None => {
+                kans += 1;
+                Entry::Daiminkan
+            }
+        };
+        actor = caller;
+    }
+
+    let rel = |seat: usize| ((4 + seat - viewer) % 4) as u8;
+    Board {
+        bakaze: t!(E),
+        jikaze: must_tile!(tuz!(E) + (4 + viewer - oya) % 4),
+        kyoku: oya as u8 + 1,
+        honba: 0,
+        kyotaku,
+        scores: std::array::from_fn(|player| scores[(viewer + player) % 4]),
+        dora_indicators,
+        kawa: std::array::from_fn(|player| seats[(viewer + player) % 4].kawa.clone()),
+        fuuro: std::array::from_fn(|player| {
+            seats[(viewer + player) % 4]
+                .fuuro
+                .iter()
+                .map(|naki| Naki {
+                    target: rel(naki.target as usize),
+                    ..*naki
+                })
+                .collect()
+        }),
+        tehai: seats[viewer].hand.clone(),
+    }
+}
+
+type Fuuro = Vec<Vec<(Tile, Vec<Tile>, u8)>>;
+
+fn kawa_tuples(board: &Board) -> Vec<Vec<(Tile, bool, bool)>> {
+    board
+        .kawa
+        .iter()
+        .map(|kawa| {
+            kawa.iter()
+                .map(|sutehai| (sutehai.pai, sutehai.tedashi, sutehai.riichi))
+                .collect()
+        })
+        .collect()
+}
+
+fn fuuro_tuples(board: &Board) -> Fuuro {
+    board
+        .fuuro
+        .iter()
+        .map(|fuuro| {
+            fuuro
+                .iter()
+                .map(|naki| (naki.pai, naki.consumed.to_vec(), naki.target))
+                .collect()
+        })
+        .collect()
+}
+
+fn sorted(tiles: &[Tile]) -> Vec<Tile> {
+    let mut tiles = tiles.to_vec();
+    tiles.sort();
+    tiles
+}
+
+proptest! {
+    #[test]
+    fn generated_logs_replay_to_the_same_board(
+        wall in Just(all_tiles()).prop_shuffle(),
+        choices in prop::collection::vec(any::<Index>(), 0..600),
+        steps in 0usize..80,
+        viewer in 0usize..4,
+        oya in 0usize..4,
+    ) {
+        let board = random_board(wall, choices, steps, viewer, oya);
+        let events = generate_mjai_logs(board.clone()).map_err(|error| TestCaseError::fail(format!("{error:#}")))?;
+        let replayed = board_from_events(&events, viewer as u8).unwrap();
+        prop_assert_eq!(kawa_tuples(&replayed), kawa_tuples(&board));
+        prop_assert_eq!(fuuro_tuples(&replayed), fuuro_tuples(&board));
+        prop_assert_eq!(sorted(&replayed.tehai), sorted(&board.tehai));
+        prop_assert_eq!(replayed.dora_indicators, board.dora_indicators);
+        prop_assert_eq!(replayed.jikaze, board.jikaze);
+        prop_assert_eq!(replayed.scores, board.scores);
+        prop_assert_eq!(replayed.kyotaku, board.kyotaku);
+    }
+}
+
+#[test]
+fn boards_outside_the_four_kyokus_are_rejected() {
+    for kyoku in ["E0", "E5", "S9"] {
+        let board = format!("{kyoku} E 0 0 1m 25000 25000 25000 25000 123m456p789s11222z / / / / / / / /");
+        let error = parse_board(board.split_whitespace().collect()).unwrap_err();
+        assert!(format!("{error:#}").contains("kyoku must be between 1 and 4"), "{error:#}");
+    }
+}
+
+#[test]
+fn melds_of_the_wrong_size_are_rejected() {
+    for meld in ["1p", "(1p)2p", "1p1p1p1p1p", "(1p)1p1p1p1p"] {
+        let board = format!("E1 E 0 0 1m 25000 25000 25000 25000 123m456p789s11z / / / / {meld} / / /");
+        let error = parse_board(board.split_whitespace().collect()).unwrap_err();
+        assert!(format!("{error:#}").contains(&format!("incorrect fuuro {meld}")), "{error:#}");
+    }
+}
@@ -0,0 +1,275 @@
+//! Mortal's action probabilities computed locally from an exported model, behind the `mortal` feature
+//! The model is an ONNX export of mortal's brain and DQN taking the observation `obs` [1, C, 34] and the action mask
+//! `mask` [1, 46] encoded by libriichi and returning the q values `q` [1, 46]. ONNX Runtime is loaded at run time from
+//! ORT_DYLIB_PATH or the library path. Probabilities are the softmax of the legal q values as in mortal's reviews.
+//! A kan is shown as the first kan possible, the second pass mortal makes to choose between kans is not run.
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result, anyhow};
+use ort::session::Session;
+use ort::value::Tensor;
+use riichi::mjai::Event;
+use riichi::must_tile;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+use crate::ekyumoe::Detail;
+
+/// Size of mortal's action space
+const ACTIONS: usize = 46;
+/// Version of the observation encoding of current mortal models
+pub const DEFAULT_OBS_VERSION: u32 = 4;
+
+pub struct MortalModel {
+    path: PathBuf,
+    version: u32,
+    session: Mutex<Session>,
+}
+
+impl std::fmt::Debug for MortalModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MortalModel")
+            .field("path", &self.path)
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
+impl MortalModel {
+    pub fn load(path: &Path, version: u32) -> Result<Self> {
+        let session = Session::builder()
+            .and_then(|builder| builder.commit_from_file(path))
+            .with_context(|| format!("failed to load mortal model {}", path.display()))?;
+        Ok(Self {
+            path: path.to_owned(),
+            version,
+            session: Mutex::new(session),
+        })
+    }
+
+    /// Action probabilities of the model in the state, in the form of ekyumoe's details
+    pub fn details(&self, state: &PlayerState) -> Result<Vec<Detail>> {
+        let (obs, mask) = state.encode_obs(self.version, false);
+        let obs_shape = [1, obs.shape()[0], obs.shape()[1]];
+        let obs = Tensor::from_array((obs_shape, obs.iter().copied().collect::<Vec<f32>>()))?;
+        let legal = mask.iter().copied().collect::<Vec<bool>>();
+        let mask = Tensor::from_array(([1, legal.len()], legal.clone()))?;
+        let mut session = self.session.lock().map_err(|_| anyhow!("mortal model poisoned"))?;
+        let outputs = session.run(ort::inputs!["obs" => obs, "mask" => mask])?;
+        let (_, q_values) = outputs["q"].try_extract_tensor::<f32>()?;
+
+        let actions = (0..ACTIONS.min(q_values.len()))
+            .filter(|&action| legal.get(action).copied().unwrap_or(false))
+            .filter_map(|action| Some((action_event(state, action)?, q_values[action])))
+            .collect::<Vec<_>>();
+        let max = actions.iter().map(|(_, q)| *q).fold(f32::NEG_INFINITY, f32::max);
+        let total = actions.iter().map(|(_, q)| (q - max).exp()).sum::<f32>();
+        let mut details = actions
+            .into_iter()
+            .map(|(action, q_value)| Detail {
+                action,
+                q_value,
+                prob: (q_value - max).exp() / total,
+            })
+            .collect::<Vec<_>>();
+        details.sort_by(|a, b| b.prob.total_cmp(&a.prob));
+        Ok(details)
+    }
+}
+
+/// Tiles consumed from our hand for a call, using the red five when we hold it
+fn consumed_tiles<const N: usize>(state: &PlayerState, tiles: [Tile; N]) -> [Tile; N] {
+    let mut aka_used = false;
+    tiles.map(|tile| {
+        let index = tile.as_usize();
+        if index < 27 && index % 9 == 4 && state.akas_in_hand[index / 9] && !aka_used {
+            aka_used = true;
+            tile.akaize()
+        } else {
+            tile
+        }
+    })
+}
+
+/// Event of one of mortal's actions: 37 discards with the red fives, riichi, the three chi, pon, kan, agari,
+/// ryukyoku and pass
+fn action_event(state: &PlayerState, action: usize) -> Option<Event> {
+    let actor = state.player_id;
+    let target = state.last_cans.target_actor;
+    let called = || state.last_kawa_tile;
+    let event = match action {
+        0..=36 => {
+            let pai = must_tile!(action);
+            Event::Dahai {
+                actor,
+                pai,
+                tsumogiri: state.last_self_tsumo == Some(pai),
+            }
+        }
+        37 => Event::Reach { actor },
+        38..=40 => {
+            let pai = called()?;
+            let number = pai.deaka().as_usize();
+            let offsets = match action {
+                38 => [1, 2],
+                39 => [-1, 1],
+                _ => [-2, -1],
+            };
+            let consumed = offsets.map(|offset: isize| must_tile!(number.checked_add_signed(offset).unwrap_or(number)));
+            Event::Chi {
+                actor,
+                target,
+                pai,
+                consumed: consumed_tiles(state, consumed),
+            }
+        }
+        41 => {
+            let pai = called()?;
+            Event::Pon {
+                actor,
+                target,
+                pai,
+                consumed: consumed_tiles(state, [pai.deaka(); 2]),
+            }
+        }
+        42 if state.last_cans.can_daiminkan => {
+            let pai = called()?;
+            Event::Daiminkan {
+                actor,
+                target,
+                pai,
+                consumed: consumed_tiles(state, [pai.deaka(); 3]),
+            }
+        }
+        42 if state.last_cans.can_ankan => Event::Ankan {
+            actor,
+            consumed: consumed_tiles(state, [state.ankan_candidates.first()?.deaka(); 4]),
+        },
+        42 => {
+            let tile = state.kakan_candidates.first()?.deaka();
+            let pon = state.fuuro_overview[0]
+                .iter()
+                .find(|fuuro| fuuro.len() == 3 && fuuro.iter().all(|called| called.deaka() == tile))?;
+            Event::Kakan {
+                actor,
+                pai: consumed_tiles(state, [tile])[0],
+                consumed: [pon[0], pon[1], pon[2]],
+            }
+        }
+        43 => Event::Hora {
+            actor,
+            target: if state.last_cans.can_tsumo_agari { actor } else { target },
+            deltas: None,
+            ura_markers: None,
+        },
+        44 => Event::Ryukyoku { deltas: None },
+        45 => Event::None,
+        _ => return None,
+    };
+    Some(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::state::ActionCandidate;
+    use riichi::t;
+    use tinyvec::ArrayVec;
+
+    use super::*;
+
+    /// Our state after the previous player discarded a tile we can call
+    fn after_discard(tile: Tile, cans: ActionCandidate) -> PlayerState {
+        PlayerState {
+            last_kawa_tile: Some(tile),
+            last_cans: ActionCandidate { target_actor: 3, ..cans },
+            ..Default::default()
+        }
+    }
+
+    fn chi(state: &PlayerState, action: usize) -> Option<[Tile; 2]> {
+        match action_event(state, action)? {
+            Event::Chi {
+                actor: 0,
+                target: 3,
+                consumed,
+                ..
+            } => Some(consumed),
+            event => panic!("{event:?} is not a chi"),
+        }
+    }
+
+    #[test]
+    fn chi_consumes_the_tiles_around_the_called_one() {
+        let cans = ActionCandidate {
+            can_chi_low: true,
+            can_chi_mid: true,
+            can_chi_high: true,
+            ..Default::default()
+        };
+        let mut state = after_discard(t!(4m), cans);
+        assert_eq!(chi(&state, 38), Some([t!(5m), t!(6m)]));
+        assert_eq!(chi(&state, 39), Some([t!(3m), t!(5m)]));
+        assert_eq!(chi(&state, 40), Some([t!(2m), t!(3m)]));
+        // the red five is given up when we hold it
+        state.akas_in_hand[0] = true;
+        assert_eq!(chi(&state, 38), Some([t!(5mr), t!(6m)]));
+        state.last_kawa_tile = None;
+        assert_eq!(chi(&state, 38), None);
+    }
+
+    #[test]
+    fn kan_is_the_kan_we_can_make() {
+        let daiminkan = after_discard(
+            t!(E),
+            ActionCandidate {
+                can_daiminkan: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            action_event(&daiminkan, 42),
+            Some(Event::Daiminkan {
+                actor: 0,
+                target: 3,
+                pai: t!(E),
+                consumed: [t!(E); 3],
+            })
+        );
+
+        let mut ankan = PlayerState {
+            last_cans: ActionCandidate {
+                can_ankan: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        ankan.ankan_candidates.push(t!(9s));
+        assert_eq!(
+            action_event(&ankan, 42),
+            Some(Event::Ankan {
+                actor: 0,
+                consumed: [t!(9s); 4],
+            })
+        );
+
+        let mut kakan = PlayerState {
+            last_cans: ActionCandidate {
+                can_kakan: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        kakan.kakan_candidates.push(t!(5p));
+        assert_eq!(action_event(&kakan, 42), None, "there is no pon to add to");
+        kakan.fuuro_overview[0].push(ArrayVec::from_array_len([t!(5pr), t!(5p), t!(5p), t!(5p)], 3));
+        assert_eq!(
+            action_event(&kakan, 42),
+            Some(Event::Kakan {
+                actor: 0,
+                pai: t!(5p),
+                consumed: [t!(5pr), t!(5p), t!(5p)],
+            })
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Hindsight analysis for logs that contain every draw
+//! Each discard is replayed against the tiles we actually drew afterwards with a greedy shanten agent,
+//! which tells an unlucky correct play apart from a mistake that the wall would have rewarded.
+//! Calls and the draws they skip or add are ignored, the draws are taken in the order they happened.
+use riichi::algo::shanten::calc_all;
+use riichi::mjai::Event;
+use riichi::must_tile;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+use crate::rollout::connectivity;
+
+/// Result of replaying a discard against the actual draws
+#[derive(Debug, Clone)]
+pub struct OracleOutcome {
+    pub discard: Tile,
+    /// Draw on which the hand would have won by tsumo, 1-based
+    pub agari_draw: Option<usize>,
+    /// Lowest shanten reached before the draws ran out
+    pub best_shanten: i8,
+}
+
+impl OracleOutcome {
+    pub fn to_log_string(&self) -> String {
+        match self.agari_draw {
+            Some(draw) => format!("{} tsumo on draw {draw}", self.discard),
+            None if self.best_shanten == 0 => format!("{} tenpai", self.discard),
+            None => format!("{} {} shanten", self.discard, self.best_shanten),
+        }
+    }
+}
+
+/// Our draws in the rest of the kyoku after an event
+pub fn future_draws(events_after: &[Event], player_id: u8) -> Vec<Tile> {
+    events_after
+        .iter()
+        .take_while(|event| !matches!(event, Event::EndKyoku))
+        .filter_map(|event| match *event {
+            Event::Tsumo { actor, pai } if actor == player_id => Some(pai),
+            _ => None,
+        })
+        .collect()
+}
+
+fn replay(hand: &mut [u8; 34], len_div3: u8, draws: &[Tile]) -> (Option<usize>, i8) {
+    let mut best_shanten = calc_all(hand, len_div3);
+    for (index, draw) in draws.iter().enumerate() {
+        hand[draw.deaka().as_usize()] += 1;
+        if calc_all(hand, len_div3) == -1 {
+            return (Some(index + 1), -1);
+        }
+        let mut scratch = *hand;
+        let discard = (0..34)
+            .filter(|&tile| hand[tile] > 0)
+            .min_by_key(|&tile| {
+                scratch[tile] -= 1;
+                let shanten = calc_all(&scratch, len_div3);
+                scratch[tile] += 1;
+                (shanten, connectivity(&scratch, tile))
+            })
+            .expect("empty hand");
+        hand[discard] -= 1;
+        best_shanten = best_shanten.min(calc_all(hand, len_div3));
+    }
+    (None, best_shanten)
+}
+
+/// Outcome of every discard in hand against the actual draws, best first. Empty when not at a discard.
+pub fn oracle_outcomes(state: &PlayerState, draws: &[Tile]) -> Vec<OracleOutcome> {
+    if !state.last_cans.can_discard {
+        return vec![];
+    }
+    let mut outcomes = (0..34)
+        .filter(|&tile| state.tehai[tile] > 0)
+        .map(|discard| {
+            let mut hand = state.tehai;
+            hand[discard] -= 1;
+            let (agari_draw, best_shanten) = replay(&mut hand, state.tehai_len_div3, draws);
+            OracleOutcome {
+                discard: must_tile!(discard),
+                agari_draw,
+                best_shanten,
+            }
+        })
+        .collect::<Vec<_>>();
+    outcomes.sort_by_key(|outcome| (outcome.agari_draw.unwrap_or(usize::MAX), outcome.best_shanten));
+    outcomes
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::t;
+
+    use super::*;
+
+    /// 123m456m789p11s45s with a north to discard
+    fn iishanten_north() -> PlayerState {
+        let mut state = PlayerState {
+            tehai_len_div3: 4,
+            ..Default::default()
+        };
+        state.last_cans.can_discard = true;
+        for tile in [t!(1m), t!(2m), t!(3m), t!(4m), t!(5m), t!(6m), t!(7p), t!(8p), t!(9p)] {
+            state.tehai[tile.as_usize()] += 1;
+        }
+        for tile in [t!(1s), t!(1s), t!(4s), t!(5s), t!(N)] {
+            state.tehai[tile.as_usize()] += 1;
+        }
+        state
+    }
+
+    fn outcome(outcomes: &[OracleOutcome], discard: Tile) -> &OracleOutcome {
+        outcomes.iter().find(|outcome| outcome.discard == discard).unwrap()
+    }
+
+    #[test]
+    fn only_our_draws_of_the_kyoku_are_replayed() {
+        let events = [
+            Event::Tsumo { actor: 1, pai: t!(1m) },
+            Event::Tsumo { actor: 0, pai: t!(9m) },
+            Event::Tsumo { actor: 0, pai: t!(6s) },
+            Event::EndKyoku,
+            Event::Tsumo { actor: 0, pai: t!(E) },
+        ];
+        assert_eq!(future_draws(&events, 0), [t!(9m), t!(6s)]);
+    }
+
+    #[test]
+    fn discards_are_replayed_against_the_actual_draws() {
+        let outcomes = oracle_outcomes(&iishanten_north(), &[t!(9m), t!(6s)]);
+        assert_eq!(outcomes.len(), 13);
+        // the north keeps the 36s wait, the 9m is thrown back and the 6s wins
+        assert_eq!(outcomes[0].discard, t!(N));
+        assert_eq!(outcomes[0].agari_draw, Some(2));
+        assert_eq!(outcomes[0].to_log_string(), "N tsumo on draw 2");
+        assert!(outcomes[1..].iter().all(|outcome| outcome.agari_draw.is_none()));
+        // the 6s reconnects the broken 45s
+        let four_sou = outcome(&outcomes, t!(4s));
+        assert_eq!(four_sou.best_shanten, 0);
+        assert_eq!(four_sou.to_log_string(), "4s tenpai");
+    }
+
+    #[test]
+    fn without_draws_the_shanten_after_the_discard_stays() {
+        let outcomes = oracle_outcomes(&iishanten_north(), &[]);
+        assert_eq!(outcome(&outcomes, t!(N)).best_shanten, 0);
+        assert_eq!(outcome(&outcomes, t!(1m)).best_shanten, 1);
+        assert_eq!(outcome(&outcomes, t!(1m)).to_log_string(), "1m 1 shanten");
+        // best first, tenpai before iishanten
+        assert_eq!(outcomes[0].discard, t!(N));
+    }
+
+    #[test]
+    fn no_outcomes_outside_a_discard() {
+        let mut state = iishanten_north();
+        state.last_cans.can_discard = false;
+        assert!(oracle_outcomes(&state, &[t!(6s)]).is_empty());
+    }
+}
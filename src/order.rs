@@ -0,0 +1,289 @@
+//! Stable order of candidates shared by the tables, the reports and every display
+//! Candidates are ordered by the key of the `SortSpec`, anything else ranked by a value such as the scorer's ranking,
+//! the calls or the fast estimates by that value. Ties are broken in this order:
+//! 1. action priority: agari, riichi, discard, ankan, kakan, daiminkan, pon, chi, ryukyoku, pass
+//! 2. lower combined danger of the discard, when the spec has danger
+//! 3. tile order: lower tile first in mortal's order (manzu, pinzu, souzu, honors), a red five after its plain five
+//!
+//! Values are compared exactly, so two candidates only tie when the calculator gave them identical values.
+use std::cmp::Ordering;
+
+use riichi::algo::sp::EventCandidate;
+use riichi::mjai::Event;
+use riichi::tile::Tile;
+
+use crate::report::CandidateReport;
+
+/// Primary key of the order
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    /// Highest expected value over all remaining tsumos first
+    #[default]
+    ExpValue,
+    WinProb,
+    TenpaiProb,
+    /// Lowest combined danger of the discard first, candidates without a discard last
+    Safety,
+}
+
+/// How to order candidates
+#[derive(Debug, Clone, Default)]
+pub struct SortSpec {
+    pub key: SortKey,
+    /// Combined danger of discarding each tile, see `defense::combined_danger`
+    pub danger: Option<[f32; 34]>,
+    /// Values added to the expected value of a candidate for the order, such as the draw payments
+    pub extra_values: Vec<(Event, f32)>,
+}
+
+/// Values of a candidate the order looks at, implemented for mortal's candidates and the report's
+pub trait SortableCandidate {
+    fn event(&self) -> &Event;
+    fn exp_value(&self) -> f32;
+    fn win_prob(&self) -> f32;
+    fn tenpai_prob(&self) -> f32;
+}
+
+impl SortableCandidate for EventCandidate {
+    fn event(&self) -> &Event {
+        &self.event
+    }
+    fn exp_value(&self) -> f32 {
+        self.exp_values.first().copied().unwrap_or(0.0)
+    }
+    fn win_prob(&self) -> f32 {
+        self.win_probs.first().copied().unwrap_or(0.0)
+    }
+    fn tenpai_prob(&self) -> f32 {
+        self.tenpai_probs.first().copied().unwrap_or(0.0)
+    }
+}
+
+impl<C: SortableCandidate> SortableCandidate for &C {
+    fn event(&self) -> &Event {
+        (*self).event()
+    }
+    fn exp_value(&self) -> f32 {
+        (*self).exp_value()
+    }
+    fn win_prob(&self) -> f32 {
+        (*self).win_prob()
+    }
+    fn tenpai_prob(&self) -> f32 {
+        (*self).tenpai_prob()
+    }
+}
+
+impl SortableCandidate for CandidateReport {
+    fn event(&self) -> &Event {
+        &self.event
+    }
+    fn exp_value(&self) -> f32 {
+        self.exp_value
+    }
+    fn win_prob(&self) -> f32 {
+        self.win_prob
+    }
+    fn tenpai_prob(&self) -> f32 {
+        self.tenpai_prob
+    }
+}
+
+/// Rank of an action for the tie-break, lower first
+pub fn action_priority(event: &Event) -> u8 {
+    match event {
+        Event::Hora { .. } => 0,
+        Event::Reach { .. } => 1,
+        Event::Dahai { .. } => 2,
+        Event::Ankan { .. } => 3,
+        Event::Kakan { .. } => 4,
+        Event::Daiminkan { .. } => 5,
+        Event::Pon { .. } => 6,
+        Event::Chi { .. } => 7,
+        Event::Ryukyoku { .. } => 8,
+        _ => 9,
+    }
+}
+
+/// Tile an action is about: the discard, the called tile or the tile of a kan
+pub fn action_tile(event: &Event) -> Option<Tile> {
+    match event {
+        Event::Dahai { pai, .. }
+        | Event::Chi { pai, .. }
+        | Event::Pon { pai, .. }
+        | Event::Daiminkan { pai, .. }
+        | Event::Kakan { pai, .. } => Some(*pai),
+        Event::Ankan { consumed, .. } => Some(consumed[0]),
+        _ => None,
+    }
+}
+
+/// Danger of an action under the spec, actions without a discard count as infinitely dangerous
+fn danger_of(spec: &SortSpec, event: &Event) -> f32 {
+    match (event, spec.danger) {
+        (Event::Dahai { pai, .. }, Some(danger)) => danger[pai.deaka().as_usize()],
+        _ => f32::INFINITY,
+    }
+}
+
+/// Order of two actions that tie on their key, `Less` for the one that goes first
+pub fn tie_break(a: &Event, b: &Event, spec: &SortSpec) -> Ordering {
+    let tile_key = |event: &Event| action_tile(event).map(|tile| (tile.deaka().as_usize(), tile.is_aka()));
+    action_priority(a)
+        .cmp(&action_priority(b))
+        .then_with(|| match spec.danger {
+            Some(_) => danger_of(spec, a).total_cmp(&danger_of(spec, b)),
+            None => Ordering::Equal,
+        })
+        .then_with(|| tile_key(a).cmp(&tile_key(b)))
+}
+
+/// Order of two candidates under the spec, `Less` for the one that goes first
+pub fn compare_candidates<C: SortableCandidate>(a: &C, b: &C, spec: &SortSpec) -> Ordering {
+    let danger = |candidate: &C| danger_of(spec, candidate.event());
+    let exp_value = |candidate: &C| {
+        let extra = spec
+            .extra_values
+            .iter()
+            .find(|(event, _)| event == candidate.event())
+            .map_or(0.0, |(_, value)| *value);
+        candidate.exp_value() + extra
+    };
+    let primary = match spec.key {
+        SortKey::ExpValue => exp_value(b).total_cmp(&exp_value(a)),
+        SortKey::WinProb => b.win_prob().total_cmp(&a.win_prob()),
+        SortKey::TenpaiProb => b.tenpai_prob().total_cmp(&a.tenpai_prob()),
+        SortKey::Safety => danger(a).total_cmp(&danger(b)),
+    };
+    primary.then_with(|| tie_break(a.event(), b.event(), spec))
+}
+
+/// Sort candidates in the documented order, the sort is stable for candidates that tie on every rule
+pub fn sort_candidates<C: SortableCandidate>(candidates: &mut [C], spec: &SortSpec) {
+    candidates.sort_by(|a, b| compare_candidates(a, b, spec));
+}
+
+/// Sort anything ranked by a value, highest first, with the tie-break of the candidates. `key` gives the value and
+/// the action of an item.
+pub fn sort_by_value<T>(items: &mut [T], spec: &SortSpec, key: impl Fn(&T) -> (f32, Event)) {
+    items.sort_by(|a, b| {
+        let ((a_value, a_event), (b_value, b_event)) = (key(a), key(b));
+        b_value.total_cmp(&a_value).then_with(|| tie_break(&a_event, &b_event, spec))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::mjai::Event;
+
+    use super::*;
+    use crate::report::CandidateReport;
+    use crate::tiles::t;
+
+    fn candidate(event: Event, exp_value: f32, win_prob: f32) -> CandidateReport {
+        CandidateReport {
+            action: String::new(),
+            event,
+            exp_value,
+            draw_value: 0.0,
+            delta: 0.0,
+            win_prob,
+            expected_draws: None,
+            tenpai_prob: 0.0,
+            shanten: 1,
+            num_required_tiles: 0,
+            required_tiles: vec![],
+            yaku: vec![],
+        }
+    }
+
+    fn dahai(pai: riichi::tile::Tile) -> Event {
+        Event::Dahai {
+            actor: 0,
+            pai,
+            tsumogiri: false,
+        }
+    }
+
+    fn order(candidates: &[CandidateReport]) -> Vec<Event> {
+        candidates.iter().map(|candidate| candidate.event.clone()).collect()
+    }
+
+    #[test]
+    fn ties_are_broken_by_action_then_tile_order() {
+        let riichi = Event::Reach { actor: 0 };
+        let mut candidates = vec![
+            candidate(dahai(t!(E)), 1000.0, 0.2),
+            candidate(dahai(t!(5mr)), 1000.0, 0.2),
+            candidate(dahai(t!(5m)), 1000.0, 0.2),
+            candidate(dahai(t!(9p)), 1000.0, 0.3),
+            candidate(riichi.clone(), 1000.0, 0.1),
+            candidate(dahai(t!(1s)), 1200.0, 0.1),
+        ];
+        sort_candidates(&mut candidates, &SortSpec::default());
+        assert_eq!(
+            order(&candidates),
+            [
+                dahai(t!(1s)),
+                riichi,
+                dahai(t!(5m)),
+                dahai(t!(5mr)),
+                dahai(t!(9p)),
+                dahai(t!(E))
+            ]
+        );
+    }
+
+    #[test]
+    fn danger_breaks_ties_before_tile_order() {
+        let mut danger = [0.0; 34];
+        danger[t!(1m).as_usize()] = 30.0;
+        let mut candidates = vec![candidate(dahai(t!(1m)), 1000.0, 0.2), candidate(dahai(t!(E)), 1000.0, 0.2)];
+        let spec = SortSpec {
+            danger: Some(danger),
+            ..Default::default()
+        };
+        sort_candidates(&mut candidates, &spec);
+        assert_eq!(order(&candidates), [dahai(t!(E)), dahai(t!(1m))]);
+        let mut ranked = vec![(dahai(t!(1m)), 5.0), (dahai(t!(E)), 5.0), (dahai(t!(9p)), 6.0)];
+        sort_by_value(&mut ranked, &spec, |(event, value)| (*value, event.clone()));
+        let events = ranked.into_iter().map(|(event, _)| event).collect::<Vec<_>>();
+        assert_eq!(events, [dahai(t!(9p)), dahai(t!(E)), dahai(t!(1m))]);
+    }
+
+    #[test]
+    fn safety_puts_the_least_dangerous_discard_first() {
+        let mut danger = [1.0; 34];
+        danger[t!(E).as_usize()] = 0.0;
+        let mut candidates = vec![
+            candidate(Event::None, 0.0, 0.0),
+            candidate(dahai(t!(5m)), 1000.0, 0.2),
+            candidate(dahai(t!(E)), 500.0, 0.1),
+        ];
+        sort_candidates(
+            &mut candidates,
+            &SortSpec {
+                key: SortKey::Safety,
+                danger: Some(danger),
+                ..Default::default()
+            },
+        );
+        assert_eq!(order(&candidates), [dahai(t!(E)), dahai(t!(5m)), Event::None]);
+    }
+
+    #[test]
+    fn extra_values_are_added_to_the_expected_value() {
+        let mut candidates = vec![candidate(dahai(t!(1s)), 1000.0, 0.2), candidate(dahai(t!(9p)), 900.0, 0.2)];
+        sort_candidates(
+            &mut candidates,
+            &SortSpec {
+                extra_values: vec![(dahai(t!(9p)), 200.0)],
+                ..Default::default()
+            },
+        );
+        assert_eq!(order(&candidates), [dahai(t!(9p)), dahai(t!(1s))]);
+        // the candidates keep the calculator's value
+        assert_eq!(candidates[0].exp_value, 900.0);
+    }
+}
@@ -0,0 +1,188 @@
+//! External executables that annotate the analysis
+//! A plugin is started once and kept running: it receives the `AnalysisReport` of every decision as a single line of
+//! json on stdin and answers each with a json object on one line of stdout whose fields are added to the report's
+//! annotations. A plugin that exits after answering is started again for the next report, one that does not answer in
+//! time is killed.
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, channel};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde_json::{Map, Value};
+
+use crate::report::AnalysisReport;
+
+/// Time a plugin has to answer a report when none is given
+#[cfg(feature = "cli")]
+pub const DEFAULT_PLUGIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Process of a plugin with the lines it printed, read on their own thread so waiting for them can time out
+struct Running {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<std::io::Result<String>>,
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Registered plugin command, clones share the running process
+#[derive(Clone)]
+pub struct Plugin {
+    program: String,
+    args: Vec<String>,
+    timeout: Duration,
+    running: Arc<Mutex<Option<Running>>>,
+}
+
+impl std::fmt::Debug for Plugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Plugin")
+            .field("program", &self.program)
+            .field("args", &self.args)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl Plugin {
+    /// Parse a command line split on whitespace, e.g. `python3 notify.py --quiet`
+    pub fn parse(s: &str, timeout: Duration) -> Result<Self> {
+        let mut parts = s.split_whitespace().map(str::to_owned);
+        let program = parts.next().context("plugin command is empty")?;
+        Ok(Self {
+            program,
+            args: parts.collect(),
+            timeout,
+            running: Arc::default(),
+        })
+    }
+
+    fn start(&self) -> Result<Running> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to start plugin {}", self.program))?;
+        let stdin = child.stdin.take().context("plugin stdin is not piped")?;
+        let stdout = child.stdout.take().context("plugin stdout is not piped")?;
+        let (sender, lines) = channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Running { child, stdin, lines })
+    }
+
+    /// Write a report to the process and wait for its answer, `None` when it exited instead of answering
+    fn exchange(&self, process: &mut Running, line: &[u8]) -> Result<Option<String>> {
+        if process.stdin.write_all(line).and_then(|()| process.stdin.flush()).is_err() {
+            return Ok(None);
+        }
+        match process.lines.recv_timeout(self.timeout) {
+            Ok(answer) => Ok(Some(answer?)),
+            Err(RecvTimeoutError::Timeout) => {
+                bail!("plugin {} did not answer within {}ms", self.program, self.timeout.as_millis())
+            }
+            Err(RecvTimeoutError::Disconnected) => Ok(None),
+        }
+    }
+
+    /// Send a report to the plugin, starting it when it is not running, and return the fields it added
+    pub fn annotate(&self, report: &AnalysisReport) -> Result<Map<String, Value>> {
+        let mut line = serde_json::to_vec(report)?;
+        line.push(b'\n');
+        let mut running = self.running.lock().map_err(|_| anyhow!("plugin {} poisoned", self.program))?;
+        // a process failing to answer is dropped and so killed
+        let mut reused = running.take();
+        let answer = loop {
+            let fresh = reused.is_none();
+            let mut process = match reused.take() {
+                Some(process) => process,
+                None => self.start()?,
+            };
+            match self.exchange(&mut process, &line)? {
+                Some(answer) => {
+                    *running = Some(process);
+                    break answer;
+                }
+                // a plugin answering once may still be exiting when the next report comes
+                None if !fresh => continue,
+                None => bail!("plugin {} exited without answering", self.program),
+            }
+        };
+        match serde_json::from_str(&answer).context("plugin output is not json")? {
+            Value::Object(fields) => Ok(fields),
+            _ => bail!("plugin {} must print a json object", self.program),
+        }
+    }
+}
+
+/// Fields added by all plugins in order, later plugins override earlier ones.
+/// Failing plugins are reported and skipped so a broken extension never hides the analysis.
+pub fn run_plugins(plugins: &[Plugin], report: &AnalysisReport) -> Map<String, Value> {
+    let mut annotations = Map::new();
+    for plugin in plugins {
+        match plugin.annotate(report) {
+            Ok(fields) => annotations.extend(fields),
+            Err(e) => eprintln!("{e:#}"),
+        }
+    }
+    annotations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell(script: &str, timeout: Duration) -> Plugin {
+        Plugin {
+            program: "sh".to_owned(),
+            args: vec!["-c".to_owned(), script.to_owned()],
+            timeout,
+            running: Arc::default(),
+        }
+    }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn plugin_process_is_reused_between_reports() {
+        let plugin = shell(
+            r#"n=0; while read line; do n=$((n + 1)); echo "{\"n\": $n}"; done"#,
+            DEFAULT_PLUGIN_TIMEOUT,
+        );
+        let report = AnalysisReport::default();
+        assert_eq!(plugin.annotate(&report).unwrap()["n"], 1);
+        assert_eq!(plugin.clone().annotate(&report).unwrap()["n"], 2);
+    }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn plugin_answering_once_is_started_again() {
+        let plugin = shell(r#"read line; echo '{"once": true}'"#, DEFAULT_PLUGIN_TIMEOUT);
+        let report = AnalysisReport::default();
+        for _ in 0..3 {
+            assert_eq!(plugin.annotate(&report).unwrap()["once"], true);
+        }
+    }
+
+    #[test]
+    fn slow_plugin_times_out() {
+        let plugin = shell("sleep 10", Duration::from_millis(100));
+        let start = std::time::Instant::now();
+        let error = plugin.annotate(&AnalysisReport::default()).unwrap_err();
+        assert!(error.to_string().contains("did not answer within 100ms"), "{error:#}");
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(plugin.running.lock().unwrap().is_none());
+    }
+}
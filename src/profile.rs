@@ -0,0 +1,105 @@
+//! Time spent in the expensive stages of every analysis
+//! The single player calculation grows roughly exponentially with shanten and tsumos left, so its timings are also
+//! grouped by that configuration to find which ones need tighter thresholds.
+#[cfg(feature = "cli")]
+use std::collections::BTreeMap;
+#[cfg(feature = "cli")]
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Timings of one analyzed decision
+#[derive(Debug, Clone, Copy)]
+pub struct DecisionProfile {
+    pub shanten: i8,
+    pub tsumos_left: u8,
+    pub sp: Duration,
+    pub danger: Duration,
+    pub agari: Duration,
+}
+
+/// Collector shared by every analysis run with the same options
+#[derive(Debug, Clone, Default)]
+pub struct Profiler(Arc<Mutex<Vec<DecisionProfile>>>);
+
+impl Profiler {
+    pub fn record(&self, profile: DecisionProfile) {
+        self.0.lock().expect("profiler poisoned").push(profile);
+    }
+
+    pub fn profiles(&self) -> Vec<DecisionProfile> {
+        self.0.lock().expect("profiler poisoned").clone()
+    }
+}
+
+/// Count, max, 95th percentile and total of a set of durations
+#[cfg(feature = "cli")]
+fn stage_line(name: &str, mut durations: Vec<Duration>) -> String {
+    durations.sort();
+    let total = durations.iter().sum::<Duration>();
+    let max = durations.last().copied().unwrap_or_default();
+    let p95 = durations
+        .get((durations.len() * 95).div_ceil(100).saturating_sub(1))
+        .copied()
+        .unwrap_or_default();
+    format!(
+        "{name:<10} n={:<5} max={:>9.3}ms p95={:>9.3}ms total={:>10.3}ms",
+        durations.len(),
+        max.as_secs_f64() * 1e3,
+        p95.as_secs_f64() * 1e3,
+        total.as_secs_f64() * 1e3
+    )
+}
+
+/// Summary of each stage and of the single player calculation per shanten and tsumos left, slowest first
+#[cfg(feature = "cli")]
+pub fn profile_summary(profiles: &[DecisionProfile]) -> String {
+    let mut summary = format!("profile of {} decisions\n", profiles.len());
+    for (name, stage) in [
+        ("sp", (|p: &DecisionProfile| p.sp) as fn(&DecisionProfile) -> Duration),
+        ("danger", |p| p.danger),
+        ("agari", |p| p.agari),
+    ] {
+        writeln!(summary, "{}", stage_line(name, profiles.iter().map(stage).collect())).unwrap();
+    }
+    let mut configurations = BTreeMap::<(i8, u8), Vec<Duration>>::new();
+    for profile in profiles {
+        configurations
+            .entry((profile.shanten, profile.tsumos_left))
+            .or_default()
+            .push(profile.sp);
+    }
+    let mut configurations = configurations.into_iter().collect::<Vec<_>>();
+    configurations.sort_by_key(|(_, durations)| std::cmp::Reverse(durations.iter().max().copied()));
+    write!(summary, "sp by shanten and tsumos left:").unwrap();
+    for ((shanten, tsumos_left), durations) in configurations {
+        write!(summary, "\n{}", stage_line(&format!("{shanten}s {tsumos_left}t"), durations)).unwrap();
+    }
+    summary
+}
+
+#[cfg(all(test, feature = "cli"))]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn summary_groups_sp_time_by_shanten_and_tsumos_left() {
+        let profile = |shanten, tsumos_left, sp_ms| DecisionProfile {
+            shanten,
+            tsumos_left,
+            sp: Duration::from_millis(sp_ms),
+            danger: Duration::from_millis(1),
+            agari: Duration::ZERO,
+        };
+        let profiles = [profile(1, 10, 5), profile(3, 15, 900), profile(3, 15, 100)];
+        let summary = profile_summary(&profiles);
+        let lines = summary.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "profile of 3 decisions");
+        assert!(lines[1].contains("max=  900.000ms") && lines[1].contains("total=  1005.000ms"));
+        // the slowest configuration comes first
+        assert!(lines[5].starts_with("3s 15t") && lines[5].contains("n=2"));
+        assert!(lines[6].starts_with("1s 10t"));
+    }
+}
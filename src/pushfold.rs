@@ -0,0 +1,256 @@
+//! Push or fold verdict against a riichi from a threshold model
+//! Both lines are followed for the turns the best offensive candidate still needs. Pushing discards that candidate,
+//! then the tiles it draws, dealing in with the unseen average danger of a drawn tile. Folding discards the safest
+//! tiles in hand from the safest, copies of a tile already passed being safe. The danger weights are the chance in
+//! percent that a tile is a wait, so every discard deals in at most once and survives the opponents one by one.
+//! The verdict compares the two expected score changes, with the deal-in penalty weighted by what it costs in expected
+//! placement against what the same points would gain, and asks to keep safe tiles when the margin is within a share
+//! of the penalty.
+use riichi::algo::sp::EventCandidate;
+use riichi::must_tile;
+use riichi::state::PlayerState;
+
+use crate::defense::candidate_discard;
+use crate::scorer::PlacementContext;
+use crate::scoring::{RankPoints, expected_rank_points};
+use crate::tradeoff::DealInRisk;
+use crate::ukeire::unseen_tiles;
+
+/// Share of the deal-in penalty below which pushing keeps the safe tiles in hand
+const KEEP_SAFE_SHARE: f32 = 0.1;
+/// Placement itself as rank points, so the penalty weight does not depend on a ruleset's uma
+const PLACEMENTS: RankPoints = RankPoints([3.0, 2.0, 1.0, 0.0]);
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Push,
+    /// Push, but discard the dangerous tiles only while the hand needs them
+    KeepSafe,
+    Fold,
+}
+
+impl Verdict {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Push => "push",
+            Self::KeepSafe => "keep safe tiles",
+            Self::Fold => "fold",
+        }
+    }
+}
+
+/// Verdict of a decision with the values behind it
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct PushFold {
+    pub verdict: Verdict,
+    /// Push value minus fold value, the verdict is push above `threshold` and fold below 0
+    pub margin: f32,
+    pub threshold: f32,
+    /// Expected score change of pushing the best candidate for the turns it needs
+    pub push_value: f32,
+    /// Expected score change of folding for the same turns
+    pub fold_value: f32,
+    /// Turns of deal-in exposure assumed for pushing and folding
+    pub turns: u32,
+    /// Weight of the deal-in penalty from our placement
+    pub placement_weight: f32,
+}
+
+/// Weight of paying `penalty` points against receiving them in expected placement, between 0 and 2.
+/// Heavier when paying loses more placement than winning gains, e.g. protecting first place in the last kyoku.
+fn placement_weight(state: &PlayerState, penalty: f32) -> f32 {
+    let placement = PlacementContext::from_state(state);
+    let expected = |change: f32| {
+        let mut scores = placement.scores;
+        scores[placement.player_id as usize] += change as i32;
+        // the hand at stake is also the typical hand of the kyoku left
+        expected_rank_points(scores, placement.player_id, placement.kyoku_left, &PLACEMENTS, penalty)
+    };
+    let loss = expected(0.0) - expected(-penalty);
+    let gain = expected(penalty) - expected(0.0);
+    if loss + gain > f32::EPSILON {
+        2.0 * loss / (loss + gain)
+    } else {
+        1.0
+    }
+}
+
+/// Chance to survive a sequence of discards with their deal-in chances and losses, and the expected loss
+fn exposure(discards: impl IntoIterator<Item = (f32, f32)>) -> (f32, f32) {
+    let mut alive = 1.0;
+    let mut loss = 0.0;
+    for (deal_in, discard_loss) in discards {
+        loss += alive * discard_loss;
+        alive *= 1.0 - deal_in;
+    }
+    (alive, loss)
+}
+
+/// Deal-in chance and loss of a tile drawn from the unseen tiles, None when every tile is seen
+fn drawn_risk(state: &PlayerState, risk: &DealInRisk) -> Option<(f32, f32)> {
+    let unseen = unseen_tiles(state);
+    let copies = unseen.iter().map(|&count| count as f32).sum::<f32>();
+    (copies > 0.0).then(|| {
+        (0..34).fold((0.0, 0.0), |(deal_in, loss), tile| {
+            let share = unseen[tile] as f32 / copies;
+            let (tile_deal_in, tile_loss) = risk.of(must_tile!(tile));
+            (deal_in + share * tile_deal_in, loss + share * tile_loss)
+        })
+    })
+}
+
+/// Safest discards of the hand from the safest with their deal-in chance and loss.
+/// Once a tile is discarded without dealing in, its other copies are genbutsu against every threat.
+fn fold_discards(state: &PlayerState, risk: &DealInRisk) -> Vec<(usize, (f32, f32))> {
+    let mut tiles = (0..34)
+        .filter(|&tile| state.tehai[tile] > 0)
+        .map(|tile| (tile, risk.of(must_tile!(tile))))
+        .collect::<Vec<_>>();
+    tiles.sort_by(|(_, (a, _)), (_, (b, _))| a.total_cmp(b));
+    tiles
+        .into_iter()
+        .flat_map(|(tile, risk)| {
+            std::iter::once((tile, risk)).chain(std::iter::repeat_n((tile, (0.0, 0.0)), state.tehai[tile] as usize - 1))
+        })
+        .collect()
+}
+
+/// Push or fold at a discard against the opponents in riichi, None without a riichi or a discard candidate
+pub fn push_fold(state: &PlayerState, candidates: &[EventCandidate], risk: &DealInRisk, shanten: i8) -> Option<PushFold> {
+    if risk.threats.is_empty() || !state.last_cans.can_discard || state.riichi_declared[0] {
+        return None;
+    }
+    let value = |candidate: Option<&EventCandidate>| candidate.and_then(|candidate| candidate.exp_values.first().copied());
+
+    let best = candidates.iter().find(|candidate| candidate_discard(candidate).is_some())?;
+    let (deal_in, loss) = risk.of(candidate_discard(best)?);
+    let drawn = drawn_risk(state, risk).unwrap_or((deal_in, loss));
+    let (raw_penalty, weight) = match [(deal_in, loss), drawn].into_iter().find(|&(deal_in, _)| deal_in > 0.0) {
+        Some((deal_in, loss)) => (loss / deal_in, placement_weight(state, loss / deal_in)),
+        None => (0.0, 1.0),
+    };
+    let penalty = raw_penalty * weight;
+    let draws = (state.tiles_left as u32).div_ceil(4).max(1);
+    let turns = (shanten.max(0) as u32 + 1).min(draws);
+
+    let push_discards = std::iter::once((deal_in, loss)).chain(std::iter::repeat_n(drawn, turns as usize - 1));
+    let (push_alive, push_loss) = exposure(push_discards);
+    let push_value = push_alive * value(Some(best)).unwrap_or(0.0) - push_loss * weight;
+
+    let fold = fold_discards(state, risk);
+    let safest_tile = fold.first()?.0;
+    let fold_candidate = candidates
+        .iter()
+        .find(|candidate| candidate_discard(candidate).is_some_and(|tile| tile.deaka().as_usize() == safest_tile));
+    // a folding hand runs out of its own tiles only when it keeps discarding what it draws
+    let fold_discards = fold
+        .into_iter()
+        .map(|(_, risk)| risk)
+        .chain(std::iter::repeat(drawn))
+        .take(turns as usize);
+    let (fold_alive, fold_loss) = exposure(fold_discards);
+    let fold_value = fold_alive * value(fold_candidate).unwrap_or(0.0) - fold_loss * weight;
+
+    let margin = push_value - fold_value;
+    let threshold = KEEP_SAFE_SHARE * penalty;
+    let verdict = if margin < 0.0 {
+        Verdict::Fold
+    } else if margin < threshold {
+        Verdict::KeepSafe
+    } else {
+        Verdict::Push
+    };
+    Some(PushFold {
+        verdict,
+        margin,
+        threshold,
+        push_value,
+        fold_value,
+        turns,
+        placement_weight: weight,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::t;
+    use riichi::tile::Tile;
+
+    use super::*;
+    use crate::tradeoff::DealInPenalty;
+
+    fn state(scores: [i32; 4], is_all_last: bool) -> PlayerState {
+        PlayerState {
+            scores,
+            is_all_last,
+            bakaze: t!(E),
+            ..Default::default()
+        }
+    }
+
+    fn against_riichi(tehai: [u8; 34], weights: Vec<(Tile, f32)>) -> (PlayerState, DealInRisk) {
+        let mut riichi_declared = [false; 4];
+        riichi_declared[1] = true;
+        let state = PlayerState {
+            tehai,
+            riichi_declared,
+            bakaze: t!(E),
+            ..Default::default()
+        };
+        let risk = DealInRisk::new(
+            &state,
+            &[vec![], weights, vec![], vec![]],
+            &Default::default(),
+            DealInPenalty::default(),
+        );
+        (state, risk)
+    }
+
+    #[test]
+    fn exposure_charges_each_discard_only_while_alive() {
+        let (alive, loss) = exposure([(0.1, 500.0), (0.2, 1000.0)]);
+        assert!((alive - 0.72).abs() < 1e-6);
+        assert!((loss - 1400.0).abs() < 1e-3);
+        assert_eq!(exposure([]), (1.0, 0.0));
+    }
+
+    #[test]
+    fn folding_reuses_passed_tiles_as_genbutsu() {
+        let mut tehai = [0; 34];
+        tehai[t!(1m).as_usize()] = 2;
+        tehai[t!(5p).as_usize()] = 1;
+        let (state, risk) = against_riichi(tehai, vec![(t!(5p), 50.0), (t!(1m), 10.0)]);
+        let discards = fold_discards(&state, &risk);
+        let tiles = discards
+            .iter()
+            .map(|&(tile, (deal_in, _))| (tile, (deal_in * 100.0).round() as u8))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tiles,
+            [(t!(1m).as_usize(), 10), (t!(1m).as_usize(), 0), (t!(5p).as_usize(), 50)]
+        );
+    }
+
+    #[test]
+    fn drawn_tiles_deal_in_with_the_unseen_average() {
+        let (mut state, risk) = against_riichi([0; 34], vec![(t!(5p), 50.0)]);
+        state.tiles_seen = [4; 34];
+        assert!(drawn_risk(&state, &risk).is_none());
+        state.tiles_seen[t!(5p).as_usize()] = 3;
+        state.tiles_seen[t!(1m).as_usize()] = 1;
+        let (deal_in, loss) = drawn_risk(&state, &risk).unwrap();
+        assert!((deal_in - 0.125).abs() < 1e-6);
+        assert!((loss - 0.125 * DealInPenalty::default().non_dealer).abs() < 1e-3);
+    }
+
+    #[test]
+    fn penalty_weighs_the_placement_it_costs() {
+        // paying drops first place to last, winning the same points keeps it
+        assert_eq!(placement_weight(&state([30000, 25000, 22500, 22500], true), 8000.0), 2.0);
+        // paying keeps last place, winning the same points takes first
+        assert_eq!(placement_weight(&state([20000, 27000, 27000, 26000], true), 8000.0), 0.0);
+        let even = placement_weight(&state([25000; 4], false), 8000.0);
+        assert!((even - 1.0).abs() < 0.01, "{even}");
+    }
+}
@@ -0,0 +1,111 @@
+//! How our potential waits look from the opponents' perspective
+//! Based only on information opponents can see: our discards and visible tiles.
+use riichi::algo::sp::EventCandidate;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+use crate::defense::candidate_discard;
+
+/// How safe a wait tile looks to opponents reading our discards
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+pub enum WaitLook {
+    /// We discarded the tile ourselves, the wait is furiten
+    Genbutsu,
+    /// Suji of our discards, a suji trap
+    Suji,
+    /// Honor tile with at least two copies visible to opponents
+    VisibleHonor,
+    /// One side of a middle tile is suji
+    HalfSuji,
+    Honor,
+    NonSuji,
+}
+
+impl WaitLook {
+    /// Chance an opponent avoids the tile because they read our wait, from 0 (looks safe) to 1 (looks dangerous)
+    pub fn readability(self) -> f32 {
+        match self {
+            Self::Genbutsu => 0.0,
+            Self::Suji => 0.3,
+            Self::VisibleHonor => 0.3,
+            Self::HalfSuji => 0.6,
+            Self::Honor => 0.7,
+            Self::NonSuji => 1.0,
+        }
+    }
+
+    #[cfg(feature = "cli")]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Genbutsu => "furiten",
+            Self::Suji => "suji",
+            Self::VisibleHonor => "visible",
+            Self::HalfSuji => "half-suji",
+            Self::Honor => "honor",
+            Self::NonSuji => "non-suji",
+        }
+    }
+}
+
+/// How a wait tile looks given our discards and the amount of copies opponents can see
+pub fn wait_look(tile: Tile, discarded: &[bool; 34], visible: u8) -> WaitLook {
+    let tile = tile.deaka().as_usize();
+    if discarded[tile] {
+        return WaitLook::Genbutsu;
+    }
+    if tile >= 27 {
+        return if visible >= 2 {
+            WaitLook::VisibleHonor
+        } else {
+            WaitLook::Honor
+        };
+    }
+    let num = tile % 9;
+    let lower = num >= 3 && discarded[tile - 3];
+    let upper = num <= 5 && discarded[tile + 3];
+    match (num, lower, upper) {
+        (0..=2, _, true) | (6..=8, true, _) | (3..=5, true, true) => WaitLook::Suji,
+        (3..=5, true, false) | (3..=5, false, true) => WaitLook::HalfSuji,
+        _ => WaitLook::NonSuji,
+    }
+}
+
+/// Looks of the waits of every candidate that reaches tenpai, sorted from the hardest to read with furiten waits last
+pub fn candidate_wait_looks(state: &PlayerState, candidates: &[EventCandidate]) -> Vec<(EventCandidate, Vec<(Tile, WaitLook)>)> {
+    let mut wait_looks = candidates
+        .iter()
+        .filter(|candidate| candidate.shanten == 0)
+        .filter_map(|candidate| {
+            let mut discarded = state.discarded_tiles;
+            discarded[candidate_discard(candidate)?.deaka().as_usize()] = true;
+            let looks = candidate
+                .required_tiles
+                .iter()
+                .map(|required| {
+                    let tile = required.tile.deaka().as_usize();
+                    // opponents cannot see the copies in our hand
+                    let visible = state.tiles_seen[tile].saturating_sub(state.tehai[tile]);
+                    (required.tile, wait_look(required.tile, &discarded, visible))
+                })
+                .collect::<Vec<_>>();
+            Some((candidate.clone(), looks))
+        })
+        .collect::<Vec<_>>();
+    wait_looks.sort_by(|(_, a), (_, b)| {
+        is_furiten(a)
+            .cmp(&is_furiten(b))
+            .then(average_readability(a).total_cmp(&average_readability(b)))
+    });
+    wait_looks
+}
+
+fn is_furiten(looks: &[(Tile, WaitLook)]) -> bool {
+    looks.iter().any(|(_, look)| *look == WaitLook::Genbutsu)
+}
+
+fn average_readability(looks: &[(Tile, WaitLook)]) -> f32 {
+    if looks.is_empty() {
+        return 0.0;
+    }
+    looks.iter().map(|(_, look)| look.readability()).sum::<f32>() / looks.len() as f32
+}
@@ -0,0 +1,78 @@
+//! Terminal rendering for live analysis
+use std::io;
+
+use console::{Term, truncate_str};
+
+const ENTER_ALTERNATE_SCREEN: &str = "\x1B[?1049h";
+const LEAVE_ALTERNATE_SCREEN: &str = "\x1B[?1049l";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Redraw only the lines that changed since the last analysis
+    Diff,
+    /// Like diff but on the alternate screen, the last analysis is printed to the normal screen on exit
+    Alternate,
+    /// Print every analysis after the previous one, used when piping output
+    Append,
+}
+
+pub struct LiveRenderer {
+    term: Term,
+    mode: RenderMode,
+    previous: Vec<String>,
+}
+
+impl LiveRenderer {
+    /// Falls back to append mode when stdout is not a terminal
+    pub fn new(mode: RenderMode) -> Self {
+        let term = Term::stdout();
+        let mode = if term.is_term() { mode } else { RenderMode::Append };
+        if mode == RenderMode::Alternate {
+            _ = term.write_str(ENTER_ALTERNATE_SCREEN);
+        }
+        Self {
+            term,
+            mode,
+            previous: vec![],
+        }
+    }
+
+    pub fn render(&mut self, output: &str) -> io::Result<()> {
+        if self.mode == RenderMode::Append {
+            return self.term.write_line(output);
+        }
+
+        let width = self.term.size().1 as usize;
+        let lines = output
+            .lines()
+            .map(|line| truncate_str(line, width, "").into_owned())
+            .collect::<Vec<_>>();
+        if self.previous.is_empty() {
+            self.term.clear_screen()?;
+        }
+        for (row, line) in lines.iter().enumerate() {
+            if self.previous.get(row) != Some(line) {
+                self.term.move_cursor_to(0, row)?;
+                self.term.clear_line()?;
+                self.term.write_str(line)?;
+            }
+        }
+        if self.previous.len() > lines.len() {
+            self.term.move_cursor_to(0, lines.len())?;
+            self.term.clear_to_end_of_screen()?;
+        }
+        self.term.move_cursor_to(0, lines.len())?;
+        self.term.flush()?;
+        self.previous = lines;
+        Ok(())
+    }
+}
+
+impl Drop for LiveRenderer {
+    fn drop(&mut self) {
+        if self.mode == RenderMode::Alternate {
+            _ = self.term.write_str(LEAVE_ALTERNATE_SCREEN);
+            _ = self.term.write_line(&self.previous.join("\n"));
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! Prompt for entering and modifying a hand incrementally
+//! Every command edits the setup kept between commands, `analyze` runs the same analysis as the hand command on it.
+//! A board loaded with `board` is kept as its events instead, edits append the event of the action so mortal's state
+//! checks them. With `auto on`, the default, the analysis is rerun after every edit of a complete hand.
+use std::io::{BufRead, Write};
+
+use anyhow::{Context, Result, bail, ensure};
+use clap::Parser;
+use riichi::hand::{hand_with_aka, tiles_to_string};
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+use crate::decisions::state_from_events;
+use crate::input::{HandArgs, hand_with_aka_vec, single_tile_hand, state_from_hand_args};
+use crate::mjaigen::parse_board;
+use crate::state::{AnalysisOptions, ExpandedState};
+
+const HELP: &str = "hand <hand command args> | board <board command args> | tehai <tiles> | draw <tile> | discard <tile> \
+                    | pon <tile> [consumed] | kan <tile> [consumed] | chi <tile> <consumed> | fuuro [melds] \
+                    | ankan [kans] | dora <indicators> | bakaze <tile> | jikaze <tile> | left <tiles left> \
+                    | auto on|off | show | analyze | help | quit";
+
+/// Hand being built, in the same form as the arguments of the hand command
+pub struct Repl {
+    /// Tile counts with aka fives counted separately
+    tehai: [u8; 37],
+    fuuro: Option<String>,
+    ankan: Option<String>,
+    dora_indicators: Option<String>,
+    bakaze: Option<String>,
+    jikaze: Option<String>,
+    tiles_left: u8,
+    /// Events of a loaded board, replacing the hand above
+    board: Option<Vec<Event>>,
+    /// Rerun the analysis after every edit
+    auto: bool,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self {
+            tehai: [0; 37],
+            fuuro: None,
+            ankan: None,
+            dora_indicators: None,
+            bakaze: None,
+            jikaze: None,
+            tiles_left: 70,
+            board: None,
+            auto: true,
+        }
+    }
+}
+
+/// Counts of a single tile argument
+fn single_tile(arg: Option<&str>) -> Result<[u8; 37]> {
+    let tile = hand_with_aka(arg.context("missing tile")?)?;
+    ensure!(tile.iter().sum::<u8>() == 1, "expected a single tile");
+    Ok(tile)
+}
+
+/// Tiles consumed by a call, the explicit ones or copies of the called tile
+fn consumed<const N: usize>(tile: Tile, explicit: Option<&str>) -> Result<[Tile; N]> {
+    match explicit {
+        Some(tiles) => hand_with_aka_vec(tiles)?
+            .try_into()
+            .map_err(|tiles: Vec<Tile>| anyhow::anyhow!("expected {N} consumed tiles, got {}", tiles.len())),
+        None => Ok([tile.deaka(); N]),
+    }
+}
+
+impl Repl {
+    /// Remove tiles from the hand, failing without changes when one is missing
+    fn take(&mut self, tiles: &[Tile]) -> Result<()> {
+        let mut tehai = self.tehai;
+        for tile in tiles {
+            let count = &mut tehai[tile.as_usize()];
+            ensure!(*count > 0, "{tile} is not in hand");
+            *count -= 1;
+        }
+        self.tehai = tehai;
+        Ok(())
+    }
+
+    /// Whether the hand has the 13 or 14 tiles the analysis needs
+    fn complete(&self) -> bool {
+        let melds = [&self.fuuro, &self.ankan]
+            .into_iter()
+            .flatten()
+            .map(|melds| melds.split_whitespace().count())
+            .sum::<usize>();
+        let tiles = self.tehai.iter().map(|&count| count as usize).sum::<usize>() + melds * 3;
+        self.board.is_some() || tiles == 13 || tiles == 14
+    }
+
+    fn state(&self) -> Result<PlayerState> {
+        match &self.board {
+            Some(events) => state_from_events(events.clone()),
+            None => state_from_hand_args(self.hand_args()),
+        }
+    }
+
+    /// Append the event of an action to the board, kept only when the state accepts it
+    fn push_event(&mut self, event: impl FnOnce(&PlayerState, &[Event]) -> Result<Event>) -> Result<()> {
+        let events = self.board.as_ref().context("no board loaded")?;
+        let state = state_from_events(events.clone())?;
+        let mut events = events.clone();
+        events.push(event(&state, &events)?);
+        state_from_events(events.clone())?;
+        self.board = Some(events);
+        Ok(())
+    }
+
+    /// Call of the last discard on the board by an opponent
+    fn call_event(&mut self, call: &str, tile: Tile, explicit: Option<&str>) -> Result<()> {
+        self.push_event(|state, events| {
+            let actor = state.player_id;
+            let target = events
+                .iter()
+                .rev()
+                .find_map(|event| match *event {
+                    Event::Dahai { actor: target, pai, .. } if target != actor && pai.deaka() == tile.deaka() => Some(target),
+                    _ => None,
+                })
+                .with_context(|| format!("no discard of {tile} to call"))?;
+            Ok(match call {
+                "pon" => Event::Pon {
+                    actor,
+                    target,
+                    pai: tile,
+                    consumed: consumed(tile, explicit)?,
+                },
+                "kan" => Event::Daiminkan {
+                    actor,
+                    target,
+                    pai: tile,
+                    consumed: consumed(tile, explicit)?,
+                },
+                _ => Event::Chi {
+                    actor,
+                    target,
+                    pai: tile,
+                    consumed: consumed(tile, Some(explicit.context("missing consumed tiles")?))?,
+                },
+            })
+        })
+    }
+
+    /// Call in the hand: the consumed tiles leave the hand and the meld joins the fuuro
+    fn call_hand(&mut self, call: &str, tile: Tile, explicit: Option<&str>) -> Result<()> {
+        let consumed = match call {
+            "pon" => consumed::<2>(tile, explicit)?.to_vec(),
+            "kan" => consumed::<3>(tile, explicit)?.to_vec(),
+            _ => consumed::<2>(tile, Some(explicit.context("missing consumed tiles")?))?.to_vec(),
+        };
+        self.take(&consumed)?;
+        let mut meld = [0; 34];
+        let mut akas = [false; 3];
+        for tile in consumed.iter().chain([&tile]) {
+            meld[tile.deaka().as_usize()] += 1;
+            if tile.is_aka() {
+                akas[tile.deaka().as_usize() / 9] = true;
+            }
+        }
+        let meld = tiles_to_string(&meld, akas);
+        self.fuuro = Some(match self.fuuro.take() {
+            Some(fuuro) => format!("{fuuro} {meld}"),
+            None => meld,
+        });
+        Ok(())
+    }
+
+    fn tehai_string(&self) -> String {
+        let mut tehai = [0; 34];
+        tehai.copy_from_slice(&self.tehai[..34]);
+        let mut akas = [false; 3];
+        for (i, &count) in self.tehai[34..].iter().enumerate() {
+            akas[i] = count > 0;
+            tehai[i * 9 + 4] += count;
+        }
+        tiles_to_string(&tehai, akas)
+    }
+
+    fn hand_args(&self) -> HandArgs {
+        HandArgs {
+            tehai: self.tehai_string(),
+            fuuro: self.fuuro.clone(),
+            ankan: self.ankan.clone(),
+            dora_indicators: self.dora_indicators.clone(),
+            bakaze: self.bakaze.clone(),
+            jikaze: self.jikaze.clone(),
+            tiles_left: Some(self.tiles_left),
+        }
+    }
+
+    fn show(&self) -> String {
+        if let Some(events) = &self.board {
+            let last = events.last().map(|event| serde_json::to_string(event).unwrap_or_default());
+            return format!("board of {} events, last {}", events.len(), last.unwrap_or_default());
+        }
+        let optional = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_owned());
+        format!(
+            "{} | fuuro {} | ankan {} | dora {} | bakaze {} jikaze {} | {} left",
+            self.tehai_string(),
+            optional(&self.fuuro),
+            optional(&self.ankan),
+            optional(&self.dora_indicators),
+            optional(&self.bakaze),
+            optional(&self.jikaze),
+            self.tiles_left
+        )
+    }
+
+    /// Run a single command and return its output, None when the prompt should exit
+    pub fn execute(&mut self, line: &str, options: &AnalysisOptions) -> Result<Option<String>> {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return Ok(Some(String::new()));
+        };
+        let rest = words.collect::<Vec<_>>();
+        let joined = (!rest.is_empty()).then(|| rest.join(" "));
+        let tile = || single_tile_hand(rest.first().context("missing tile")?);
+        match command {
+            "hand" => {
+                let args = HandArgs::try_parse_from(std::iter::once("hand").chain(rest.iter().copied()))?;
+                let repl = Self {
+                    tehai: hand_with_aka(&args.tehai)?,
+                    fuuro: args.fuuro,
+                    ankan: args.ankan,
+                    dora_indicators: args.dora_indicators,
+                    bakaze: args.bakaze,
+                    jikaze: args.jikaze,
+                    tiles_left: args.tiles_left.unwrap_or(70),
+                    board: None,
+                    auto: self.auto,
+                };
+                state_from_hand_args(repl.hand_args())?;
+                *self = repl;
+            }
+            "board" => {
+                let events = parse_board(rest.clone())?;
+                state_from_events(events.clone())?;
+                self.board = Some(events);
+            }
+            "draw" if self.board.is_some() => {
+                let pai = tile()?;
+                self.push_event(|state, _| {
+                    Ok(Event::Tsumo {
+                        actor: state.player_id,
+                        pai,
+                    })
+                })?;
+            }
+            "discard" if self.board.is_some() => {
+                let pai = tile()?;
+                self.push_event(|state, _| {
+                    Ok(Event::Dahai {
+                        actor: state.player_id,
+                        pai,
+                        tsumogiri: state.last_self_tsumo == Some(pai),
+                    })
+                })?;
+            }
+            "dora" if self.board.is_some() => {
+                for dora_marker in hand_with_aka_vec(joined.as_deref().context("missing indicators")?)? {
+                    self.push_event(|_, _| Ok(Event::Dora { dora_marker }))?;
+                }
+            }
+            "pon" | "kan" | "chi" if self.board.is_some() => self.call_event(command, tile()?, rest.get(1).copied())?,
+            "pon" | "kan" | "chi" => self.call_hand(command, tile()?, rest.get(1).copied())?,
+            "tehai" => self.tehai = hand_with_aka(joined.as_deref().context("missing tiles")?)?,
+            "draw" => {
+                let tile = single_tile(rest.first().copied())?;
+                for (count, added) in self.tehai.iter_mut().zip(tile) {
+                    *count += added;
+                }
+                self.tiles_left = self.tiles_left.saturating_sub(1);
+            }
+            "discard" => self.take(&[tile()?])?,
+            "fuuro" => self.fuuro = joined,
+            "ankan" => self.ankan = joined,
+            "dora" => self.dora_indicators = joined,
+            "bakaze" => self.bakaze = joined,
+            "jikaze" => self.jikaze = joined,
+            "left" => {
+                self.tiles_left = joined
+                    .context("missing tiles left")?
+                    .parse()
+                    .context("incorrect tiles left")?
+            }
+            "auto" => {
+                self.auto = match joined.as_deref() {
+                    Some("on") => true,
+                    Some("off") => false,
+                    _ => bail!("expected auto on or auto off"),
+                };
+                return Ok(Some(format!("auto {}", if self.auto { "on" } else { "off" })));
+            }
+            "show" => return Ok(Some(self.show())),
+            "analyze" => return Ok(Some(self.analyze(options)?)),
+            "help" => return Ok(Some(HELP.to_owned())),
+            "quit" | "exit" => return Ok(None),
+            _ => bail!("unknown command {command}, try help"),
+        }
+        if !self.auto || !self.complete() {
+            return Ok(Some(self.show()));
+        }
+        // the edit stays even when the analysis fails, e.g. for a hand that is not ready yet
+        Ok(Some(match self.analyze(options) {
+            Ok(analysis) => format!("{}\n{analysis}", self.show()),
+            Err(e) => format!("{}\nanalysis failed: {e:#}", self.show()),
+        }))
+    }
+
+    fn analyze(&self, options: &AnalysisOptions) -> Result<String> {
+        Ok(ExpandedState::from_state(self.state()?, None, options)?.to_log_string())
+    }
+}
+
+/// Read commands from stdin until quit or end of input
+pub fn run_repl(options: &AnalysisOptions) {
+    let mut repl = Repl::default();
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        eprint!("washizu> ");
+        _ = std::io::stderr().flush();
+        let Some(Ok(line)) = lines.next() else {
+            return;
+        };
+        match repl.execute(&line, options) {
+            Ok(Some(output)) if output.is_empty() => {}
+            Ok(Some(output)) => println!("{output}"),
+            Ok(None) => return,
+            Err(e) => eprintln!("{e:#}"),
+        }
+    }
+}
@@ -0,0 +1,295 @@
+//! Machine-readable form of the analysis output
+use riichi::algo::agari::Agari;
+use riichi::hand::tiles_to_string;
+use riichi::mjai::Event;
+use riichi::t;
+use riichi::tile::Tile;
+
+use crate::agari::FuPart;
+use crate::calls::CallDecision;
+use crate::chankan::ChankanAnalysis;
+use crate::damaten::RiichiDecision;
+use crate::ekyumoe::Detail;
+use crate::fast::FastEstimate;
+use crate::fold::{FoldPlan, SafetyRanking};
+use crate::fu::FuBoundary;
+use crate::handshape::HandShape;
+use crate::hora::HoraDecision;
+use crate::kandora::KanDoraCost;
+use crate::pushfold::PushFold;
+use crate::rollout::CandidateRollout;
+use crate::ryukyoku::DrawPayments;
+use crate::state::{ExpandedState, expected_draws, yaku_breakdown};
+use crate::suji::{SujiKind, WallKind};
+use crate::tenpai::TenpaiWait;
+use crate::ukeire::UkeireBreakdown;
+use crate::yakuhai::YakuhaiAdvice;
+
+/// Analysis of a single decision point
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+pub struct AnalysisReport {
+    pub tehai: String,
+    /// Shanten of the current hand, -1 for agari hands
+    pub shanten: i8,
+    pub furiten: bool,
+    /// Percentile of the hand compared to typical hands at the same turn
+    pub percentile: f32,
+    /// Details given by mortal, empty when unavailable
+    pub details: Vec<Detail>,
+    /// Candidates sorted by expected value, with the win listed at its value when the last event allows one
+    pub candidates: Vec<CandidateReport>,
+    pub agari: Vec<AgariReport>,
+    /// Deal-in probability in percent of each tile for each player relative to us, sorted from most dangerous, safe tiles are omitted
+    pub danger: Vec<Vec<(Tile, f32)>>,
+    /// Least dangerous discard that keeps the current shanten and its combined danger, when requested
+    pub safe_keep: Option<(Event, f32)>,
+    /// Estimated expected value of declaring open riichi for each tenpai candidate, when the rules allow it
+    pub open_riichi_values: Vec<(Event, f32)>,
+    /// Number of genbutsu in our hand against each player relative to us
+    pub safety_reserve: [u8; 4],
+    /// Expected score change of each discard including the chance of dealing into a riichi, best first
+    pub defense_values: Vec<(Event, f32)>,
+    /// Push or fold verdict against a riichi with its margin, null without a riichi
+    #[serde(default)]
+    pub push_fold: Option<PushFold>,
+    /// Whether to keep each isolated yakuhai for its pairing value
+    pub yakuhai_advice: Vec<YakuhaiAdvice>,
+    /// Tenpai discards whose wins sit on a fu boundary
+    pub fu_boundaries: Vec<FuBoundary>,
+    /// Best tenpai discard with and without riichi, only when riichi can be declared
+    pub riichi_decision: Option<RiichiDecision>,
+    /// Every possible call with its best follow-up discard compared to passing
+    pub call_decisions: Vec<CallDecision>,
+    /// Ron on an opponent's kakan compared to passing it, only at a chankan prompt
+    pub chankan: Option<ChankanAnalysis>,
+    /// Tsumo or ron on the last tile compared to declining it, null unless the last event allows winning
+    #[serde(default)]
+    pub hora: Option<HoraDecision>,
+    /// Suji and kabe of every number tile against each player relative to us, empty unless in riichi or with three calls
+    pub suji: Vec<Vec<(Tile, SujiKind, Option<WallKind>)>>,
+    /// Approximate values of every discard, in fast mode, over the time budget or when the single player tables are empty
+    pub fast_estimates: Vec<FastEstimate>,
+    /// Advancing and upgrade tiles of every discard with their copies left, empty unless requested
+    #[serde(default)]
+    pub ukeire: Vec<UkeireBreakdown>,
+    /// Wait of every tenpai discard, whether it is furiten and whether it lacks a yaku for ron
+    #[serde(default)]
+    pub tenpai_waits: Vec<TenpaiWait>,
+    /// Class of the hand shape, e.g. perfect iishanten, and the blocks it is decomposed into
+    #[serde(default)]
+    pub hand_shape: HandShape,
+    /// Defensive plan of a hopeless hand against a riichi, the candidates are empty when set
+    #[serde(default)]
+    pub fold: Option<FoldPlan>,
+    /// Tiles in hand ranked by safety alone when an opponent threatens a hand worth little, null with a fold plan
+    #[serde(default)]
+    pub safety_ranking: Option<SafetyRanking>,
+    /// Outcomes of playouts of the rest of the kyoku after each discard, best average placement first, when requested
+    #[serde(default)]
+    pub rollouts: Vec<CandidateRollout>,
+    /// Fields added by plugins
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub annotations: serde_json::Map<String, serde_json::Value>,
+    pub tables: TablesReport,
+}
+
+/// How the single player tables were calculated
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+pub struct TablesReport {
+    pub max_shanten: i8,
+    pub calc_tegawari: Option<i8>,
+    pub calc_shanten_down: Option<i8>,
+    /// Chance per go-around of an abortive draw applied to the candidates, when requested
+    pub abortive_draw_hazard: Option<f32>,
+    /// Chance of a nine terminals abort before our next draw applied to the candidates, when requested
+    #[serde(default)]
+    pub kyuushu_hazard: Option<f32>,
+    /// Payments at an exhaustive draw, when requested
+    #[serde(default)]
+    pub draw_payments: Option<DrawPayments>,
+    /// Cost of the opponents' new dora of every kan candidate, subtracted from its `exp_value`, when requested
+    #[serde(default)]
+    pub kan_dora_costs: Vec<KanDoraCost>,
+    /// Whether the tables exceeded the time budget and `fast_estimates` replace the candidates
+    #[serde(default)]
+    pub over_budget: bool,
+    pub elapsed_ms: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct CandidateReport {
+    /// Short decision string as displayed in the log output
+    pub action: String,
+    pub event: Event,
+    /// Expected value over all remaining tsumos, including `draw_value` and the kan dora cost of `kan_dora_costs`
+    pub exp_value: f32,
+    /// Value of the payments at an exhaustive draw, 0 unless requested
+    #[serde(default)]
+    pub draw_value: f32,
+    /// Gap to the best candidate, in placement utility when ranked by a scorer and EV otherwise
+    pub delta: f32,
+    pub win_prob: f32,
+    /// Expected number of tsumos until the win given that the candidate wins
+    pub expected_draws: Option<f32>,
+    pub tenpai_prob: f32,
+    /// Shanten after the action
+    pub shanten: i8,
+    /// Number of tiles that advance shanten
+    pub num_required_tiles: u32,
+    pub required_tiles: Vec<(Tile, u32)>,
+    /// Localized yaku names and the chance they are part of a winning hand
+    pub yaku: Vec<(String, f32)>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct AgariReport {
+    /// Winning tile, "?" for an already complete hand
+    pub tile: Tile,
+    /// None for yakuman
+    pub han: Option<u8>,
+    /// None for yakuman
+    pub fu: Option<u8>,
+    /// Yakuman count, 0 for normal hands
+    pub yakuman: u8,
+    /// Points without honba and riichi sticks, 0 for yakunashi
+    pub points: i32,
+    pub yaku: Vec<String>,
+    /// Parts adding up to the fu, empty for yakuman and yakunashi
+    #[serde(default)]
+    pub fu_breakdown: Vec<FuPart>,
+}
+
+impl ExpandedState {
+    pub fn to_report(&self) -> AnalysisReport {
+        let is_oya = self.state.is_oya();
+        let deltas = self.deltas();
+        let mut candidates = self
+            .candidates
+            .iter()
+            .zip(deltas)
+            .map(|(candidate, delta)| CandidateReport {
+                action: candidate.event.to_decision_string(),
+                event: candidate.event.clone(),
+                exp_value: self.total_exp_value(candidate),
+                draw_value: self.draw_value(candidate),
+                delta,
+                win_prob: candidate.win_probs.first().copied().unwrap_or(0.0),
+                expected_draws: expected_draws(candidate),
+                tenpai_prob: candidate.tenpai_probs.first().copied().unwrap_or(0.0),
+                shanten: candidate.shanten,
+                num_required_tiles: candidate.num_required_tiles as u32,
+                required_tiles: candidate.required_tiles.iter().map(|r| (r.tile, r.count as u32)).collect(),
+                yaku: yaku_breakdown(candidate, self.yaku_naming)
+                    .map(|breakdown| breakdown.yaku)
+                    .unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+        if let (Some(hora), Some(position)) = (&self.hora, self.hora_position()) {
+            let event = hora.event(&self.state);
+            let best_value = candidates.first().map_or(0.0, |best| best.exp_value);
+            let yaku = self
+                .agari
+                .iter()
+                .find(|(tile, _)| *tile == hora.agari_tile())
+                .and_then(|(tile, agari)| Some(self.yaku_names(*tile, agari.as_ref()?)))
+                .unwrap_or_default();
+            candidates.insert(
+                position,
+                CandidateReport {
+                    action: event.to_decision_string(),
+                    event,
+                    exp_value: hora.points_with_ura,
+                    draw_value: 0.0,
+                    delta: hora.points_with_ura - best_value,
+                    win_prob: 1.0,
+                    expected_draws: None,
+                    tenpai_prob: 1.0,
+                    shanten: -1,
+                    num_required_tiles: 0,
+                    required_tiles: vec![],
+                    yaku: yaku.into_iter().map(|name| (name, 1.0)).collect(),
+                },
+            );
+        }
+        AnalysisReport {
+            tehai: tiles_to_string(&self.state.tehai, self.state.akas_in_hand),
+            shanten: self.shanten,
+            furiten: self.state.at_furiten,
+            percentile: self.percentile,
+            details: self.details.clone(),
+            candidates,
+            agari: self
+                .agari
+                .iter()
+                .map(|(tile, agari)| match agari {
+                    None => AgariReport {
+                        tile: *tile,
+                        han: None,
+                        fu: None,
+                        yakuman: 0,
+                        points: 0,
+                        yaku: vec![],
+                        fu_breakdown: vec![],
+                    },
+                    Some(agari_with_yaku) => {
+                        let point = agari_with_yaku.agari.point(is_oya);
+                        let points = if *tile == t!(?) {
+                            point.tsumo_total(is_oya)
+                        } else {
+                            point.ron
+                        };
+                        let (han, fu, yakuman) = match agari_with_yaku.agari {
+                            Agari::Normal { fu, han } => (Some(han), Some(fu), 0),
+                            Agari::Yakuman(count) => (None, None, count),
+                        };
+                        AgariReport {
+                            tile: *tile,
+                            han,
+                            fu,
+                            yakuman,
+                            points,
+                            yaku: self.yaku_names(*tile, agari_with_yaku),
+                            fu_breakdown: self.fu_breakdown(*tile, agari_with_yaku),
+                        }
+                    }
+                })
+                .collect(),
+            danger: self
+                .danger_weights
+                .iter()
+                .map(|weights| weights.iter().copied().filter(|(_, weight)| *weight > 0.0).collect())
+                .collect(),
+            safe_keep: self.safe_keep.clone(),
+            open_riichi_values: self.open_riichi_values.clone(),
+            safety_reserve: self.safety_reserve,
+            defense_values: self.defense_values.clone(),
+            push_fold: self.push_fold.clone(),
+            yakuhai_advice: self.yakuhai_advice.clone(),
+            fu_boundaries: self.fu_boundaries.clone(),
+            fast_estimates: self.fast_estimates.clone(),
+            ukeire: self.ukeire.clone(),
+            tenpai_waits: self.tenpai_waits.clone(),
+            hand_shape: self.hand_shape.clone(),
+            fold: self.fold.clone(),
+            safety_ranking: self.safety_ranking.clone(),
+            rollouts: self.rollouts.clone(),
+            riichi_decision: self.riichi_decision.clone(),
+            call_decisions: self.call_decisions.clone(),
+            chankan: self.chankan.clone(),
+            hora: self.hora.clone(),
+            suji: self.suji.to_vec(),
+            annotations: self.annotations.clone(),
+            tables: TablesReport {
+                max_shanten: self.sp_options.max_shanten,
+                calc_tegawari: self.sp_options.calc_tegawari,
+                calc_shanten_down: self.sp_options.calc_shanten_down,
+                abortive_draw_hazard: self.abortive_draw_hazard,
+                kyuushu_hazard: self.kyuushu_hazard,
+                draw_payments: self.draw_payments,
+                kan_dora_costs: self.kan_dora_costs.clone(),
+                over_budget: self.sp_over_budget,
+                elapsed_ms: self.sp_elapsed.as_millis() as u64,
+            },
+        }
+    }
+}
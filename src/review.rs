@@ -0,0 +1,273 @@
+//! Review of the decisions actually taken in a log against the single player tables
+use riichi::hand::tiles_to_string;
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+use crate::ekyumoe::Detail;
+use crate::report::AnalysisReport;
+
+/// Board as seen by the reviewed player at a decision
+pub struct BoardSnapshot {
+    /// Round wind, kyoku and honba, e.g. E1-0
+    pub round: String,
+    pub turn: u8,
+    /// Scores relative to us
+    pub scores: [i32; 4],
+    pub dora_indicators: Vec<Tile>,
+    pub tehai: String,
+    pub fuuro: Vec<Vec<Tile>>,
+    /// Discards of each player relative to us
+    pub kawa: [Vec<Tile>; 4],
+    pub riichi_declared: [bool; 4],
+}
+
+impl BoardSnapshot {
+    pub fn from_state(state: &PlayerState) -> Self {
+        Self {
+            round: format!("{}{}-{}", state.bakaze, state.kyoku + 1, state.honba),
+            turn: state.at_turn,
+            scores: state.scores,
+            dora_indicators: state.dora_indicators.to_vec(),
+            tehai: tiles_to_string(&state.tehai, state.akas_in_hand),
+            fuuro: state.fuuro_overview[0].iter().map(|fuuro| fuuro.to_vec()).collect(),
+            kawa: std::array::from_fn(|player| state.kawa_overview[player].to_vec()),
+            riichi_declared: state.riichi_declared,
+        }
+    }
+}
+
+/// Decision point of the reviewed player
+pub struct ReviewDecision {
+    /// Event that led to the decision
+    pub trigger: Event,
+    pub board: BoardSnapshot,
+    pub report: AnalysisReport,
+    /// Action actually taken, `Event::None` when passing on a call. None when the log ends before it.
+    pub actual: Option<Event>,
+}
+
+impl ReviewDecision {
+    /// Action mortal assigns the highest probability to
+    pub fn mortal_choice(&self) -> Option<&Detail> {
+        self.report.details.iter().max_by(|a, b| a.prob.total_cmp(&b.prob))
+    }
+
+    /// Whether the actual action differs from mortal's choice
+    pub fn is_mistake(&self) -> bool {
+        match (self.mortal_choice(), &self.actual) {
+            (Some(choice), Some(actual)) => choice.action.to_decision_string() != actual.to_decision_string(),
+            _ => false,
+        }
+    }
+
+    /// Probability mortal assigns to the actual action
+    pub fn actual_prob(&self) -> Option<f32> {
+        let actual = self.actual.as_ref()?.to_decision_string();
+        self.report
+            .details
+            .iter()
+            .find(|detail| detail.action.to_decision_string() == actual)
+            .map(|detail| detail.prob)
+    }
+
+    /// Expected value lost by the actual action compared to the best candidate, taken from the candidates' deltas.
+    /// A hopeless hand folding against a riichi has no candidates, its discards lose the expected deal-in payment
+    /// over the safest tile instead.
+    /// None when the actual action is not among the candidates, e.g. calls outside the single player tables.
+    pub fn ev_loss(&self) -> Option<EvLoss> {
+        let actual = self.actual.as_ref()?.to_decision_string();
+        if let Some(fold) = &self.report.fold {
+            let (cheapest, _) = fold.cheapest()?;
+            return Some(EvLoss {
+                best: cheapest.to_string(),
+                loss: fold.deal_in_loss(&actual)?,
+                actual,
+            });
+        }
+        let best = self.report.candidates.iter().max_by(|a, b| a.delta.total_cmp(&b.delta))?;
+        let taken = self.report.candidates.iter().find(|candidate| candidate.action == actual)?;
+        Some(EvLoss {
+            best: best.action.clone(),
+            actual,
+            loss: -taken.delta,
+        })
+    }
+}
+
+/// Filter of the reviewed decisions by the EV their action lost.
+/// An action outside the candidates has no known loss, it is a mistake when it differs from mortal's choice and never
+/// reaches a minimum loss. A decision without a response yet is always kept.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LossFilter {
+    pub only_mistakes: bool,
+    pub min_ev_loss: Option<f32>,
+}
+
+impl LossFilter {
+    pub fn is_set(&self) -> bool {
+        self.only_mistakes || self.min_ev_loss.is_some()
+    }
+
+    pub fn matches(&self, decision: &ReviewDecision) -> bool {
+        if decision.actual.is_none() {
+            return true;
+        }
+        match decision.ev_loss() {
+            Some(ev_loss) => {
+                (!self.only_mistakes || ev_loss.loss > 0.0) && self.min_ev_loss.is_none_or(|min| ev_loss.loss >= min)
+            }
+            None => (!self.only_mistakes || decision.is_mistake()) && self.min_ev_loss.is_none(),
+        }
+    }
+}
+
+/// Fill in the action of the last decision from the event right after it, anything but our own action means we passed
+pub fn record_response(decisions: &mut [ReviewDecision], event: &Event, player_id: u8) {
+    if let Some(decision) = decisions.last_mut()
+        && decision.actual.is_none()
+    {
+        let responded = event.actor() == Some(player_id) && !matches!(event, Event::Tsumo { .. });
+        decision.actual = Some(if responded { event.clone() } else { Event::None });
+    }
+}
+
+/// Difference between the best candidate and the action actually taken
+#[derive(Debug, Clone)]
+pub struct EvLoss {
+    pub best: String,
+    pub actual: String,
+    pub loss: f32,
+}
+
+/// Decisions that lost expected value sorted from the most severe, with their index in `decisions`
+pub fn ev_losses(decisions: &[ReviewDecision]) -> Vec<(usize, EvLoss)> {
+    let mut losses = decisions
+        .iter()
+        .enumerate()
+        .filter_map(|(index, decision)| Some((index, decision.ev_loss()?)))
+        .filter(|(_, ev_loss)| ev_loss.loss > 0.0)
+        .collect::<Vec<_>>();
+    losses.sort_by(|(_, a), (_, b)| b.loss.total_cmp(&a.loss));
+    losses
+}
+
+/// Game-level summary of EV loss per decision
+pub fn review_summary(decisions: &[ReviewDecision]) -> String {
+    let losses = ev_losses(decisions);
+    let total: f32 = losses.iter().map(|(_, ev_loss)| ev_loss.loss).sum();
+    let mut lines = vec![format!(
+        "review: {} of {} decisions lose EV, {} total",
+        losses.len(),
+        decisions.len(),
+        total.round()
+    )];
+    lines.extend(losses.iter().map(|(index, ev_loss)| {
+        let board = &decisions[*index].board;
+        format!(
+            "{} turn {:<2} {:<3} instead of {:<3} -{}",
+            board.round,
+            board.turn,
+            ev_loss.actual,
+            ev_loss.best,
+            ev_loss.loss.round()
+        )
+    }));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::t;
+
+    use super::*;
+    use crate::report::CandidateReport;
+
+    fn discard(tile: Tile) -> Event {
+        Event::Dahai {
+            actor: 0,
+            pai: tile,
+            tsumogiri: false,
+        }
+    }
+
+    /// Decision between discarding 3z and 1m, losing 500 with 1m, where mortal prefers 3z
+    fn decision(actual: Option<Event>) -> ReviewDecision {
+        let candidate = |tile: Tile, delta: f32| CandidateReport {
+            action: tile.to_string(),
+            event: discard(tile),
+            exp_value: 2000.0 + delta,
+            draw_value: 0.0,
+            delta,
+            win_prob: 0.0,
+            expected_draws: None,
+            tenpai_prob: 0.0,
+            shanten: 0,
+            num_required_tiles: 0,
+            required_tiles: vec![],
+            yaku: vec![],
+        };
+        ReviewDecision {
+            trigger: Event::None,
+            board: BoardSnapshot {
+                round: "E1-0".to_owned(),
+                turn: 0,
+                scores: [25000; 4],
+                dora_indicators: vec![t!(1m)],
+                tehai: String::new(),
+                fuuro: vec![],
+                kawa: Default::default(),
+                riichi_declared: [false; 4],
+            },
+            report: AnalysisReport {
+                details: vec![Detail {
+                    action: discard(t!(W)),
+                    q_value: 1.0,
+                    prob: 0.9,
+                }],
+                candidates: vec![candidate(t!(W), 0.0), candidate(t!(1m), -500.0)],
+                ..Default::default()
+            },
+            actual,
+        }
+    }
+
+    #[test]
+    fn loss_filter_keeps_the_decisions_losing_enough() {
+        let only_mistakes = LossFilter {
+            only_mistakes: true,
+            min_ev_loss: None,
+        };
+        let min_ev_loss = |min| LossFilter {
+            only_mistakes: false,
+            min_ev_loss: Some(min),
+        };
+        assert!(!LossFilter::default().is_set());
+        assert!(LossFilter::default().matches(&decision(Some(discard(t!(W))))));
+        assert!(!only_mistakes.matches(&decision(Some(discard(t!(W))))));
+        assert!(only_mistakes.matches(&decision(Some(discard(t!(1m))))));
+        assert!(min_ev_loss(500.0).matches(&decision(Some(discard(t!(1m))))));
+        assert!(!min_ev_loss(501.0).matches(&decision(Some(discard(t!(1m))))));
+    }
+
+    #[test]
+    fn loss_filter_does_not_take_unvalued_actions_as_lossless() {
+        let only_mistakes = LossFilter {
+            only_mistakes: true,
+            min_ev_loss: None,
+        };
+        // 9p is not a candidate, mortal's choice still tells it is a mistake
+        let unvalued = decision(Some(discard(t!(9p))));
+        assert!(unvalued.ev_loss().is_none());
+        assert!(only_mistakes.matches(&unvalued));
+        assert!(
+            !LossFilter {
+                only_mistakes: false,
+                min_ev_loss: Some(0.0),
+            }
+            .matches(&unvalued)
+        );
+        // the log ended before the response
+        assert!(only_mistakes.matches(&decision(None)));
+    }
+}
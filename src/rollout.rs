@@ -0,0 +1,321 @@
+//! Monte Carlo playouts of the rest of a kyoku with a heuristic agent in every seat
+//! Hidden hands and the wall are dealt randomly from the unseen tiles.
+//! The agent only discards for shanten and never calls or declares riichi, wins ignore yaku and furiten
+//! and are all valued as a typical 30 fu 3 han hand. Meant as a sanity check next to the analytic tables.
+//! Candidates are compared by forcing our first discard over the same deals, so the differences between them are not
+//! drowned in the noise of the deals.
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use riichi::algo::point::Point;
+use riichi::algo::shanten::calc_all;
+use riichi::algo::sp::EventCandidate;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+use crate::defense::candidate_discard;
+use crate::scoring::{WinKind, apply_ryukyoku, apply_win, placements_after};
+
+/// Fu and han every simulated win is valued at
+const TYPICAL_FU: u8 = 30;
+const TYPICAL_HAN: u8 = 3;
+/// Seed of the candidate rollouts, fixed so the analysis of a board does not change between runs
+const CANDIDATE_SEED: u64 = 0;
+
+/// How a single playout ended from our perspective
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Win,
+    DealIn,
+    OtherWin,
+    Draw,
+}
+
+/// Aggregate over all playouts
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct RolloutStats {
+    pub playouts: usize,
+    pub wins: usize,
+    pub deal_ins: usize,
+    /// Wins by opponents that we did not deal into
+    pub other_wins: usize,
+    pub draws: usize,
+    /// Exhaustive draws where we were tenpai
+    pub tenpai_draws: usize,
+    /// Number of playouts ending in each of our placements, first to fourth
+    pub placements: [usize; 4],
+    /// Sum of our score change over all playouts
+    pub total_delta: i64,
+}
+
+impl RolloutStats {
+    fn ratio(&self, count: usize) -> f32 {
+        count as f32 / self.playouts.max(1) as f32 * 100.0
+    }
+
+    pub fn average_delta(&self) -> f64 {
+        self.total_delta as f64 / self.playouts.max(1) as f64
+    }
+
+    /// Average placement after the kyoku, 1 is first
+    pub fn average_placement(&self) -> f32 {
+        let total = self
+            .placements
+            .iter()
+            .enumerate()
+            .map(|(i, &count)| (i + 1) * count)
+            .sum::<usize>();
+        total as f32 / self.playouts.max(1) as f32
+    }
+
+    fn placements_string(&self) -> String {
+        self.placements
+            .iter()
+            .map(|&count| format!("{:.1}%", self.ratio(count)))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    pub fn to_log_string(&self) -> String {
+        format!(
+            "{} playouts\nwin {:.1}% | deal-in {:.1}% | other win {:.1}% | draw {:.1}% (tenpai {:.1}%)\naverage score delta {:.0}\nplacements {} (average {:.2})",
+            self.playouts,
+            self.ratio(self.wins),
+            self.ratio(self.deal_ins),
+            self.ratio(self.other_wins),
+            self.ratio(self.draws),
+            self.ratio(self.tenpai_draws),
+            self.average_delta(),
+            self.placements_string(),
+            self.average_placement()
+        )
+    }
+
+    /// One line summary next to a candidate
+    pub fn to_short_string(&self) -> String {
+        format!(
+            "win {:.1}% | deal-in {:.1}% | tenpai draw {:.1}% | delta {:+.0} | place {} ({:.2})",
+            self.ratio(self.wins),
+            self.ratio(self.deal_ins),
+            self.ratio(self.tenpai_draws),
+            self.average_delta(),
+            self.placements_string(),
+            self.average_placement()
+        )
+    }
+
+    fn record(&mut self, state: &PlayerState, playout: Playout) {
+        match playout.outcome {
+            Outcome::Win => self.wins += 1,
+            Outcome::DealIn => self.deal_ins += 1,
+            Outcome::OtherWin => self.other_wins += 1,
+            Outcome::Draw => self.draws += 1,
+        }
+        self.tenpai_draws += playout.tenpai as usize;
+        // placements are broken by the absolute seats
+        let mut scores = playout.scores;
+        scores.rotate_right(state.player_id as usize);
+        self.placements[placements_after(scores)[state.player_id as usize] as usize] += 1;
+        self.playouts += 1;
+        self.total_delta += (playout.scores[0] - state.scores[0]) as i64;
+    }
+}
+
+/// Rollouts after one of our discards
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct CandidateRollout {
+    pub discard: Tile,
+    pub stats: RolloutStats,
+}
+
+/// End of a single playout
+struct Playout {
+    outcome: Outcome,
+    /// Scores relative to us after the kyoku
+    scores: [i32; 4],
+    /// Whether we were tenpai at an exhaustive draw
+    tenpai: bool,
+}
+
+struct Seat {
+    hand: [u8; 34],
+    len_div3: u8,
+    riichi: bool,
+}
+
+impl Seat {
+    fn shanten(&self) -> i8 {
+        calc_all(&self.hand, self.len_div3)
+    }
+
+    /// Discard that keeps the lowest shanten, preferring the most isolated tile
+    fn choose_discard(&mut self, drawn: Option<usize>) -> usize {
+        let mut hand = self.hand;
+        let tile = match drawn {
+            Some(drawn) if self.riichi => drawn,
+            _ => (0..34)
+                .filter(|&tile| self.hand[tile] > 0)
+                .min_by_key(|&tile| {
+                    hand[tile] -= 1;
+                    let shanten = calc_all(&hand, self.len_div3);
+                    hand[tile] += 1;
+                    (shanten, connectivity(&hand, tile))
+                })
+                .expect("empty hand"),
+        };
+        self.hand[tile] -= 1;
+        tile
+    }
+}
+
+/// Number of other tiles in hand that can form a shape with the tile
+pub fn connectivity(hand: &[u8; 34], tile: usize) -> u8 {
+    if tile >= 27 {
+        return hand[tile] - 1;
+    }
+    let suit_start = tile / 9 * 9;
+    let low = tile.saturating_sub(2).max(suit_start);
+    let high = (tile + 2).min(suit_start + 8);
+    (low..=high).map(|other| hand[other]).sum::<u8>() - 1
+}
+
+/// Play out the kyoku once, with our first discard forced to `first_discard` when the board is at our discard
+fn playout(state: &PlayerState, mut first_discard: Option<usize>, rng: &mut StdRng) -> Playout {
+    let mut pool = (0..34)
+        .flat_map(|tile| std::iter::repeat_n(tile, 4usize.saturating_sub(state.tiles_seen[tile] as usize)))
+        .collect::<Vec<_>>();
+    pool.shuffle(rng);
+
+    let mut seats: [Seat; 4] = std::array::from_fn(|player| {
+        if player == 0 {
+            return Seat {
+                hand: state.tehai,
+                len_div3: state.tehai_len_div3,
+                riichi: state.riichi_declared[0],
+            };
+        }
+        let melds = state.fuuro_overview[player].len() + state.ankan_overview[player].len();
+        let mut hand = [0; 34];
+        for _ in 0..13 - 3 * melds {
+            hand[pool.pop().expect("not enough unseen tiles")] += 1;
+        }
+        Seat {
+            hand,
+            len_div3: (4 - melds) as u8,
+            riichi: state.riichi_declared[player],
+        }
+    });
+    let mut wall = pool.into_iter().take(state.tiles_left as usize);
+
+    let oya = state.oya;
+    let win = |outcome: Outcome, kind: WinKind, winner: u8| {
+        let point = Point::calc(winner == oya, TYPICAL_FU, TYPICAL_HAN);
+        Playout {
+            outcome,
+            scores: apply_win(state.scores, winner, oya, point, kind, state.honba, state.kyotaku),
+            tenpai: false,
+        }
+    };
+
+    // the board is either at our discard or right after it
+    let (mut current, mut drawn) = if state.last_cans.can_discard {
+        (0, state.last_self_tsumo.map(|tile| tile.deaka().as_usize()))
+    } else {
+        let Some(tile) = wall.next() else {
+            return Playout {
+                outcome: Outcome::Draw,
+                scores: state.scores,
+                tenpai: false,
+            };
+        };
+        seats[1].hand[tile] += 1;
+        (1, Some(tile))
+    };
+    loop {
+        if drawn.is_some() && seats[current].shanten() == -1 {
+            let outcome = if current == 0 { Outcome::Win } else { Outcome::OtherWin };
+            return win(outcome, WinKind::Tsumo, current as u8);
+        }
+        let discard = if current == 0
+            && let Some(tile) = first_discard.take()
+        {
+            seats[0].hand[tile] -= 1;
+            tile
+        } else {
+            seats[current].choose_discard(drawn)
+        };
+        for offset in 1..4 {
+            let player = (current + offset) % 4;
+            seats[player].hand[discard] += 1;
+            let agari = seats[player].shanten() == -1;
+            seats[player].hand[discard] -= 1;
+            if agari {
+                let outcome = match (player, current) {
+                    (0, _) => Outcome::Win,
+                    (_, 0) => Outcome::DealIn,
+                    _ => Outcome::OtherWin,
+                };
+                return win(outcome, WinKind::Ron { target: current as u8 }, player as u8);
+            }
+        }
+
+        current = (current + 1) % 4;
+        let Some(tile) = wall.next() else {
+            let tenpai: [bool; 4] = std::array::from_fn(|player| seats[player].shanten() == 0);
+            return Playout {
+                outcome: Outcome::Draw,
+                scores: apply_ryukyoku(state.scores, tenpai),
+                tenpai: tenpai[0],
+            };
+        };
+        seats[current].hand[tile] += 1;
+        drawn = Some(tile);
+    }
+}
+
+fn rollout_from(state: &PlayerState, first_discard: Option<usize>, n: usize, mut rng: StdRng) -> RolloutStats {
+    let mut stats = RolloutStats::default();
+    for _ in 0..n {
+        stats.record(state, playout(state, first_discard, &mut rng));
+    }
+    stats
+}
+
+/// Play out the rest of the kyoku `n` times from the state
+#[cfg(feature = "cli")]
+pub fn rollout(state: &PlayerState, n: usize, seed: Option<u64>) -> RolloutStats {
+    let rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    rollout_from(state, None, n, rng)
+}
+
+/// Play out the rest of the kyoku `n` times after each discard candidate, best average placement first.
+/// Empty unless at our discard.
+pub fn rollout_candidates(state: &PlayerState, candidates: &[EventCandidate], n: usize) -> Vec<CandidateRollout> {
+    if n == 0 || !state.last_cans.can_discard {
+        return vec![];
+    }
+    let mut discards = vec![];
+    for discard in candidates.iter().filter_map(candidate_discard) {
+        if !discards.contains(&discard.deaka()) {
+            discards.push(discard.deaka());
+        }
+    }
+    let mut rollouts = discards
+        .into_iter()
+        .filter(|discard| state.tehai[discard.as_usize()] > 0)
+        .map(|discard| CandidateRollout {
+            discard,
+            stats: rollout_from(state, Some(discard.as_usize()), n, StdRng::seed_from_u64(CANDIDATE_SEED)),
+        })
+        .collect::<Vec<_>>();
+    rollouts.sort_by(|a, b| {
+        a.stats
+            .average_placement()
+            .total_cmp(&b.stats.average_placement())
+            .then(b.stats.average_delta().total_cmp(&a.stats.average_delta()))
+    });
+    rollouts
+}
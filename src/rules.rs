@@ -0,0 +1,311 @@
+//! Rule variants that are not part of mortal's ruleset
+use anyhow::{Context, Result, ensure};
+use riichi::algo::agari::{Agari, AgariWithYaku};
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+use riichi::{must_tile, t};
+
+use crate::yakunames::{AKA_DORA, DORA, URA_DORA};
+
+/// Tanyao's yaku id in tenhou's numbering, which mortal follows
+const TANYAO: u8 = 8;
+
+/// Rules of the room being analyzed, defaults to tenhou rules.
+/// Mortal's single player calculator always plays by tenhou rules, the other rules only adjust washizu's own
+/// agari and dora bookkeeping.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleSet {
+    /// Open riichi is allowed: the hand is revealed on declaration and is worth an extra han over riichi
+    pub open_riichi: bool,
+    /// Three player game, norths can be set aside as kita for an extra dora each
+    pub sanma: bool,
+    /// Red fives in the wall, at most one per suit since mortal's tiles have a single red five of each.
+    /// Suits without one are taken from manzu first.
+    pub aka: u8,
+    /// Open tanyao counts as a yaku
+    pub kuitan: bool,
+    /// Suuankou tanki, kokushi 13-sided, junsei chuuren and daisuushii are worth two yakuman
+    pub double_yakuman: bool,
+    /// 13 han and more are worth a yakuman instead of sanbaiman
+    pub kazoe_yakuman: bool,
+    /// 30 fu 4 han and 60 fu 3 han are rounded up to mangan
+    pub kiriage: bool,
+    /// Local yaku: ron before our first draw with no calls in between
+    pub renhou: Option<RenhouValue>,
+    /// Local yaku: closed 22334455667788p as a yakuman
+    pub daisharin: bool,
+    /// Washizu mahjong: three of every four tiles are transparent and visible in the opponents' hands
+    pub washizu: bool,
+}
+
+/// Value of renhou in rooms that enable it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenhouValue {
+    /// At least mangan, other yaku are counted if they are worth more
+    Mangan,
+    Yakuman,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self::tenhou()
+    }
+}
+
+impl RuleSet {
+    pub fn tenhou() -> Self {
+        Self {
+            open_riichi: false,
+            sanma: false,
+            aka: 3,
+            kuitan: true,
+            double_yakuman: false,
+            kazoe_yakuman: true,
+            kiriage: false,
+            renhou: None,
+            daisharin: false,
+            washizu: false,
+        }
+    }
+
+    pub fn majsoul() -> Self {
+        Self {
+            double_yakuman: true,
+            ..Self::tenhou()
+        }
+    }
+
+    /// Preset by name (tenhou or majsoul) or a toml file overriding tenhou rules, e.g. `aka = 0`
+    pub fn load(spec: &str) -> Result<Self> {
+        match spec {
+            "tenhou" => Ok(Self::tenhou()),
+            "majsoul" => Ok(Self::majsoul()),
+            path => {
+                let text = std::fs::read_to_string(path).with_context(|| format!("failed to read rules {path}"))?;
+                Self::from_toml(&text).with_context(|| format!("malformed rules {path}"))
+            }
+        }
+    }
+
+    /// Tenhou rules overridden by a toml document, for callers without a filesystem
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let rules: Self = toml::from_str(text)?;
+        ensure!(rules.aka <= 3, "at most 3 red fives are supported, one per suit");
+        Ok(rules)
+    }
+
+    /// Mark the red fives the wall does not contain as seen so they are never drawn
+    pub fn apply_aka(&self, state: &mut PlayerState) {
+        for seen in state.akas_seen.iter_mut().take(3 - self.aka.min(3) as usize) {
+            *seen = true;
+        }
+    }
+
+    /// Adjust mortal's agari of `tile` to the rules, the hand is complete when the tile is unknown
+    pub fn apply_to_agari(&self, state: &PlayerState, tile: Tile, agari: &mut Option<AgariWithYaku>) {
+        let Some(agari_with_yaku) = agari else {
+            return;
+        };
+        if let Agari::Normal { fu, han } = agari_with_yaku.agari
+            && !self.kuitan
+            && !state.is_menzen
+            && agari_with_yaku.yaku.contains(&TANYAO)
+        {
+            agari_with_yaku.yaku.retain(|&yaku| yaku != TANYAO);
+            // dora alone are no yaku
+            if agari_with_yaku
+                .yaku
+                .iter()
+                .all(|yaku| [DORA, URA_DORA, AKA_DORA].contains(yaku))
+            {
+                *agari = None;
+                return;
+            }
+            agari_with_yaku.agari = Agari::Normal { fu, han: han - 1 };
+        }
+        match agari_with_yaku.agari {
+            Agari::Normal { fu, han } if !self.kazoe_yakuman && han >= 13 => {
+                agari_with_yaku.agari = Agari::Normal { fu, han: 12 };
+            }
+            Agari::Yakuman(count) if self.double_yakuman => {
+                let winning = if tile == t!(?) { state.last_self_tsumo } else { Some(tile) };
+                if let Some(winning) = winning {
+                    agari_with_yaku.agari = Agari::Yakuman(count + double_yakuman_shapes(state, winning));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Add the enabled local yaku to mortal's agari of `tile`, returns the names of the local yaku added
+    pub fn apply_local_yaku(&self, state: &PlayerState, tile: Tile, agari: &mut Option<AgariWithYaku>) -> Vec<&'static str> {
+        let mut names = vec![];
+        let mut winning_hand = state.tehai;
+        if tile != t!(?) {
+            winning_hand[tile.deaka().as_usize()] += 1;
+        }
+        let daisharin_hand = (0..34).all(|tile| winning_hand[tile] == if (10..=16).contains(&tile) { 2 } else { 0 });
+        if self.daisharin && state.is_menzen && daisharin_hand {
+            *agari = Some(AgariWithYaku {
+                agari: Agari::Yakuman(1),
+                yaku: vec![],
+            });
+            names.push("Daisharin");
+        }
+        let first_go_around = state.at_turn == 0 && (0..4).all(|player| state.fuuro_overview[player].is_empty());
+        if let Some(value) = self.renhou
+            && tile != t!(?)
+            && !state.is_oya()
+            && first_go_around
+        {
+            let agari_with_yaku = agari.get_or_insert_with(|| AgariWithYaku {
+                agari: Agari::Normal { fu: 30, han: 0 },
+                yaku: vec![],
+            });
+            agari_with_yaku.agari = match (value, agari_with_yaku.agari) {
+                (RenhouValue::Yakuman, Agari::Yakuman(count)) => Agari::Yakuman(count + 1),
+                (RenhouValue::Yakuman, Agari::Normal { .. }) => {
+                    // a yakuman replaces the normal yaku
+                    agari_with_yaku.yaku.clear();
+                    Agari::Yakuman(1)
+                }
+                (RenhouValue::Mangan, Agari::Normal { fu, han }) => Agari::Normal { fu, han: han.max(5) },
+                (RenhouValue::Mangan, yakuman) => yakuman,
+            };
+            names.push("Renhou");
+        }
+        names
+    }
+}
+
+/// Yakuman of a winning hand that count double, judged from the shape before the winning tile
+fn double_yakuman_shapes(state: &PlayerState, winning: Tile) -> u8 {
+    let winning = winning.deaka().as_usize();
+    let mut before = state.tehai;
+    if state.last_cans.can_discard {
+        before[winning] -= 1;
+    }
+    let kokushi = (0..34)
+        .filter(|&tile| must_tile!(tile).is_yaokyuu())
+        .all(|tile| before[tile] == 1);
+    let junsei_chuuren = state.tehai_len_div3 == 4
+        && (0..3).any(|suit| {
+            let counts = &before[suit * 9..suit * 9 + 9];
+            counts == [3, 1, 1, 1, 1, 1, 1, 1, 3] && before.iter().sum::<u8>() == 13
+        });
+    let suuankou_tanki = state.is_menzen
+        && before[winning] == 1
+        && before
+            .iter()
+            .enumerate()
+            .all(|(tile, &count)| tile == winning || count == 0 || count == 3);
+    let mut winds = [0; 4];
+    for (wind, count) in winds.iter_mut().enumerate() {
+        let is_wind = |called: &&Tile| called.deaka().as_usize() == 27 + wind;
+        let called = state.fuuro_overview[0].iter().flatten().filter(is_wind).count()
+            + 4 * state.ankan_overview[0].iter().filter(is_wind).count();
+        *count = before[27 + wind] as usize + (winning == 27 + wind) as usize + called;
+    }
+    let daisuushii = winds.iter().all(|&count| count >= 3);
+    [kokushi, junsei_chuuren, suuankou_tanki, daisuushii]
+        .into_iter()
+        .filter(|&double| double)
+        .count() as u8
+}
+
+/// Riichi declared open, mjai logs have no event for it so it has to be given explicitly
+#[derive(Debug, Clone)]
+pub struct OpenRiichi {
+    /// Player relative to us
+    pub player: u8,
+    /// Revealed waits, empty for our own riichi
+    pub waits: Vec<Tile>,
+}
+
+impl OpenRiichi {
+    /// Parse `<relative player>:<waits>`, e.g. `1:36m`
+    pub fn parse(s: &str) -> Result<Self> {
+        let (player, waits) = s.split_once(':').context("open riichi must be <player>:<waits>")?;
+        let player = player.parse().context("incorrect open riichi player")?;
+        ensure!(player < 4, "open riichi player must be relative to us (0-3)");
+        Ok(Self {
+            player,
+            waits: crate::input::hand_with_aka_vec(waits)?,
+        })
+    }
+}
+
+/// Add the extra han of open riichi to an agari
+pub fn add_open_riichi_han(agari: &mut AgariWithYaku) {
+    if let Agari::Normal { fu, han } = agari.agari {
+        agari.agari = Agari::Normal { fu, han: han + 1 };
+    }
+}
+
+/// Estimated ratio by which a hand's value grows when it gains `han` han, given its average points as a non-dealer
+pub fn han_gain_ratio(average_points: f32, han: u8) -> f32 {
+    let mut points = average_points;
+    for _ in 0..han {
+        points *= match points {
+            p if p < 4000.0 => 2.0,
+            p if p < 8000.0 => 8000.0 / p,
+            p if p < 12000.0 => 1.5,
+            p if p < 16000.0 => 1.2,
+            _ => 1.1,
+        };
+    }
+    if average_points > 0.0 { points / average_points } else { 1.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Yakuhai haku in tenhou's numbering
+    const HAKU: u8 = 18;
+
+    fn open_agari(yaku: Vec<u8>) -> Option<AgariWithYaku> {
+        Some(AgariWithYaku {
+            agari: Agari::Normal {
+                fu: 30,
+                han: yaku.len() as u8,
+            },
+            yaku,
+        })
+    }
+
+    #[test]
+    fn open_tanyao_loses_its_han_without_kuitan() {
+        let rules = RuleSet {
+            kuitan: false,
+            ..RuleSet::tenhou()
+        };
+        let state = PlayerState::default();
+        let mut agari = open_agari(vec![TANYAO, HAKU]);
+        rules.apply_to_agari(&state, t!(5m), &mut agari);
+        let agari = agari.unwrap();
+        assert!(matches!(agari.agari, Agari::Normal { fu: 30, han: 1 }));
+        assert_eq!(agari.yaku, [HAKU]);
+
+        let mut dora_only = open_agari(vec![TANYAO, DORA]);
+        rules.apply_to_agari(&state, t!(5m), &mut dora_only);
+        assert!(dora_only.is_none());
+
+        let mut closed = open_agari(vec![TANYAO]);
+        let menzen = PlayerState {
+            is_menzen: true,
+            ..Default::default()
+        };
+        rules.apply_to_agari(&menzen, t!(5m), &mut closed);
+        assert!(matches!(closed.unwrap().agari, Agari::Normal { han: 1, .. }));
+    }
+
+    #[test]
+    fn open_tanyao_counts_with_kuitan() {
+        let mut agari = open_agari(vec![TANYAO, HAKU]);
+        RuleSet::tenhou().apply_to_agari(&PlayerState::default(), t!(5m), &mut agari);
+        assert!(matches!(agari.unwrap().agari, Agari::Normal { han: 2, .. }));
+    }
+}
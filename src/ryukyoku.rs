@@ -0,0 +1,85 @@
+//! Tenpai and noten payments at an exhaustive draw
+//! The single player tables value a kyoku that ends without a win at 0. Within the tsumo-only model a kyoku we do not
+//! win ends in an exhaustive draw, where being tenpai is worth the noten payments of the others.
+//! The value is kept apart from the candidates so their win chances and averages stay the calculator's, it is only
+//! added where the total expected value over every tsumo left is shown or ranked.
+use riichi::algo::sp::EventCandidate;
+use riichi::state::PlayerState;
+
+use crate::scoring::apply_ryukyoku;
+use crate::waitmodel::tenpai_prior;
+
+/// Our expected score change at an exhaustive draw
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy)]
+pub struct DrawPayments {
+    pub tenpai: f32,
+    pub noten: f32,
+}
+
+/// Expected payments at a draw, each opponent is tenpai with the chance the defense reads from their riichi and calls
+pub fn draw_payments(state: &PlayerState) -> DrawPayments {
+    let opponent_tenpai = |player: usize| tenpai_prior(state, player);
+    let expected = |we_are_tenpai: bool| {
+        (0..8)
+            .map(|mask: usize| {
+                let tenpai: [bool; 4] = std::array::from_fn(|player| match player {
+                    0 => we_are_tenpai,
+                    player => (mask >> (player - 1)) & 1 == 1,
+                });
+                let prob = (1..4)
+                    .map(|player| {
+                        if tenpai[player] {
+                            opponent_tenpai(player)
+                        } else {
+                            1.0 - opponent_tenpai(player)
+                        }
+                    })
+                    .product::<f32>();
+                prob * apply_ryukyoku([0; 4], tenpai)[0] as f32
+            })
+            .sum()
+    };
+    DrawPayments {
+        tenpai: expected(true),
+        noten: expected(false),
+    }
+}
+
+/// Value of the payments of a draw reached without winning, over every tsumo left
+pub fn draw_value(candidate: &EventCandidate, payments: DrawPayments) -> f32 {
+    let win_prob = candidate.win_probs.first().copied().unwrap_or(0.0);
+    let tenpai_prob = candidate.tenpai_probs.first().copied().unwrap_or(0.0);
+    (tenpai_prob - win_prob).max(0.0) * payments.tenpai + (1.0 - tenpai_prob) * payments.noten
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::t;
+    use tinyvec::ArrayVec;
+
+    use super::*;
+
+    #[test]
+    fn opponents_in_riichi_are_always_tenpai() {
+        let state = PlayerState {
+            riichi_declared: [false, true, true, true],
+            ..Default::default()
+        };
+        let payments = draw_payments(&state);
+        assert_eq!(payments.tenpai, 0.0);
+        assert_eq!(payments.noten, -3000.0);
+    }
+
+    #[test]
+    fn calls_make_opponents_more_likely_tenpai() {
+        let quiet = draw_payments(&PlayerState::default());
+        let mut state = PlayerState::default();
+        for tile in [t!(E), t!(S)] {
+            state.fuuro_overview[1].push(ArrayVec::from_array_len([tile; 4], 3));
+        }
+        let calling = draw_payments(&state);
+        assert!(calling.tenpai < quiet.tenpai);
+        assert!(calling.noten < quiet.noten);
+        assert!(quiet.tenpai > 0.0 && quiet.noten < 0.0);
+    }
+}
@@ -0,0 +1,169 @@
+//! Kita (nuki-dora) handling for sanma
+//! Mortal's state has no three player support, nukidora is tracked next to it and only removes the north from the state.
+//! The extra dora is accounted for by washizu so mortal's own dora counting is left untouched.
+#[cfg(feature = "cli")]
+use anyhow::{Context, Result, ensure};
+use riichi::algo::agari::{Agari, AgariWithYaku};
+use riichi::algo::sp::EventCandidate;
+#[cfg(feature = "cli")]
+use riichi::mjai::Event;
+#[cfg(feature = "cli")]
+use riichi::state::ActionCandidate;
+use riichi::state::PlayerState;
+use riichi::t;
+#[cfg(feature = "cli")]
+use riichi::tile::Tile;
+
+use crate::defense::candidate_discard;
+use crate::rules::han_gain_ratio;
+#[cfg(feature = "cli")]
+use crate::variant::normalize_player_count;
+
+/// Event of a log that may contain sanma or washizu only events
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone)]
+pub enum LogEvent {
+    Mjai(Event),
+    /// North set aside as dora, followed by a replacement tsumo
+    Nukidora {
+        actor: u8,
+    },
+    /// Transparent tiles currently in a player's hand, replacing the ones given before
+    Transparent {
+        actor: u8,
+        tiles: Vec<Tile>,
+    },
+}
+
+#[cfg(feature = "cli")]
+#[derive(serde::Deserialize)]
+struct RawNukidora {
+    actor: u8,
+}
+
+#[cfg(feature = "cli")]
+#[derive(serde::Deserialize)]
+struct RawTransparent {
+    actor: u8,
+    pai: Vec<Tile>,
+}
+
+/// Parse a line of a mjai log, accepting `{"type":"nukidora","actor":0,"pai":"N"}` and
+/// `{"type":"transparent","actor":1,"pai":["1m","2m"]}` on top of mjai events
+#[cfg(feature = "cli")]
+pub fn parse_log_event(line: &str) -> Result<LogEvent> {
+    let mut value: serde_json::Value = serde_json::from_str(line).context("malformed json")?;
+    normalize_player_count(&mut value)?;
+    if value.get("type").and_then(|t| t.as_str()) == Some("nukidora") {
+        let raw: RawNukidora = serde_json::from_value(value).context("malformed nukidora")?;
+        return Ok(LogEvent::Nukidora { actor: raw.actor });
+    }
+    if value.get("type").and_then(|t| t.as_str()) == Some("transparent") {
+        let raw: RawTransparent = serde_json::from_value(value).context("malformed transparent")?;
+        return Ok(LogEvent::Transparent {
+            actor: raw.actor,
+            tiles: raw.pai,
+        });
+    }
+    Ok(LogEvent::Mjai(serde_json::from_value(value).context("malformed mjai event")?))
+}
+
+/// Kita set aside by each player relative to us
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Default)]
+pub struct Nukidora {
+    pub counts: [u8; 4],
+}
+
+#[cfg(feature = "cli")]
+impl Nukidora {
+    /// Apply a nukidora of an absolute actor to the state
+    pub fn apply(&mut self, state: &mut PlayerState, actor: u8) -> Result<()> {
+        ensure!(actor < 4, "nukidora actor out of range");
+        let player = ((actor + 4 - state.player_id) % 4) as usize;
+        let north = t!(N).as_usize();
+        if player == 0 {
+            state.tehai[north] = state.tehai[north]
+                .checked_sub(1)
+                .context("nukidora without a north in hand")?;
+        } else {
+            state.tiles_seen[north] = state.tiles_seen[north]
+                .checked_add(1)
+                .filter(|&seen| seen <= 4)
+                .context("nukidora of a fifth north")?;
+        }
+        self.counts[player] = self.counts[player].checked_add(1).context("too many nukidora")?;
+        // waiting for the replacement tsumo
+        state.last_cans = ActionCandidate::default();
+        Ok(())
+    }
+
+    /// Reset at the start of every kyoku
+    pub fn update(&mut self, event: &Event) {
+        if matches!(event, Event::StartKyoku { .. }) {
+            self.counts = [0; 4];
+        }
+    }
+}
+
+/// Add the han of our kita to an agari
+pub fn add_nukidora_han(agari: &mut AgariWithYaku, count: u8) {
+    if let Agari::Normal { fu, han } = agari.agari {
+        agari.agari = Agari::Normal { fu, han: han + count };
+    }
+}
+
+/// Scale a candidate's expected values by the extra han of our kita
+pub fn apply_nukidora_dora(candidate: &mut EventCandidate, count: u8, is_oya: bool) {
+    for (exp_value, win_prob) in candidate.exp_values.iter_mut().zip(&candidate.win_probs) {
+        if *win_prob <= 0.0 {
+            continue;
+        }
+        let average = *exp_value / win_prob;
+        let non_dealer_average = if is_oya { average / 1.5 } else { average };
+        *exp_value *= han_gain_ratio(non_dealer_average, count);
+    }
+}
+
+/// Estimated expected value of setting a north aside and of the best candidate keeping it.
+/// Nuki keeps the same shape as discarding the north but gains a dora and a replacement tsumo.
+pub fn nuki_values(state: &PlayerState, candidates: &[EventCandidate]) -> Option<(f32, f32)> {
+    if !state.last_cans.can_discard || state.tehai[t!(N).as_usize()] == 0 {
+        return None;
+    }
+    let is_north = |candidate: &&EventCandidate| candidate_discard(candidate).is_some_and(|tile| tile == t!(N));
+    let discard_north = candidates.iter().find(is_north)?;
+    let exp_value = discard_north.exp_values.first().copied().unwrap_or(0.0);
+    let win_prob = discard_north.win_probs.first().copied().unwrap_or(0.0);
+    let average = if win_prob > 0.0 { exp_value / win_prob } else { 0.0 };
+    let non_dealer_average = if state.is_oya() { average / 1.5 } else { average };
+    let nuki = exp_value * han_gain_ratio(non_dealer_average, 1);
+    let keep = candidates
+        .iter()
+        .filter(|candidate| !is_north(candidate))
+        .filter_map(|candidate| candidate.exp_values.first().copied())
+        .fold(0.0, f32::max);
+    Some((nuki, keep))
+}
+
+#[cfg(all(test, feature = "cli"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nukidora_needs_a_north_to_set_aside() {
+        let mut state = PlayerState::new(0);
+        let mut nukidora = Nukidora::default();
+        assert!(nukidora.apply(&mut state, 0).is_err());
+        assert_eq!(nukidora.counts, [0; 4]);
+        state.tehai[t!(N).as_usize()] = 1;
+        nukidora.apply(&mut state, 0).unwrap();
+        assert_eq!(state.tehai[t!(N).as_usize()], 0);
+        for _ in 0..4 {
+            nukidora.apply(&mut state, 2).unwrap();
+        }
+        assert!(nukidora.apply(&mut state, 2).is_err(), "there are only four norths");
+        assert!(nukidora.apply(&mut state, 4).is_err());
+        assert_eq!(nukidora.counts, [1, 0, 4, 0]);
+    }
+}
@@ -0,0 +1,93 @@
+//! JSON schemas of the analysis output and a validator for the subset of JSON Schema they use
+//! The schemas live in `schemas/` as the contract for integrators, only `type`, `required`, `properties`, `items`,
+//! `prefixItems` and `$ref` between the bundled schemas are checked.
+use anyhow::{Context, Result, bail};
+use serde_json::Value;
+
+/// Bundled schemas by name
+pub const SCHEMAS: [(&str, &str); 4] = [
+    ("analysis-report", include_str!("../schemas/analysis-report.schema.json")),
+    ("board", include_str!("../schemas/board.schema.json")),
+    ("candidate", include_str!("../schemas/candidate.schema.json")),
+    ("danger", include_str!("../schemas/danger.schema.json")),
+];
+
+/// Text of a bundled schema
+pub fn schema_text(name: &str) -> Result<&'static str> {
+    let names = SCHEMAS.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+    SCHEMAS
+        .iter()
+        .find(|(schema, _)| *schema == name)
+        .map(|(_, text)| *text)
+        .with_context(|| format!("unknown schema {name}, expected one of {}", names.join(", ")))
+}
+
+fn schema(name: &str) -> Result<Value> {
+    serde_json::from_str(schema_text(name)?).with_context(|| format!("malformed schema {name}"))
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => false,
+    }
+}
+
+fn check(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) -> Result<()> {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let name = reference.trim_end_matches(".schema.json");
+        return check(&self::schema(name)?, value, path, errors);
+    }
+    let types = match schema.get("type") {
+        Some(Value::String(expected)) => vec![expected.as_str()],
+        Some(Value::Array(expected)) => expected.iter().filter_map(Value::as_str).collect(),
+        _ => vec![],
+    };
+    if !types.is_empty() && !types.iter().any(|expected| type_matches(expected, value)) {
+        errors.push(format!("{path}: expected {}, found {value}", types.join(" or ")));
+        return Ok(());
+    }
+    if let Some(object) = value.as_object() {
+        for field in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+            if let Some(field) = field.as_str()
+                && !object.contains_key(field)
+            {
+                errors.push(format!("{path}: missing field {field}"));
+            }
+        }
+        for (field, field_schema) in schema.get("properties").and_then(Value::as_object).into_iter().flatten() {
+            if let Some(field_value) = object.get(field) {
+                check(field_schema, field_value, &format!("{path}/{field}"), errors)?;
+            }
+        }
+    }
+    if let Some(array) = value.as_array() {
+        let prefix = schema
+            .get("prefixItems")
+            .and_then(Value::as_array)
+            .map_or(&[][..], Vec::as_slice);
+        for (index, item) in array.iter().enumerate() {
+            let item_schema = prefix.get(index).or_else(|| schema.get("items"));
+            if let Some(item_schema) = item_schema {
+                check(item_schema, item, &format!("{path}/{index}"), errors)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check a value against a bundled schema, the error lists every mismatch by JSON pointer
+pub fn validate(name: &str, value: &Value) -> Result<()> {
+    let mut errors = vec![];
+    check(&schema(name)?, value, "", &mut errors)?;
+    if !errors.is_empty() {
+        bail!("output does not match schema {name}:\n{}", errors.join("\n"));
+    }
+    Ok(())
+}
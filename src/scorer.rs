@@ -0,0 +1,284 @@
+//! Pluggable decision criteria for ranking candidates
+//! Scorers can be composed at runtime with a spec such as `ev+safety*100`.
+use std::fmt::Debug;
+use std::sync::Arc;
+
+#[cfg(feature = "cli")]
+use anyhow::{Context, Result, bail};
+#[cfg(feature = "cli")]
+use riichi::algo::point::Point;
+use riichi::algo::sp::EventCandidate;
+use riichi::state::PlayerState;
+use riichi::t;
+
+#[cfg(feature = "cli")]
+use crate::defense::candidate_discard;
+#[cfg(feature = "cli")]
+use crate::scoring::{RankPoints, WinKind, apply_win, expected_rank_points, placements_after};
+
+/// Danger of the board from our perspective
+pub struct DangerContext {
+    /// Danger of discarding each tile summed over all opponents
+    pub combined: [f32; 34],
+}
+
+/// Score situation of the board
+pub struct PlacementContext {
+    /// Absolute scores
+    pub scores: [i32; 4],
+    pub player_id: u8,
+    pub oya: u8,
+    pub honba: u8,
+    pub kyotaku: u8,
+    /// Kyoku left after the current one, assuming a hanchan ending with south 4
+    pub kyoku_left: u8,
+}
+
+impl PlacementContext {
+    pub fn from_state(state: &PlayerState) -> Self {
+        let mut scores = state.scores;
+        scores.rotate_right(state.player_id as usize);
+        Self {
+            scores,
+            player_id: state.player_id,
+            oya: (state.oya + state.player_id) % 4,
+            honba: state.honba,
+            kyotaku: state.kyotaku,
+            kyoku_left: if state.is_all_last {
+                0
+            } else {
+                let played = (state.bakaze.as_u8() - t!(E).as_u8()) * 4 + state.kyoku;
+                7u8.saturating_sub(played)
+            },
+        }
+    }
+
+    /// Scores after we tsumo a hand worth `average` points
+    #[cfg(feature = "cli")]
+    fn after_tsumo(&self, average: i32) -> [i32; 4] {
+        let point = if self.player_id == self.oya {
+            Point {
+                ron: average,
+                tsumo_oya: 0,
+                tsumo_ko: average / 3,
+            }
+        } else {
+            Point {
+                ron: average,
+                tsumo_oya: average / 2,
+                tsumo_ko: average / 4,
+            }
+        };
+        apply_win(
+            self.scores,
+            self.player_id,
+            self.oya,
+            point,
+            WinKind::Tsumo,
+            self.honba,
+            self.kyotaku,
+        )
+    }
+}
+
+/// Value of a candidate according to a scorer, higher is better
+#[derive(Debug, Clone)]
+pub struct Score {
+    pub value: f32,
+    pub explanation: String,
+}
+
+pub trait CandidateScorer: Debug + Send + Sync {
+    fn score(&self, candidate: &EventCandidate, danger: &DangerContext, placement: &PlacementContext) -> Score;
+}
+
+/// Expected value over all remaining tsumos
+#[cfg(feature = "cli")]
+#[derive(Debug)]
+pub struct ExpectedValueScorer;
+
+#[cfg(feature = "cli")]
+impl CandidateScorer for ExpectedValueScorer {
+    fn score(&self, candidate: &EventCandidate, _: &DangerContext, _: &PlacementContext) -> Score {
+        let value = candidate.exp_values.first().copied().unwrap_or(0.0);
+        Score {
+            value,
+            explanation: format!("ev {}", value.round()),
+        }
+    }
+}
+
+/// Win probability over all remaining tsumos
+#[cfg(feature = "cli")]
+#[derive(Debug)]
+pub struct WinProbabilityScorer;
+
+#[cfg(feature = "cli")]
+impl CandidateScorer for WinProbabilityScorer {
+    fn score(&self, candidate: &EventCandidate, _: &DangerContext, _: &PlacementContext) -> Score {
+        let value = candidate.win_probs.first().copied().unwrap_or(0.0);
+        Score {
+            value,
+            explanation: format!("win {:.2}%", value * 100.0),
+        }
+    }
+}
+
+/// Expected placement gain from winning the average hand of the candidate by tsumo
+#[cfg(feature = "cli")]
+#[derive(Debug)]
+pub struct PlacementScorer;
+
+#[cfg(feature = "cli")]
+impl CandidateScorer for PlacementScorer {
+    fn score(&self, candidate: &EventCandidate, _: &DangerContext, placement: &PlacementContext) -> Score {
+        let exp_value = candidate.exp_values.first().copied().unwrap_or(0.0);
+        let win_prob = candidate.win_probs.first().copied().unwrap_or(0.0);
+        if win_prob <= 0.0 {
+            return Score {
+                value: 0.0,
+                explanation: "placement +0.00".to_owned(),
+            };
+        }
+        let before = placements_after(placement.scores)[placement.player_id as usize];
+        let after_scores = placement.after_tsumo((exp_value / win_prob) as i32);
+        let after = placements_after(after_scores)[placement.player_id as usize];
+        let value = win_prob * (before as f32 - after as f32);
+        Score {
+            value,
+            explanation: format!("placement {value:+.2}"),
+        }
+    }
+}
+
+/// Expected rank points at the end of the game, winning the average hand of the candidate by tsumo or nothing changing
+#[cfg(feature = "cli")]
+#[derive(Debug)]
+pub struct RankPointScorer {
+    pub points: RankPoints,
+    /// Value of a typical hand the spread of the final scores is estimated from, see `kyoku_score_sd`
+    pub hand_value: f32,
+}
+
+#[cfg(feature = "cli")]
+impl CandidateScorer for RankPointScorer {
+    fn score(&self, candidate: &EventCandidate, _: &DangerContext, placement: &PlacementContext) -> Score {
+        let exp_value = candidate.exp_values.first().copied().unwrap_or(0.0);
+        let win_prob = candidate.win_probs.first().copied().unwrap_or(0.0);
+        let expected = |scores| {
+            expected_rank_points(
+                scores,
+                placement.player_id,
+                placement.kyoku_left,
+                &self.points,
+                self.hand_value,
+            )
+        };
+        let mut value = expected(placement.scores);
+        if win_prob > 0.0 {
+            let after_scores = placement.after_tsumo((exp_value / win_prob) as i32);
+            value += win_prob * (expected(after_scores) - value);
+        }
+        Score {
+            value,
+            explanation: format!("pt {value:+.1}"),
+        }
+    }
+}
+
+/// Negative combined danger of the discarded tile, calls and other actions are considered safe
+#[cfg(feature = "cli")]
+#[derive(Debug)]
+pub struct SafetyScorer;
+
+#[cfg(feature = "cli")]
+impl CandidateScorer for SafetyScorer {
+    fn score(&self, candidate: &EventCandidate, danger: &DangerContext, _: &PlacementContext) -> Score {
+        let value = candidate_discard(candidate).map_or(0.0, |tile| -danger.combined[tile.deaka().as_usize()]);
+        Score {
+            value,
+            explanation: format!("safety {value:.1}"),
+        }
+    }
+}
+
+/// Weighted sum of other scorers
+#[derive(Debug, Clone)]
+pub struct WeightedScorer {
+    pub scorers: Vec<(f32, Arc<dyn CandidateScorer>)>,
+}
+
+impl CandidateScorer for WeightedScorer {
+    fn score(&self, candidate: &EventCandidate, danger: &DangerContext, placement: &PlacementContext) -> Score {
+        let scores = self
+            .scorers
+            .iter()
+            .map(|(weight, scorer)| (weight, scorer.score(candidate, danger, placement)))
+            .collect::<Vec<_>>();
+        Score {
+            value: scores.iter().map(|(weight, score)| *weight * score.value).sum(),
+            explanation: scores
+                .iter()
+                .map(|(weight, score)| {
+                    if **weight == 1.0 {
+                        score.explanation.clone()
+                    } else {
+                        format!("{}*{weight}", score.explanation)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" + "),
+        }
+    }
+}
+
+/// What candidates are ranked by without a custom scorer
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Objective {
+    /// Raw expected points of the single player tables
+    #[default]
+    Points,
+    /// Expected rank points at the end of the game
+    Placement,
+}
+
+impl Objective {
+    /// Scorer ranking the candidates, None when the expected points are used as they are
+    #[cfg(feature = "cli")]
+    pub fn scorer(self, points: RankPoints, hand_value: f32) -> Option<WeightedScorer> {
+        match self {
+            Self::Points => None,
+            Self::Placement => Some(WeightedScorer {
+                scorers: vec![(1.0, Arc::new(RankPointScorer { points, hand_value }))],
+            }),
+        }
+    }
+}
+
+/// Built-in scorer by name: ev, win, placement or safety
+#[cfg(feature = "cli")]
+pub fn builtin_scorer(name: &str) -> Result<Arc<dyn CandidateScorer>> {
+    Ok(match name {
+        "ev" => Arc::new(ExpectedValueScorer),
+        "win" => Arc::new(WinProbabilityScorer),
+        "placement" => Arc::new(PlacementScorer),
+        "safety" => Arc::new(SafetyScorer),
+        _ => bail!("unknown scorer {name}, expected one of ev, win, placement, safety"),
+    })
+}
+
+/// Parse a scorer spec of `+` separated terms `<name>` or `<name>*<weight>`, e.g. `ev+safety*100`
+#[cfg(feature = "cli")]
+pub fn parse_scorer(spec: &str) -> Result<WeightedScorer> {
+    let scorers = spec
+        .split('+')
+        .map(|term| {
+            let (name, weight) = match term.trim().split_once('*') {
+                Some((name, weight)) => (name.trim(), weight.trim().parse().context("incorrect scorer weight")?),
+                None => (term.trim(), 1.0),
+            };
+            Ok((weight, builtin_scorer(name)?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(WeightedScorer { scorers })
+}
@@ -0,0 +1,222 @@
+//! Score and placement arithmetic
+//! All scores are absolute (seat 0 is the starting oya) unless stated otherwise.
+#[cfg(feature = "cli")]
+use anyhow::{Context, Result};
+use riichi::algo::point::Point;
+
+/// Points of a single riichi stick
+pub const KYOTAKU_POINTS: i32 = 1000;
+/// Points of a single honba, split evenly between the payers on tsumo
+pub const HONBA_POINTS: i32 = 300;
+/// Total noten bappu paid at an exhaustive draw
+pub const NOTEN_PAYMENT: i32 = 3000;
+
+/// How a hand was won
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WinKind {
+    /// Ron off the given seat's discard
+    Ron {
+        target: u8,
+    },
+    Tsumo,
+}
+
+/// Points the winner gets on top of the hand value from honba and riichi sticks
+pub fn extra_points(honba: u8, kyotaku: u8) -> i32 {
+    honba as i32 * HONBA_POINTS + kyotaku as i32 * KYOTAKU_POINTS
+}
+
+/// Scores after `winner` wins a hand worth `point`.
+/// `point` must be calculated for the winner's oya status, all riichi sticks on the table go to the winner.
+pub fn apply_win(scores: [i32; 4], winner: u8, oya: u8, point: Point, kind: WinKind, honba: u8, kyotaku: u8) -> [i32; 4] {
+    let mut scores = scores;
+    let mut gained = kyotaku as i32 * KYOTAKU_POINTS;
+    match kind {
+        WinKind::Ron { target } => {
+            let payment = point.ron + honba as i32 * HONBA_POINTS;
+            scores[target as usize] -= payment;
+            gained += payment;
+        }
+        WinKind::Tsumo => {
+            for payer in (0..4).filter(|&payer| payer != winner) {
+                let payment = if payer == oya { point.tsumo_oya } else { point.tsumo_ko } + honba as i32 * HONBA_POINTS / 3;
+                scores[payer as usize] -= payment;
+                gained += payment;
+            }
+        }
+    }
+    scores[winner as usize] += gained;
+    scores
+}
+
+/// Scores after an exhaustive draw with the given players in tenpai.
+/// Riichi sticks stay on the table and are not included.
+pub fn apply_ryukyoku(scores: [i32; 4], tenpai: [bool; 4]) -> [i32; 4] {
+    let tenpai_count = tenpai.iter().filter(|&&t| t).count() as i32;
+    if tenpai_count == 0 || tenpai_count == 4 {
+        return scores;
+    }
+    let mut scores = scores;
+    for (score, is_tenpai) in scores.iter_mut().zip(tenpai) {
+        if is_tenpai {
+            *score += NOTEN_PAYMENT / tenpai_count;
+        } else {
+            *score -= NOTEN_PAYMENT / (4 - tenpai_count);
+        }
+    }
+    scores
+}
+
+/// Placement of each seat (0 is first), ties are broken in favor of the seat closer to the starting oya
+pub fn placements_after(scores: [i32; 4]) -> [u8; 4] {
+    let mut placements = [0; 4];
+    for (seat, placement) in placements.iter_mut().enumerate() {
+        *placement = (0..4)
+            .filter(|&other| scores[other] > scores[seat] || (scores[other] == scores[seat] && other < seat))
+            .count() as u8;
+    }
+    placements
+}
+
+/// Standard deviation of a player's score change over a single kyoku when a typical hand is worth `hand_value`.
+/// Every kyoku one of the four players wins such a hand off another, so a player gains it a quarter of the time and
+/// pays it a quarter of the time; draws and split tsumo payments only narrow the spread.
+pub fn kyoku_score_sd(hand_value: f32) -> f32 {
+    hand_value / std::f32::consts::SQRT_2
+}
+
+/// Rank points for finishing first to fourth
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RankPoints(pub [f32; 4]);
+
+impl RankPoints {
+    /// Parse four comma separated values from first to fourth, e.g. `90,45,0,-135`
+    #[cfg(feature = "cli")]
+    pub fn parse(s: &str) -> Result<Self> {
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<f32>().context("incorrect rank points"))
+            .collect::<Result<Vec<_>>>()?;
+        let values: [f32; 4] = values
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("rank points must be four values from first to fourth"))?;
+        Ok(Self(values))
+    }
+}
+
+/// Standard normal cumulative distribution, Abramowitz and Stegun 7.1.26
+fn normal_cdf(x: f32) -> f32 {
+    let z = x.abs() / std::f32::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.327_591_1 * z);
+    let poly = t * (0.254_829_6 + t * (-0.284_496_7 + t * (1.421_413_7 + t * (-1.453_152 + t * 1.061_405_4))));
+    let erf = 1.0 - poly * (-z * z).exp();
+    if x >= 0.0 { 0.5 * (1.0 + erf) } else { 0.5 * (1.0 - erf) }
+}
+
+/// Expected rank points of `seat` at the end of the game with `kyoku_left` kyoku to go.
+/// Every player's final score is the current one plus independent normal noise per kyoku with the spread of
+/// `kyoku_score_sd`, with no kyoku left the current placement is final.
+pub fn expected_rank_points(scores: [i32; 4], seat: u8, kyoku_left: u8, points: &RankPoints, hand_value: f32) -> f32 {
+    if kyoku_left == 0 {
+        return points.0[placements_after(scores)[seat as usize] as usize];
+    }
+    // the gap to an opponent carries the noise of both scores
+    let sd = kyoku_score_sd(hand_value) * (2.0 * kyoku_left as f32).sqrt();
+    // chance of finishing at each placement, each opponent finishes above us independently
+    let mut placement_probs = [1.0, 0.0, 0.0, 0.0];
+    for other in (0..4).filter(|&other| other != seat as usize) {
+        let above = normal_cdf((scores[other] - scores[seat as usize]) as f32 / sd);
+        for placement in (0..4).rev() {
+            let stay = placement_probs[placement] * (1.0 - above);
+            let moved = if placement > 0 {
+                placement_probs[placement - 1] * above
+            } else {
+                0.0
+            };
+            placement_probs[placement] = stay + moved;
+        }
+    }
+    placement_probs.iter().zip(points.0).map(|(prob, points)| prob * points).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCORES: [i32; 4] = [25000; 4];
+
+    fn point(ron: i32, tsumo_oya: i32, tsumo_ko: i32) -> Point {
+        Point {
+            ron,
+            tsumo_oya,
+            tsumo_ko,
+        }
+    }
+
+    #[test]
+    fn ron_is_paid_by_the_discarder_with_honba_and_sticks() {
+        let scores = apply_win(SCORES, 2, 0, point(3900, 2000, 1000), WinKind::Ron { target: 1 }, 2, 1);
+        assert_eq!(scores, [25000, 20500, 30500, 25000]);
+        assert_eq!(scores.iter().sum::<i32>(), 100000 + KYOTAKU_POINTS);
+    }
+
+    #[test]
+    fn tsumo_is_paid_by_everyone_with_the_dealer_paying_more() {
+        let scores = apply_win(SCORES, 1, 0, point(3900, 2000, 1000), WinKind::Tsumo, 1, 0);
+        assert_eq!(scores, [22900, 29300, 23900, 23900]);
+        // the dealer's tsumo is paid evenly
+        let scores = apply_win(SCORES, 0, 0, point(5800, 2000, 2000), WinKind::Tsumo, 0, 2);
+        assert_eq!(scores, [33000, 23000, 23000, 23000]);
+    }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn rank_points_are_four_values_from_first_to_fourth() {
+        assert_eq!(
+            RankPoints::parse("90, 45,0,-135").unwrap(),
+            RankPoints([90.0, 45.0, 0.0, -135.0])
+        );
+        assert!(RankPoints::parse("90,45,0").is_err());
+        assert!(RankPoints::parse("90,45,0,x").is_err());
+    }
+
+    #[test]
+    fn expected_rank_points_spread_with_the_kyoku_left() {
+        let points = RankPoints([90.0, 45.0, 0.0, -135.0]);
+        let scores = [35000, 25000, 22000, 18000];
+        let expected = |seat, kyoku_left, hand_value| expected_rank_points(scores, seat, kyoku_left, &points, hand_value);
+        assert_eq!(expected(0, 0, 6000.0), 90.0);
+        assert_eq!(expected(3, 0, 6000.0), -135.0);
+        // the leader's first place is less certain with more kyoku or bigger hands to go
+        assert!(expected(0, 1, 6000.0) < 90.0);
+        assert!(expected(0, 4, 6000.0) < expected(0, 1, 6000.0));
+        assert!(expected(0, 4, 12000.0) < expected(0, 4, 6000.0));
+        assert!(expected(3, 4, 6000.0) > -135.0);
+        for seat in 0..3 {
+            assert!(expected(seat, 4, 6000.0) > expected(seat + 1, 4, 6000.0));
+        }
+        // even scores leave every seat the same chances
+        let even = expected_rank_points([25000; 4], 0, 4, &points, 6000.0);
+        for seat in 1..4 {
+            assert!((expected_rank_points([25000; 4], seat, 4, &points, 6000.0) - even).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn noten_pay_is_split_between_tenpai_and_noten() {
+        assert_eq!(
+            apply_ryukyoku(SCORES, [true, false, false, false]),
+            [28000, 24000, 24000, 24000]
+        );
+        assert_eq!(
+            apply_ryukyoku(SCORES, [true, true, false, false]),
+            [26500, 26500, 23500, 23500]
+        );
+        assert_eq!(
+            apply_ryukyoku(SCORES, [true, true, true, false]),
+            [26000, 26000, 26000, 22000]
+        );
+        assert_eq!(apply_ryukyoku(SCORES, [false; 4]), SCORES);
+        assert_eq!(apply_ryukyoku(SCORES, [true; 4]), SCORES);
+    }
+}
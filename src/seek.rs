@@ -0,0 +1,129 @@
+//! Jumping to a kyoku or turn and stepping back and forth through analyzed decisions
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail, ensure};
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+#[derive(clap::Args, Debug, Default)]
+pub struct SeekArgs {
+    /// Only analyze decisions in this kyoku, e.g. E2
+    #[arg(long)]
+    pub kyoku: Option<String>,
+    /// Only analyze decisions at this turn
+    #[arg(long)]
+    pub turn: Option<u8>,
+    /// Wait for input after every analysis to step forward or back
+    #[arg(long)]
+    pub step: bool,
+}
+
+/// Position in a log to analyze
+#[derive(Debug, Default)]
+pub struct Seek {
+    /// Round wind and 1-based kyoku
+    pub kyoku: Option<(Tile, u8)>,
+    pub turn: Option<u8>,
+}
+
+impl Seek {
+    pub fn from_args(args: &SeekArgs) -> Result<Self> {
+        let kyoku = match &args.kyoku {
+            Some(kyoku) => {
+                let mut chars = kyoku.chars();
+                let (Some(bakaze), Some(number), None) = (chars.next(), chars.next(), chars.next()) else {
+                    bail!("kyoku must be <bakaze><kyoku> (e.g. E2)");
+                };
+                let bakaze = Tile::from_str(&bakaze.to_string()).context("incorrect bakaze")?;
+                let number = number.to_digit(10).context("incorrect kyoku")? as u8;
+                ensure!((1..=4).contains(&number), "kyoku must be between 1 and 4");
+                Some((bakaze, number))
+            }
+            None => None,
+        };
+        Ok(Self { kyoku, turn: args.turn })
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.kyoku.is_some() || self.turn.is_some()
+    }
+
+    pub fn matches(&self, state: &PlayerState) -> bool {
+        self.kyoku
+            .is_none_or(|(bakaze, number)| state.bakaze == bakaze && state.kyoku + 1 == number)
+            && self.turn.is_none_or(|turn| state.at_turn == turn)
+    }
+}
+
+/// Snapshots of the analyzed decisions so earlier ones can be shown again without replaying the log
+pub struct StateHistory<T> {
+    snapshots: Vec<T>,
+    cursor: usize,
+}
+
+impl<T> Default for StateHistory<T> {
+    fn default() -> Self {
+        Self {
+            snapshots: vec![],
+            cursor: 0,
+        }
+    }
+}
+
+impl<T> StateHistory<T> {
+    pub fn push(&mut self, snapshot: T) {
+        self.snapshots.push(snapshot);
+        self.cursor = self.snapshots.len() - 1;
+    }
+
+    /// Prompt until the user steps past the newest snapshot, showing older ones on the way.
+    /// Returns false when the user quits.
+    pub fn step(&mut self, mut show: impl FnMut(&T) -> Result<()>) -> Result<bool> {
+        let stdin = std::io::stdin();
+        let mut lines = stdin.lock().lines();
+        loop {
+            eprint!("[enter] next, b back, q quit > ");
+            _ = std::io::stderr().flush();
+            let Some(Ok(line)) = lines.next() else {
+                return Ok(false);
+            };
+            match line.trim() {
+                "q" => return Ok(false),
+                "b" if self.cursor > 0 => {
+                    self.cursor -= 1;
+                    show(&self.snapshots[self.cursor])?;
+                }
+                "b" => eprintln!("already at the first decision"),
+                _ if self.cursor + 1 < self.snapshots.len() => {
+                    self.cursor += 1;
+                    show(&self.snapshots[self.cursor])?;
+                }
+                _ => return Ok(true),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::t;
+
+    use super::*;
+
+    fn seek_kyoku(kyoku: &str) -> Result<Seek> {
+        Seek::from_args(&SeekArgs {
+            kyoku: Some(kyoku.to_owned()),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn kyoku_is_read_by_characters() {
+        assert_eq!(seek_kyoku("S3").unwrap().kyoku, Some((t!(S), 3)));
+        // two bytes but a single character
+        assert!(seek_kyoku("é").is_err());
+        assert!(seek_kyoku("東1").is_err());
+        assert!(seek_kyoku("E5").is_err());
+    }
+}
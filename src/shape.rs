@@ -0,0 +1,97 @@
+//! Classification of candidates by the hand shape they commit to
+use riichi::algo::sp::EventCandidate;
+
+/// Share of winning hands a shape yaku needs to have for the candidate to be considered committed to it
+const DOMINANT_YAKU_THRESHOLD: f32 = 0.5;
+
+/// Yaku ids of the shape yaku in tenhou's numbering
+const CHIITOITSU: u8 = 22;
+const ITTSUU: u8 = 24;
+const SANSHOKU_DOUJUN: u8 = 25;
+const TOITOI: u8 = 28;
+const HONITSU: u8 = 34;
+const CHINITSU: u8 = 35;
+
+/// Hand shape a candidate commits to, ordered by priority when multiple shape yaku are dominant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShapeClass {
+    Chinitsu,
+    Honitsu,
+    Chiitoitsu,
+    Toitoi,
+    Sanshoku,
+    Ittsuu,
+    Standard,
+}
+
+impl ShapeClass {
+    /// Shape a yaku commits to by its id
+    fn from_yaku(yaku: u8) -> Option<Self> {
+        match yaku {
+            CHINITSU => Some(Self::Chinitsu),
+            HONITSU => Some(Self::Honitsu),
+            CHIITOITSU => Some(Self::Chiitoitsu),
+            TOITOI => Some(Self::Toitoi),
+            SANSHOKU_DOUJUN => Some(Self::Sanshoku),
+            ITTSUU => Some(Self::Ittsuu),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Chinitsu => "chinitsu line",
+            Self::Honitsu => "honitsu line",
+            Self::Chiitoitsu => "chiitoi line",
+            Self::Toitoi => "toitoi line",
+            Self::Sanshoku => "standard with sanshoku",
+            Self::Ittsuu => "standard with ittsuu",
+            Self::Standard => "standard",
+        }
+    }
+}
+
+/// Classify a candidate by the shape yaku that appear in most of its winning hands
+pub fn classify_candidate(candidate: &EventCandidate) -> ShapeClass {
+    let win_prob = candidate.win_probs.first().copied().unwrap_or(0.0);
+    let Some(yaku_probs) = candidate.yaku.first() else {
+        return ShapeClass::Standard;
+    };
+    if win_prob <= 0.0 {
+        return ShapeClass::Standard;
+    }
+    yaku_probs
+        .sorted_yaku()
+        .into_iter()
+        .filter(|&(_, p)| p / win_prob >= DOMINANT_YAKU_THRESHOLD)
+        .filter_map(|(y, _)| ShapeClass::from_yaku(y))
+        .min()
+        .unwrap_or(ShapeClass::Standard)
+}
+
+/// Group candidates by shape class, groups are ordered by their best candidate and keep the original order within
+pub fn group_candidates(candidates: &[EventCandidate]) -> Vec<(ShapeClass, Vec<&EventCandidate>)> {
+    let mut groups: Vec<(ShapeClass, Vec<&EventCandidate>)> = vec![];
+    for candidate in candidates {
+        let class = classify_candidate(candidate);
+        match groups.iter_mut().find(|(group_class, _)| *group_class == class) {
+            Some((_, group)) => group.push(candidate),
+            None => groups.push((class, vec![candidate])),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shapes_are_recognized_by_yaku_id() {
+        assert_eq!(ShapeClass::from_yaku(CHINITSU), Some(ShapeClass::Chinitsu));
+        assert_eq!(ShapeClass::from_yaku(SANSHOKU_DOUJUN), Some(ShapeClass::Sanshoku));
+        // sanshoku doukou and riichi commit to no shape
+        assert_eq!(ShapeClass::from_yaku(26), None);
+        assert_eq!(ShapeClass::from_yaku(1), None);
+    }
+}
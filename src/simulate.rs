@@ -0,0 +1,305 @@
+//! Tenpai hands an opponent could hold, enumerated from the tiles we have not seen
+//! Every tenpai hand is a number of complete sets plus a wait core: a pair with a ryanmen, kanchan or penchan, two pairs
+//! of a shanpon, or a single tile of a tanki. The sets fill the rest of the hand the same way whatever the core, so
+//! only the cores are enumerated, each weighted by the ways to hold its tiles among the unseen copies and a prior of
+//! its shape. Cores waiting on a tile of the opponent's own kawa are dropped since they cannot ron. Values count
+//! riichi, tanyao, pinfu, yakuhai and the dora of the core and the calls, with the dora of the filling sets averaged
+//! from the unseen tiles. Hands without riichi or any of these yaku cannot ron and are dropped, open or closed.
+use riichi::algo::agari::Agari;
+use riichi::must_tile;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+use crate::dora::dora_factor;
+use crate::scoring::extra_points;
+
+/// Bounds of the value buckets, the last bucket is everything above
+const VALUE_BUCKETS: [i32; 4] = [2000, 4000, 8000, 12000];
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CoreShape {
+    Ryanmen,
+    Kanchan,
+    Penchan,
+    Shanpon,
+    Tanki,
+}
+
+impl CoreShape {
+    pub const ALL: [Self; 5] = [Self::Ryanmen, Self::Kanchan, Self::Penchan, Self::Shanpon, Self::Tanki];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ryanmen => "ryanmen",
+            Self::Kanchan => "kanchan",
+            Self::Penchan => "penchan",
+            Self::Shanpon => "shanpon",
+            Self::Tanki => "tanki",
+        }
+    }
+
+    /// Prior of the shape before the tiles are counted, players keep good shapes when they can
+    fn prior(self) -> f64 {
+        match self {
+            Self::Ryanmen => 1.0,
+            Self::Kanchan => 0.4,
+            Self::Penchan => 0.25,
+            Self::Shanpon => 0.5,
+            Self::Tanki => 0.15,
+        }
+    }
+}
+
+/// Chance and average value of one wait
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct SimulatedWait {
+    pub tile: Tile,
+    /// Share of the tenpai hands waiting on the tile
+    pub prob: f32,
+    /// Average ron value of those hands on the tile including honba and kyotaku
+    pub value: f32,
+}
+
+/// Distribution of an opponent's waits and hand values over the enumerated tenpai hands
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct WaitSimulation {
+    /// Opponent relative to us
+    pub player: usize,
+    /// Wait cores consistent with the board
+    pub cores: usize,
+    /// Waits sorted from the most likely
+    pub waits: Vec<SimulatedWait>,
+    /// Share of each shape
+    pub shapes: Vec<(CoreShape, f32)>,
+    /// Share of the ron values below each bound of `VALUE_BUCKETS` and above the last
+    pub values: Vec<f32>,
+    pub average_value: f32,
+}
+
+impl WaitSimulation {
+    pub fn to_log_string(&self) -> String {
+        let waits = self
+            .waits
+            .iter()
+            .map(|wait| format!("{} {:.1}% ({})", wait.tile, wait.prob * 100.0, wait.value.round()))
+            .collect::<Vec<_>>();
+        let shapes = self
+            .shapes
+            .iter()
+            .map(|(shape, prob)| format!("{} {:.1}%", shape.name(), prob * 100.0))
+            .collect::<Vec<_>>();
+        let mut bounds = VALUE_BUCKETS.iter().map(|bound| format!("<{bound}")).collect::<Vec<_>>();
+        bounds.push(format!(">={}", VALUE_BUCKETS[VALUE_BUCKETS.len() - 1]));
+        let values = bounds
+            .iter()
+            .zip(&self.values)
+            .map(|(bound, prob)| format!("{bound} {:.1}%", prob * 100.0))
+            .collect::<Vec<_>>();
+        format!(
+            "player {}: {} wait cores, average value {}\nwaits: {}\nshapes: {}\nvalues: {}",
+            self.player,
+            self.cores,
+            self.average_value.round(),
+            waits.join(" | "),
+            shapes.join(" | "),
+            values.join(" | ")
+        )
+    }
+}
+
+/// Tiles of a wait core with the tiles it wins on
+struct Core {
+    shape: CoreShape,
+    tiles: Vec<usize>,
+    waits: Vec<usize>,
+    /// Pair that is not part of the wait, None for shanpon and tanki
+    pair: Option<usize>,
+}
+
+fn is_simple(tile: usize) -> bool {
+    tile < 27 && !matches!(tile % 9, 0 | 8)
+}
+
+/// Every wait core of a hand with `sets` sets not called, a tanki when there are none
+fn wait_cores(sets: usize) -> Vec<Core> {
+    let mut cores = (0..34)
+        .map(|tile| Core {
+            shape: CoreShape::Tanki,
+            tiles: vec![tile],
+            waits: vec![tile],
+            pair: None,
+        })
+        .collect::<Vec<_>>();
+    if sets == 0 {
+        return cores;
+    }
+    for first in 0..34 {
+        for second in first + 1..34 {
+            cores.push(Core {
+                shape: CoreShape::Shanpon,
+                tiles: vec![first, first, second, second],
+                waits: vec![first, second],
+                pair: None,
+            });
+        }
+    }
+    let mut blocks = vec![];
+    for low in (0..27).filter(|tile| tile % 9 <= 7) {
+        let (shape, waits) = match low % 9 {
+            0 => (CoreShape::Penchan, vec![low + 2]),
+            7 => (CoreShape::Penchan, vec![low - 1]),
+            _ => (CoreShape::Ryanmen, vec![low - 1, low + 2]),
+        };
+        blocks.push((shape, [low, low + 1], waits));
+        if low % 9 <= 6 {
+            blocks.push((CoreShape::Kanchan, [low, low + 2], vec![low + 1]));
+        }
+    }
+    for pair in 0..34 {
+        for (shape, block, waits) in &blocks {
+            cores.push(Core {
+                shape: *shape,
+                tiles: vec![pair, pair, block[0], block[1]],
+                waits: waits.clone(),
+                pair: Some(pair),
+            });
+        }
+    }
+    cores
+}
+
+/// Ways to hold `count` of `pool` copies
+fn choose(pool: u8, count: u8) -> f64 {
+    (0..count).fold(1.0, |ways, i| ways * (pool.saturating_sub(i)) as f64 / (i + 1) as f64)
+}
+
+/// Distribution of the waits and values of an opponent relative to us
+pub fn simulate_waits(state: &PlayerState, player: usize) -> WaitSimulation {
+    let pool = std::array::from_fn::<u8, 34, _>(|tile| 4 - state.tiles_seen[tile].min(4));
+    let mut furiten = [false; 34];
+    for tile in &state.kawa_overview[player] {
+        furiten[tile.deaka().as_usize()] = true;
+    }
+    let called = state.fuuro_overview[player].len() + state.ankan_overview[player].len();
+    let sets = 4usize.saturating_sub(called);
+    let open = !state.fuuro_overview[player].is_empty();
+    let riichi = state.riichi_declared[player];
+    let is_oya = player as u8 == state.oya;
+    let seat_wind = must_tile!(27 + (state.jikaze.as_usize() - 27 + player) % 4);
+    let yakuhai = |tile: usize| {
+        (31..34).contains(&tile) as u8 + (tile == state.bakaze.as_usize()) as u8 + (tile == seat_wind.as_usize()) as u8
+    };
+    let factor = dora_factor(&state.dora_indicators);
+    let called_tiles = state.fuuro_overview[player]
+        .iter()
+        .flatten()
+        .copied()
+        .chain(state.ankan_overview[player].iter().flat_map(|&tile| [tile; 4]))
+        .map(|tile| tile.deaka().as_usize())
+        .collect::<Vec<_>>();
+    let pons = state.fuuro_overview[player]
+        .iter()
+        .filter(|fuuro| fuuro.iter().all(|tile| tile.deaka() == fuuro[0].deaka()))
+        .map(|fuuro| fuuro[0])
+        .chain(state.ankan_overview[player].iter().copied());
+    let called_yakuhai = pons.map(|tile| yakuhai(tile.deaka().as_usize())).sum::<u8>();
+    let called_dora = called_tiles.iter().map(|&tile| factor[tile]).sum::<u8>();
+    let unseen_total = pool.iter().map(|&count| count as u32).sum::<u32>().max(1);
+    let dora_density = (0..34).map(|tile| pool[tile] as u32 * factor[tile] as u32).sum::<u32>() as f32 / unseen_total as f32;
+    let called_simple = called_tiles.iter().all(|&tile| is_simple(tile));
+
+    let mut wait_weights = [0.0f64; 34];
+    let mut wait_values = [0.0f64; 34];
+    let mut shape_weights = [0.0f64; 5];
+    let mut value_weights = vec![0.0f64; VALUE_BUCKETS.len() + 1];
+    let mut total = 0.0f64;
+    let mut value_total = 0.0f64;
+    let mut cores_count = 0;
+    for core in wait_cores(sets) {
+        let mut counts = [0u8; 34];
+        for &tile in &core.tiles {
+            counts[tile] += 1;
+        }
+        if (0..34).any(|tile| counts[tile] > pool[tile]) || core.waits.iter().any(|&wait| furiten[wait]) {
+            continue;
+        }
+        let live = core
+            .waits
+            .iter()
+            .copied()
+            .filter(|&wait| pool[wait] > counts[wait])
+            .collect::<Vec<_>>();
+        if live.is_empty() {
+            continue;
+        }
+        let weight = core.shape.prior() * (0..34).map(|tile| choose(pool[tile], counts[tile])).product::<f64>();
+        let filler_sets = if core.shape == CoreShape::Tanki { sets } else { sets - 1 };
+        let filler_dora = dora_density * (3 * filler_sets) as f32;
+        let mut valued = vec![];
+        for &wait in &live {
+            let mut han = riichi as u8 + called_yakuhai;
+            let simple = called_simple && is_simple(wait) && core.tiles.iter().all(|&tile| is_simple(tile));
+            han += simple as u8;
+            let pinfu = !open && core.shape == CoreShape::Ryanmen && core.pair.is_some_and(|pair| yakuhai(pair) == 0);
+            han += pinfu as u8;
+            if core.shape == CoreShape::Shanpon {
+                han += yakuhai(wait);
+            }
+            if han == 0 {
+                continue;
+            }
+            let dora = core
+                .tiles
+                .iter()
+                .chain(std::iter::once(&wait))
+                .map(|&tile| factor[tile])
+                .sum::<u8>()
+                + called_dora;
+            let han = (han as f32 + dora as f32 + filler_dora).round() as u8;
+            let fu = if pinfu || open { 30 } else { 40 };
+            let points = Agari::Normal { fu, han: han.max(1) }.point(is_oya).ron + extra_points(state.honba, state.kyotaku);
+            valued.push((wait, points));
+        }
+        if valued.is_empty() {
+            continue;
+        }
+        cores_count += 1;
+        total += weight;
+        shape_weights[CoreShape::ALL.iter().position(|&shape| shape == core.shape).unwrap_or(0)] += weight;
+        for (wait, points) in valued {
+            wait_weights[wait] += weight;
+            wait_values[wait] += weight * points as f64;
+            let bucket = VALUE_BUCKETS
+                .iter()
+                .position(|&bound| points < bound)
+                .unwrap_or(VALUE_BUCKETS.len());
+            value_weights[bucket] += weight;
+            value_total += weight;
+        }
+    }
+
+    let share = |weight: f64, total: f64| if total > 0.0 { (weight / total) as f32 } else { 0.0 };
+    let mut waits = (0..34)
+        .filter(|&tile| wait_weights[tile] > 0.0)
+        .map(|tile| SimulatedWait {
+            tile: must_tile!(tile),
+            prob: share(wait_weights[tile], total),
+            value: share(wait_values[tile], wait_weights[tile]),
+        })
+        .collect::<Vec<_>>();
+    waits.sort_by(|a, b| b.prob.total_cmp(&a.prob));
+    let average_value = share(wait_values.iter().sum(), value_total);
+    WaitSimulation {
+        player,
+        cores: cores_count,
+        waits,
+        shapes: CoreShape::ALL
+            .iter()
+            .zip(shape_weights)
+            .map(|(&shape, weight)| (shape, share(weight, total)))
+            .collect(),
+        values: value_weights.iter().map(|&weight| share(weight, value_total)).collect(),
+        average_value,
+    }
+}
@@ -0,0 +1,88 @@
+//! Commentary for logs and streams that reveal all four hands, such as replays
+//! Every seat keeps its own state, the seat to act gets a full analysis and the rest of the table a short standing.
+use anyhow::Result;
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+
+use crate::decisions::Seats;
+use crate::oracle::{future_draws, oracle_outcomes};
+use crate::state::{AnalysisOptions, ExpandedState};
+
+/// Commentary on the four seats of a table
+pub struct Spectator {
+    seats: Seats,
+    options: AnalysisOptions,
+}
+
+/// What a seat should be doing given its shanten and whether someone else is in riichi
+fn stance(state: &PlayerState, shanten: i8) -> &'static str {
+    let threatened = (1..4).any(|player| state.riichi_declared[player]);
+    match shanten {
+        _ if state.riichi_declared[0] => "riichi",
+        -1 | 0 => "push",
+        _ if !threatened => "build",
+        1 => "judge",
+        _ => "fold",
+    }
+}
+
+impl Spectator {
+    pub fn new(options: &AnalysisOptions) -> Self {
+        Self {
+            seats: Seats::default(),
+            options: options.clone(),
+        }
+    }
+
+    /// Apply an event to every seat and comment on the seats that have a decision.
+    /// `events_after` are the rest of the log when known, used to show what each discard would have drawn.
+    /// A seat failing to take the event is reported and sits out until the next kyoku, the other seats stay in sync.
+    pub fn update(&mut self, event: &Event, events_after: Option<&[Event]>) -> Result<Vec<String>> {
+        self.seats.update(event)?;
+        let mut comments = vec![];
+        for state in self.seats.deciding() {
+            let expanded = ExpandedState::from_state(state.clone(), None, &self.options)?;
+            let best = expanded.candidates.first().map_or_else(
+                || "-".to_owned(),
+                |candidate| {
+                    format!(
+                        "{} (EV {}, win {:.1}%)",
+                        candidate.event.to_decision_string(),
+                        candidate.exp_values.first().copied().unwrap_or(0.0).round(),
+                        candidate.win_probs.first().copied().unwrap_or(0.0) * 100.0
+                    )
+                },
+            );
+            let mut comment = format!("seat {} best {best}", state.player_id);
+            if let Some(events_after) = events_after
+                && let Some(outcome) = oracle_outcomes(state, &future_draws(events_after, state.player_id)).first()
+            {
+                comment += &format!(" | oracle {}", outcome.to_log_string());
+            }
+            comments.push(comment);
+        }
+        if !comments.is_empty() {
+            comments.push(self.standing());
+        }
+        Ok(comments)
+    }
+
+    /// Shanten and stance of every seat, with the seat closest to winning marked as ahead
+    pub fn standing(&self) -> String {
+        let states = self.seats.states();
+        let shanten = states.each_ref().map(|state| state.real_time_shanten());
+        let ahead = (0..4).min_by_key(|&seat| (shanten[seat], !states[seat].riichi_declared[0]));
+        (0..4)
+            .map(|seat| {
+                format!(
+                    "seat {seat}{}: {} shanten, {} points, {}",
+                    if Some(seat) == ahead { " (ahead)" } else { "" },
+                    shanten[seat].max(0),
+                    states[seat].scores[0],
+                    stance(&states[seat], shanten[seat])
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+}
@@ -1,14 +1,139 @@
-use riichi::algo::agari::yaku::{YakuLanguage, localize_yaku};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
 use riichi::algo::agari::{Agari, AgariWithYaku};
-use riichi::algo::danger::{PlayerDanger, WaitShape};
+use riichi::algo::danger::PlayerDanger;
+#[cfg(feature = "cli")]
+use riichi::algo::danger::WaitShape;
 use riichi::algo::sp::{EventCandidate, SPOptions};
+#[cfg(feature = "cli")]
 use riichi::hand::tiles_to_string;
+use riichi::mjai::Event;
 use riichi::state::PlayerState;
 use riichi::tile::Tile;
 use riichi::{must_tile, t};
 
+#[cfg(feature = "cli")]
+use crate::agari::fu_string;
+use crate::agari::{FuPart, fu_breakdown};
+#[cfg(feature = "cli")]
+use crate::benchmark::ordinal;
 /// Expanded mortal state
+use crate::benchmark::{best_ukeire, hand_percentile};
+use crate::calls::{CallDecision, call_decisions};
+use crate::chankan::{ChankanAnalysis, chankan_analysis};
+use crate::damaten::{RiichiDecision, riichi_decision};
+#[cfg(feature = "cli")]
+use crate::defense::candidate_discard;
+use crate::defense::{combined_danger, genbutsu, reserve_below_floor, safe_keep_discard, safety_reserve};
 use crate::ekyumoe::Detail;
+use crate::error::WashizuError;
+use crate::fast::{FastEstimate, approximated, fast_estimates, rank_estimates};
+#[cfg(feature = "cli")]
+use crate::fold::SafetyTier;
+use crate::fold::{FoldPlan, SafetyRanking, betaori_plausible, fold_plan, hopeless_against, safety_ranking};
+#[cfg(feature = "cli")]
+use crate::footnotes::footnotes;
+use crate::fu::{FuBoundary, fu_boundaries};
+use crate::furiten::{furiten_wait, opponent_furiten};
+use crate::glyphs::TileStyle;
+#[cfg(feature = "cli")]
+use crate::glyphs::render_tiles;
+use crate::handshape::{HandShape, hand_shape};
+#[cfg(feature = "cli")]
+use crate::highlight::{DANGER_HIGHLIGHT, Severity, paint};
+use crate::hora::{HoraDecision, hora_decision};
+use crate::kandora::KanDoraCost;
+#[cfg(feature = "cli")]
+use crate::kawa::kawa_lines;
+#[cfg(feature = "mortal")]
+use crate::model::MortalModel;
+use crate::order::{SortSpec, sort_by_value};
+use crate::plugin::{Plugin, run_plugins};
+use crate::profile::{DecisionProfile, Profiler};
+use crate::pushfold::{PushFold, push_fold};
+use crate::readability::{WaitLook, candidate_wait_looks};
+use crate::rollout::{CandidateRollout, rollout_candidates};
+use crate::rules::{OpenRiichi, RuleSet, add_open_riichi_han, han_gain_ratio};
+use crate::ryukyoku::DrawPayments;
+use crate::sanma::{add_nukidora_han, nuki_values};
+use crate::schema::validate;
+use crate::scorer::{CandidateScorer, DangerContext, PlacementContext, Score, WeightedScorer};
+#[cfg(feature = "cli")]
+use crate::scoring::{extra_points, placements_after};
+#[cfg(feature = "cli")]
+use crate::shape::group_candidates;
+use crate::suji::{SujiKind, WallKind, suji_annotations};
+use crate::tables::{SinglePlayerTables, single_player_tables, sp_options};
+use crate::tenpai::{TenpaiWait, tenpai_waits};
+use crate::tradeoff::{DealInPenalty, DealInRisk, defense_values};
+use crate::transparent::{apply_visible, impossible_wait};
+use crate::ukeire::{UkeireBreakdown, ukeire_breakdown};
+use crate::uradora::UraDora;
+use crate::waitmodel::deal_in_weights;
+use crate::yakuhai::{YakuhaiAdvice, yakuhai_advice};
+use crate::yakunames::YakuNaming;
+#[cfg(feature = "cli")]
+use crate::yakunames::{AKA_DORA, DORA, URA_DORA};
+
+/// Options for the washizu-side adjustments of the analysis
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisOptions {
+    /// Discount later tsumos by the chance of an abortive draw
+    pub abortive_draws: bool,
+    /// Value a kyoku ending without a win by the tenpai and noten payments at the draw
+    pub draw_payments: bool,
+    /// Charge kan candidates for the dora the new indicator gives the opponents
+    pub kan_dora_cost: bool,
+    /// Upper bound for the shanten analyzed by the single player calculator
+    pub max_shanten: Option<i8>,
+    /// Look for the least dangerous discard that keeps the current shanten
+    pub safe_keep: bool,
+    pub rules: RuleSet,
+    /// Riichi declarations that were open, only used when the rules allow open riichi
+    pub open_riichi: Vec<OpenRiichi>,
+    /// Custom criterion to rank candidates by
+    pub scorer: Option<WeightedScorer>,
+    /// Kita we have set aside, only used in sanma
+    pub nukidora: u8,
+    /// Warn when the best candidate leaves fewer genbutsu than this against a riichi, 0 disables the warning
+    pub safety_floor: u8,
+    /// Estimated score paid when dealing into a riichi, used for the defense-aware expected values
+    pub deal_in_penalty: DealInPenalty,
+    /// Tiles each player relative to us passed on and cannot ron, tracked from the events in live mode
+    pub passed_tiles: [Vec<Tile>; 4],
+    /// Transparent tiles in each opponent's hand relative to us, only used with washizu rules
+    pub visible_tiles: [Vec<Tile>; 4],
+    /// Replace the single player tables of 3+ shanten hands by the fast estimates
+    pub fast: bool,
+    /// Time the single player tables may take before the fast estimates replace them, unlimited when unset
+    pub sp_budget: Option<Duration>,
+    /// Break down the advancing and upgrade tiles of every discard
+    pub ukeire: bool,
+    /// External commands annotating every analysis
+    pub plugins: Vec<Plugin>,
+    /// Check every analysis against the bundled JSON schema of the report
+    pub validate_output: bool,
+    /// Run the offensive analysis even for hopeless hands against a riichi
+    pub no_auto_fold: bool,
+    /// Rank the hand by safety against a threat when the best candidate is worth less than this, 0 disables it
+    pub betaori_ev: f32,
+    /// Collector of the time spent in each stage of every analysis
+    pub profiler: Option<Profiler>,
+    /// How ura dora are counted in the single player tables and the agari of the waits
+    pub ura_dora: UraDora,
+    /// Naming scheme of the yaku in the output
+    pub yaku_naming: YakuNaming,
+    /// Display style of the tiles in the text output
+    pub tile_style: TileStyle,
+    /// Color the text output by severity
+    pub color: bool,
+    /// Playouts of the rest of the kyoku after each discard candidate, 0 disables the rollouts
+    pub rollout: usize,
+    /// Local mortal model filling in the details of decisions that have none
+    #[cfg(feature = "mortal")]
+    pub mortal_model: Option<std::sync::Arc<MortalModel>>,
+}
 
 /// State of the board that is not immediately evident such as shanten, expected score or tile danger
 pub struct ExpandedState {
@@ -26,6 +151,22 @@ pub struct ExpandedState {
     /// Candidates are sorted by expected value.
     /// Shanten down candidates are not processed for hands with 3+ shanten.
     pub candidates: Vec<EventCandidate>,
+    /// Options the single player tables were calculated with
+    pub sp_options: SPOptions,
+    /// Chance per go-around of an abortive draw applied to the candidates, only calculated when requested
+    pub abortive_draw_hazard: Option<f32>,
+    /// Chance of a nine terminals abort before our next draw applied to the candidates, only calculated when requested
+    pub kyuushu_hazard: Option<f32>,
+    /// Payments at an exhaustive draw, only calculated when requested
+    pub draw_payments: Option<DrawPayments>,
+    /// Value of the draw payments for each candidate, left out of `candidates`, see `total_exp_value`
+    pub draw_values: Vec<(Event, f32)>,
+    /// Cost of the opponents' new dora for every kan candidate, only calculated when requested, see `total_exp_value`
+    pub kan_dora_costs: Vec<KanDoraCost>,
+    /// Whether the single player tables exceeded `AnalysisOptions::sp_budget` and were replaced by the fast estimates
+    pub sp_over_budget: bool,
+    /// Time spent calculating the single player tables
+    pub sp_elapsed: Duration,
     /// Agari state (including specific yaku names, han and fu) of individual waits.
     /// For tenpai hands assumes the score is calculated as ron with no ura-dora.
     /// For agari hands (implied tsumo) the tile will be "?" and the score will be calculated as tsumo with no ura-dora.
@@ -33,57 +174,529 @@ pub struct ExpandedState {
     /// Danger weights and wait types for each tile based on a player's discard.
     /// Estimates danger by calculating the amount of tile combinations that can lead to a player having this wait.
     /// Uses multipliers for more common types of waits. Does not analyze tedashi patterns.
+    #[cfg_attr(not(feature = "cli"), allow(dead_code))]
     pub danger: [PlayerDanger; 4],
+    /// Least dangerous discard that keeps the current shanten (tenpai for agari hands) and its combined danger.
+    /// Only calculated when requested.
+    pub safe_keep: Option<(Event, f32)>,
+    /// Percentile of the hand compared to typical hands at the same turn
+    pub percentile: f32,
+    /// How the waits of tenpai candidates look to opponents reading our discards, sorted from the hardest to read
+    #[cfg_attr(not(feature = "cli"), allow(dead_code))]
+    pub wait_looks: Vec<(EventCandidate, Vec<(Tile, WaitLook)>)>,
+    /// Whether we are in open riichi, agari includes its extra han
+    pub open_riichi: bool,
+    /// Estimated expected value of declaring open riichi instead of damaten for each tenpai candidate
+    pub open_riichi_values: Vec<(Event, f32)>,
+    /// Kita we have set aside, agari and expected values include their dora
+    pub nukidora: u8,
+    /// Local yaku enabled by the rules that agari of each tile include
+    pub local_yaku: Vec<(Tile, &'static str)>,
+    /// Naming scheme of the yaku
+    pub yaku_naming: YakuNaming,
+    #[cfg_attr(not(feature = "cli"), allow(dead_code))]
+    pub tile_style: TileStyle,
+    #[cfg_attr(not(feature = "cli"), allow(dead_code))]
+    pub color: bool,
+    /// Estimated expected value of setting a north aside and of the best candidate keeping it, only in sanma
+    #[cfg_attr(not(feature = "cli"), allow(dead_code))]
+    pub nuki_values: Option<(f32, f32)>,
+    /// Waits revealed by opponents in open riichi, all other tiles are safe against them
+    #[cfg_attr(not(feature = "cli"), allow(dead_code))]
+    pub exposed_waits: [Option<Vec<Tile>>; 4],
+    /// Candidates ranked by the custom scorer, only calculated when requested
+    pub ranking: Vec<(Event, Score)>,
+    /// Number of genbutsu in our hand against each opponent
+    pub safety_reserve: [u8; 4],
+    /// Opponents in riichi against whom the best candidate leaves fewer genbutsu than the floor, with the count left
+    #[cfg_attr(not(feature = "cli"), allow(dead_code))]
+    pub reserve_warnings: Vec<(usize, u8)>,
+    #[cfg_attr(not(feature = "cli"), allow(dead_code))]
+    pub safety_floor: u8,
+    /// Expected score change of each discard including the chance of dealing into a riichi, best first.
+    /// Empty when no opponent is in riichi.
+    pub defense_values: Vec<(Event, f32)>,
+    /// Whether to push the best candidate or fold against a riichi, only at a discard
+    pub push_fold: Option<PushFold>,
+    /// Whether to keep each isolated yakuhai for its pairing value, only at a discard
+    pub yakuhai_advice: Vec<YakuhaiAdvice>,
+    /// Tenpai discards whose wins sit on a fu boundary, only at a discard
+    pub fu_boundaries: Vec<FuBoundary>,
+    /// Deal-in probability in percent of each tile against each player sorted from the most dangerous, dead waits taken out
+    pub danger_weights: [Vec<(Tile, f32)>; 4],
+    /// Best tenpai discard with and without riichi, only when riichi can be declared
+    pub riichi_decision: Option<RiichiDecision>,
+    /// Every possible call with its best follow-up discard compared to passing, only at a call prompt
+    pub call_decisions: Vec<CallDecision>,
+    /// Ron on an opponent's kakan compared to passing it, only at a chankan prompt
+    pub chankan: Option<ChankanAnalysis>,
+    /// Tsumo or ron on the last tile compared to declining it, only when the last event allows winning
+    pub hora: Option<HoraDecision>,
+    /// Suji and kabe of every number tile against opponents in riichi or with three calls, relative to us
+    pub suji: [Vec<(Tile, SujiKind, Option<WallKind>)>; 4],
+    /// Approximate values of every discard, calculated in fast mode, over the time budget or when the single player tables are empty
+    pub fast_estimates: Vec<FastEstimate>,
+    /// Advancing and upgrade tiles of every discard, only at a discard
+    pub ukeire: Vec<UkeireBreakdown>,
+    /// Wait of every tenpai discard with its furiten and yaku, marked in the candidate table
+    pub tenpai_waits: Vec<TenpaiWait>,
+    /// Class of the hand shape and its block decomposition
+    pub hand_shape: HandShape,
+    /// Defensive plan of a hopeless hand against a riichi, the single player tables are skipped when set
+    pub fold: Option<FoldPlan>,
+    /// Tiles in hand ranked by safety alone when an opponent threatens a hand worth little, None with a fold plan
+    pub safety_ranking: Option<SafetyRanking>,
+    /// Win, deal-in, tenpai and placement rates of playouts after each discard, only when requested and at a discard
+    pub rollouts: Vec<CandidateRollout>,
+    /// Fields added by plugins, empty without plugins
+    pub annotations: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Yaku and dora of a candidate's winning hands
+pub struct YakuBreakdown {
+    /// Localized yaku names and the chance they are part of a winning hand
+    pub yaku: Vec<(String, f32)>,
+    /// Average dora count in a winning hand
+    #[cfg_attr(not(feature = "cli"), allow(dead_code))]
+    pub dora: f32,
+    #[cfg_attr(not(feature = "cli"), allow(dead_code))]
+    pub aka_dora: f32,
+    #[cfg_attr(not(feature = "cli"), allow(dead_code))]
+    pub ura_dora: f32,
+}
+
+/// Yaku breakdown over all remaining tsumos conditional on winning, None for candidates that cannot win
+pub fn yaku_breakdown(candidate: &EventCandidate, naming: YakuNaming) -> Option<YakuBreakdown> {
+    let win_prob = candidate.win_probs.first().copied().unwrap_or(0.0);
+    let yaku_probs = candidate.yaku.first()?;
+    if win_prob <= 0.0 {
+        return None;
+    }
+    Some(YakuBreakdown {
+        yaku: yaku_probs
+            .sorted_yaku()
+            .into_iter()
+            .map(|(y, p)| (naming.yaku(y), p / win_prob))
+            .collect(),
+        dora: yaku_probs.dora / win_prob,
+        aka_dora: yaku_probs.aka_dora / win_prob,
+        ura_dora: yaku_probs.ura_dora / win_prob,
+    })
+}
+
+/// Expected number of tsumos until the win given that the candidate wins, from the win probability curve
+pub fn expected_draws(candidate: &EventCandidate) -> Option<f32> {
+    // win_probs[n] is the chance to win within `len - n` tsumos
+    let total = candidate.win_probs.first().copied()?;
+    if total <= 0.0 {
+        return None;
+    }
+    let len = candidate.win_probs.len();
+    let mut previous = 0.0;
+    let mut weighted = 0.0;
+    for draws in 1..=len {
+        let within = candidate.win_probs[len - draws];
+        weighted += draws as f32 * (within - previous);
+        previous = within;
+    }
+    Some(weighted / total)
 }
 
 impl ExpandedState {
-    pub fn from_state(state: PlayerState, details: Option<Vec<Detail>>) -> Self {
+    pub fn from_state(mut state: PlayerState, details: Option<Vec<Detail>>, analysis_options: &AnalysisOptions) -> Result<Self> {
+        // the model sees the board as it was played, before the rule adjustments below
+        #[cfg(feature = "mortal")]
+        let details = match (details, &analysis_options.mortal_model) {
+            (None, Some(model)) => Some(model.details(&state)?),
+            (details, _) => details,
+        };
+        analysis_options.rules.apply_aka(&mut state);
+        let visible_tiles = if analysis_options.rules.washizu {
+            analysis_options.visible_tiles.clone()
+        } else {
+            Default::default()
+        };
+        apply_visible(&mut state, &visible_tiles);
         let shanten = state.real_time_shanten();
 
-        let options = if shanten <= 3 {
-            SPOptions {
-                max_shanten: 3,
-                calc_tegawari: Some(2),
-                calc_shanten_down: Some(2),
-                ..Default::default()
+        let fold_against = if analysis_options.no_auto_fold {
+            vec![]
+        } else {
+            hopeless_against(&state, shanten)
+        };
+        let tables = if fold_against.is_empty() {
+            single_player_tables(&state, analysis_options)
+        } else {
+            SinglePlayerTables::skipped(sp_options(shanten, analysis_options))
+        };
+        let candidates = tables.candidates;
+
+        let percentile = hand_percentile(state.at_turn, shanten, best_ukeire(&candidates));
+        let wait_looks = candidate_wait_looks(&state, &candidates);
+        let tenpai_waits = tenpai_waits(&state, &candidates);
+        let open_riichi_rules = analysis_options.rules.open_riichi;
+        let exposed_waits: [Option<Vec<Tile>>; 4] = std::array::from_fn(|player| {
+            analysis_options
+                .open_riichi
+                .iter()
+                .find(|open| open_riichi_rules && player != 0 && open.player as usize == player && state.riichi_declared[player])
+                .map(|open| open.waits.clone())
+        });
+        let open_riichi =
+            open_riichi_rules && state.riichi_declared[0] && analysis_options.open_riichi.iter().any(|open| open.player == 0);
+        let open_riichi_values = if open_riichi_rules && state.last_cans.can_riichi {
+            candidates
+                .iter()
+                .filter(|candidate| candidate.shanten == 0)
+                .map(|candidate| {
+                    let exp_value = candidate.exp_values.first().copied().unwrap_or(0.0);
+                    let win_prob = candidate.win_probs.first().copied().unwrap_or(0.0);
+                    let average = if win_prob > 0.0 { exp_value / win_prob } else { 0.0 };
+                    let non_dealer_average = if state.is_oya() { average / 1.5 } else { average };
+                    // riichi and open riichi over damaten, tsumo-only values already ignore the lost ron chances
+                    (candidate.event.clone(), exp_value * han_gain_ratio(non_dealer_average, 2))
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
+        let danger_start = Instant::now();
+        let danger = state.calculate_danger();
+        let danger_elapsed = danger_start.elapsed();
+        let opponent_furiten = opponent_furiten(&state, &analysis_options.passed_tiles);
+        let danger_weights: [Vec<(Tile, f32)>; 4] = std::array::from_fn(|player| {
+            deal_in_weights(&state, player, &danger[player], |wait| {
+                furiten_wait(wait, &opponent_furiten[player]) || impossible_wait(&state, player, &visible_tiles[player], wait)
+            })
+        });
+        let safe_keep = if analysis_options.safe_keep && state.last_cans.can_discard {
+            safe_keep_discard(&candidates, &danger_weights, &exposed_waits, shanten)
+                .map(|(candidate, danger)| (candidate.event.clone(), danger))
+        } else {
+            None
+        };
+        let ranking = match &analysis_options.scorer {
+            Some(scorer) => {
+                let danger_context = DangerContext {
+                    combined: combined_danger(&danger_weights, &exposed_waits),
+                };
+                let placement_context = PlacementContext::from_state(&state);
+                let mut ranking = candidates
+                    .iter()
+                    .map(|candidate| {
+                        (
+                            candidate.event.clone(),
+                            scorer.score(candidate, &danger_context, &placement_context),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let spec = SortSpec {
+                    danger: Some(danger_context.combined),
+                    ..Default::default()
+                };
+                sort_by_value(&mut ranking, &spec, |(event, score)| (score.value, event.clone()));
+                ranking
             }
+            None => vec![],
+        };
+
+        let genbutsu = genbutsu(&state, &exposed_waits, &analysis_options.passed_tiles);
+        let safety_reserve = safety_reserve(&state, &genbutsu);
+        let risk = DealInRisk::new(&state, &danger_weights, &exposed_waits, analysis_options.deal_in_penalty);
+        let fold = (!fold_against.is_empty())
+            .then(|| fold_plan(&state, fold_against, &danger_weights, &exposed_waits, &genbutsu, &risk));
+        let betaori_against = if fold.is_none() {
+            betaori_plausible(&state, &candidates, analysis_options.betaori_ev)
         } else {
-            SPOptions {
-                max_shanten: 5,
-                ..Default::default()
+            vec![]
+        };
+        let safety_ranking = (!betaori_against.is_empty())
+            .then(|| safety_ranking(&state, betaori_against, &danger_weights, &exposed_waits, &genbutsu));
+        let reserve_warnings = match candidates.first() {
+            // pushing is forced once we are in riichi ourselves
+            Some(best) if analysis_options.safety_floor > 0 && !state.riichi_declared[0] => {
+                reserve_below_floor(&state, best, &genbutsu, &safety_reserve, analysis_options.safety_floor)
             }
+            _ => vec![],
         };
 
+        let agari_start = Instant::now();
         // TODO: proper agari after Hora event
         // Hora is not available in live so low priority
-        Self {
-            shanten,
-            details: details.unwrap_or_default(),
-            candidates: state.single_player_tables_for_events(&options),
-            agari: if shanten == -1
-                && let Some(winning_tile) = state.last_self_tsumo
-            {
-                vec![(
-                    t!(?),
-                    state.calculate_agari(winning_tile, false, &[]).expect("incorrect shanten"),
-                )]
-            } else if !state.last_cans.can_discard {
-                state
-                    .waits
-                    .iter()
-                    .enumerate()
-                    .filter(|&(_, &b)| b)
-                    .map(|(tile, _)| must_tile!(tile))
-                    .map(|tile| (tile, state.calculate_agari(tile, true, &[]).expect("incorrect wait")))
-                    .collect()
+        let mut agari: Vec<(Tile, Option<AgariWithYaku>)> = if shanten == -1
+            && let Some(winning_tile) = state.last_self_tsumo
+        {
+            vec![(
+                t!(?),
+                state.calculate_agari(winning_tile, false, &[]).context("incorrect shanten")?,
+            )]
+        } else if !state.last_cans.can_discard {
+            state
+                .waits
+                .iter()
+                .enumerate()
+                .filter(|&(_, &b)| b)
+                .map(|(tile, _)| must_tile!(tile))
+                .map(|tile| Ok((tile, state.calculate_agari(tile, true, &[]).context("incorrect wait")?)))
+                .collect::<Result<_>>()?
+        } else {
+            vec![]
+        };
+        let agari_elapsed = agari_start.elapsed();
+        if let Some(profiler) = &analysis_options.profiler {
+            profiler.record(DecisionProfile {
+                shanten,
+                tsumos_left: state.tiles_left.div_ceil(4),
+                sp: tables.elapsed,
+                danger: danger_elapsed,
+                agari: agari_elapsed,
+            });
+        }
+        let mut local_yaku = vec![];
+        for (tile, agari_with_yaku) in agari.iter_mut() {
+            analysis_options.rules.apply_to_agari(&state, *tile, agari_with_yaku);
+            analysis_options.ura_dora.apply_to_agari(&state, *tile, agari_with_yaku);
+            for name in analysis_options.rules.apply_local_yaku(&state, *tile, agari_with_yaku) {
+                local_yaku.push((*tile, name));
+            }
+        }
+        if open_riichi {
+            for agari_with_yaku in agari.iter_mut().filter_map(|(_, agari)| agari.as_mut()) {
+                add_open_riichi_han(agari_with_yaku);
+            }
+        }
+        let nukidora = if analysis_options.rules.sanma {
+            analysis_options.nukidora
+        } else {
+            0
+        };
+        if nukidora > 0 {
+            for agari_with_yaku in agari.iter_mut().filter_map(|(_, agari)| agari.as_mut()) {
+                add_nukidora_han(agari_with_yaku, nukidora);
+            }
+        }
+        let defense_values = defense_values(
+            &state,
+            &candidates,
+            &danger_weights,
+            &exposed_waits,
+            analysis_options.deal_in_penalty,
+        );
+        let push_fold = push_fold(&state, &candidates, &risk, shanten);
+        let yakuhai_advice = yakuhai_advice(&state, &candidates);
+        let fu_boundaries = fu_boundaries(&state, &analysis_options.rules, &candidates);
+        let chankan = chankan_analysis(&state, &agari, &candidates);
+        let hora = hora_decision(&state, &agari, &candidates, &analysis_options.ura_dora);
+        let call_decisions = call_decisions(&state, &candidates, analysis_options);
+        let riichi_decision = riichi_decision(&state, analysis_options);
+        let suji = suji_annotations(&state);
+        let fast_estimates =
+            if approximated(shanten, analysis_options.fast) || tables.over_budget || (candidates.is_empty() && fold.is_none()) {
+                let mut estimates = fast_estimates(&state);
+                rank_estimates(&mut estimates, &combined_danger(&danger_weights, &exposed_waits), &risk);
+                estimates
             } else {
                 vec![]
-            },
-            danger: state.calculate_danger(),
+            };
+        let ukeire = if analysis_options.ukeire {
+            ukeire_breakdown(&state)
+        } else {
+            vec![]
+        };
+        // the playouts deal four hands
+        let rollouts = if analysis_options.rules.sanma {
+            vec![]
+        } else {
+            rollout_candidates(&state, &candidates, analysis_options.rollout)
+        };
+        let nuki_values = if analysis_options.rules.sanma {
+            nuki_values(&state, &candidates)
+        } else {
+            None
+        };
+
+        let mut expanded = Self {
+            shanten,
+            details: details.unwrap_or_default(),
+            candidates,
+            sp_options: tables.options,
+            abortive_draw_hazard: tables.abortive_draw_hazard,
+            kyuushu_hazard: tables.kyuushu_hazard,
+            draw_payments: tables.draw_payments,
+            draw_values: tables.draw_values,
+            kan_dora_costs: tables.kan_dora_costs,
+            sp_over_budget: tables.over_budget,
+            sp_elapsed: tables.elapsed,
+            agari,
+            danger,
+            safe_keep,
+            percentile,
+            wait_looks,
+            open_riichi,
+            open_riichi_values,
+            nukidora,
+            local_yaku,
+            yaku_naming: analysis_options.yaku_naming,
+            tile_style: analysis_options.tile_style,
+            color: analysis_options.color,
+            nuki_values,
+            exposed_waits,
+            ranking,
+            safety_reserve,
+            reserve_warnings,
+            safety_floor: analysis_options.safety_floor,
+            defense_values,
+            push_fold,
+            yakuhai_advice,
+            fu_boundaries,
+            fast_estimates,
+            ukeire,
+            tenpai_waits,
+            hand_shape: hand_shape(&state),
+            fold,
+            safety_ranking,
+            rollouts,
+            danger_weights,
+            riichi_decision,
+            call_decisions,
+            chankan,
+            hora,
+            suji,
+            annotations: serde_json::Map::new(),
             state,
+        };
+        if !analysis_options.plugins.is_empty() {
+            expanded.annotations = run_plugins(&analysis_options.plugins, &expanded.to_report());
+        }
+        if analysis_options.validate_output {
+            let report = serde_json::to_value(expanded.to_report())?;
+            validate("analysis-report", &report).map_err(|e| WashizuError::Report(format!("{e:#}")))?;
+        }
+        Ok(expanded)
+    }
+
+    /// Localized yaku names of an agari including the ones washizu adds on top of mortal's calculation
+    pub fn yaku_names(&self, tile: Tile, agari: &AgariWithYaku) -> Vec<String> {
+        let naming = self.yaku_naming;
+        let mut names = naming.yaku_list(&agari.yaku);
+        names.extend(
+            self.local_yaku
+                .iter()
+                .filter(|(local_tile, _)| *local_tile == tile)
+                .map(|(_, name)| naming.washizu_yaku(name)),
+        );
+        if self.open_riichi {
+            names.push(naming.washizu_yaku("Open Riichi"));
+        }
+        if self.nukidora > 0 {
+            names.push(format!("{} {}", naming.washizu_yaku("Kita"), self.nukidora));
+        }
+        names
+    }
+
+    /// Fu breakdown of an agari, empty for yakuman
+    pub fn fu_breakdown(&self, tile: Tile, agari: &AgariWithYaku) -> Vec<FuPart> {
+        let Agari::Normal { fu, .. } = agari.agari else {
+            return vec![];
+        };
+        let tsumo = tile == t!(?);
+        match if tsumo { self.state.last_self_tsumo } else { Some(tile) } {
+            Some(winning) => fu_breakdown(&self.state, winning, tsumo, fu),
+            None => vec![],
+        }
+    }
+
+    /// Scores and placements of every seat relative to us with the gaps to the placements next to ours,
+    /// e.g. `scores: 0: 25000 2nd* | 1: 31000 1st | ... | gap +6000 to 1st, -3000 to 3rd | honba 1 kyotaku 0`
+    #[cfg(feature = "cli")]
+    pub fn score_situation(&self) -> String {
+        let placement = PlacementContext::from_state(&self.state);
+        let placements = placements_after(placement.scores);
+        let absolute = |player: usize| (player + self.state.player_id as usize) % 4;
+        let seats = (0..4)
+            .map(|player| {
+                let seat = absolute(player);
+                let oya = if seat as u8 == placement.oya { "*" } else { "" };
+                format!(
+                    "{player}: {} {}{oya}",
+                    placement.scores[seat],
+                    ordinal(placements[seat] as u32 + 1)
+                )
+            })
+            .collect::<Vec<_>>();
+        let ours = placements[absolute(0)];
+        let score_at = |rank: u8| {
+            (0..4)
+                .find(|&seat| placements[seat] == rank)
+                .map(|seat| placement.scores[seat])
+        };
+        let score = placement.scores[absolute(0)];
+        let mut gaps = vec![];
+        if let Some(above) = ours.checked_sub(1).and_then(score_at) {
+            gaps.push(format!("+{} to {}", above - score, ordinal(ours as u32)));
+        }
+        if let Some(below) = score_at(ours + 1) {
+            gaps.push(format!("-{} to {}", score - below, ordinal(ours as u32 + 2)));
+        }
+        let mut parts = seats;
+        if !gaps.is_empty() {
+            parts.push(format!("gap {}", gaps.join(", ")));
         }
+        parts.push(format!("honba {} kyotaku {}", self.state.honba, self.state.kyotaku));
+        format!("scores: {}", parts.join(" | "))
     }
 
+    /// Value of the draw payments of a candidate, 0 when they were not requested
+    pub fn draw_value(&self, candidate: &EventCandidate) -> f32 {
+        self.draw_values
+            .iter()
+            .find(|(event, _)| *event == candidate.event)
+            .map_or(0.0, |(_, value)| *value)
+    }
+
+    /// Cost of the opponents' new dora of a kan candidate, 0 for other candidates and when not requested
+    pub fn kan_dora_cost(&self, candidate: &EventCandidate) -> f32 {
+        self.kan_dora_costs
+            .iter()
+            .find(|cost| cost.kan == candidate.event)
+            .map_or(0.0, |cost| cost.cost)
+    }
+
+    /// Expected value over all remaining tsumos including the draw payments and the kan dora cost, the total shown and
+    /// ranked. Averages and win chances keep using the calculator's value without them.
+    pub fn total_exp_value(&self, candidate: &EventCandidate) -> f32 {
+        candidate.exp_values.first().copied().unwrap_or(0.0) + self.draw_value(candidate) - self.kan_dora_cost(candidate)
+    }
+
+    /// Index among the candidates the win is listed at, before the first one worth less, None without a win
+    pub fn hora_position(&self) -> Option<usize> {
+        let hora = self.hora.as_ref()?;
+        let position = self
+            .candidates
+            .iter()
+            .position(|candidate| self.total_exp_value(candidate) < hora.points_with_ura);
+        Some(position.unwrap_or(self.candidates.len()))
+    }
+
+    /// Gap of every candidate to the best one, in placement utility when ranked by a scorer and EV otherwise.
+    /// Zero for the best candidate and negative for the rest, in the order of `candidates`.
+    pub fn deltas(&self) -> Vec<f32> {
+        let values = self
+            .candidates
+            .iter()
+            .map(
+                |candidate| match self.ranking.iter().find(|(event, _)| *event == candidate.event) {
+                    Some((_, score)) => score.value,
+                    None => self.total_exp_value(candidate),
+                },
+            )
+            .collect::<Vec<_>>();
+        let best = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        values.into_iter().map(|value| value - best).collect()
+    }
+
+    #[cfg(feature = "cli")]
     pub fn to_log_string(&self) -> String {
         let details_string = self
             .details
@@ -92,7 +705,7 @@ impl ExpandedState {
             .collect::<Vec<_>>()
             .join(" ");
         let extra_points_string = if self.state.honba > 0 || self.state.kyotaku > 0 {
-            format!("+{}", self.state.honba as i32 * 300 + self.state.kyotaku as i32 * 1000)
+            format!("+{}", extra_points(self.state.honba, self.state.kyotaku))
         } else {
             "".to_owned()
         };
@@ -104,7 +717,7 @@ impl ExpandedState {
                     "{} - {}",
                     tile,
                     match agari {
-                        None => "yakunashi = 0".to_owned(),
+                        None => paint("yakunashi = 0", Severity::Warning, self.color),
                         Some(agari_with_yaku) => match agari_with_yaku.agari {
                             a @ Agari::Normal { fu, han } => format!(
                                 "{}han{}fu = {}{extra_points_string} [{}]",
@@ -115,13 +728,13 @@ impl ExpandedState {
                                 } else {
                                     a.point(self.state.is_oya()).ron
                                 },
-                                agari_with_yaku.localize_yaku(YakuLanguage::RomajiShort).join(", "),
+                                self.yaku_names(*tile, agari_with_yaku).join(", "),
                             ),
                             a @ Agari::Yakuman(count) => format!(
                                 "{}yakuman = {}{extra_points_string} [{}]",
                                 if count == 1 { "".to_owned() } else { format!("{count}x ") },
                                 a.point(self.state.is_oya()).tsumo_total(self.state.is_oya()),
-                                agari_with_yaku.localize_yaku(YakuLanguage::RomajiShort).join(", "),
+                                self.yaku_names(*tile, agari_with_yaku).join(", "),
                             ),
                         },
                     }
@@ -129,60 +742,114 @@ impl ExpandedState {
             })
             .collect::<Vec<_>>()
             .join(" | ");
-        let candidates_string = self
-            .candidates
-            .iter()
-            .map(|candidate| {
-                let exp_value = candidate.exp_values.first().cloned().unwrap_or(0.0);
-                let win_prob = candidate.win_probs.first().cloned().unwrap_or(0.0);
-                let tenpai_prob = candidate.tenpai_probs.first().cloned().unwrap_or(0.0);
-                let mut yaku_str = vec![];
-                if let Some(yaku_probs) = candidate.yaku.first() {
-                    for (y, p) in yaku_probs.sorted_yaku() {
-                        yaku_str.push(format!(
-                            "{} ({}%)",
-                            localize_yaku(y, YakuLanguage::RomajiShort),
-                            ((p / win_prob) * 100.0).round()
-                        ));
-                    }
-                    if candidate.yaku[0].dora > 0.0 {
-                        yaku_str.push(format!("Dora ({:.2})", candidate.yaku[0].dora / win_prob));
-                    }
-                    if candidate.yaku[0].aka_dora > 0.0 {
-                        yaku_str.push(format!("Aka ({:.2})", candidate.yaku[0].aka_dora / win_prob));
-                    }
-                    if candidate.yaku[0].ura_dora > 0.0 {
-                        yaku_str.push(format!("Ura ({:.2})", candidate.yaku[0].ura_dora / win_prob));
-                    }
-                }
-                format!(
-                    "{:<3} {:>5} {:>6} {:>6.2}% {:>6.2}% {} {} {} {}",
-                    candidate.event.to_decision_string(),
-                    exp_value.round(),
-                    if win_prob > 0.0 { (exp_value / win_prob).round() } else { 0.0 },
-                    win_prob * 100.0,
-                    tenpai_prob * 100.0,
-                    candidate.shanten,
-                    candidate.num_required_tiles,
-                    candidate
-                        .required_tiles
-                        .iter()
-                        .map(|r| format!("{}@{}", r.tile, r.count))
-                        .collect::<Vec<_>>()
-                        .join(" "),
-                    yaku_str.join(" "),
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
+        let deltas = self.deltas();
+        let candidate_line = |candidate: &EventCandidate| {
+            let delta = self
+                .candidates
+                .iter()
+                .position(|other| other.event == candidate.event)
+                .map_or(0.0, |index| deltas[index]);
+            let exp_value = candidate.exp_values.first().cloned().unwrap_or(0.0);
+            let win_prob = candidate.win_probs.first().cloned().unwrap_or(0.0);
+            let tenpai_prob = candidate.tenpai_probs.first().cloned().unwrap_or(0.0);
+            let total_exp_value = self.total_exp_value(candidate);
+            let wait = candidate_discard(candidate)
+                .and_then(|discard| self.tenpai_waits.iter().find(|wait| wait.discard == discard))
+                .map_or_else(String::new, |wait| {
+                    let marker = if wait.furiten || wait.yakuless {
+                        paint(&wait.marker(), Severity::Warning, self.color)
+                    } else {
+                        wait.marker()
+                    };
+                    format!(" {marker}")
+                });
+            let line = format!(
+                "{:<3} {:>5} {:>5} {:>6} {:>6.2}% {:>5} {:>6.2}% {} {} {}",
+                candidate.event.to_decision_string(),
+                total_exp_value.round(),
+                delta.round(),
+                if win_prob > 0.0 { (exp_value / win_prob).round() } else { 0.0 },
+                win_prob * 100.0,
+                expected_draws(candidate).map_or_else(|| "-".to_owned(), |draws| format!("{draws:.1}")),
+                tenpai_prob * 100.0,
+                candidate.shanten,
+                candidate.num_required_tiles,
+                candidate
+                    .required_tiles
+                    .iter()
+                    .map(|r| format!("{}@{}", r.tile, r.count))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            );
+            let best =
+                self.hora_position() != Some(0) && self.candidates.first().is_some_and(|best| best.event == candidate.event);
+            if best {
+                paint(&line, Severity::Recommended, self.color) + &wait
+            } else {
+                line + &wait
+            }
+        };
+        let hora_line = self.hora.as_ref().map(|hora| {
+            let best_value = self.candidates.first().map_or(0.0, |best| self.total_exp_value(best));
+            let line = format!(
+                "{:<3} {:>5} {:>5} {:>6} {:>6.2}% {:>5} {:>6.2}% {} {} ",
+                hora.event(&self.state).to_decision_string(),
+                hora.points_with_ura.round(),
+                (hora.points_with_ura - best_value).round(),
+                hora.points_with_ura.round(),
+                100.0,
+                "-",
+                100.0,
+                -1,
+                0,
+            );
+            if self.hora_position() == Some(0) {
+                paint(&line, Severity::Recommended, self.color)
+            } else {
+                line
+            }
+        });
+        let candidate_groups = group_candidates(&self.candidates);
+        let candidates_string = if candidate_groups.len() > 1 {
+            let groups = candidate_groups
+                .iter()
+                .map(|(class, group)| {
+                    format!(
+                        "-- {} --\n{}",
+                        class.name(),
+                        group
+                            .iter()
+                            .map(|candidate| candidate_line(candidate))
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    )
+                })
+                .collect::<Vec<_>>();
+            hora_line
+                .map(|line| format!("-- agari --\n{line}"))
+                .into_iter()
+                .chain(groups)
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            let mut lines = self.candidates.iter().map(candidate_line).collect::<Vec<_>>();
+            if let (Some(line), Some(position)) = (hora_line, self.hora_position()) {
+                lines.insert(position, line);
+            }
+            lines.join("\n")
+        };
         let danger_string = self
-            .danger
+            .danger_weights
             .iter()
             .enumerate()
-            .clone()
-            .map(|(i, danger)| {
-                danger
-                    .sorted_tile_weights()
+            .map(|(i, weights)| {
+                if let Some(waits) = &self.exposed_waits[i] {
+                    return format!(
+                        "open riichi waits: {}",
+                        waits.iter().map(|tile| tile.to_string()).collect::<Vec<_>>().join(" ")
+                    );
+                }
+                weights
                     .iter()
                     .filter(|(_, danger)| *danger > 0.0)
                     .map(|(tile, danger)| {
@@ -211,7 +878,13 @@ impl ExpandedState {
                                 }
                             }
                         }
-                        format!(
+                        if let Some((_, suji, wall)) = self.suji[i].iter().find(|(t, _, _)| *t == tile.deaka()) {
+                            danger_info.insert(suji.name());
+                            if let Some(wall) = wall {
+                                danger_info.insert(wall.name());
+                            }
+                        }
+                        let entry = format!(
                             "{}({:.1}{})",
                             tile,
                             danger,
@@ -220,31 +893,389 @@ impl ExpandedState {
                             } else {
                                 "".to_owned()
                             }
-                        )
+                        );
+                        if *danger >= DANGER_HIGHLIGHT {
+                            paint(&entry, Severity::Danger, self.color)
+                        } else {
+                            entry
+                        }
                     })
                     .collect::<Vec<_>>()
                     .join(" ")
             })
             .collect::<Vec<_>>()
             .join("\n");
-        format!(
-            "{} ({}{}){}{}\n{}\n{}\n{}",
+        let wait_looks_string = self
+            .wait_looks
+            .iter()
+            .map(|(candidate, looks)| {
+                format!(
+                    "{}: {}",
+                    candidate.event.to_decision_string(),
+                    looks
+                        .iter()
+                        .map(|(tile, look)| format!("{tile}({})", look.name()))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" | ");
+        let mut sections = vec![format!(
+            "{} ({}{}) {} percentile for turn {}, safety reserve {}",
             tiles_to_string(&self.state.tehai, self.state.akas_in_hand),
             self.shanten,
-            if self.state.at_furiten { " - furiten" } else { "" },
-            if !agari_string.is_empty() {
-                format!("\nwaits: {agari_string}")
+            if self.state.at_furiten {
+                paint(" - furiten", Severity::Warning, self.color)
             } else {
-                "".to_string()
+                String::new()
             },
-            if !details_string.is_empty() {
-                format!("\n{details_string}")
+            ordinal(self.percentile.round() as u32),
+            self.state.at_turn,
+            (1..4)
+                .map(|player| format!(
+                    "{}{}",
+                    self.safety_reserve[player],
+                    if self.state.riichi_declared[player] { "*" } else { "" }
+                ))
+                .collect::<Vec<_>>()
+                .join("/"),
+        )];
+        sections.push(self.score_situation());
+        sections.extend(kawa_lines(&self.state));
+        if let Some(fold) = &self.fold {
+            sections.push(format!(
+                "fold against riichi of {}: {} genbutsu left, keiten {:.1}% worth {}",
+                fold.against
+                    .iter()
+                    .map(|player| player.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                fold.genbutsu_left,
+                fold.keiten_prob * 100.0,
+                fold.keiten_value.round()
+            ));
+            sections.push(format!(
+                "safest: {}",
+                fold.safe_order
+                    .iter()
+                    .map(|(tile, danger, genbutsu)| format!("{tile}({}{danger:.1})", if *genbutsu { "genbutsu " } else { "" }))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ));
+        }
+        if let Some(ranking) = &self.safety_ranking {
+            sections.push(format!(
+                "betaori against {}: {} safe turns",
+                ranking
+                    .against
+                    .iter()
+                    .map(|player| player.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                ranking.safe_turns
+            ));
+            sections.push(format!(
+                "safest: {}",
+                ranking
+                    .tiles
+                    .iter()
+                    .map(|safe| {
+                        let mut reads = vec![safe.tier.name().to_owned()];
+                        if safe.tier == SafetyTier::Other
+                            && let Some(suji) = safe.suji
+                        {
+                            reads.push(suji.name().to_owned());
+                        }
+                        if let Some(wall) = safe.wall {
+                            reads.push(wall.name().to_owned());
+                        }
+                        format!("{}({} {:.1})", safe.tile, reads.join(" "), safe.danger)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ));
+        }
+        for (player, left) in &self.reserve_warnings {
+            let warning = format!(
+                "warning: {} leaves {left} genbutsu against player {player}'s riichi (floor {})",
+                self.candidates[0].event.to_decision_string(),
+                self.safety_floor
+            );
+            sections.push(paint(&warning, Severity::Warning, self.color));
+        }
+        if !agari_string.is_empty() {
+            sections.push(format!("waits: {agari_string}"));
+        }
+        for (tile, agari_with_yaku) in self.agari.iter().filter_map(|(tile, agari)| Some((tile, agari.as_ref()?))) {
+            let parts = self.fu_breakdown(*tile, agari_with_yaku);
+            if !parts.is_empty() {
+                sections.push(format!("fu {tile}: {}", fu_string(&parts)));
+            }
+        }
+        if let Some(decision) = &self.riichi_decision {
+            sections.push(format!(
+                "riichi {} {} | damaten {} {} -> {} by {}",
+                decision.riichi_discard.to_decision_string(),
+                decision.riichi_ev.round(),
+                decision.damaten_discard.to_decision_string(),
+                decision.damaten_ev.round(),
+                if decision.declare() { "riichi" } else { "damaten" },
+                (decision.riichi_ev - decision.damaten_ev).abs().round()
+            ));
+            for outcome in &decision.stick_outcomes {
+                sections.push(format!(
+                    "stick {}: recovered {:.1}% | lost {:.1}% | carried over {:.1}%",
+                    outcome.discard.to_decision_string(),
+                    outcome.recovered * 100.0,
+                    outcome.lost * 100.0,
+                    outcome.carried_over * 100.0
+                ));
+            }
+        }
+        for cost in &self.kan_dora_costs {
+            sections.push(format!(
+                "kan dora {}: opponents +{:.2}/{:.2}/{:.2} dora -> -{}",
+                cost.kan.to_decision_string(),
+                cost.opponent_dora[1],
+                cost.opponent_dora[2],
+                cost.opponent_dora[3],
+                cost.cost.round()
+            ));
+        }
+        if let Some(chankan) = &self.chankan {
+            sections.push(format!(
+                "chankan {}: ron {} | pass {} tsumo-only, furiten with {} waits left -> {}",
+                chankan.tile,
+                chankan
+                    .ron_points
+                    .map_or_else(|| "yakunashi".to_owned(), |points| points.to_string()),
+                chankan.pass_ev.round(),
+                chankan.waits_left,
+                if chankan.ron() { "ron" } else { "pass" }
+            ));
+        }
+        if let Some(hora) = &self.hora {
+            let yaku = self
+                .agari
+                .iter()
+                .find(|(tile, _)| *tile == hora.agari_tile())
+                .and_then(|(tile, agari)| Some(self.yaku_names(*tile, agari.as_ref()?)))
+                .unwrap_or_default();
+            let ura = if hora.expected_ura > 0.0 {
+                format!(" (+{:.2} ura = {})", hora.expected_ura, hora.points_with_ura.round())
             } else {
-                "".to_string()
-            },
-            "act   EV  avg.win  win%  tenpai% s. ukeire",
-            candidates_string,
-            danger_string,
-        )
+                String::new()
+            };
+            sections.push(format!(
+                "{} {}: {}{ura} [{}] | decline {} -> {}",
+                hora.kind.name(),
+                hora.tile,
+                hora.points,
+                yaku.join(", "),
+                hora.decline_ev.round(),
+                if hora.take() { hora.kind.name() } else { "decline" }
+            ));
+        }
+        if let Some((event, danger)) = &self.safe_keep {
+            sections.push(format!("safe keep: {} ({danger:.1})", event.to_decision_string()));
+        }
+        if !wait_looks_string.is_empty() {
+            sections.push(format!("wait looks: {wait_looks_string}"));
+        }
+        if !self.open_riichi_values.is_empty() {
+            sections.push(format!(
+                "open riichi: {}",
+                self.open_riichi_values
+                    .iter()
+                    .map(|(event, exp_value)| format!("{} {}", event.to_decision_string(), exp_value.round()))
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            ));
+        }
+        if !self.defense_values.is_empty() {
+            sections.push(format!(
+                "defense EV: {}",
+                self.defense_values
+                    .iter()
+                    .map(|(event, value)| format!("{} {}", event.to_decision_string(), value.round()))
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            ));
+        }
+        if let Some(push_fold) = &self.push_fold {
+            sections.push(format!(
+                "verdict: {} by {:+} (push {} over {} turns, fold {}, keep safe below {}, penalty x{:.2})",
+                push_fold.verdict.name(),
+                push_fold.margin.round(),
+                push_fold.push_value.round(),
+                push_fold.turns,
+                push_fold.fold_value.round(),
+                push_fold.threshold.round(),
+                push_fold.placement_weight
+            ));
+        }
+        for rollout in &self.rollouts {
+            sections.push(format!("rollout {}: {}", rollout.discard, rollout.stats.to_short_string()));
+        }
+        for advice in &self.yakuhai_advice {
+            sections.push(format!(
+                "yakuhai {}: {} ({}han, {} left, pair {:.1}%, triplet {:.1}%) keep {} | discard {}",
+                advice.tile,
+                if advice.keep() { "keep" } else { "discard" },
+                advice.han,
+                advice.left,
+                advice.pair_prob * 100.0,
+                advice.triplet_prob * 100.0,
+                advice.keep_ev.round(),
+                advice.discard_ev.round()
+            ));
+        }
+        for boundary in &self.fu_boundaries {
+            sections.push(format!("fu: {}", boundary.to_log_string()));
+        }
+        if let Some((nuki, keep)) = self.nuki_values {
+            sections.push(format!("kita: nuki {} | keep {}", nuki.round(), keep.round()));
+        }
+        if !self.annotations.is_empty() {
+            sections.push(format!(
+                "plugins: {}",
+                self.annotations
+                    .iter()
+                    .map(|(key, value)| match value {
+                        serde_json::Value::String(s) => format!("{key}={s}"),
+                        value => format!("{key}={value}"),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            ));
+        }
+        if !details_string.is_empty() {
+            sections.push(details_string);
+        }
+        if !self.candidates.is_empty() {
+            sections.push("act   EV     Δ avg.win  win%  draws tenpai% s. ukeire".to_owned());
+            sections.push(candidates_string);
+            if !self.call_decisions.is_empty() {
+                sections.push(format!(
+                    "calls vs pass:\n{}",
+                    self.call_decisions
+                        .iter()
+                        .map(|decision| format!(
+                            "{:<3} -> {:<3} {:>5} {:+}",
+                            decision.call.to_decision_string(),
+                            decision
+                                .follow_up
+                                .as_ref()
+                                .map_or_else(|| "-".to_owned(), |event| event.to_decision_string()),
+                            decision.exp_value.round(),
+                            decision.delta.round()
+                        ))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ));
+            }
+            sections.push(format!(
+                "notes: {}",
+                footnotes(
+                    &self.state,
+                    &self.sp_options,
+                    self.abortive_draw_hazard,
+                    self.kyuushu_hazard,
+                    self.draw_payments
+                )
+                .join(" | ")
+            ));
+        }
+        if !self.fast_estimates.is_empty() {
+            sections.push(if self.sp_over_budget {
+                "fast estimate (tables over budget):".to_owned()
+            } else {
+                "fast estimate:".to_owned()
+            });
+            sections.extend(self.fast_estimates.iter().map(|estimate| {
+                format!(
+                    "{:<3} {:>5} {:>6.2}% {} {} danger {:.1}",
+                    estimate.discard,
+                    estimate.exp_value.round(),
+                    estimate.win_prob * 100.0,
+                    estimate.shanten,
+                    estimate.ukeire,
+                    estimate.danger
+                )
+            }));
+        }
+        sections.push(format!("shape: {}", self.hand_shape.to_log_string()));
+        if !self.ukeire.is_empty() {
+            sections.push("ukeire:".to_owned());
+            sections.extend(self.ukeire.iter().map(|breakdown| {
+                let advance = breakdown
+                    .advance
+                    .iter()
+                    .map(|(tile, left)| format!("{tile}({left})"))
+                    .collect::<Vec<_>>();
+                let upgrade = breakdown
+                    .upgrade
+                    .iter()
+                    .map(|(tile, left, kind)| format!("{tile}({left} {})", kind.name()))
+                    .collect::<Vec<_>>();
+                format!(
+                    "{:<3} {} | advance {} {} | upgrade {} {}",
+                    breakdown.discard,
+                    breakdown.shanten,
+                    breakdown.advance_left(),
+                    advance.join(" "),
+                    breakdown.upgrade_left(),
+                    upgrade.join(" ")
+                )
+            }));
+        }
+        let yaku_lines = self
+            .candidates
+            .iter()
+            .filter_map(|candidate| {
+                let breakdown = yaku_breakdown(candidate, self.yaku_naming)?;
+                let mut line = format!(
+                    "{:<3} {}",
+                    candidate.event.to_decision_string(),
+                    breakdown
+                        .yaku
+                        .iter()
+                        .map(|(name, p)| format!("{name} {}%", (p * 100.0).round()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                for (yaku, count) in [
+                    (DORA, breakdown.dora),
+                    (AKA_DORA, breakdown.aka_dora),
+                    (URA_DORA, breakdown.ura_dora),
+                ] {
+                    if count > 0.0 {
+                        line += &format!(" | {} {count:.2}", self.yaku_naming.yaku(yaku));
+                    }
+                }
+                Some(line)
+            })
+            .collect::<Vec<_>>();
+        if !self.ranking.is_empty() {
+            sections.push(format!(
+                "ranking:\n{}",
+                self.ranking
+                    .iter()
+                    .map(|(event, score)| format!(
+                        "{:<3} {:>8.2} = {}",
+                        event.to_decision_string(),
+                        score.value,
+                        score.explanation
+                    ))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ));
+        }
+        if !yaku_lines.is_empty() {
+            sections.push(format!("yaku:\n{}", yaku_lines.join("\n")));
+        }
+        sections.push(danger_string);
+        render_tiles(&sections.join("\n"), self.tile_style)
     }
 }
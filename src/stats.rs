@@ -0,0 +1,161 @@
+//! Standard statistics of a player over one or more logs
+//! Only the events are used, no analysis is run. Rates are per kyoku.
+use std::fmt::Write;
+
+use riichi::algo::shanten::calc_all;
+use riichi::mjai::Event;
+use riichi::t;
+
+use crate::scoring::placements_after;
+
+/// Worst starting shanten tracked separately, worse hands are counted with it
+const MAX_START_SHANTEN: usize = 6;
+
+#[derive(Debug, Default)]
+pub struct PlayerStats {
+    pub games: usize,
+    pub kyokus: usize,
+    pub riichis: usize,
+    /// Kyokus won after declaring riichi
+    pub riichi_wins: usize,
+    /// Kyokus with at least one chi, pon or open kan
+    pub calls: usize,
+    pub wins: usize,
+    /// Sum of the score gained by every win
+    pub win_points: i64,
+    pub deal_ins: usize,
+    /// Sum of the score lost by every deal-in
+    pub deal_in_points: i64,
+    /// Games finished in each place, games without EndGame are not counted
+    pub placements: [usize; 4],
+    /// Kyokus and wins by the shanten of the dealt hand, kyokus with hidden tiles in it are left out
+    pub by_start_shanten: [(usize, usize); MAX_START_SHANTEN + 1],
+}
+
+impl PlayerStats {
+    /// Add the events of one game from the perspective of a seat
+    pub fn add_game(&mut self, events: &[Event], player_id: u8) {
+        let player = player_id as usize;
+        self.games += 1;
+        let mut scores = [0; 4];
+        let mut start_shanten = None;
+        let (mut riichi, mut called, mut won) = (false, false, false);
+        for event in events {
+            match event {
+                Event::StartKyoku {
+                    scores: kyoku_scores,
+                    tehais,
+                    ..
+                } => {
+                    scores = *kyoku_scores;
+                    start_shanten = (!tehais[player].contains(&t!(?))).then(|| {
+                        let mut hand = [0; 34];
+                        for tile in &tehais[player] {
+                            hand[tile.deaka().as_usize()] += 1;
+                        }
+                        (calc_all(&hand, 4).max(0) as usize).min(MAX_START_SHANTEN)
+                    });
+                    (riichi, called, won) = (false, false, false);
+                    self.kyokus += 1;
+                }
+                Event::ReachAccepted { actor } if *actor == player_id => riichi = true,
+                Event::Chi { actor, .. } | Event::Pon { actor, .. } | Event::Daiminkan { actor, .. } if *actor == player_id => {
+                    called = true;
+                }
+                Event::Hora {
+                    actor, target, deltas, ..
+                } => {
+                    let delta = deltas.map_or(0, |deltas| deltas[player]);
+                    if *actor == player_id {
+                        won = true;
+                        self.win_points += delta as i64;
+                    } else if *target == player_id {
+                        self.deal_ins += 1;
+                        self.deal_in_points -= delta as i64;
+                    }
+                    if let Some(deltas) = deltas {
+                        scores.iter_mut().zip(deltas).for_each(|(score, delta)| *score += delta);
+                    }
+                }
+                Event::Ryukyoku { deltas: Some(deltas) } => {
+                    scores.iter_mut().zip(deltas).for_each(|(score, delta)| *score += delta);
+                }
+                Event::EndKyoku => {
+                    self.riichis += riichi as usize;
+                    self.riichi_wins += (riichi && won) as usize;
+                    self.calls += called as usize;
+                    self.wins += won as usize;
+                    if let Some(shanten) = start_shanten {
+                        self.by_start_shanten[shanten].0 += 1;
+                        self.by_start_shanten[shanten].1 += won as usize;
+                    }
+                }
+                Event::EndGame => self.placements[placements_after(scores)[player] as usize] += 1,
+                _ => {}
+            }
+        }
+    }
+
+    pub fn to_log_string(&self) -> String {
+        let rate = |count: usize, total: usize| count as f32 / total.max(1) as f32 * 100.0;
+        let average = |sum: i64, count: usize| sum as f64 / count.max(1) as f64;
+        let placed: usize = self.placements.iter().sum();
+        let average_placement = self
+            .placements
+            .iter()
+            .enumerate()
+            .map(|(place, count)| (place + 1) * count)
+            .sum::<usize>() as f32
+            / placed.max(1) as f32;
+        let mut out = format!(
+            "{} games, {} kyokus\nwin {:.1}% | deal-in {:.1}% | riichi {:.1}% | call {:.1}%\naverage win {:.0} | average deal-in {:.0}\nriichi win {:.1}%\naverage placement {average_placement:.2} ({})\n",
+            self.games,
+            self.kyokus,
+            rate(self.wins, self.kyokus),
+            rate(self.deal_ins, self.kyokus),
+            rate(self.riichis, self.kyokus),
+            rate(self.calls, self.kyokus),
+            average(self.win_points, self.wins),
+            average(self.deal_in_points, self.deal_ins),
+            rate(self.riichi_wins, self.riichis),
+            self.placements.map(|count| count.to_string()).join("/"),
+        );
+        out.push_str("win by starting shanten:");
+        for (shanten, (kyokus, wins)) in self.by_start_shanten.iter().enumerate() {
+            if *kyokus > 0 {
+                let plus = if shanten == MAX_START_SHANTEN { "+" } else { "" };
+                _ = write!(out, " {shanten}{plus}: {:.1}% of {kyokus}", rate(*wins, *kyokus));
+            }
+        }
+        out.push('\n');
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hidden_starting_hands_are_counted_without_their_shanten() {
+        let events = [
+            Event::StartKyoku {
+                bakaze: t!(E),
+                dora_marker: t!(1m),
+                kyoku: 1,
+                honba: 0,
+                kyotaku: 0,
+                oya: 0,
+                scores: [25000, 25000, 24000, 26000],
+                tehais: [[t!(?); 13]; 4],
+            },
+            Event::EndKyoku,
+            Event::EndGame,
+        ];
+        let mut stats = PlayerStats::default();
+        stats.add_game(&events, 2);
+        assert_eq!(stats.kyokus, 1);
+        assert!(stats.by_start_shanten.iter().all(|&(kyokus, _)| kyokus == 0));
+        assert_eq!(stats.placements, [0, 0, 0, 1]);
+    }
+}
@@ -0,0 +1,164 @@
+//! Suji and kabe of every number tile, the reasoning players do by hand from the kawa
+use riichi::must_tile;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+/// Whether the ryanmen waits on a tile are cut by the opponent's own discards
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SujiKind {
+    /// Every ryanmen wait on the tile would be furiten
+    Suji,
+    /// One of the two ryanmen waits on a 4, 5 or 6 would be furiten
+    HalfSuji,
+    NonSuji,
+}
+
+impl SujiKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Suji => "suji",
+            Self::HalfSuji => "half-suji",
+            Self::NonSuji => "non-suji",
+        }
+    }
+}
+
+/// Whether the ryanmen waits on a tile are blocked by visible tiles
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WallKind {
+    /// All four copies of a tile needed for every ryanmen wait are visible
+    NoChance,
+    /// Only one copy of such a tile is left
+    OneChance,
+}
+
+impl WallKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::NoChance => "no-chance",
+            Self::OneChance => "one-chance",
+        }
+    }
+}
+
+/// Opponents that are in riichi or suspected to be tenpai with three or more calls
+pub fn suspected_tenpai(state: &PlayerState, player: usize) -> bool {
+    player != 0 && (state.riichi_declared[player] || state.fuuro_overview[player].len() >= 3)
+}
+
+/// The two ryanmen shapes waiting on a number tile as the pair of tiles and the other wait, None past the suit's edge
+fn ryanmen_shapes(tile: usize) -> [Option<([usize; 2], usize)>; 2] {
+    let number = tile % 9;
+    [
+        // shape above the tile, e.g. 23 waiting on 1 and 4
+        (number <= 5).then(|| ([tile + 1, tile + 2], tile + 3)),
+        // shape below the tile, e.g. 23 waiting on 4 and 1
+        (number >= 3).then(|| ([tile - 2, tile - 1], tile - 3)),
+    ]
+}
+
+/// Suji of every number tile against an opponent, None for honors
+pub fn suji_kinds(state: &PlayerState, player: usize) -> [Option<SujiKind>; 34] {
+    let mut discarded = [false; 34];
+    for tile in &state.kawa_overview[player] {
+        discarded[tile.deaka().as_usize()] = true;
+    }
+    std::array::from_fn(|tile| {
+        if tile >= 27 {
+            return None;
+        }
+        let shapes = ryanmen_shapes(tile).into_iter().flatten().collect::<Vec<_>>();
+        let cut = shapes.iter().filter(|(_, other)| discarded[*other]).count();
+        Some(match (cut, shapes.len()) {
+            (cut, len) if cut == len => SujiKind::Suji,
+            (0, _) => SujiKind::NonSuji,
+            _ => SujiKind::HalfSuji,
+        })
+    })
+}
+
+/// Kabe of every number tile from the tiles visible to us, None when a ryanmen wait on it is still possible
+pub fn wall_kinds(state: &PlayerState) -> [Option<WallKind>; 34] {
+    std::array::from_fn(|tile| {
+        if tile >= 27 {
+            return None;
+        }
+        let left = ryanmen_shapes(tile)
+            .into_iter()
+            .flatten()
+            .map(|(pair, _)| pair.map(|t| 4 - state.tiles_seen[t].min(4)).into_iter().min().unwrap_or(0))
+            .max()?;
+        match left {
+            0 => Some(WallKind::NoChance),
+            1 => Some(WallKind::OneChance),
+            _ => None,
+        }
+    })
+}
+
+/// Suji and kabe of every number tile against each suspected opponent, relative to us
+pub fn suji_annotations(state: &PlayerState) -> [Vec<(Tile, SujiKind, Option<WallKind>)>; 4] {
+    let walls = wall_kinds(state);
+    std::array::from_fn(|player| {
+        if !suspected_tenpai(state, player) {
+            return vec![];
+        }
+        suji_kinds(state, player)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(tile, suji)| Some((must_tile!(tile), suji?, walls[tile])))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::t;
+
+    use super::*;
+
+    fn riichi_after(kawa: &[Tile]) -> PlayerState {
+        let mut state = PlayerState {
+            riichi_declared: [false, true, false, false],
+            ..Default::default()
+        };
+        state.kawa_overview[1].extend(kawa.iter().copied());
+        state
+    }
+
+    #[test]
+    fn suji_follows_the_discards_of_the_opponent() {
+        let kinds = suji_kinds(&riichi_after(&[t!(4m), t!(1p)]), 1);
+        let kind = |tile: Tile| kinds[tile.as_usize()];
+        assert_eq!(kind(t!(1m)), Some(SujiKind::Suji));
+        assert_eq!(kind(t!(7m)), Some(SujiKind::Suji));
+        assert_eq!(kind(t!(4p)), Some(SujiKind::HalfSuji));
+        assert_eq!(kind(t!(5m)), Some(SujiKind::NonSuji));
+        // a discard is genbutsu but not suji of itself
+        assert_eq!(kind(t!(4m)), Some(SujiKind::NonSuji));
+        assert_eq!(kind(t!(E)), None);
+    }
+
+    #[test]
+    fn kabe_counts_the_visible_copies() {
+        let mut state = PlayerState::default();
+        state.tiles_seen[t!(8p).as_usize()] = 4;
+        state.tiles_seen[t!(2s).as_usize()] = 3;
+        let walls = wall_kinds(&state);
+        assert_eq!(walls[t!(9p).as_usize()], Some(WallKind::NoChance));
+        assert_eq!(walls[t!(1s).as_usize()], Some(WallKind::OneChance));
+        // 7p can still be waited on by 56p
+        assert_eq!(walls[t!(7p).as_usize()], None);
+        assert_eq!(walls[t!(S).as_usize()], None);
+    }
+
+    #[test]
+    fn only_suspected_opponents_are_annotated() {
+        let mut state = riichi_after(&[t!(4m)]);
+        state.tiles_seen[t!(2m).as_usize()] = 4;
+        let annotations = suji_annotations(&state);
+        assert!(annotations[0].is_empty() && annotations[2].is_empty() && annotations[3].is_empty());
+        assert_eq!(annotations[1].len(), 27);
+        assert!(annotations[1].contains(&(t!(1m), SujiKind::Suji, Some(WallKind::NoChance))));
+    }
+}
@@ -0,0 +1,251 @@
+//! Single player tables of a state
+//! Mortal calculates them through `PlayerState::single_player_tables_for_events`, which includes the yaku extension,
+//! washizu picks the options for the hand and applies its own adjustments on top.
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{RecvTimeoutError, channel};
+use std::time::{Duration, Instant};
+
+use riichi::algo::sp::{EventCandidate, SPOptions};
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+
+use crate::fast::approximated;
+use crate::horizon::{abortive_draw_hazard, apply_hazard, kyuushu_hazard};
+use crate::kandora::{KanDoraCost, kan_dora_cost};
+use crate::order::{SortSpec, sort_candidates};
+use crate::ryukyoku::{DrawPayments, draw_payments, draw_value};
+use crate::sanma::apply_nukidora_dora;
+use crate::state::AnalysisOptions;
+use crate::uradora::{UraDora, apply_known_ura, remove_ura_average};
+
+/// Result of a single player calculation
+#[derive(Debug, Clone)]
+pub struct SinglePlayerTables {
+    /// Candidates sorted by expected value, see `ExpandedState::candidates`
+    pub candidates: Vec<EventCandidate>,
+    /// Options the calculation was run with
+    pub options: SPOptions,
+    /// Chance per go-around of an abortive draw that was applied to the candidates
+    pub abortive_draw_hazard: Option<f32>,
+    /// Chance of a nine terminals abort before our next draw that was applied to the candidates
+    pub kyuushu_hazard: Option<f32>,
+    /// Payments at an exhaustive draw, when requested
+    pub draw_payments: Option<DrawPayments>,
+    /// Value of the draw payments for each candidate, kept out of `candidates` so their win chances and averages stay
+    /// the calculator's
+    pub draw_values: Vec<(Event, f32)>,
+    /// Cost of the opponents' new dora for every kan candidate, when requested
+    pub kan_dora_costs: Vec<KanDoraCost>,
+    /// Whether the calculation was given up for exceeding `AnalysisOptions::sp_budget`
+    pub over_budget: bool,
+    /// Time spent calculating
+    pub elapsed: Duration,
+}
+
+impl SinglePlayerTables {
+    /// Tables of a calculation that was skipped
+    pub fn skipped(options: SPOptions) -> Self {
+        Self {
+            candidates: vec![],
+            options,
+            abortive_draw_hazard: None,
+            kyuushu_hazard: None,
+            draw_payments: None,
+            draw_values: vec![],
+            kan_dora_costs: vec![],
+            over_budget: false,
+            elapsed: Duration::ZERO,
+        }
+    }
+}
+
+/// Budgeted calculations that exceeded their budget and are still running
+static OVERRUNS: AtomicUsize = AtomicUsize::new(0);
+
+/// Marks budgeted work as finished, also when it panics, and releases its overrun
+struct Finished(Arc<AtomicBool>);
+
+impl Drop for Finished {
+    fn drop(&mut self) {
+        // whoever marks the work second, the work or the caller giving up on it, settles the overrun
+        if self.0.swap(true, Ordering::SeqCst) {
+            OVERRUNS.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Result of `work` when it finishes within the budget.
+/// Mortal's calculator cannot be interrupted, so work over budget keeps running on its own thread and until it ends
+/// every other budgeted work is given up right away instead of piling up more threads.
+fn within_budget<T: Send + 'static>(budget: Duration, work: impl FnOnce() -> T + Send + 'static) -> Option<T> {
+    if budget.is_zero() || OVERRUNS.load(Ordering::SeqCst) > 0 {
+        return None;
+    }
+    let finished = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = channel();
+    let worker = {
+        let finished = Finished(finished.clone());
+        std::thread::spawn(move || {
+            let _finished = finished;
+            let _ = sender.send(work());
+        })
+    };
+    match receiver.recv_timeout(budget) {
+        Ok(result) => Some(result),
+        Err(RecvTimeoutError::Timeout) => {
+            OVERRUNS.fetch_add(1, Ordering::SeqCst);
+            drop(Finished(finished));
+            None
+        }
+        Err(RecvTimeoutError::Disconnected) => match worker.join() {
+            Err(panic) => std::panic::resume_unwind(panic),
+            Ok(()) => unreachable!("budgeted work ended without a result"),
+        },
+    }
+}
+
+/// Options used for a hand of the given shanten.
+/// Tegawari and shanten down are only calculated up to 3-shanten since they are too slow for worse hands.
+pub fn sp_options(shanten: i8, analysis_options: &AnalysisOptions) -> SPOptions {
+    let mut options = if shanten <= 3 {
+        SPOptions {
+            max_shanten: 3,
+            calc_tegawari: Some(2),
+            calc_shanten_down: Some(2),
+            ..Default::default()
+        }
+    } else {
+        SPOptions {
+            max_shanten: 5,
+            ..Default::default()
+        }
+    };
+    if let Some(max_shanten) = analysis_options.max_shanten {
+        options.max_shanten = options.max_shanten.min(max_shanten);
+    }
+    options
+}
+
+/// Calculate the single player tables of a state with washizu's adjustments, skipped for deep hands in fast mode
+pub fn single_player_tables(state: &PlayerState, analysis_options: &AnalysisOptions) -> SinglePlayerTables {
+    let options = sp_options(state.real_time_shanten(), analysis_options);
+    single_player_tables_with(state, options, analysis_options)
+}
+
+/// Calculate the single player tables with explicit calculator options
+pub fn single_player_tables_with(
+    state: &PlayerState,
+    options: SPOptions,
+    analysis_options: &AnalysisOptions,
+) -> SinglePlayerTables {
+    if approximated(state.real_time_shanten(), analysis_options.fast) {
+        return SinglePlayerTables::skipped(options);
+    }
+    let start = Instant::now();
+    let candidates = match analysis_options.sp_budget {
+        Some(budget) => {
+            let (state, options) = (state.clone(), options.clone());
+            within_budget(budget, move || state.single_player_tables_for_events(&options))
+        }
+        None => Some(state.single_player_tables_for_events(&options)),
+    };
+    let Some(mut candidates) = candidates else {
+        return SinglePlayerTables {
+            over_budget: true,
+            elapsed: start.elapsed(),
+            ..SinglePlayerTables::skipped(options)
+        };
+    };
+
+    let hazard = analysis_options.abortive_draws.then(|| abortive_draw_hazard(state));
+    let kyuushu = analysis_options.abortive_draws.then(|| kyuushu_hazard(state));
+    if let (Some(hazard), Some(kyuushu)) = (hazard, kyuushu) {
+        for candidate in candidates.iter_mut() {
+            apply_hazard(candidate, hazard, kyuushu);
+        }
+    }
+    let draw_payments = analysis_options.draw_payments.then(|| draw_payments(state));
+    let draw_values = draw_payments.map_or_else(Vec::new, |payments| {
+        candidates
+            .iter()
+            .map(|candidate| (candidate.event.clone(), draw_value(candidate, payments)))
+            .collect()
+    });
+    if analysis_options.ura_dora != UraDora::Averaged {
+        for candidate in candidates.iter_mut() {
+            remove_ura_average(candidate, state.is_oya());
+            if let UraDora::Known(indicators) = &analysis_options.ura_dora {
+                apply_known_ura(candidate, state, indicators);
+            }
+        }
+    }
+    let kan_dora_costs = if analysis_options.kan_dora_cost {
+        candidates
+            .iter()
+            .filter_map(|candidate| kan_dora_cost(state, &candidate.event, analysis_options.deal_in_penalty))
+            .collect()
+    } else {
+        vec![]
+    };
+    let nukidora = if analysis_options.rules.sanma {
+        analysis_options.nukidora
+    } else {
+        0
+    };
+    if nukidora > 0 {
+        for candidate in candidates.iter_mut() {
+            apply_nukidora_dora(candidate, nukidora, state.is_oya());
+        }
+    }
+    // resorted even without adjustments so ties follow the documented order instead of the calculator's
+    sort_candidates(
+        &mut candidates,
+        &SortSpec {
+            extra_values: draw_values
+                .iter()
+                .cloned()
+                .chain(kan_dora_costs.iter().map(|cost| (cost.kan.clone(), -cost.cost)))
+                .collect(),
+            ..Default::default()
+        },
+    );
+
+    SinglePlayerTables {
+        candidates,
+        options,
+        abortive_draw_hazard: hazard,
+        kyuushu_hazard: kyuushu,
+        draw_payments,
+        draw_values,
+        kan_dora_costs,
+        over_budget: false,
+        elapsed: start.elapsed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one test since the overruns are shared by every budgeted calculation
+    #[test]
+    fn work_over_budget_is_given_up_until_it_ends() {
+        assert_eq!(within_budget(Duration::from_secs(5), || 1), Some(1));
+        assert_eq!(within_budget(Duration::ZERO, || 1), None);
+        let panicked = std::panic::catch_unwind(|| within_budget(Duration::from_secs(5), || -> u8 { panic!("calculator") }));
+        assert!(panicked.is_err());
+
+        let (release, wait) = channel::<()>();
+        assert_eq!(within_budget(Duration::from_millis(10), move || wait.recv().is_ok()), None);
+        // the overrunning work still runs on its thread so nothing else is started
+        assert_eq!(within_budget(Duration::from_secs(5), || 1), None);
+        release.send(()).unwrap();
+        let start = Instant::now();
+        while OVERRUNS.load(Ordering::SeqCst) > 0 {
+            assert!(start.elapsed() < Duration::from_secs(5), "overrun never released");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(within_budget(Duration::from_secs(5), || 1), Some(1));
+    }
+}
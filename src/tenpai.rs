@@ -0,0 +1,69 @@
+//! Wait of every discard that reaches tenpai, with the furiten and missing yaku that make it worse than it looks
+//! The discard is applied to a copy of the state, so the wait and the kawa are the ones after it. A wait is furiten
+//! when any of its tiles is in our own kawa, and yakuless when none of its tiles wins by ron without riichi.
+use riichi::algo::sp::EventCandidate;
+use riichi::must_tile;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+use crate::defense::candidate_discard;
+
+/// Wait after a tenpai discard
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct TenpaiWait {
+    pub discard: Tile,
+    /// Winning tiles with the copies we have not seen
+    pub waits: Vec<(Tile, u8)>,
+    pub furiten: bool,
+    pub yakuless: bool,
+}
+
+impl TenpaiWait {
+    pub fn left(&self) -> u32 {
+        self.waits.iter().map(|&(_, left)| left as u32).sum()
+    }
+
+    /// Annotation next to the candidate, e.g. `[1z 2z 4 furiten]`
+    pub fn marker(&self) -> String {
+        let mut marker = self.waits.iter().map(|(tile, _)| tile.to_string()).collect::<Vec<_>>();
+        marker.push(self.left().to_string());
+        if self.furiten {
+            marker.push("furiten".to_owned());
+        }
+        if self.yakuless {
+            marker.push("yakuless".to_owned());
+        }
+        format!("[{}]", marker.join(" "))
+    }
+}
+
+/// Wait of every discard candidate that reaches tenpai
+pub fn tenpai_waits(state: &PlayerState, candidates: &[EventCandidate]) -> Vec<TenpaiWait> {
+    candidates
+        .iter()
+        .filter(|candidate| candidate.shanten == 0)
+        .filter_map(|candidate| {
+            let discard = candidate_discard(candidate)?;
+            let mut after = state.clone();
+            after.update(&candidate.event).ok()?;
+            let waits = (0..34)
+                .filter(|&tile| after.waits[tile])
+                .map(|tile| (must_tile!(tile), 4 - after.tiles_seen[tile].min(4)))
+                .collect::<Vec<_>>();
+            if waits.is_empty() {
+                return None;
+            }
+            let furiten = waits.iter().any(|(tile, _)| after.discarded_tiles[tile.as_usize()]);
+            let yakuless = !after.riichi_declared[0]
+                && waits
+                    .iter()
+                    .all(|&(tile, _)| matches!(after.calculate_agari(tile, true, &[]), Ok(None)));
+            Some(TenpaiWait {
+                discard,
+                waits,
+                furiten,
+                yakuless,
+            })
+        })
+        .collect()
+}
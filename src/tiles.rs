@@ -0,0 +1,28 @@
+//! Tile type and literal macros of the vendored riichi crate.
+//! Use these paths instead of depending on riichi directly, its macro paths may change with submodule updates.
+//!
+//! ```
+//! use washizu::tiles::{Tile, must_tile, t, tu8, tuz};
+//!
+//! let five_man: Tile = t!(5m);
+//! assert_eq!(five_man.as_u8(), tu8!(5m));
+//! assert_eq!(five_man.as_usize(), tuz!(5m));
+//! assert_eq!(must_tile!(tu8!(5m)), five_man);
+//! ```
+
+pub use riichi::tile::Tile;
+
+/// Tile literal, e.g. `t!(5m)`, `t!(5mr)` for aka dora, `t!(E)` for honors and `t!(?)` for an unknown tile
+pub use riichi::t;
+
+/// Tile literal as its `u8` index
+pub use riichi::tu8;
+
+/// Tile literal as its `usize` index
+pub use riichi::tuz;
+
+/// Tile from a runtime index, panics when the index is not a valid tile
+pub use riichi::must_tile;
+
+/// Pattern matching tile indices, e.g. `matches_tu8!(tile, 1m | 9m)`
+pub use riichi::matches_tu8;
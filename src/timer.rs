@@ -0,0 +1,88 @@
+//! Think time per decision from timestamped event streams
+//! Events may carry a `timestamp` field in seconds next to the mjai fields. The time between a decision point and
+//! our response is correlated with the EV lost by the response.
+use riichi::mjai::Event;
+
+use crate::state::ExpandedState;
+
+/// Think time and EV lost by one decision
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy)]
+pub struct DecisionTiming {
+    pub think_secs: f64,
+    pub ev_loss: f32,
+}
+
+/// Timestamp of a raw event line, None when the stream has no timestamps
+pub fn event_timestamp(line: &str) -> Option<f64> {
+    serde_json::from_str::<serde_json::Value>(line)
+        .ok()?
+        .get("timestamp")?
+        .as_f64()
+}
+
+/// Decision waiting for a response
+struct Pending {
+    timestamp: f64,
+    /// Decision string and EV gap to the best candidate of each candidate
+    deltas: Vec<(String, f32)>,
+}
+
+#[derive(Default)]
+pub struct DecisionTimer {
+    pending: Option<Pending>,
+    pub timings: Vec<DecisionTiming>,
+}
+
+impl DecisionTimer {
+    /// Start timing a decision point
+    pub fn decided(&mut self, timestamp: Option<f64>, expanded: &ExpandedState) {
+        self.pending = timestamp.map(|timestamp| Pending {
+            timestamp,
+            deltas: expanded
+                .candidates
+                .iter()
+                .zip(expanded.deltas())
+                .map(|(candidate, delta)| (candidate.event.to_decision_string(), delta))
+                .collect(),
+        });
+    }
+
+    /// Finish timing with the event after the decision point, anything but our own action counts as passing
+    pub fn responded(&mut self, timestamp: Option<f64>, event: &Event, player_id: u8) {
+        let (Some(pending), Some(timestamp)) = (self.pending.take(), timestamp) else {
+            return;
+        };
+        let responded = event.actor() == Some(player_id) && !matches!(event, Event::Tsumo { .. });
+        let action = if responded { event.clone() } else { Event::None }.to_decision_string();
+        if let Some((_, delta)) = pending.deltas.iter().find(|(candidate, _)| *candidate == action) {
+            self.timings.push(DecisionTiming {
+                think_secs: timestamp - pending.timestamp,
+                ev_loss: -delta,
+            });
+        }
+    }
+}
+
+/// Compare the EV lost by the faster and the slower half of the decisions
+pub fn timing_summary(timings: &[DecisionTiming]) -> Option<String> {
+    if timings.len() < 2 {
+        return None;
+    }
+    let mut sorted = timings.to_vec();
+    sorted.sort_by(|a, b| a.think_secs.total_cmp(&b.think_secs));
+    let (fast, slow) = sorted.split_at(sorted.len() / 2);
+    let average_loss = |half: &[DecisionTiming]| half.iter().map(|timing| timing.ev_loss).sum::<f32>() / half.len() as f32;
+    let (fast_loss, slow_loss) = (average_loss(fast), average_loss(slow));
+    let comparison = if slow_loss > 0.0 {
+        format!("{:.1}x the EV of", fast_loss / slow_loss)
+    } else {
+        format!("{} more EV than", (fast_loss - slow_loss).round())
+    };
+    Some(format!(
+        "{} timed decisions: fast ones (under {:.1}s) lose {comparison} slow ones, {} vs {} per decision",
+        timings.len(),
+        slow[0].think_secs,
+        fast_loss.round(),
+        slow_loss.round()
+    ))
+}
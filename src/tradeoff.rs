@@ -0,0 +1,114 @@
+//! Offense and defense combined into a single expected score change per discard
+//! The single player tables only know about our own tsumos, the danger weights only about dealing in.
+//! Merging them treats every discard as a one-off gamble: deal in with the combined danger of the opponents in riichi
+//! and pay their estimated hand, or survive and keep the candidate's expected value.
+use riichi::algo::sp::EventCandidate;
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+use crate::defense::{CERTAIN_DANGER, candidate_discard};
+use crate::order::{SortSpec, sort_by_value};
+
+/// Estimated score paid when dealing into a riichi
+#[derive(Debug, Clone, Copy)]
+pub struct DealInPenalty {
+    pub non_dealer: f32,
+    pub dealer: f32,
+}
+
+impl DealInPenalty {
+    /// Value of a typical hand, a quarter of the hands being the dealer's
+    pub fn typical_hand(&self) -> f32 {
+        (self.dealer + 3.0 * self.non_dealer) / 4.0
+    }
+}
+
+impl Default for DealInPenalty {
+    fn default() -> Self {
+        Self {
+            non_dealer: 5200.0,
+            dealer: 7700.0,
+        }
+    }
+}
+
+/// Chance and cost of dealing into the opponents in riichi
+pub struct DealInRisk {
+    /// Opponents in riichi relative to us
+    pub threats: Vec<usize>,
+    weights: [[f32; 34]; 4],
+    exposed_waits: [Option<Vec<Tile>>; 4],
+    /// Penalty of each player relative to us
+    penalties: [f32; 4],
+}
+
+impl DealInRisk {
+    pub fn new(
+        state: &PlayerState,
+        danger_weights: &[Vec<(Tile, f32)>; 4],
+        exposed_waits: &[Option<Vec<Tile>>; 4],
+        penalty: DealInPenalty,
+    ) -> Self {
+        let weights = danger_weights.each_ref().map(|player_weights| {
+            let mut weights = [0.0; 34];
+            for &(tile, weight) in player_weights {
+                weights[tile.deaka().as_usize()] = weight;
+            }
+            weights
+        });
+        Self {
+            threats: (1..4).filter(|&player| state.riichi_declared[player]).collect(),
+            weights,
+            exposed_waits: exposed_waits.clone(),
+            penalties: std::array::from_fn(|player| {
+                if player as u8 == state.oya {
+                    penalty.dealer
+                } else {
+                    penalty.non_dealer
+                }
+            }),
+        }
+    }
+
+    /// Chance to deal in with a tile against any of the threats and the expected score paid
+    pub fn of(&self, tile: Tile) -> (f32, f32) {
+        let probs = self.threats.iter().map(|&player| {
+            let weight = match &self.exposed_waits[player] {
+                Some(waits) if waits.iter().any(|wait| wait.deaka() == tile.deaka()) => CERTAIN_DANGER,
+                Some(_) => 0.0,
+                None => self.weights[player][tile.deaka().as_usize()],
+            };
+            ((weight / CERTAIN_DANGER).clamp(0.0, 1.0), self.penalties[player])
+        });
+        let deal_in = 1.0 - probs.clone().map(|(prob, _)| 1.0 - prob).product::<f32>();
+        let loss = probs.map(|(prob, penalty)| prob * penalty).sum::<f32>();
+        (deal_in, loss)
+    }
+}
+
+/// Expected score change of every discard candidate against the opponents in riichi, best first.
+/// Empty when nobody is in riichi since the danger of unannounced hands is not known to be tenpai.
+pub fn defense_values(
+    state: &PlayerState,
+    candidates: &[EventCandidate],
+    danger_weights: &[Vec<(Tile, f32)>; 4],
+    exposed_waits: &[Option<Vec<Tile>>; 4],
+    penalty: DealInPenalty,
+) -> Vec<(Event, f32)> {
+    let risk = DealInRisk::new(state, danger_weights, exposed_waits, penalty);
+    if risk.threats.is_empty() {
+        return vec![];
+    }
+    let mut values = candidates
+        .iter()
+        .filter_map(|candidate| {
+            let tile = candidate_discard(candidate)?;
+            let exp_value = candidate.exp_values.first().copied().unwrap_or(0.0);
+            let (deal_in, loss) = risk.of(tile);
+            Some((candidate.event.clone(), (1.0 - deal_in) * exp_value - loss))
+        })
+        .collect::<Vec<_>>();
+    sort_by_value(&mut values, &SortSpec::default(), |(event, value)| (*value, event.clone()));
+    values
+}
@@ -0,0 +1,79 @@
+//! Washizu mahjong, where three of every four tiles are transparent
+//! The transparent tiles in opponents' hands are visible to everyone. They are taken out of the wall for the single
+//! player tables and rule out every wait whose shape the hidden rest of the hand cannot hold.
+#[cfg(feature = "cli")]
+use anyhow::{Context, Result, ensure};
+use riichi::algo::danger::Wait;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+/// Transparent tiles in an opponent's hand given on the command line, live logs carry them as transparent events
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone)]
+pub struct VisibleTiles {
+    /// Player relative to us
+    pub player: u8,
+    pub tiles: Vec<Tile>,
+}
+
+#[cfg(feature = "cli")]
+impl VisibleTiles {
+    /// Parse `<relative player>:<tiles>`, e.g. `1:1234m`
+    pub fn parse(s: &str) -> Result<Self> {
+        let (player, tiles) = s.split_once(':').context("visible tiles must be <player>:<tiles>")?;
+        let player = player.parse().context("incorrect visible tiles player")?;
+        ensure!(
+            (1..4).contains(&player),
+            "visible tiles must belong to an opponent relative to us (1-3)"
+        );
+        Ok(Self {
+            player,
+            tiles: crate::input::hand_with_aka_vec(tiles)?,
+        })
+    }
+}
+
+/// Visible tiles of each player relative to us
+#[cfg(feature = "cli")]
+pub fn relative_visible(visible: &[VisibleTiles]) -> [Vec<Tile>; 4] {
+    std::array::from_fn(|player| {
+        visible
+            .iter()
+            .filter(|visible| visible.player as usize == player)
+            .flat_map(|visible| visible.tiles.iter().copied())
+            .collect()
+    })
+}
+
+/// Count the visible tiles of every opponent as seen so they are never drawn
+pub fn apply_visible(state: &mut PlayerState, visible: &[Vec<Tile>; 4]) {
+    for tile in visible.iter().skip(1).flatten() {
+        let seen = &mut state.tiles_seen[tile.deaka().as_usize()];
+        *seen = (*seen + 1).min(4);
+    }
+}
+
+/// Whether the hidden rest of an opponent's hand is too small to hold the shape of a wait next to the visible tiles
+pub fn impossible_wait(state: &PlayerState, player: usize, visible: &[Tile], wait: &Wait) -> bool {
+    if visible.is_empty() {
+        return false;
+    }
+    let melds = state.fuuro_overview[player].len() + state.ankan_overview[player].len();
+    let hidden = (13 - 3 * melds).saturating_sub(visible.len());
+    let mut counts = [0u8; 34];
+    for tile in visible {
+        counts[tile.deaka().as_usize()] += 1;
+    }
+    let missing = wait
+        .kind
+        .tiles
+        .iter()
+        .filter(|&&tile| {
+            let count = &mut counts[tile as usize];
+            let in_visible = *count > 0;
+            *count = count.saturating_sub(1);
+            !in_visible
+        })
+        .count();
+    missing > hidden
+}
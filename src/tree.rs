@@ -0,0 +1,203 @@
+//! Top of the single player search tree of a position, exported as JSON or DOT
+//! Mortal's calculator only returns the aggregate values of every candidate, so the tree is rebuilt by applying each
+//! discard and each draw that advances the hand to a copy of the state and calculating the tables again.
+//! Draws that keep the shanten are not expanded, a draw node's probability is its share of the unseen tiles.
+use std::fmt::Write;
+
+use anyhow::{Context, Result, bail, ensure};
+use riichi::algo::shanten::calc_all;
+use riichi::algo::sp::EventCandidate;
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+use crate::state::AnalysisOptions;
+use crate::tables::single_player_tables;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeFormat {
+    Json,
+    Dot,
+}
+
+/// Shape of an exported tree, parsed from e.g. `depth=2,width=3,format=dot`
+#[derive(Debug, Clone, Copy)]
+pub struct TreeSpec {
+    /// Number of discards along the deepest path
+    pub depth: u8,
+    /// Best candidates expanded at every decision, the rest are leaves
+    pub width: usize,
+    pub format: TreeFormat,
+}
+
+impl TreeSpec {
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut spec = Self {
+            depth: 2,
+            width: 3,
+            format: TreeFormat::Json,
+        };
+        for part in s.split(',').filter(|part| !part.is_empty()) {
+            let (key, value) = part.split_once('=').context("tree spec must be key=value pairs")?;
+            match key {
+                "depth" => spec.depth = value.parse().context("incorrect tree depth")?,
+                "width" => spec.width = value.parse().context("incorrect tree width")?,
+                "format" => {
+                    spec.format = match value {
+                        "json" => TreeFormat::Json,
+                        "dot" => TreeFormat::Dot,
+                        _ => bail!("unknown tree format {value}, expected json or dot"),
+                    }
+                }
+                _ => bail!("unknown tree spec key {key}, expected depth, width or format"),
+            }
+        }
+        ensure!(spec.depth >= 1, "tree depth must be at least 1");
+        Ok(spec)
+    }
+}
+
+/// Node of the exported tree, values are those of the best candidate below a draw
+#[derive(serde::Serialize, Debug)]
+pub struct TreeNode {
+    pub label: String,
+    /// Chance of the draw, only set on draw nodes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prob: Option<f32>,
+    pub exp_value: f32,
+    pub win_prob: f32,
+    pub tenpai_prob: f32,
+    pub shanten: i8,
+    pub children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    fn from_candidate(label: String, candidate: &EventCandidate) -> Self {
+        Self {
+            label,
+            prob: None,
+            exp_value: candidate.exp_values.first().copied().unwrap_or(0.0),
+            win_prob: candidate.win_probs.first().copied().unwrap_or(0.0),
+            tenpai_prob: candidate.tenpai_probs.first().copied().unwrap_or(0.0),
+            shanten: candidate.shanten,
+            children: vec![],
+        }
+    }
+
+    /// Node taking its values from the best of its children
+    fn from_best(label: String, shanten: i8, children: Vec<TreeNode>) -> Self {
+        let best = children.first();
+        Self {
+            label,
+            prob: None,
+            exp_value: best.map_or(0.0, |best| best.exp_value),
+            win_prob: best.map_or(0.0, |best| best.win_prob),
+            tenpai_prob: best.map_or(0.0, |best| best.tenpai_prob),
+            shanten: best.map_or(shanten, |best| best.shanten),
+            children,
+        }
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut out = "digraph tree {\n    node [shape=box, fontname=monospace];\n".to_owned();
+        let mut next_id = 0;
+        self.write_dot(&mut out, &mut next_id);
+        out.push_str("}\n");
+        out
+    }
+
+    fn write_dot(&self, out: &mut String, next_id: &mut usize) -> usize {
+        let id = *next_id;
+        *next_id += 1;
+        let prob = self.prob.map_or_else(String::new, |prob| format!(" ({:.1}%)", prob * 100.0));
+        _ = writeln!(
+            out,
+            "    n{id} [label=\"{}{prob}\\nEV {:.0} win {:.1}% tenpai {:.1}% s{}\"];",
+            self.label.replace('"', "\\\""),
+            self.exp_value,
+            self.win_prob * 100.0,
+            self.tenpai_prob * 100.0,
+            self.shanten
+        );
+        for child in &self.children {
+            let child_id = child.write_dot(out, next_id);
+            _ = writeln!(out, "    n{id} -> n{child_id};");
+        }
+        id
+    }
+}
+
+/// State after discarding a tile and drawing another one a go-around later
+fn after_discard_and_draw(state: &PlayerState, discard: Tile, draw: Tile) -> PlayerState {
+    let mut state = state.clone();
+    let discard_id = discard.deaka().as_usize();
+    let draw_id = draw.deaka().as_usize();
+    state.tehai[discard_id] -= 1;
+    state.doras_owned[0] = state.doras_owned[0].saturating_sub(state.dora_factor[discard_id]);
+    if discard.is_aka() {
+        state.akas_in_hand[discard_id / 9] = false;
+        state.doras_owned[0] = state.doras_owned[0].saturating_sub(1);
+    }
+    state.discarded_tiles[discard_id] = true;
+    state.kawa_overview[0].push(discard);
+    state.tehai[draw_id] += 1;
+    state.tiles_seen[draw_id] += 1;
+    state.doras_owned[0] += state.dora_factor[draw_id];
+    state.tiles_left = state.tiles_left.saturating_sub(4);
+    state.at_turn += 1;
+    state.last_self_tsumo = Some(draw);
+    state.shanten = calc_all(&state.tehai, state.tehai_len_div3);
+    state
+}
+
+fn expand(state: &PlayerState, depth: u8, width: usize, options: &AnalysisOptions) -> Vec<TreeNode> {
+    let candidates = single_player_tables(state, options).candidates;
+    let unseen: u32 = (0..34).map(|tile| 4u32.saturating_sub(state.tiles_seen[tile] as u32)).sum();
+    candidates
+        .iter()
+        .enumerate()
+        .map(|(index, candidate)| {
+            let mut node = TreeNode::from_candidate(candidate.event.to_decision_string(), candidate);
+            let Event::Dahai { pai, .. } = candidate.event else {
+                return node;
+            };
+            if depth <= 1 || index >= width || candidate.shanten < 0 || state.tiles_left < 4 {
+                return node;
+            }
+            node.children = candidate
+                .required_tiles
+                .iter()
+                .map(|required| {
+                    let next = after_discard_and_draw(state, pai, required.tile);
+                    let mut draw = TreeNode::from_best(
+                        format!("draw {}", required.tile),
+                        next.shanten,
+                        expand(&next, depth - 1, width, options),
+                    );
+                    draw.prob = Some(required.count as f32 / unseen.max(1) as f32);
+                    draw
+                })
+                .collect();
+            node
+        })
+        .collect()
+}
+
+/// Tree of the position down to `spec.depth` discards, rooted at the position with the values of its best candidate
+pub fn decision_tree(state: &PlayerState, spec: &TreeSpec, options: &AnalysisOptions) -> Result<TreeNode> {
+    ensure!(state.last_cans.can_discard, "the decision tree starts at a discard");
+    let children = expand(state, spec.depth, spec.width, options);
+    ensure!(!children.is_empty(), "no candidates for the position");
+    let mut root = TreeNode::from_best("position".to_owned(), state.shanten, children);
+    root.shanten = state.shanten;
+    Ok(root)
+}
+
+/// Tree in the format of the spec
+pub fn export_tree(state: &PlayerState, spec: &TreeSpec, options: &AnalysisOptions) -> Result<String> {
+    let tree = decision_tree(state, spec, options)?;
+    Ok(match spec.format {
+        TreeFormat::Json => serde_json::to_string_pretty(&tree)?,
+        TreeFormat::Dot => tree.to_dot(),
+    })
+}
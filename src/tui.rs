@@ -0,0 +1,220 @@
+//! Interactive terminal interface for stepping through the decisions of a log
+use anyhow::Result;
+use clap::Parser;
+use ratatui::crossterm::event::{self, Event as TermEvent, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use riichi::mjai::Event;
+use riichi::state::PlayerState;
+
+use crate::input::{HandArgs, state_from_hand_args};
+use crate::report::AnalysisReport;
+use crate::state::{AnalysisOptions, ExpandedState};
+
+const HELP: &str = "←/→ decision  ↑/↓ scroll  d danger  e edit hand  esc back to log  q quit";
+
+struct Analysis {
+    title: String,
+    header: String,
+    report: AnalysisReport,
+}
+
+impl Analysis {
+    fn new(title: String, state: PlayerState, options: &AnalysisOptions) -> Result<Self> {
+        let expanded = ExpandedState::from_state(state, None, options)?;
+        Ok(Self {
+            title,
+            header: expanded.to_log_string().lines().next().unwrap_or_default().to_owned(),
+            report: expanded.to_report(),
+        })
+    }
+}
+
+struct App {
+    decisions: Vec<(Event, PlayerState)>,
+    /// Analyses are calculated when a decision is first shown
+    analyses: Vec<Option<Analysis>>,
+    index: usize,
+    table: TableState,
+    show_danger: bool,
+    /// Hand being typed in the editor
+    editor: Option<String>,
+    /// Analysis of an edited hand, shown instead of the log until dismissed
+    edited: Option<Analysis>,
+    error: Option<String>,
+    options: AnalysisOptions,
+}
+
+impl App {
+    fn current(&mut self) -> Option<&Analysis> {
+        if self.edited.is_some() {
+            return self.edited.as_ref();
+        }
+        let (event, state) = self.decisions.get(self.index)?;
+        if self.analyses[self.index].is_none() {
+            let title = format!("{}/{} after {event:?}", self.index + 1, self.decisions.len());
+            match Analysis::new(title, state.clone(), &self.options) {
+                Ok(analysis) => self.analyses[self.index] = Some(analysis),
+                Err(e) => self.error = Some(format!("{e:#}")),
+            }
+        }
+        self.analyses[self.index].as_ref()
+    }
+
+    fn step(&mut self, forward: bool) {
+        self.edited = None;
+        self.index = if forward {
+            (self.index + 1).min(self.decisions.len().saturating_sub(1))
+        } else {
+            self.index.saturating_sub(1)
+        };
+        self.table.select(Some(0));
+    }
+
+    fn analyze_edited(&mut self, input: &str) {
+        let args = std::iter::once("hand").chain(input.split_whitespace());
+        let result = HandArgs::try_parse_from(args)
+            .map_err(anyhow::Error::from)
+            .and_then(state_from_hand_args)
+            .and_then(|state| Analysis::new(format!("edited {input}"), state, &self.options));
+        match result {
+            Ok(analysis) => {
+                self.edited = Some(analysis);
+                self.table.select(Some(0));
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("{e:#}")),
+        }
+    }
+
+    /// Returns false when the app should exit
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        if let Some(input) = &mut self.editor {
+            match code {
+                KeyCode::Enter => {
+                    let input = std::mem::take(input);
+                    self.editor = None;
+                    self.analyze_edited(&input);
+                }
+                KeyCode::Esc => self.editor = None,
+                KeyCode::Backspace => _ = input.pop(),
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return true;
+        }
+        match code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Right | KeyCode::Char('l') => self.step(true),
+            KeyCode::Left | KeyCode::Char('h') => self.step(false),
+            KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            KeyCode::Char('d') => self.show_danger = !self.show_danger,
+            KeyCode::Char('e') => self.editor = Some(String::new()),
+            KeyCode::Esc => self.edited = None,
+            _ => {}
+        }
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, danger_area, status] = Layout::vertical([
+            Constraint::Min(5),
+            Constraint::Length(if self.show_danger { 5 } else { 0 }),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let status_line = match (&self.editor, &self.error) {
+            (Some(input), _) => format!("hand> {input}"),
+            (None, Some(error)) => error.clone(),
+            (None, None) => HELP.to_owned(),
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+
+        let show_danger = self.show_danger;
+        let mut table_state = std::mem::take(&mut self.table);
+        let Some(analysis) = self.current() else {
+            frame.render_widget(Paragraph::new("no decisions, press e to enter a hand"), main);
+            self.table = table_state;
+            return;
+        };
+
+        let [header_area, table_area] = Layout::vertical([Constraint::Length(1), Constraint::Min(3)]).areas(main);
+        frame.render_widget(Paragraph::new(analysis.header.clone()), header_area);
+
+        let rows = analysis.report.candidates.iter().map(|candidate| {
+            Row::new(vec![
+                candidate.action.clone(),
+                format!("{:.0}", candidate.exp_value),
+                format!("{:.0}", candidate.delta),
+                format!("{:.2}", candidate.win_prob * 100.0),
+                candidate
+                    .expected_draws
+                    .map_or_else(|| "-".to_owned(), |draws| format!("{draws:.1}")),
+                format!("{:.2}", candidate.tenpai_prob * 100.0),
+                candidate.shanten.to_string(),
+                candidate.num_required_tiles.to_string(),
+            ])
+        });
+        let table = Table::new(rows, [Constraint::Length(6); 8])
+            .header(
+                Row::new(vec!["act", "EV", "Δ", "win%", "draws", "tenpai%", "s.", "ukeire"])
+                    .style(Style::new().add_modifier(Modifier::BOLD)),
+            )
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .block(Block::bordered().title(analysis.title.clone()));
+        frame.render_stateful_widget(table, table_area, &mut table_state);
+
+        if show_danger {
+            let lines = analysis.report.danger[1..]
+                .iter()
+                .map(|danger| {
+                    Line::from(
+                        danger
+                            .iter()
+                            .map(|(tile, weight)| format!("{tile}:{weight:.1}"))
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                    )
+                })
+                .collect::<Vec<_>>();
+            frame.render_widget(Paragraph::new(lines).block(Block::bordered().title("danger")), danger_area);
+        }
+        self.table = table_state;
+    }
+
+    fn run(mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let TermEvent::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && !self.handle_key(key.code)
+            {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Run the interface over decision points given as the event leading to them and the state after it
+pub fn run_tui(decisions: Vec<(Event, PlayerState)>, options: AnalysisOptions) -> Result<()> {
+    let app = App {
+        analyses: decisions.iter().map(|_| None).collect(),
+        decisions,
+        index: 0,
+        table: TableState::default().with_selected(Some(0)),
+        show_danger: false,
+        editor: None,
+        edited: None,
+        error: None,
+        options,
+    };
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    result
+}
@@ -0,0 +1,194 @@
+//! Tiles that advance or upgrade the hand after each discard, independent of the single player tables
+//! Advancing tiles lower shanten. Upgrade tiles keep shanten while pairing an isolated yakuhai, adding a dora or a red
+//! five, or leaving more advancing tiles after the best discard that keeps shanten. Counts are the copies we have not
+//! seen, a red five is listed on its own when only it upgrades the hand.
+//! Trying every draw after every discard is slow, so the breakdown is only calculated when requested.
+use riichi::algo::shanten::calc_all;
+use riichi::must_tile;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+use crate::yakuhai::yakuhai_han;
+
+/// Why a tile that keeps shanten is still worth drawing
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UpgradeKind {
+    Yakuhai,
+    Dora,
+    /// Red five of a suit whose red five we have not seen
+    Aka,
+    /// More advancing tiles after the best discard
+    Wider,
+}
+
+impl UpgradeKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Yakuhai => "yakuhai",
+            Self::Dora => "dora",
+            Self::Aka => "aka",
+            Self::Wider => "wider",
+        }
+    }
+}
+
+/// Advancing and upgrade tiles after one discard
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct UkeireBreakdown {
+    pub discard: Tile,
+    /// Shanten after the discard
+    pub shanten: i8,
+    /// Tiles lowering shanten with their copies left
+    pub advance: Vec<(Tile, u8)>,
+    /// Tiles keeping shanten that upgrade the hand with their copies left
+    pub upgrade: Vec<(Tile, u8, UpgradeKind)>,
+}
+
+impl UkeireBreakdown {
+    pub fn advance_left(&self) -> u32 {
+        self.advance.iter().map(|&(_, left)| left as u32).sum()
+    }
+
+    pub fn upgrade_left(&self) -> u32 {
+        self.upgrade.iter().map(|&(_, left, _)| left as u32).sum()
+    }
+}
+
+/// Copies of every tile we have not seen
+pub fn unseen_tiles(state: &PlayerState) -> [u8; 34] {
+    std::array::from_fn(|tile| 4 - state.tiles_seen[tile].min(4))
+}
+
+/// Shanten of a 3n+1 hand and the tiles lowering it with their copies left
+pub fn advancing_tiles(hand: &mut [u8; 34], len_div3: u8, unseen: &[u8; 34]) -> (i8, Vec<(Tile, u8)>) {
+    let shanten = calc_all(hand, len_div3);
+    let advance = (0..34)
+        .filter(|&tile| {
+            if hand[tile] >= 4 || unseen[tile] == 0 {
+                return false;
+            }
+            hand[tile] += 1;
+            let advanced = calc_all(hand, len_div3) < shanten;
+            hand[tile] -= 1;
+            advanced
+        })
+        .map(|tile| (must_tile!(tile), unseen[tile]))
+        .collect();
+    (shanten, advance)
+}
+
+/// Red five we have not seen of a five, None for other tiles
+fn unseen_aka(state: &PlayerState, tile: usize) -> Option<Tile> {
+    let suit = tile / 9;
+    (tile < 27 && tile % 9 == 4 && !state.akas_seen[suit]).then(|| must_tile!(tile).akaize())
+}
+
+/// Upgrade of drawing `tile` into a 3n+1 hand of `shanten` whose advancing tiles leave `advance_left` copies
+fn upgrade_kind(
+    state: &PlayerState,
+    hand: &mut [u8; 34],
+    tile: usize,
+    shanten: i8,
+    advance_left: u32,
+    unseen: &[u8; 34],
+) -> Option<UpgradeKind> {
+    let len_div3 = state.tehai_len_div3;
+    hand[tile] += 1;
+    let mut kept = vec![];
+    for discard in (0..34).filter(|&discard| discard != tile) {
+        if hand[discard] == 0 {
+            continue;
+        }
+        hand[discard] -= 1;
+        if calc_all(hand, len_div3) == shanten {
+            kept.push(discard);
+        }
+        hand[discard] += 1;
+    }
+    let kind = if kept.is_empty() {
+        None
+    } else if tile >= 27 && hand[tile] == 2 && yakuhai_han(state, must_tile!(tile)) > 0 {
+        Some(UpgradeKind::Yakuhai)
+    } else if state.dora_factor[tile] > 0 {
+        Some(UpgradeKind::Dora)
+    } else if kept.into_iter().any(|discard| {
+        hand[discard] -= 1;
+        let (_, advance) = advancing_tiles(hand, len_div3, unseen);
+        hand[discard] += 1;
+        advance.iter().map(|&(_, left)| left as u32).sum::<u32>() > advance_left
+    }) {
+        Some(UpgradeKind::Wider)
+    } else {
+        unseen_aka(state, tile).map(|_| UpgradeKind::Aka)
+    };
+    hand[tile] -= 1;
+    kind
+}
+
+/// Breakdown of every discard in hand, the ones keeping the best shanten with the most advancing tiles first.
+/// Empty when not at a discard.
+pub fn ukeire_breakdown(state: &PlayerState) -> Vec<UkeireBreakdown> {
+    if !state.last_cans.can_discard {
+        return vec![];
+    }
+    let unseen = unseen_tiles(state);
+    let mut hand = state.tehai;
+    let mut breakdowns = (0..34)
+        .filter(|&discard| state.tehai[discard] > 0)
+        .map(|discard| {
+            hand[discard] -= 1;
+            let (shanten, advance) = advancing_tiles(&mut hand, state.tehai_len_div3, &unseen);
+            let advance_left = advance.iter().map(|&(_, left)| left as u32).sum();
+            let draws = (0..34)
+                .filter(|&tile| hand[tile] < 4 && unseen[tile] > 0)
+                .filter(|&tile| !advance.iter().any(|(advancing, _)| advancing.as_usize() == tile))
+                .collect::<Vec<_>>();
+            let upgrade = draws
+                .into_iter()
+                .filter_map(|tile| {
+                    let kind = upgrade_kind(state, &mut hand, tile, shanten, advance_left, &unseen)?;
+                    Some(match unseen_aka(state, tile) {
+                        // only the red copy upgrades the hand
+                        Some(aka) if kind == UpgradeKind::Aka => (aka, 1, kind),
+                        _ => (must_tile!(tile), unseen[tile], kind),
+                    })
+                })
+                .collect();
+            hand[discard] += 1;
+            UkeireBreakdown {
+                discard: must_tile!(discard),
+                shanten,
+                advance,
+                upgrade,
+            }
+        })
+        .collect::<Vec<_>>();
+    breakdowns.sort_by(|a, b| {
+        a.shanten
+            .cmp(&b.shanten)
+            .then(b.advance_left().cmp(&a.advance_left()))
+            .then(b.upgrade_left().cmp(&a.upgrade_left()))
+    });
+    breakdowns
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::t;
+
+    use super::*;
+
+    #[test]
+    fn only_unseen_red_fives_are_aka_upgrades() {
+        let state = PlayerState {
+            akas_seen: [false, true, false],
+            ..Default::default()
+        };
+        assert_eq!(unseen_aka(&state, t!(5m).as_usize()), Some(t!(5mr)));
+        assert_eq!(unseen_aka(&state, t!(5p).as_usize()), None);
+        assert_eq!(unseen_aka(&state, t!(5s).as_usize()), Some(t!(5sr)));
+        assert_eq!(unseen_aka(&state, t!(4m).as_usize()), None);
+        assert_eq!(unseen_aka(&state, t!(E).as_usize()), None);
+    }
+}
@@ -0,0 +1,169 @@
+//! Ura dora modeling of the single player tables
+//! Mortal averages ura dora into every riichi win by its probability table. Washizu takes that average back out for
+//! rooms without ura dora and for finished hands whose ura indicators are known. Known indicators are then counted
+//! instead: in the values by the ura dora the hand keeps after the candidate, and in the agari of the waits, since
+//! mortal's agari only counts ura dora it revealed itself.
+use anyhow::{Context, Result};
+use riichi::algo::agari::{Agari, AgariWithYaku};
+use riichi::algo::sp::EventCandidate;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+use riichi::{must_tile, t};
+
+use crate::defense::candidate_discard;
+use crate::dora::{dora_factor, indicated_dora};
+use crate::input::hand_with_aka_vec;
+use crate::rules::han_gain_ratio;
+use crate::yakunames::URA_DORA;
+
+/// How ura dora are counted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum UraDora {
+    /// Averaged into riichi wins as mortal does
+    #[default]
+    Averaged,
+    /// Not counted at all
+    Disabled,
+    /// Indicators revealed at the end of the hand
+    Known(Vec<Tile>),
+}
+
+impl UraDora {
+    pub fn parse(no_ura: bool, known: Option<&str>) -> Result<Self> {
+        Ok(match known {
+            Some(indicators) => Self::Known(hand_with_aka_vec(indicators).context("incorrect ura indicators")?),
+            None if no_ura => Self::Disabled,
+            None => Self::Averaged,
+        })
+    }
+
+    /// Add the known ura dora of a riichi hand to mortal's agari of `tile`, the hand is complete when the tile is
+    /// unknown
+    pub fn apply_to_agari(&self, state: &PlayerState, tile: Tile, agari: &mut Option<AgariWithYaku>) {
+        let Self::Known(indicators) = self else {
+            return;
+        };
+        let Some(agari_with_yaku) = agari else {
+            return;
+        };
+        let Agari::Normal { fu, han } = agari_with_yaku.agari else {
+            return;
+        };
+        if !state.riichi_declared[0] {
+            return;
+        }
+        let factor = dora_factor(indicators);
+        let hand = hand_tiles(state, tile);
+        let ura = (0..34).map(|tile| hand[tile] * factor[tile]).sum::<u8>();
+        if ura > 0 {
+            agari_with_yaku.agari = Agari::Normal { fu, han: han + ura };
+            agari_with_yaku.yaku.push(URA_DORA);
+        }
+    }
+}
+
+/// Tiles of our riichi hand completed by `tile` that ura dora can hit, melds included
+fn hand_tiles(state: &PlayerState, tile: Tile) -> [u8; 34] {
+    let mut hand = state.tehai;
+    if tile != t!(?) {
+        hand[tile.deaka().as_usize()] += 1;
+    }
+    let ankan_tiles = state.ankan_overview[0].iter().flat_map(|&tile| [tile; 4]);
+    for meld in state.fuuro_overview[0].iter().flatten().copied().chain(ankan_tiles) {
+        hand[meld.deaka().as_usize()] += 1;
+    }
+    hand
+}
+
+/// Ura dora a riichi win on `tile` is expected to reveal, one indicator under every dora indicator drawn from the
+/// tiles we have not seen
+pub fn expected_ura(state: &PlayerState, tile: Tile) -> f32 {
+    let hand = hand_tiles(state, tile);
+    let unseen = std::array::from_fn::<_, 34, _>(|tile| 4 - state.tiles_seen[tile].min(4) as u32);
+    let total = unseen.iter().sum::<u32>().max(1) as f32;
+    let per_indicator = (0..34)
+        .map(|indicator| {
+            let dora = indicated_dora(must_tile!(indicator));
+            unseen[indicator] as f32 / total * hand[dora.as_usize()] as f32
+        })
+        .sum::<f32>();
+    per_indicator * state.dora_indicators.len() as f32
+}
+
+/// Scale a candidate's expected values up by the known ura dora in the hand it keeps, on the wins mortal averaged ura
+/// dora into. Tiles drawn later can add more, which only the agari of the waits count.
+pub fn apply_known_ura(candidate: &mut EventCandidate, state: &PlayerState, indicators: &[Tile]) {
+    let mut hand = hand_tiles(state, t!(?));
+    if let Some(discard) = candidate_discard(candidate) {
+        hand[discard.deaka().as_usize()] = hand[discard.deaka().as_usize()].saturating_sub(1);
+    }
+    let factor = dora_factor(indicators);
+    let ura = (0..34).map(|tile| hand[tile] * factor[tile]).sum::<u8>();
+    if ura == 0 {
+        return;
+    }
+    for (index, (exp_value, win_prob)) in candidate.exp_values.iter_mut().zip(&candidate.win_probs).enumerate() {
+        let Some(yaku_probs) = candidate.yaku.get(index) else {
+            continue;
+        };
+        if *win_prob <= 0.0 || yaku_probs.ura_dora <= 0.0 {
+            continue;
+        }
+        let average = *exp_value / win_prob;
+        let non_dealer_average = if state.is_oya() { average / 1.5 } else { average };
+        *exp_value *= han_gain_ratio(non_dealer_average, ura);
+    }
+}
+
+/// Scale a candidate's expected values down by the ura dora averaged into its wins
+pub fn remove_ura_average(candidate: &mut EventCandidate, is_oya: bool) {
+    for (index, (exp_value, win_prob)) in candidate.exp_values.iter_mut().zip(&candidate.win_probs).enumerate() {
+        let Some(yaku_probs) = candidate.yaku.get(index) else {
+            continue;
+        };
+        if *win_prob <= 0.0 || yaku_probs.ura_dora <= 0.0 {
+            continue;
+        }
+        let average = *exp_value / win_prob;
+        let non_dealer_average = if is_oya { average / 1.5 } else { average };
+        // a fraction of a han is interpolated from the gain of a whole one
+        let ura = yaku_probs.ura_dora / win_prob;
+        *exp_value /= 1.0 + ura * (han_gain_ratio(non_dealer_average, 1) - 1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Riichi hand holding only a concealed kan of 5m
+    fn riichi_with_ankan() -> PlayerState {
+        let mut state = PlayerState {
+            riichi_declared: [true, false, false, false],
+            ..Default::default()
+        };
+        state.ankan_overview[0].push(t!(5m));
+        state
+    }
+
+    #[test]
+    fn every_tile_of_an_ankan_counts_as_ura_dora() {
+        let mut agari = Some(AgariWithYaku {
+            agari: Agari::Normal { fu: 40, han: 1 },
+            yaku: vec![1],
+        });
+        UraDora::Known(vec![t!(4m)]).apply_to_agari(&riichi_with_ankan(), t!(?), &mut agari);
+        let agari = agari.unwrap();
+        assert!(matches!(agari.agari, Agari::Normal { fu: 40, han: 5 }));
+        assert!(agari.yaku.contains(&URA_DORA));
+    }
+
+    #[test]
+    fn expected_ura_counts_every_tile_of_an_ankan() {
+        let mut state = riichi_with_ankan();
+        state.dora_indicators.push(t!(E));
+        // only a 4m indicator of the 136 unseen tiles hits the four 5m
+        let expected = 4.0 / 136.0 * 4.0;
+        assert!((expected_ura(&state, t!(?)) - expected).abs() < 1e-6);
+    }
+}
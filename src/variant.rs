@@ -0,0 +1,127 @@
+//! Player counts other than four
+//! Mortal's events always have four seats. Three player logs that leave the empty seat out are padded with it so
+//! they can be analyzed as sanma, any other player count is rejected by name instead of failing in seat math.
+use anyhow::{Context, Result, bail};
+use riichi::mjai::Event;
+use serde_json::Value;
+
+use crate::error::WashizuError;
+
+/// Number of seats of a raw start_game or start_kyoku event, None for other events
+pub fn player_count(value: &Value) -> Option<usize> {
+    let field = match value.get("type")?.as_str()? {
+        "start_game" => "names",
+        "start_kyoku" => "tehais",
+        _ => return None,
+    };
+    let seats = value.get(field)?.as_array()?;
+    Some(
+        seats
+            .iter()
+            .filter(|seat| !seat.as_array().is_some_and(|tiles| tiles.is_empty()))
+            .count(),
+    )
+}
+
+fn variant_name(count: usize) -> String {
+    match count {
+        1 => "single player".to_owned(),
+        2 => "two player".to_owned(),
+        count => format!("{count} player"),
+    }
+}
+
+/// Pad the empty seat of three player events, fails for player counts that cannot be analyzed.
+/// The empty seat keeps its place when the hands mark it with an empty array and is the last seat otherwise, every
+/// field is padded at that seat so seat ids, names, hands and scores stay aligned.
+pub fn normalize_player_count(value: &mut Value) -> Result<()> {
+    let Some(count) = player_count(value) else {
+        return Ok(());
+    };
+    match count {
+        4 => Ok(()),
+        3 => {
+            let empty_seat = value
+                .get("tehais")
+                .and_then(Value::as_array)
+                .and_then(|tehais| {
+                    tehais
+                        .iter()
+                        .position(|seat| seat.as_array().is_some_and(|tiles| tiles.is_empty()))
+                })
+                .unwrap_or(3);
+            let pad = |value: &mut Value, field: &str, empty: Value| {
+                if let Some(seats) = value.get_mut(field).and_then(Value::as_array_mut) {
+                    if seats.len() == 3 {
+                        seats.insert(empty_seat, empty);
+                    } else if let Some(seat) = seats.get_mut(empty_seat) {
+                        *seat = empty;
+                    }
+                }
+            };
+            pad(value, "names", Value::from(""));
+            pad(value, "tehais", Value::from(vec!["?"; 13]));
+            pad(value, "scores", Value::from(0));
+            Ok(())
+        }
+        count => bail!(
+            "unsupported variant: {} game, only four player and sanma logs can be analyzed",
+            variant_name(count)
+        ),
+    }
+}
+
+/// Parse a line of a mjai log, padding three player events to four seats
+pub fn parse_event(line: &str) -> Result<Event> {
+    parse_padded_event(line).with_context(|| WashizuError::Event(line.to_owned()))
+}
+
+fn parse_padded_event(line: &str) -> Result<Event> {
+    let mut value: Value = serde_json::from_str(line).context("malformed json")?;
+    normalize_player_count(&mut value)?;
+    serde_json::from_value(value).context("malformed mjai event")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn empty_seat_in_the_middle_keeps_its_place() {
+        let tehai = vec!["1m"; 13];
+        let mut start_kyoku = json!({
+            "type": "start_kyoku",
+            "tehais": [tehai, [], tehai, tehai],
+            "scores": [35000, 35000, 35000],
+        });
+        normalize_player_count(&mut start_kyoku).unwrap();
+        assert_eq!(start_kyoku["tehais"][0], json!(tehai));
+        assert_eq!(start_kyoku["tehais"][1], json!(vec!["?"; 13]));
+        assert_eq!(start_kyoku["tehais"][3], json!(tehai));
+        assert_eq!(start_kyoku["scores"], json!([35000, 0, 35000, 35000]));
+
+        let mut start_kyoku = json!({
+            "type": "start_kyoku",
+            "tehais": [tehai, [], tehai, tehai],
+            "scores": [30000, 0, 35000, 40000],
+        });
+        normalize_player_count(&mut start_kyoku).unwrap();
+        assert_eq!(start_kyoku["scores"], json!([30000, 0, 35000, 40000]));
+    }
+
+    #[test]
+    fn missing_seat_is_padded_last() {
+        let mut start_game = json!({"type": "start_game", "names": ["a", "b", "c"]});
+        normalize_player_count(&mut start_game).unwrap();
+        assert_eq!(start_game["names"], json!(["a", "b", "c", ""]));
+    }
+
+    #[test]
+    fn other_player_counts_are_rejected_by_name() {
+        let mut start_game = json!({"type": "start_game", "names": ["a", "b"]});
+        let error = normalize_player_count(&mut start_game).unwrap_err().to_string();
+        assert!(error.contains("two player"), "{error}");
+    }
+}
@@ -0,0 +1,34 @@
+//! Version handshake for bindings generated against the washizu core
+//! Bindings record the core version they were built for and check it when loaded,
+//! so a stale binding fails with a clear message instead of misreading the report json.
+use anyhow::{Context, Result, ensure};
+
+/// Version of the core crate
+pub const CORE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Version of the `AnalysisReport` json layout, bumped whenever a field is removed or changes meaning
+pub const REPORT_SCHEMA_VERSION: u32 = 1;
+
+fn major_minor(version: &str) -> Result<(u64, u64)> {
+    let mut parts = version.trim().split('.');
+    let mut next = |name| -> Result<u64> {
+        parts
+            .next()
+            .with_context(|| format!("version {version} has no {name} number"))?
+            .parse()
+            .with_context(|| format!("version {version} has an invalid {name} number"))
+    };
+    Ok((next("major")?, next("minor")?))
+}
+
+/// Check that a binding built for `binding_core_version` can talk to this core.
+/// Follows semver: before 1.0 the minor version has to match as well.
+pub fn check_binding_version(binding_core_version: &str) -> Result<()> {
+    let (core_major, core_minor) = major_minor(CORE_VERSION)?;
+    let (major, minor) = major_minor(binding_core_version)?;
+    ensure!(
+        major == core_major && (major > 0 || minor == core_minor),
+        "binding was built for washizu {binding_core_version} but the core is {CORE_VERSION}, rebuild the binding"
+    );
+    Ok(())
+}
@@ -0,0 +1,276 @@
+//! Deal-in probabilities of each opponent from a posterior over mortal's waits
+//! Mortal's wait weights are fixed multipliers of each shape, so the tile weights only rank tiles within one situation.
+//! They are taken as the prior here and conditioned on the order of the kawa, the calls and the riichi turn, then
+//! normalized to a distribution over the live waits and scaled by the chance the opponent is tenpai. The result is a
+//! deal-in probability in percent, on the scale the rest of the defense already assumes.
+use riichi::algo::danger::{PlayerDanger, Wait, WaitShape};
+use riichi::must_tile;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+/// Discards that count as early in the kawa
+const EARLY_DISCARDS: usize = 6;
+/// Likelihood of a wait whose tiles sit next to an early discard of the same suit, the shape was cut or never held
+const EARLY_NEIGHBOR: f32 = 0.6;
+/// Likelihood of a ryanmen when most early discards are honors and terminals, a plain hand built from the middle
+const PLAIN_RYANMEN: f32 = 1.2;
+/// Likelihood of shanpon and tanki when middle tiles went out early, a hand built around pairs or value
+const ODD_SHAPES: f32 = 1.3;
+/// Likelihood of shanpon and tanki per pon, a hand with pons keeps pairs
+const PON_PAIRS: f32 = 1.15;
+/// Likelihood of waits in the suit of every called meld and outside of it, a flush
+const FLUSH_SUIT: f32 = 1.5;
+const OFF_SUIT: f32 = 0.4;
+/// Riichi turns that count as early and late, early riichi are more often good shapes
+const EARLY_RIICHI: usize = 6;
+const LATE_RIICHI: usize = 12;
+const EARLY_RIICHI_RYANMEN: f32 = 1.2;
+const LATE_RIICHI_GUKEI: f32 = 1.2;
+/// Likelihood of the suji reads mortal marks on a wait
+const MATAGI_RIICHI: f32 = 1.3;
+const SUJI_TRAP: f32 = 1.2;
+const URA_SUJI: f32 = 1.1;
+const MATAGI_EARLY: f32 = 0.8;
+/// Tenpai chance of an opponent by calls, riichi is certain
+const TENPAI_BY_CALLS: [f32; 5] = [0.05, 0.15, 0.35, 0.7, 1.0];
+
+fn suit(tile: u8) -> Option<u8> {
+    (tile < 27).then_some(tile / 9)
+}
+
+/// Chance that an opponent is tenpai before reading the waits
+pub fn tenpai_prior(state: &PlayerState, player: usize) -> f32 {
+    if state.riichi_declared[player] {
+        1.0
+    } else {
+        TENPAI_BY_CALLS[state.fuuro_overview[player].len().min(4)]
+    }
+}
+
+/// Evidence of the kawa and calls that does not depend on the wait
+struct Evidence {
+    early: Vec<u8>,
+    plain: bool,
+    odd: bool,
+    pons: usize,
+    flush: Option<u8>,
+    riichi_turn: Option<usize>,
+}
+
+impl Evidence {
+    fn new(state: &PlayerState, player: usize) -> Self {
+        let early = state.kawa_overview[player]
+            .iter()
+            .take(EARLY_DISCARDS)
+            .map(|tile| tile.deaka().as_u8())
+            .collect::<Vec<_>>();
+        let outside = early.iter().filter(|&&tile| tile >= 27 || matches!(tile % 9, 0 | 8)).count();
+        let middle = early
+            .iter()
+            .filter(|&&tile| tile < 27 && (2..=6).contains(&(tile % 9)))
+            .count();
+        let melds = &state.fuuro_overview[player];
+        let pons = melds
+            .iter()
+            .filter(|meld| meld.len() >= 3 && meld.iter().all(|tile| tile.deaka() == meld[0].deaka()))
+            .count();
+        let suits = melds
+            .iter()
+            .filter_map(|meld| suit(meld[0].deaka().as_u8()))
+            .collect::<Vec<_>>();
+        let flush = suits
+            .first()
+            .copied()
+            .filter(|first| suits.len() >= 2 && suits.iter().all(|suit| suit == first));
+        let riichi_turn = state.kawa[player]
+            .iter()
+            .position(|item| item.as_ref().is_some_and(|item| item.sutehai.is_riichi));
+        Self {
+            plain: early.len() >= 4 && outside * 3 >= early.len() * 2,
+            odd: middle >= 2,
+            early,
+            pons,
+            flush,
+            riichi_turn,
+        }
+    }
+
+    /// Likelihood of the evidence under a wait, relative to mortal's prior
+    fn likelihood(&self, wait: &Wait) -> f32 {
+        let mut likelihood = self.shape_likelihood(&wait.kind.shape, &wait.kind.tiles, &wait.kind.waits);
+        if wait.matagi_suji_riichi {
+            likelihood *= MATAGI_RIICHI;
+        }
+        if wait.riichi_suji_trap {
+            likelihood *= SUJI_TRAP;
+        }
+        if wait.ura_suji {
+            likelihood *= URA_SUJI;
+        }
+        if wait.matagi_suji_early {
+            likelihood *= MATAGI_EARLY;
+        }
+        likelihood
+    }
+
+    /// Likelihood of the kawa and calls under a wait shape made of `tiles` and waiting on `waits`
+    fn shape_likelihood(&self, shape: &WaitShape, tiles: &[u8], waits: &[u8]) -> f32 {
+        let mut likelihood = 1.0;
+        let near_early = tiles.iter().any(|&tile| {
+            self.early
+                .iter()
+                .any(|&early| suit(early).is_some() && suit(early) == suit(tile) && early != tile && early.abs_diff(tile) <= 1)
+        });
+        if near_early && matches!(shape, WaitShape::Ryanmen | WaitShape::Kanchan | WaitShape::Penchan) {
+            likelihood *= EARLY_NEIGHBOR;
+        }
+        let pairs = matches!(shape, WaitShape::Shanpon | WaitShape::Tanki);
+        if self.plain && matches!(shape, WaitShape::Ryanmen) {
+            likelihood *= PLAIN_RYANMEN;
+        }
+        if self.odd && pairs {
+            likelihood *= ODD_SHAPES;
+        }
+        if pairs {
+            likelihood *= PON_PAIRS.powi(self.pons as i32);
+        }
+        if let Some(flush) = self.flush {
+            let wait_suit = waits.first().and_then(|&tile| suit(tile));
+            match wait_suit {
+                Some(wait_suit) if wait_suit == flush => likelihood *= FLUSH_SUIT,
+                Some(_) => likelihood *= OFF_SUIT,
+                None => {}
+            }
+        }
+        match self.riichi_turn {
+            Some(turn) if turn < EARLY_RIICHI && matches!(shape, WaitShape::Ryanmen) => likelihood *= EARLY_RIICHI_RYANMEN,
+            Some(turn) if turn >= LATE_RIICHI && !matches!(shape, WaitShape::Ryanmen) => likelihood *= LATE_RIICHI_GUKEI,
+            _ => {}
+        }
+        likelihood
+    }
+}
+
+/// Chance of each wait from its prior weight times the likelihood of the evidence, summing to the tenpai chance.
+/// All zero when no wait has any weight.
+fn wait_posterior(weights: &[f32], tenpai: f32) -> Vec<f32> {
+    let total = weights.iter().sum::<f32>();
+    weights
+        .iter()
+        .map(|weight| if total > 0.0 { tenpai * weight / total } else { 0.0 })
+        .collect()
+}
+
+/// Deal-in probability in percent of every tile against an opponent, sorted from the most dangerous.
+/// Waits that are `dead` are left out of the posterior, always empty for ourselves.
+pub fn deal_in_weights(
+    state: &PlayerState,
+    player: usize,
+    danger: &PlayerDanger,
+    dead: impl Fn(&Wait) -> bool,
+) -> Vec<(Tile, f32)> {
+    if player == 0 {
+        return vec![];
+    }
+    let evidence = Evidence::new(state, player);
+    let posterior = danger
+        .waits
+        .iter()
+        .filter(|wait| !wait.genbutsu && !dead(wait))
+        .map(|wait| (wait, wait.weight.max(0.0) * evidence.likelihood(wait)))
+        .collect::<Vec<_>>();
+    let priors = posterior.iter().map(|(_, weight)| *weight).collect::<Vec<_>>();
+    let mut probs = [0.0; 34];
+    for ((wait, _), prob) in posterior.iter().zip(wait_posterior(&priors, tenpai_prior(state, player))) {
+        for &tile in &wait.kind.waits {
+            probs[tile as usize] += 100.0 * prob;
+        }
+    }
+    let mut weights = (0..34)
+        .filter(|&tile| probs[tile] > 0.0)
+        .map(|tile| (must_tile!(tile), probs[tile].min(100.0)))
+        .collect::<Vec<_>>();
+    weights.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    weights
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Evidence of a kawa that tells nothing about the wait
+    fn neutral() -> Evidence {
+        Evidence {
+            early: vec![],
+            plain: false,
+            odd: false,
+            pons: 0,
+            flush: None,
+            riichi_turn: None,
+        }
+    }
+
+    /// 3m4m waiting on 2m5m
+    fn ryanmen(evidence: &Evidence) -> f32 {
+        evidence.shape_likelihood(&WaitShape::Ryanmen, &[2, 3], &[1, 4])
+    }
+
+    /// 3m5m waiting on 4m
+    fn kanchan(evidence: &Evidence) -> f32 {
+        evidence.shape_likelihood(&WaitShape::Kanchan, &[2, 4], &[3])
+    }
+
+    #[test]
+    fn posterior_sums_to_the_tenpai_prior() {
+        let mut state = PlayerState::default();
+        state.riichi_declared[1] = true;
+        let riichi = wait_posterior(&[3.0, 1.0, 0.5], tenpai_prior(&state, 1));
+        assert!((riichi.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+        assert!((riichi[0] - 3.0 / 4.5).abs() < 1e-6);
+        let open = wait_posterior(&[3.0, 1.0, 0.5], TENPAI_BY_CALLS[2]);
+        assert!((open.iter().sum::<f32>() - TENPAI_BY_CALLS[2]).abs() < 1e-6);
+        assert_eq!(wait_posterior(&[0.0, 0.0], 1.0), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn flush_favors_waits_in_the_called_suit() {
+        let flush = Evidence {
+            flush: Some(0),
+            ..neutral()
+        };
+        assert!(ryanmen(&flush) > ryanmen(&neutral()));
+        // 3p4p waiting on 2p5p
+        let off_suit = |evidence: &Evidence| evidence.shape_likelihood(&WaitShape::Ryanmen, &[11, 12], &[10, 13]);
+        assert!(off_suit(&flush) < off_suit(&neutral()));
+    }
+
+    #[test]
+    fn early_neighbors_make_sequence_waits_less_likely() {
+        // 5m went out early, next to the 4m of the ryanmen
+        let neighbor = Evidence {
+            early: vec![4],
+            ..neutral()
+        };
+        assert!(ryanmen(&neighbor) < ryanmen(&neutral()));
+        // 5p is in another suit
+        let other_suit = Evidence {
+            early: vec![13],
+            ..neutral()
+        };
+        assert_eq!(ryanmen(&other_suit), ryanmen(&neutral()));
+        let tanki = |evidence: &Evidence| evidence.shape_likelihood(&WaitShape::Tanki, &[3], &[3]);
+        assert_eq!(tanki(&neighbor), tanki(&neutral()));
+    }
+
+    #[test]
+    fn riichi_turn_shifts_weight_between_good_and_bad_shapes() {
+        let riichi_at = |turn| Evidence {
+            riichi_turn: Some(turn),
+            ..neutral()
+        };
+        assert!(ryanmen(&riichi_at(3)) > ryanmen(&neutral()));
+        assert_eq!(kanchan(&riichi_at(3)), kanchan(&neutral()));
+        assert!(kanchan(&riichi_at(14)) > kanchan(&neutral()));
+        assert_eq!(ryanmen(&riichi_at(14)), ryanmen(&neutral()));
+    }
+}
@@ -0,0 +1,48 @@
+//! Browser exports of the analysis for wasm32-unknown-unknown
+//! Reports are returned as JSON in the format of the bundled schema and errors are thrown with their cause chain.
+//! Rules are a preset name or the text of a rules toml, since there is no filesystem to load them from.
+use wasm_bindgen::prelude::*;
+
+use crate::api;
+use crate::report::AnalysisReport;
+use crate::rules::RuleSet;
+use crate::state::AnalysisOptions;
+
+fn options(rules: Option<String>, max_shanten: Option<i8>) -> Result<AnalysisOptions, JsError> {
+    let rules = match rules.as_deref() {
+        None | Some("tenhou") => RuleSet::tenhou(),
+        Some("majsoul") => RuleSet::majsoul(),
+        Some(text) => RuleSet::from_toml(text).map_err(|e| JsError::new(&format!("malformed rules: {e:#}")))?,
+    };
+    Ok(AnalysisOptions {
+        rules,
+        max_shanten,
+        ..AnalysisOptions::default()
+    })
+}
+
+fn to_json(report: anyhow::Result<AnalysisReport>) -> Result<String, JsError> {
+    let report = report.map_err(|e| JsError::new(&format!("{e:#}")))?;
+    serde_json::to_string(&report).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Analyze a hand of closed tiles and open melds separated by spaces, e.g. `123m456p789s11223z` and `555z`
+#[wasm_bindgen(js_name = analyzeHand)]
+pub fn analyze_hand(
+    tehai: &str,
+    fuuro: Option<String>,
+    rules: Option<String>,
+    max_shanten: Option<i8>,
+) -> Result<String, JsError> {
+    let hand = api::Hand {
+        fuuro,
+        ..api::Hand::new(tehai)
+    };
+    to_json(api::analyze_hand(&hand, &options(rules, max_shanten)?))
+}
+
+/// Analyze the last decision of a board in the syntax of the board command
+#[wasm_bindgen(js_name = analyzeBoard)]
+pub fn analyze_board(board: &str, rules: Option<String>, max_shanten: Option<i8>) -> Result<String, JsError> {
+    to_json(api::analyze_board(board, &options(rules, max_shanten)?))
+}
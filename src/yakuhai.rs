@@ -0,0 +1,155 @@
+//! Advisory for keeping a single yakuhai in the hope of pairing it
+//! Without tegawari the single player tables never draw the second copy, so an isolated yakuhai looks like a plain
+//! honor to them. This compares the value of completing its triplet against discarding it for shape, a pair alone
+//! gives no han.
+use riichi::algo::sp::EventCandidate;
+use riichi::must_tile;
+use riichi::state::PlayerState;
+use riichi::tile::Tile;
+
+use crate::defense::candidate_discard;
+use crate::rules::han_gain_ratio;
+
+/// Estimate for one isolated yakuhai in hand
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct YakuhaiAdvice {
+    pub tile: Tile,
+    /// Han of a triplet, 2 for a double wind
+    pub han: u8,
+    /// Copies not seen by us
+    pub left: u8,
+    /// Chance of drawing one of the copies left over our remaining tsumos
+    pub pair_prob: f32,
+    /// Chance of drawing two of the copies left over our remaining tsumos
+    #[serde(default)]
+    pub triplet_prob: f32,
+    /// Expected value of discarding it
+    pub discard_ev: f32,
+    /// Expected value of the best candidate keeping it, including the estimated gain of completing its triplet
+    pub keep_ev: f32,
+}
+
+impl YakuhaiAdvice {
+    pub fn keep(&self) -> bool {
+        self.keep_ev > self.discard_ev
+    }
+}
+
+/// Han of a triplet of the tile, counting the round and seat wind separately so a double east is worth 2
+pub fn yakuhai_han(state: &PlayerState, tile: Tile) -> u8 {
+    let dragon = (31..34).contains(&tile.as_usize()) as u8;
+    dragon + (tile == state.bakaze) as u8 + (tile == state.jikaze) as u8
+}
+
+/// Chance of drawing at least `needed` of `wanted` tiles out of `unseen` over `draws` tsumos
+fn draw_prob(unseen: u32, wanted: u32, needed: usize, draws: u32) -> f32 {
+    // chance of having drawn each count of the wanted tiles so far, the last one counting `needed` or more
+    let mut drawn = vec![0.0; needed + 1];
+    drawn[0] = 1.0;
+    for i in 0..draws.min(unseen) {
+        for k in (0..needed).rev() {
+            let hit = wanted.saturating_sub(k as u32) as f32 / (unseen - i) as f32;
+            drawn[k + 1] += drawn[k] * hit;
+            drawn[k] *= 1.0 - hit;
+        }
+    }
+    drawn[needed]
+}
+
+/// Advice for every single yakuhai in hand with at least one copy left, only at a discard
+pub fn yakuhai_advice(state: &PlayerState, candidates: &[EventCandidate]) -> Vec<YakuhaiAdvice> {
+    if !state.last_cans.can_discard {
+        return vec![];
+    }
+    let unseen = state.tiles_seen.iter().map(|&seen| 4 - seen.min(4) as u32).sum::<u32>();
+    let draws = (state.tiles_left as u32).div_ceil(4);
+    (27..34)
+        .filter(|&tile| state.tehai[tile] == 1)
+        .filter_map(|tile| {
+            let tile = must_tile!(tile);
+            let han = yakuhai_han(state, tile);
+            let left = 4 - state.tiles_seen[tile.as_usize()].min(4);
+            if han == 0 || left == 0 {
+                return None;
+            }
+            let is_tile = |candidate: &&EventCandidate| candidate_discard(candidate).is_some_and(|discard| discard == tile);
+            let discard = candidates.iter().find(is_tile)?;
+            let keep = candidates.iter().filter(|candidate| !is_tile(candidate)).max_by(|a, b| {
+                a.exp_values
+                    .first()
+                    .unwrap_or(&0.0)
+                    .total_cmp(b.exp_values.first().unwrap_or(&0.0))
+            })?;
+            let pair_prob = draw_prob(unseen, left as u32, 1, draws);
+            let triplet_prob = draw_prob(unseen, left as u32, 2, draws);
+            let exp_value = keep.exp_values.first().copied().unwrap_or(0.0);
+            let win_prob = keep.win_probs.first().copied().unwrap_or(0.0);
+            let average = if win_prob > 0.0 { exp_value / win_prob } else { 0.0 };
+            let non_dealer_average = if state.is_oya() { average / 1.5 } else { average };
+            let gain = exp_value * (han_gain_ratio(non_dealer_average, han) - 1.0);
+            Some(YakuhaiAdvice {
+                tile,
+                han,
+                left,
+                pair_prob,
+                triplet_prob,
+                discard_ev: discard.exp_values.first().copied().unwrap_or(0.0),
+                keep_ev: exp_value + triplet_prob * gain,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi::mjai::Event;
+    use riichi::t;
+
+    use super::*;
+
+    fn discard(pai: Tile, exp_value: f32) -> EventCandidate {
+        EventCandidate {
+            event: Event::Dahai {
+                actor: 0,
+                pai,
+                tsumogiri: false,
+            },
+            shanten: 1,
+            num_required_tiles: 0,
+            required_tiles: vec![],
+            exp_values: vec![exp_value],
+            win_probs: vec![0.25],
+            tenpai_probs: vec![0.5],
+            yaku: vec![],
+        }
+    }
+
+    #[test]
+    fn draw_prob_counts_the_needed_copies() {
+        assert!((draw_prob(4, 2, 1, 2) - 5.0 / 6.0).abs() < 1e-6);
+        assert!((draw_prob(4, 2, 2, 2) - 1.0 / 6.0).abs() < 1e-6);
+        assert_eq!(draw_prob(4, 1, 2, 4), 0.0);
+        assert_eq!(draw_prob(4, 2, 0, 1), 1.0);
+    }
+
+    #[test]
+    fn keeping_is_worth_the_triplet_not_the_pair() {
+        let mut state = PlayerState {
+            oya: 1,
+            tiles_left: 60,
+            ..Default::default()
+        };
+        state.last_cans.can_discard = true;
+        state.tehai[t!(C).as_usize()] = 1;
+        state.tiles_seen[t!(C).as_usize()] = 1;
+        let candidates = [discard(t!(C), 1000.0), discard(t!(9m), 900.0)];
+        let advice = yakuhai_advice(&state, &candidates);
+        assert_eq!(advice.len(), 1);
+        let advice = &advice[0];
+        assert_eq!((advice.tile, advice.han, advice.left), (t!(C), 1, 3));
+        assert!(advice.triplet_prob > 0.0 && advice.triplet_prob < advice.pair_prob);
+        // the keep value grows by the han weighted by the chance of the triplet, not of merely pairing it
+        let gain = 900.0 * (han_gain_ratio(900.0 / 0.25, 1) - 1.0);
+        assert!((advice.keep_ev - (900.0 + advice.triplet_prob * gain)).abs() < 1e-3);
+    }
+}
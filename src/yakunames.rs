@@ -0,0 +1,55 @@
+//! Naming scheme of yaku in every output
+//! Mortal's names are used for its own yaku, the yaku washizu adds on top of mortal's agari are named here. The scheme
+//! follows --lang unless chosen explicitly.
+use riichi::algo::agari::yaku::{YakuLanguage, localize_yaku};
+
+/// Yaku ids of the dora counts in tenhou's numbering
+pub const DORA: u8 = 52;
+pub const URA_DORA: u8 = 53;
+pub const AKA_DORA: u8 = 54;
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum YakuNaming {
+    /// Short romaji, e.g. Riichi, Tsumo, Haitei
+    #[default]
+    Romaji,
+    English,
+    /// Kanji, e.g. 立直, 門前清自摸和
+    Japanese,
+}
+
+impl YakuNaming {
+    fn language(self) -> YakuLanguage {
+        match self {
+            Self::Romaji => YakuLanguage::RomajiShort,
+            Self::English => YakuLanguage::English,
+            Self::Japanese => YakuLanguage::Japanese,
+        }
+    }
+
+    /// Name of one of mortal's yaku by its id
+    pub fn yaku(self, yaku: u8) -> String {
+        localize_yaku(yaku, self.language())
+    }
+
+    /// Names of the yaku of mortal's agari
+    pub fn yaku_list(self, yaku: &[u8]) -> Vec<String> {
+        yaku.iter().map(|&yaku| self.yaku(yaku)).collect()
+    }
+
+    /// Name of a yaku washizu adds, given by its romaji name
+    pub fn washizu_yaku(self, name: &str) -> String {
+        let (english, japanese) = match name {
+            "Daisharin" => ("Big Wheels", "大車輪"),
+            "Renhou" => ("Blessing of Man", "人和"),
+            "Open Riichi" => ("Open Riichi", "オープン立直"),
+            "Kita" => ("North Dora", "抜きドラ"),
+            _ => (name, name),
+        };
+        match self {
+            Self::Romaji => name.to_owned(),
+            Self::English => english.to_owned(),
+            Self::Japanese => japanese.to_owned(),
+        }
+    }
+}
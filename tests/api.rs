@@ -0,0 +1,158 @@
+use washizu::error::WashizuError;
+use washizu::{AnalysisOptions, Hand, analyze_board, analyze_hand, analyze_log, board_from_mjai, mjai_from_board};
+
+const FIRST_TURN_BOARD: &str = "E1 E 0 0 1m 25000 25000 25000 25000 123m456p789s11223z / / / / / / / /";
+
+#[test]
+fn hand_is_analyzed_through_the_library() {
+    let report = analyze_hand(&Hand::new("123m456p789s11223z"), &AnalysisOptions::default()).unwrap();
+    assert_eq!(report.shanten, 0);
+    assert!(!report.candidates.is_empty());
+    assert!(report.riichi_decision.is_some());
+}
+
+#[test]
+fn malformed_hand_is_an_error() {
+    let error = analyze_hand(&Hand::new("123x"), &AnalysisOptions::default()).unwrap_err();
+    assert_eq!(error.downcast_ref(), Some(&WashizuError::Hand("123x".to_owned())));
+}
+
+#[test]
+fn hand_melds_are_read_from_their_fields() {
+    let hand = Hand {
+        fuuro: Some("555z".to_owned()),
+        ..Hand::new("123m456p789s12z")
+    };
+    let report = analyze_hand(&hand, &AnalysisOptions::default()).unwrap();
+    assert_eq!(report.tehai, "123m456p789s12z");
+    assert_eq!(report.shanten, 0);
+    // the pon leaves the hand open
+    assert!(report.riichi_decision.is_none());
+}
+
+#[test]
+fn board_is_analyzed_through_the_library() {
+    let report = analyze_board(FIRST_TURN_BOARD, &AnalysisOptions::default()).unwrap();
+    assert_eq!(report.shanten, 0);
+}
+
+#[test]
+fn board_round_trips_through_mjai() {
+    let log = mjai_from_board(FIRST_TURN_BOARD).unwrap();
+    assert_eq!(log.lines().count(), 3);
+    assert_eq!(board_from_mjai(&log, 0).unwrap(), FIRST_TURN_BOARD);
+    assert!(board_from_mjai(&log, 4).is_err());
+}
+
+#[test]
+fn log_is_analyzed_at_every_decision_of_the_player() {
+    let log = mjai_from_board(FIRST_TURN_BOARD).unwrap();
+    let reports = analyze_log(&log, Some(0), &AnalysisOptions::default()).unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(
+        reports[0].tehai,
+        analyze_board(FIRST_TURN_BOARD, &AnalysisOptions::default()).unwrap().tehai
+    );
+}
+
+#[test]
+fn unknown_discards_do_not_make_our_tiles_safer() {
+    // the second player declared riichi with discards we did not see
+    let board = "E1 E 0 0 1m 25000 25000 25000 25000 23456m3456p45678s 9s.9p. ??- ?? ?? / / / /";
+    let report = analyze_board(board, &AnalysisOptions::default()).unwrap();
+    let hand = [
+        "2m", "3m", "4m", "5m", "6m", "3p", "4p", "5p", "6p", "4s", "5s", "6s", "7s", "8s",
+    ];
+    let danger = serde_json::to_value(&report.danger[1]).unwrap();
+    let dangerous = danger
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tile_danger| tile_danger[0].as_str().unwrap())
+        .collect::<Vec<_>>();
+    for tile in hand {
+        assert!(dangerous.contains(&tile), "{tile} became safe against the riichi: {danger}");
+    }
+    let suji = serde_json::to_value(&report.suji[1]).unwrap();
+    for annotation in suji.as_array().unwrap() {
+        let tile = annotation[0].as_str().unwrap();
+        assert!(
+            !hand.contains(&tile) || annotation[1] != "Suji",
+            "{tile} became suji against the riichi: {suji}"
+        );
+    }
+}
+
+#[test]
+fn win_is_listed_among_the_candidates() {
+    let board = "E1 E 0 0 1m 25000 25000 25000 25000 123m456p789s11222z / / / / / / / /";
+    let report = analyze_board(board, &AnalysisOptions::default()).unwrap();
+    let hora = report.hora.as_ref().expect("tsumo is possible");
+    assert!(hora.take());
+    let best = &report.candidates[0];
+    assert!(
+        matches!(best.event, riichi::mjai::Event::Hora { actor: 0, target: 0, .. }),
+        "{best:?}"
+    );
+    assert_eq!(best.exp_value, hora.points_with_ura);
+    assert_eq!(best.shanten, -1);
+    assert!(best.delta > 0.0);
+}
+
+#[test]
+fn non_ascii_kyoku_is_an_error() {
+    assert!(
+        analyze_board(
+            "é E 0 0 1m 25000 25000 25000 25000 123m456p789s11223z / / / / / / / /",
+            &AnalysisOptions::default()
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn fast_estimate_picks_a_discard_of_the_better_half() {
+    // 3-shanten hands where both the estimate and the exact tables run
+    for tehai in ["2457m1368p3459s13z", "123469m179p358s26z", "156m348p135799s47z"] {
+        let exact = analyze_hand(&Hand::new(tehai), &AnalysisOptions::default()).unwrap();
+        let options = AnalysisOptions {
+            fast: true,
+            ..Default::default()
+        };
+        let fast = analyze_hand(&Hand::new(tehai), &options).unwrap();
+        assert!(fast.candidates.is_empty());
+        let pick = fast.fast_estimates[0].discard;
+        let mut values = exact
+            .candidates
+            .iter()
+            .filter(|candidate| matches!(candidate.event, riichi::mjai::Event::Dahai { .. }))
+            .map(|candidate| candidate.exp_value)
+            .collect::<Vec<_>>();
+        values.sort_by(|a, b| b.total_cmp(a));
+        let picked = exact
+            .candidates
+            .iter()
+            .find(|candidate| matches!(candidate.event, riichi::mjai::Event::Dahai { pai, .. } if pai == pick))
+            .expect("the estimate picks a discard the tables value");
+        assert!(
+            picked.exp_value >= values[values.len() / 2],
+            "{tehai}: {pick} is worth {} of {values:?}",
+            picked.exp_value
+        );
+    }
+}
+
+#[test]
+fn tables_over_budget_fall_back_to_the_fast_estimate() {
+    let options = AnalysisOptions {
+        sp_budget: Some(std::time::Duration::ZERO),
+        ..Default::default()
+    };
+    let report = analyze_board(FIRST_TURN_BOARD, &options).unwrap();
+    assert!(report.tables.over_budget);
+    assert!(report.candidates.is_empty());
+    assert!(!report.fast_estimates.is_empty());
+    let report = analyze_board(FIRST_TURN_BOARD, &AnalysisOptions::default()).unwrap();
+    assert!(!report.tables.over_budget);
+    assert!(report.fast_estimates.is_empty());
+}
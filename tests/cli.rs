@@ -0,0 +1,1081 @@
+use std::io::Write;
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+fn washizu() -> Command {
+    Command::cargo_bin("washizu").unwrap()
+}
+
+/// Dealer's first discard with an otherwise empty board
+const FIRST_TURN_BOARD: [&str; 18] = [
+    "E1",
+    "E",
+    "0",
+    "0",
+    "1m",
+    "25000",
+    "25000",
+    "25000",
+    "25000",
+    "123m456p789s11223z",
+    "/",
+    "/",
+    "/",
+    "/",
+    "/",
+    "/",
+    "/",
+    "/",
+];
+
+/// First candidate line of the analysis output
+fn best_candidate(output: &str) -> String {
+    output
+        .lines()
+        .skip_while(|line| !line.starts_with("act"))
+        .skip(1)
+        .find(|line| !line.starts_with("--"))
+        .expect("no candidates in output")
+        .to_owned()
+}
+
+#[test]
+fn hand_recommends_tenpai_discard() {
+    let output = washizu().args(["hand", "123m456p789s11223z"]).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("123m456p789s11223z (0)"), "unexpected header in {stdout}");
+    assert!(
+        best_candidate(&stdout).starts_with("3z"),
+        "unexpected best discard in {stdout}"
+    );
+}
+
+#[test]
+fn hand_shows_waits_of_tenpai_hand() {
+    washizu()
+        .args(["hand", "123m456p789s1122z"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("waits: 1z").and(predicate::str::contains("2z")));
+}
+
+#[test]
+fn hand_breaks_down_ukeire_per_discard() {
+    // the default dora indicator is a third east
+    washizu()
+        .args(["hand", "123m456p789s11223z", "--ukeire"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ukeire:").and(predicate::str::contains("3z  0 | advance 3 1z(1) 2z(2)")));
+    washizu()
+        .args(["hand", "123m456p789s11223z"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ukeire:").not());
+}
+
+#[test]
+fn hand_classifies_its_shape() {
+    washizu()
+        .args(["hand", "234m567p3467s5566z"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "shape: perfect iishanten | blocks: 234m 567p 34s 67s 55z 66z",
+        ));
+}
+
+#[test]
+fn hand_renders_unicode_tiles() {
+    washizu()
+        .args(["--tiles", "unicode", "hand", "123m456p789s11223z"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("🀇🀈🀉🀜🀝🀞🀖🀗🀘🀀🀀🀁🀁🀂 (0)"));
+}
+
+#[test]
+fn hand_prints_colors_when_forced() {
+    washizu()
+        .env("CLICOLOR_FORCE", "1")
+        .env_remove("NO_COLOR")
+        .args(["hand", "11199m24456p789s3z"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b["));
+}
+
+#[test]
+fn hand_prints_without_color() {
+    washizu()
+        .env("CLICOLOR_FORCE", "1")
+        .env_remove("NO_COLOR")
+        .args(["--no-color", "hand", "11199m24456p789s3z"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("yakuless").and(predicate::str::contains("\x1b[").not()));
+}
+
+#[test]
+fn hand_over_budget_shows_the_fast_estimate() {
+    washizu()
+        .args(["--sp-budget-ms", "0", "hand", "11199m24456p789s3z"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("fast estimate (tables over budget):"));
+}
+
+#[test]
+fn hand_rolls_out_every_discard() {
+    washizu()
+        .args(["--rollout", "20", "hand", "11199m24456p789s3z"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("rollout 3z: win").and(predicate::str::contains("place ")));
+}
+
+#[test]
+fn hand_marks_yakuless_tenpai() {
+    washizu()
+        .args(["hand", "11199m24456p789s3z"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[3p 4 yakuless]"));
+}
+
+#[test]
+fn hand_rejects_invalid_tiles() {
+    washizu().args(["hand", "123x"]).assert().failure();
+}
+
+#[test]
+fn board_analyzes_first_turn() {
+    let output = washizu()
+        .arg("board")
+        .args(FIRST_TURN_BOARD)
+        .arg("/")
+        .arg("/")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        best_candidate(&stdout).starts_with("3z"),
+        "unexpected best discard in {stdout}"
+    );
+}
+
+#[test]
+fn board_shows_score_situation() {
+    let mut board = FIRST_TURN_BOARD;
+    board[6] = "31000";
+    board[7] = "22000";
+    board[8] = "22000";
+    washizu().arg("board").args(board).arg("/").arg("/").assert().success().stdout(predicate::str::contains(
+        "scores: 0: 25000 2nd* | 1: 31000 1st | 2: 22000 3rd | 3: 22000 4th | gap +6000 to 1st, -3000 to 3rd | honba 0 kyotaku 0",
+    ));
+}
+
+#[test]
+fn dora_options_reject_unsupported_values() {
+    washizu()
+        .args(["hand", "123m456p789s11223z", "--aka-count", "2"])
+        .assert()
+        .failure();
+    washizu()
+        .args(["hand", "123m456p789s11223z", "--aka-count", "4"])
+        .assert()
+        .failure();
+    washizu()
+        .args(["hand", "123m456p789s11223z", "--no-ura", "--ura-known", "3p"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn ura_options_change_the_values() {
+    let values = |args: &[&str]| {
+        let output = washizu().arg("hand").arg("123m456p789s11223z").args(args).output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let averaged = values(&[]);
+    let disabled = values(&["--no-ura"]);
+    // 9m indicates the 1m in hand
+    let known = values(&["--ura-known", "9m"]);
+    assert_ne!(best_candidate(&averaged), best_candidate(&disabled));
+    assert_ne!(best_candidate(&known), best_candidate(&disabled));
+    // 5m indicates nothing in hand
+    assert_eq!(best_candidate(&values(&["--ura-known", "5m"])), best_candidate(&disabled));
+}
+
+#[test]
+fn agari_scores_riichi_pinfu_ron() {
+    washizu()
+        .args([
+            "agari",
+            "123456m234p56s99p",
+            "4s",
+            "--riichi",
+            "--jikaze",
+            "S",
+            "--honba",
+            "1",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("2han30fu = 2000"))
+        .stdout(predicates::str::contains("fu: base 20 + menzen ron 10 = 30"))
+        .stdout(predicates::str::contains("payment: ron 2000+300 from the discarder"));
+}
+
+#[test]
+fn agari_names_yaku_by_lang() {
+    washizu()
+        .args(["--lang", "ja", "agari", "123456m234p56s99p", "4s", "--riichi"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("立直"));
+    washizu()
+        .args([
+            "--lang",
+            "ja",
+            "--yaku-names",
+            "english",
+            "agari",
+            "123456m234p56s99p",
+            "4s",
+            "--riichi",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("立直").not());
+}
+
+#[test]
+fn parse_emits_valid_mjai_events() {
+    let output = washizu()
+        .arg("parse")
+        .args(FIRST_TURN_BOARD)
+        .arg("/")
+        .arg("/")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let events = stdout
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    let types = events.iter().map(|event| event["type"].as_str().unwrap()).collect::<Vec<_>>();
+    assert_eq!(types, ["start_game", "start_kyoku", "tsumo"]);
+    assert_eq!(events[0]["id"], 0);
+    assert_eq!(events[1]["tehais"][0].as_array().unwrap().len(), 13);
+}
+
+#[test]
+fn export_round_trips_parsed_board() {
+    let parsed = washizu().arg("parse").args(FIRST_TURN_BOARD).output().unwrap();
+    assert!(parsed.status.success());
+    washizu()
+        .args(["export", "-"])
+        .write_stdin(parsed.stdout)
+        .assert()
+        .success()
+        .stdout(format!("{}\n", FIRST_TURN_BOARD.join(" ")));
+}
+
+#[test]
+fn parse_fills_unknown_tiles() {
+    let mut board = FIRST_TURN_BOARD;
+    board[10..14].copy_from_slice(&["?", "9m", "?", "?"]);
+    let output = washizu().arg("parse").args(board).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("\"?\""), "unknown tile left in {stdout}");
+}
+
+#[test]
+fn board_json_matches_board_notation() {
+    let board = serde_json::json!({
+        "bakaze": "E",
+        "kyoku": 1,
+        "jikaze": "E",
+        "dora_indicators": ["1m"],
+        "scores": [25000, 25000, 25000, 25000],
+        "tehai": ["1m", "2m", "3m", "4p", "5p", "6p", "7s", "8s", "9s", "1z", "1z", "2z", "2z", "3z"],
+        "kawa": [[], [], [], []],
+        "fuuro": [[], [], [], []],
+    });
+    washizu()
+        .arg("board-json")
+        .arg("-")
+        .write_stdin(board.to_string())
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(format!("board: {}\n", FIRST_TURN_BOARD.join(" "))));
+    washizu()
+        .args(["board-json", "-"])
+        .write_stdin(r#"{"bakaze": "E"}"#)
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn parse_reports_the_invalid_event() {
+    let mut board = FIRST_TURN_BOARD;
+    board[9] = "11112m456p789s112z";
+    washizu()
+        .arg("parse")
+        .args(board)
+        .assert()
+        .code(2)
+        .stderr(predicates::str::contains("witnessing a 5th 1m"));
+}
+
+#[test]
+fn parse_rejects_incomplete_board() {
+    washizu().args(["parse", "E1", "E"]).assert().failure();
+}
+
+#[test]
+fn live_rejects_two_player_logs() {
+    washizu()
+        .args(["live", "0", "--no-clear"])
+        .write_stdin("{\"type\":\"start_game\",\"names\":[\"a\",\"b\"],\"id\":0}\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("unsupported variant: two player game"));
+}
+
+#[test]
+fn repl_analyzes_incrementally_built_hand() {
+    washizu()
+        .arg("repl")
+        .write_stdin("tehai 123m456p789s1122z\ndraw 3z\nanalyze\nquit\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("123m456p789s11223z (0)"));
+}
+
+#[test]
+fn schema_prints_valid_json() {
+    for name in ["analysis-report", "board", "candidate", "danger"] {
+        let output = washizu().args(["schema", name]).output().unwrap();
+        assert!(output.status.success());
+        let schema: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(schema["$id"], format!("{name}.schema.json"));
+    }
+    washizu().args(["schema", "unknown"]).assert().failure();
+}
+
+#[test]
+fn repl_moves_called_tiles_to_fuuro() {
+    washizu()
+        .arg("repl")
+        .write_stdin("auto off\ntehai 123m456p789s112277z\npon 7z\nquit\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("123m456p789s1122z | fuuro 777z"));
+}
+
+#[test]
+fn hand_output_matches_schema() {
+    washizu()
+        .args(["hand", "123m456p789s11223z", "--validate-output"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn hand_accepts_rules_presets_and_files() {
+    // 4m tanki, a red 5m keeps tenpai on an equally wide tanki and only upgrades the hand while red fives are in the wall
+    let hand = ["hand", "4789m123456p789s3z", "--dora-indicators", "5m", "--ukeire"];
+    washizu()
+        .args(hand)
+        .args(["--rules", "majsoul"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("3z  0 | advance 3 4m(3)").and(predicate::str::contains("(1 aka)")));
+    let path = std::env::temp_dir().join(format!("washizu-rules-{}.toml", std::process::id()));
+    std::fs::write(&path, "aka = 0\nkuitan = false\n").unwrap();
+    washizu()
+        .args(hand)
+        .arg("--rules")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("3z  0 | advance 3 4m(3)").and(predicate::str::contains("(1 aka)").not()));
+    std::fs::write(&path, "akadora = 0\n").unwrap();
+    washizu()
+        .args(["hand", "123m456p789s11223z", "--rules"])
+        .arg(&path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown field `akadora`"));
+    std::fs::write(&path, "aka = 4\n").unwrap();
+    washizu()
+        .args(["hand", "123m456p789s11223z", "--rules"])
+        .arg(&path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("at most 3 red fives"));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn kiriage_is_noted_only_in_rooms_with_kiriage() {
+    // dealer tanyao pinfu dora 2 on 6s, 30 fu 4 han
+    let hand = ["hand", "234m567m234p66s78s1z", "--dora-indicators", "1m3p"];
+    washizu()
+        .args(hand)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("kiriage").not());
+    let path = std::env::temp_dir().join(format!("washizu-kiriage-{}.toml", std::process::id()));
+    std::fs::write(&path, "kiriage = true\n").unwrap();
+    washizu()
+        .args(hand)
+        .arg("--rules")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("6s 30fu4han 12000").and(predicate::str::contains("kiriage rounds it up")));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn visible_tiles_require_washizu() {
+    // a transparent east in shimocha's hand leaves one east in the wall for our shanpon
+    washizu()
+        .args(["hand", "123m456p789s11223z", "--dora-indicators", "1m", "--ukeire"])
+        .args(["--washizu", "--visible", "1:1z"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("3z  0 | advance 3 1z(1) 2z(2)"));
+    washizu()
+        .args(["hand", "123m456p789s11223z", "--visible", "1:123m"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--washizu"));
+    washizu()
+        .args(["hand", "123m456p789s11223z", "--washizu", "--visible", "0:123m"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("visible tiles must belong to an opponent"));
+}
+
+#[test]
+fn batch_reviews_directory_of_logs() {
+    let dir = std::env::temp_dir().join(format!("washizu-batch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let output = washizu()
+        .arg("parse")
+        .args(FIRST_TURN_BOARD)
+        .arg("/")
+        .arg("/")
+        .output()
+        .unwrap();
+    std::fs::write(dir.join("game.mjson"), output.stdout).unwrap();
+    std::fs::write(dir.join("notes.txt"), "not a log").unwrap();
+    let csv = dir.join("summary.csv");
+    washizu()
+        .arg("batch")
+        .arg(&dir)
+        .arg("--csv")
+        .arg(&csv)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("1 games, 1 decisions"))
+        .stderr(predicate::str::contains("skipping"));
+    let csv = std::fs::read_to_string(csv).unwrap();
+    assert_eq!(csv.lines().count(), 2);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn batch_resumes_from_its_checkpoint() {
+    let dir = std::env::temp_dir().join(format!("washizu-resume-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let checkpoint = std::env::temp_dir().join(format!("washizu-resume-{}.json", std::process::id()));
+    let output = washizu().arg("parse").args(FIRST_TURN_BOARD).output().unwrap();
+    std::fs::write(dir.join("game.mjson"), output.stdout).unwrap();
+    washizu()
+        .arg("batch")
+        .arg(&dir)
+        .arg("--checkpoint")
+        .arg(&checkpoint)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("1 games, 1 decisions"));
+    // a resumed run keeps the recorded game without reading it again
+    std::fs::write(dir.join("game.mjson"), "not a log").unwrap();
+    washizu()
+        .arg("batch")
+        .arg(&dir)
+        .arg("--checkpoint")
+        .arg(&checkpoint)
+        .arg("--resume")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("1 games, 1 decisions"))
+        .stderr(predicate::str::contains("skipping").not());
+    washizu()
+        .arg("batch")
+        .arg(&dir)
+        .arg("--checkpoint")
+        .arg(&checkpoint)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("0 games"))
+        .stderr(predicate::str::contains("skipping"));
+    std::fs::remove_file(checkpoint).unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn board_emits_mjai_events() {
+    let path = std::env::temp_dir().join(format!("washizu-emit-{}.jsonl", std::process::id()));
+    let parsed = washizu().arg("parse").args(FIRST_TURN_BOARD).output().unwrap();
+    washizu()
+        .arg("--emit-mjai")
+        .arg(&path)
+        .arg("board")
+        .args(FIRST_TURN_BOARD)
+        .assert()
+        .success();
+    assert_eq!(std::fs::read(&path).unwrap(), parsed.stdout);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn duplicate_compares_log_with_engine() {
+    let path = std::env::temp_dir().join(format!("washizu-duplicate-{}.mjson", std::process::id()));
+    let output = washizu()
+        .arg("parse")
+        .args(FIRST_TURN_BOARD)
+        .arg("/")
+        .arg("/")
+        .output()
+        .unwrap();
+    std::fs::write(&path, output.stdout).unwrap();
+    washizu()
+        .arg("duplicate")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("duplicate: logged vs engine"));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn verify_data_checks_manifest() {
+    let dir = std::env::temp_dir().join(format!("washizu-data-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("table.bin"), "abc").unwrap();
+    std::fs::write(
+        dir.join("checksums.txt"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  table.bin\n",
+    )
+    .unwrap();
+    washizu()
+        .arg("verify-data")
+        .arg(&dir)
+        .assert()
+        .success()
+        .stdout("table.bin: ok\n");
+    std::fs::write(dir.join("table.bin"), "abd").unwrap();
+    washizu().arg("verify-data").arg(&dir).assert().failure();
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn verify_data_stays_in_the_data_directory() {
+    let dir = std::env::temp_dir().join(format!("washizu-confined-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let outside = dir.with_extension("bin");
+    std::fs::write(&outside, "abc").unwrap();
+    let checksum = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+    let name = outside.file_name().unwrap().to_str().unwrap();
+    std::fs::write(
+        dir.join("checksums.txt"),
+        format!("{checksum}  ../{name}\n{checksum}  {}\n", outside.display()),
+    )
+    .unwrap();
+    washizu()
+        .arg("verify-data")
+        .arg(&dir)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(format!(
+            "../{name}: FAILED outside the data directory"
+        )));
+    std::fs::remove_dir_all(dir).unwrap();
+    std::fs::remove_file(outside).unwrap();
+}
+
+#[test]
+fn hand_exports_decision_tree() {
+    let output = washizu()
+        .args(["hand", "123m456p789s11223z", "--export-tree", "depth=2,width=1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let tree: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(tree["label"], "position");
+    let children = tree["children"].as_array().unwrap();
+    assert!(!children.is_empty());
+    assert!(children[0]["children"].as_array().unwrap().iter().all(|draw| draw["prob"].is_number()));
+    washizu()
+        .args(["hand", "123m456p789s11223z", "--export-tree", "format=dot"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("digraph tree {"));
+    washizu()
+        .args(["hand", "123m456p789s11223z", "--export-tree", "depth=0"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn output_flags_are_rejected_where_they_do_not_apply() {
+    washizu()
+        .args(["--export-tree", "depth=1", "agari", "123m456p789s1122z", "2z"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--export-tree only applies"));
+    washizu()
+        .args(["--emit-mjai", "/dev/null", "hand", "123m456p789s11223z"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--emit-mjai only applies"));
+}
+
+#[test]
+fn stats_counts_kyokus_of_logs() {
+    let path = std::env::temp_dir().join(format!("washizu-stats-{}.mjson", std::process::id()));
+    let output = washizu()
+        .arg("parse")
+        .args(FIRST_TURN_BOARD)
+        .arg("/")
+        .arg("/")
+        .output()
+        .unwrap();
+    std::fs::write(&path, output.stdout).unwrap();
+    washizu()
+        .arg("stats")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("1 games, 1 kyokus"));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn hand_reads_forum_problem_notation() {
+    let output = washizu()
+        .args(["hand", "123m456p789s11223z dora 5m east seat 1, turn 6"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let plain = washizu()
+        .args(["hand", "123m456p789s11223z", "--dora-indicators", "4m", "--tiles-left", "50"])
+        .output()
+        .unwrap();
+    assert_eq!(output.stdout, plain.stdout);
+    washizu()
+        .args(["hand", "123m456p789s11223z dora 5m sideways"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn lang_localizes_help_and_errors() {
+    washizu()
+        .args(["--lang", "ja", "hand", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("一人麻雀計算で解析する向聴数の上限"));
+    washizu()
+        .args(["--lang", "ja", "ekyumoe", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Mortalと本ツールの打牌の放銃率がこのパーセント以上異なる判断も列挙する",
+        ));
+    washizu()
+        .args(["--lang", "ja", "db", "add", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("mjaiログを解析して保存する").and(predicate::str::contains("プレイヤーの席")));
+    washizu()
+        .arg("verify-data")
+        .arg("/nonexistent")
+        .env("WASHIZU_LANG", "ja")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "エラー: /nonexistent/checksums.txtを読み込めませんでした",
+        ))
+        .stderr(predicate::str::contains("原因:"));
+    washizu()
+        .args(["hand", "--help"])
+        .env("WASHIZU_LANG", "en")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Upper bound for the shanten"));
+}
+
+/// Hand far from tenpai facing the riichi of the second player
+const HOPELESS_AGAINST_RIICHI_BOARD: [&str; 18] = [
+    "E1",
+    "E",
+    "0",
+    "0",
+    "1m",
+    "25000",
+    "25000",
+    "25000",
+    "25000",
+    "1479m258p369s1357z",
+    "4z.",
+    "1z-",
+    "2z.",
+    "6z.",
+    "/",
+    "/",
+    "/",
+    "/",
+];
+
+#[test]
+fn board_folds_hopeless_hand_against_riichi() {
+    let board = HOPELESS_AGAINST_RIICHI_BOARD;
+    washizu()
+        .arg("board")
+        .args(board)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("fold against riichi of 1").and(predicate::str::contains("safest: 1z(genbutsu")));
+    washizu()
+        .arg("board")
+        .args(board)
+        .arg("--no-auto-fold")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("fold against").not());
+}
+
+#[test]
+fn board_ranks_hand_by_safety_when_folding_is_plausible() {
+    washizu()
+        .arg("board")
+        .args(HOPELESS_AGAINST_RIICHI_BOARD)
+        .arg("--no-auto-fold")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("betaori against 1").and(predicate::str::contains("safest: 1z(genbutsu")));
+}
+
+#[test]
+fn board_does_not_push_hopeless_hand_against_riichi() {
+    washizu()
+        .arg("board")
+        .args(HOPELESS_AGAINST_RIICHI_BOARD)
+        .arg("--no-auto-fold")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("verdict: ").and(predicate::str::contains("verdict: push").not()));
+}
+
+#[test]
+fn board_shows_every_kawa() {
+    washizu()
+        .arg("board")
+        .args(HOPELESS_AGAINST_RIICHI_BOARD)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("kawa 0: 4z.\nkawa 1*: 1z-\n"));
+}
+
+#[test]
+fn errors_exit_with_the_kind_of_input() {
+    washizu()
+        .args(["--rules", "/nonexistent.toml", "hand", "123m456p789s11223z"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("error: invalid options"));
+    washizu().args(["ekyumoe", "/nonexistent.json"]).assert().code(3);
+}
+
+#[test]
+fn live_skips_malformed_events() {
+    washizu()
+        .arg("live")
+        .arg("--no-clear")
+        .write_stdin("not json\n{\"type\":\"none\"}\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("skipping event: malformed event not json"));
+}
+
+#[test]
+fn live_summarizes_each_game_of_a_stream() {
+    washizu()
+        .args(["live", "--no-clear"])
+        .write_stdin(concat!(
+            "{\"type\":\"start_game\",\"names\":[\"a\",\"b\",\"c\",\"d\"],\"id\":1}\n",
+            "{\"type\":\"end_game\"}\n",
+            "{\"type\":\"start_game\",\"names\":[\"a\",\"b\",\"c\",\"d\"],\"id\":2}\n",
+            "{\"type\":\"end_game\"}\n",
+        ))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("game 1: game over as player 1"))
+        .stdout(predicate::str::contains("game 2: game over as player 2"));
+}
+
+#[test]
+fn ekyumoe_reads_gzip_from_stdin() {
+    let mut gzip = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+    gzip.write_all(b"{\"player_id\":0}").unwrap();
+    washizu()
+        .args(["ekyumoe", "-"])
+        .write_stdin(gzip.finish().unwrap())
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("failed to load log -"))
+        .stderr(predicate::str::contains("missing field `review`"));
+}
+
+/// Ekyumoe review of the first turn board with mortal's details of the first decision
+fn first_turn_review_with_details() -> String {
+    let parsed = washizu().arg("parse").args(FIRST_TURN_BOARD).output().unwrap();
+    let mjai_log = String::from_utf8(parsed.stdout)
+        .unwrap()
+        .lines()
+        .collect::<Vec<_>>()
+        .join(",");
+    let details = r#"[{"action":{"type":"dahai","actor":0,"pai":"W","tsumogiri":true},"q_value":1.0,"prob":0.75}]"#;
+    format!(
+        r#"{{"player_id":0,"review":{{"kyokus":[{{"entries":[{{"junme":0,"last_actor":0,"tile":"W","details":{details}}}]}}]}},"mjai_log":[{mjai_log}]}}"#
+    )
+}
+
+#[test]
+fn ekyumoe_analyzes_a_review_from_stdin() {
+    washizu()
+        .args(["ekyumoe", "-"])
+        .write_stdin(first_turn_review_with_details())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Tsumo"))
+        .stdout(predicate::str::contains("(75.00%)"));
+}
+
+#[test]
+fn ekyumoe_analyzes_every_review_of_a_gzip_session() {
+    let path = std::env::temp_dir().join(format!("washizu-session-{}.json.gz", std::process::id()));
+    let mut gzip = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+    for _ in 0..2 {
+        gzip.write_all(first_turn_review_with_details().as_bytes()).unwrap();
+    }
+    std::fs::write(&path, gzip.finish().unwrap()).unwrap();
+    let output = washizu().arg("ekyumoe").arg(&path).output().unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let (first, second) = stdout.split_once("game 2").expect("second review not analyzed");
+    assert!(first.contains("(75.00%)"), "{first}");
+    assert!(second.contains("(75.00%)"), "{second}");
+}
+
+#[test]
+fn ekyumoe_skips_corrupt_details_unless_strict() {
+    let path = std::env::temp_dir().join(format!("washizu-corrupt-{}.json", std::process::id()));
+    let parsed = washizu().arg("parse").args(FIRST_TURN_BOARD).output().unwrap();
+    let mjai_log = String::from_utf8(parsed.stdout)
+        .unwrap()
+        .lines()
+        .collect::<Vec<_>>()
+        .join(",");
+    // the probability of the only detail is not a number
+    let details = r#"[{"action":{"type":"dahai","actor":0,"pai":"W","tsumogiri":true},"q_value":1.0,"prob":"high"}]"#;
+    std::fs::write(
+        &path,
+        format!(
+            r#"{{"player_id":0,"review":{{"kyokus":[{{"entries":[{{"junme":0,"last_actor":0,"tile":"W","details":{details}}}]}}]}},"mjai_log":[{mjai_log}]}}"#
+        ),
+    )
+    .unwrap();
+    washizu()
+        .arg("ekyumoe")
+        .arg(&path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("warning: skipping corrupt ekyumoe details"));
+    washizu()
+        .arg("ekyumoe")
+        .arg(&path)
+        .arg("--strict-details")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("corrupt details in kyoku 0 entry 0"));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn board_offers_tsumo_on_a_complete_hand() {
+    let board = "E1 E 0 0 1m 25000 25000 25000 25000 123m456p789s11222z / / / / / / / /";
+    let output = washizu().arg("board").args(board.split_whitespace()).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let hora = stdout
+        .lines()
+        .find(|line| line.starts_with("tsumo S: "))
+        .expect("no tsumo line");
+    assert!(hora.ends_with("-> tsumo"), "{hora}");
+    // the dealer's menzen tsumo is worth more than any discard and heads the candidates
+    assert!(best_candidate(&stdout).contains("100.00% -1 0"), "{stdout}");
+}
+
+#[test]
+fn board_offers_ron_on_a_discard_completing_the_hand() {
+    // kamicha discards the double east our shanpon waits on
+    let board = "E1 E 0 0 1m 25000 25000 25000 25000 123m456p789s1122z 9m. 9m. 9p. 1z. / / / /";
+    let output = washizu().arg("board").args(board.split_whitespace()).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let hora = stdout.lines().find(|line| line.starts_with("ron E: ")).expect("no ron line");
+    assert!(hora.ends_with("-> ron"), "{hora}");
+    assert!(best_candidate(&stdout).contains("100.00% -1 0"), "{stdout}");
+}
+
+/// Ekyumoe review without details of the first turn board followed by `events`, for player 0
+fn first_turn_review(name: &str, events: &[&str]) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("washizu-{name}-{}.json", std::process::id()));
+    let parsed = washizu().arg("parse").args(FIRST_TURN_BOARD).output().unwrap();
+    let stdout = String::from_utf8(parsed.stdout).unwrap();
+    let mjai_log = stdout.lines().chain(events.iter().copied()).collect::<Vec<_>>().join(",");
+    std::fs::write(
+        &path,
+        format!(r#"{{"player_id":0,"review":{{"kyokus":[]}},"mjai_log":[{mjai_log}]}}"#),
+    )
+    .unwrap();
+    path
+}
+
+const DISCARD_WEST: &str = r#"{"type":"dahai","actor":0,"pai":"W","tsumogiri":true}"#;
+const DISCARD_1M: &str = r#"{"type":"dahai","actor":0,"pai":"1m","tsumogiri":false}"#;
+
+#[test]
+fn ekyumoe_only_mistakes_prints_the_decisions_losing_ev() {
+    let best = first_turn_review("best", &[DISCARD_WEST]);
+    let mistake = first_turn_review("mistake", &[DISCARD_1M]);
+    washizu()
+        .arg("ekyumoe")
+        .arg(&best)
+        .arg("--only-mistakes")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Tsumo").not());
+    washizu()
+        .arg("ekyumoe")
+        .arg(&mistake)
+        .arg("--only-mistakes")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Tsumo"))
+        .stdout(predicate::str::contains("123m456p789s11223z"));
+    std::fs::remove_file(best).unwrap();
+    std::fs::remove_file(mistake).unwrap();
+}
+
+#[test]
+fn ekyumoe_min_ev_loss_prints_the_decisions_losing_at_least_it() {
+    let mistake = first_turn_review("min-ev-loss", &[DISCARD_1M]);
+    washizu()
+        .arg("ekyumoe")
+        .arg(&mistake)
+        .args(["--min-ev-loss", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Tsumo"));
+    washizu()
+        .arg("ekyumoe")
+        .arg(&mistake)
+        .args(["--min-ev-loss", "100000"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Tsumo").not());
+    std::fs::remove_file(mistake).unwrap();
+}
+
+#[test]
+fn ekyumoe_filters_print_the_decision_the_log_ends_at() {
+    let unanswered = first_turn_review("unanswered", &[]);
+    washizu()
+        .arg("ekyumoe")
+        .arg(&unanswered)
+        .arg("--only-mistakes")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Tsumo"));
+    std::fs::remove_file(unanswered).unwrap();
+}
+
+#[test]
+fn ekyumoe_player_reviews_another_seat() {
+    let review = first_turn_review("player", &[DISCARD_WEST]);
+    washizu()
+        .arg("ekyumoe")
+        .arg(&review)
+        .args(["--player", "0"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Tsumo"))
+        .stderr(predicate::str::contains("ignoring review").not());
+    // the dealer's discard is the only event after the deal and player 1 cannot call it
+    washizu()
+        .arg("ekyumoe")
+        .arg(&review)
+        .args(["--player", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Dahai"))
+        .stderr(predicate::str::contains(
+            "reviewing player 1 but the review is for player 0, ignoring review",
+        ));
+    std::fs::remove_file(review).unwrap();
+}
+
+/// Tiles of the waits line of the simulate-waits output
+fn simulated_waits(kawa: &str) -> Vec<String> {
+    let board = format!("E1 E 0 0 1m 25000 25000 25000 25000 23456m3456p45678s 9s.9p. {kawa} 1s.2s. 1p.2p. / / / /");
+    let output = washizu()
+        .args(["simulate-waits", "--board", &board, "--opponent", "1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("player 1: "), "{stdout}");
+    let waits = stdout.lines().find_map(|line| line.strip_prefix("waits: ")).unwrap();
+    waits
+        .split(" | ")
+        .map(|wait| wait.split_whitespace().next().unwrap().to_owned())
+        .collect()
+}
+
+#[test]
+fn simulate_waits_needs_a_yaku_without_riichi() {
+    let riichi = simulated_waits("1m.9p-");
+    assert!(riichi.contains(&"N".to_owned()), "{riichi:?}");
+    // a closed hand without riichi cannot ron on a tanki or shanpon of a guest wind
+    let dama = simulated_waits("1m.9p.");
+    assert!(!dama.contains(&"N".to_owned()), "{dama:?}");
+    assert!(dama.contains(&"5m".to_owned()), "{dama:?}");
+    // waits on the opponent's own discards are furiten
+    assert!(!riichi.contains(&"1m".to_owned()) && !riichi.contains(&"9p".to_owned()));
+}
@@ -0,0 +1,17 @@
+use washizu::version::{CORE_VERSION, check_binding_version};
+
+#[test]
+fn binding_of_the_same_core_is_accepted() {
+    check_binding_version(CORE_VERSION).unwrap();
+}
+
+#[test]
+fn binding_of_another_minor_version_is_rejected_before_1_0() {
+    let error = check_binding_version("0.999.0").unwrap_err().to_string();
+    assert!(error.contains("rebuild the binding"), "{error}");
+}
+
+#[test]
+fn malformed_binding_version_is_rejected() {
+    assert!(check_binding_version("washizu").is_err());
+}